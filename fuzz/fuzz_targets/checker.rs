@@ -0,0 +1,16 @@
+#![no_main]
+
+use karma::{lexer::Lexer, parser::Parser, source::Source};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|source: &str| {
+    let mut parser = Parser::new(Lexer::from_source(source));
+    if parser.parse().is_err() {
+        return;
+    }
+    parser.generate_ast();
+
+    if let Ok(source) = Source::new(parser.ast) {
+        source.generate_bytecode();
+    }
+});
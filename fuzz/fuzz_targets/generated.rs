@@ -0,0 +1,39 @@
+#![no_main]
+
+use karma::{compile_str, diagnostic::Stage, vm};
+use karma_fuzz::Program;
+use libfuzzer_sys::fuzz_target;
+
+/// How many instructions each node runs for, since generated loops need not end.
+const STEPS: usize = 10_000;
+
+fuzz_target!(|program: Program| {
+    let compilation = compile_str(&program.source);
+
+    // the generator only writes programs the grammar accepts
+    assert!(
+        compilation
+            .diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.stage == Stage::Semantic),
+        "{}\n{:?}",
+        program.source,
+        compilation.diagnostics
+    );
+
+    if !compilation.is_ok() {
+        return;
+    }
+
+    // a checked program may still divide by zero or index out of bounds, but the checker
+    // should have caught any value of the wrong type
+    for bytes in compilation.bytecode.values() {
+        if let Err(error) = vm::run_for(bytes, &mut std::io::sink(), STEPS) {
+            assert!(
+                !error.message.starts_with("expected a"),
+                "{}\n{error}",
+                program.source
+            );
+        }
+    }
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use karma::lexer::Lexer;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|source: &str| {
+    let mut lexer = Lexer::from_source(source);
+    while let Ok(Some(_)) = lexer.next_token() {}
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use karma::{lexer::Lexer, parser::Parser};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|source: &str| {
+    let mut parser = Parser::new(Lexer::from_source(source));
+    if parser.parse().is_ok() {
+        parser.generate_ast();
+    }
+});
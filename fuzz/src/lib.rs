@@ -0,0 +1,418 @@
+//! A generator of Karma programs for the fuzz targets.
+//!
+//! Programs are written by walking the grammar in `specs/grammar.toml`, with every choice
+//! taken from the fuzzer's input, so every program parses. Names are drawn from small pools
+//! so that declarations and uses meet often enough for the checker and code generator to be
+//! exercised past their first error.
+//!
+//! Repetitions are counted up front rather than decided one at a time: an exhausted input
+//! keeps answering `ratio` with yes, which would never end a loop.
+
+use std::fmt::Write;
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+const NODES: &[&str] = &["A", "B", "C"];
+const MODULES: &[&str] = &["m", "n"];
+const STRUCTS: &[&str] = &["P", "Q"];
+const FUNCTIONS: &[&str] = &["main", "f", "g"];
+const VARIABLES: &[&str] = &["a", "b", "xs", "p"];
+const FIELDS: &[&str] = &["x", "y"];
+/// Units for `float` types, of which the single symbols also suffix literals.
+const UNITS: &[&str] = &["m", "mm", "s", "deg", "rad", "m/s", "N*m", "1/s^2"];
+/// Fixed-point types, the last with more bits than the VM has.
+const FIXED: &[&str] = &[
+    "fixed<16, 16>",
+    "fixed<8, 8, wrap>",
+    "fixed<4, 4, sat>",
+    "fixed<40, 8>",
+];
+/// Numbers of explicit width, which also suffix literals.
+const SIZED: &[&str] = &[
+    "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64",
+];
+const BUILTINS: &[&str] = &[
+    "print_int",
+    "print_float",
+    "print_char",
+    "print_bool",
+    "print_fixed",
+    "println",
+];
+
+const ASSIGN_OPS: &[&str] = &[
+    "=", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "<<=", ">>=",
+];
+const BINARY_OPS: &[&str] = &[
+    "||", "&&", "|", "^", "&", "<<", ">>", "+", "-", "*", "/", "%",
+];
+/// The operators an array index can use without parentheses.
+const INDEX_OPS: &[&str] = &["+", "-", "*", "/", "%"];
+const COMPARISONS: &[&str] = &["==", "!=", "<", ">", "<=", ">="];
+
+/// How deeply blocks and expressions nest before the generator only picks leaves.
+const MAX_DEPTH: usize = 4;
+
+/// How deeply array types nest.
+const MAX_ARRAY_DEPTH: usize = 2;
+
+/// A syntactically valid program.
+#[derive(Debug)]
+pub struct Program {
+    pub source: String,
+}
+
+impl<'a> Arbitrary<'a> for Program {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut generator = Generator {
+            u,
+            out: String::new(),
+            indent: 0,
+        };
+        generator.program()?;
+
+        Ok(Program {
+            source: generator.out,
+        })
+    }
+}
+
+struct Generator<'a, 'b> {
+    u: &'b mut Unstructured<'a>,
+    out: String,
+    indent: usize,
+}
+
+impl Generator<'_, '_> {
+    fn program(&mut self) -> Result<()> {
+        for _ in 0..self.u.int_in_range(0..=2)? {
+            let name = self.pick(MODULES)?;
+            self.line(&format!("module {name} {{"));
+            self.items(true)?;
+            self.line("}");
+        }
+
+        for _ in 0..self.u.int_in_range(1..=3)? {
+            let name = self.pick(NODES)?;
+            let mut header = format!("node {name}");
+            if self.u.ratio(1, 3)? {
+                let count = self.u.int_in_range(1..=2)?;
+                let deps: Result<Vec<_>> = (0..count).map(|_| self.pick(NODES)).collect();
+                header += &format!(" : {}", deps?.join(", "));
+            }
+
+            self.line(&format!("{header} {{"));
+            self.items(false)?;
+            self.line("}");
+        }
+
+        Ok(())
+    }
+
+    /// The top level statements of a node, or of a module when `module`.
+    fn items(&mut self, module: bool) -> Result<()> {
+        self.indent += 1;
+
+        for _ in 0..self.u.int_in_range(0..=4)? {
+            match self.u.int_in_range(0..=5)? {
+                0 => self.structure(module)?,
+                1 => {
+                    let (module, item) = (self.pick(MODULES)?, self.pick(FUNCTIONS)?);
+                    self.line(&format!("use {module}::{item};"));
+                }
+                2 if !module => {
+                    self.write_indent();
+                    self.out += "export ";
+                    self.definition(0)?;
+                }
+                3 => self.function(if module { "pub " } else { "test " })?,
+                _ => {
+                    let public = module && self.u.arbitrary()?;
+                    self.function(if public { "pub " } else { "" })?;
+                }
+            }
+        }
+
+        if !module {
+            self.function("")?;
+        }
+
+        self.indent -= 1;
+        Ok(())
+    }
+
+    fn structure(&mut self, module: bool) -> Result<()> {
+        let visibility = if module && self.u.arbitrary()? {
+            "pub "
+        } else {
+            ""
+        };
+        let name = self.pick(STRUCTS)?;
+        let fields = self.params(FIELDS)?;
+        match fields.is_empty() {
+            true => self.line(&format!("{visibility}struct {name} {{}}")),
+            false => self.line(&format!("{visibility}struct {name} {{ {fields} }}")),
+        }
+
+        Ok(())
+    }
+
+    fn function(&mut self, prefix: &str) -> Result<()> {
+        let name = match prefix {
+            "" if self.u.ratio(1, 2)? => "main",
+            _ => self.pick(FUNCTIONS)?,
+        };
+        let params = self.params(VARIABLES)?;
+        let ret = match self.u.int_in_range(0..=4)? {
+            0 => "!".to_string(),
+            1 | 2 => "()".to_string(),
+            // the parser takes no array return types
+            _ => self.ty(MAX_ARRAY_DEPTH)?,
+        };
+
+        self.line(&format!("{prefix}fn {name}({params}) -> {ret} {{"));
+        self.block(0)?;
+        self.line("}");
+
+        Ok(())
+    }
+
+    fn params(&mut self, names: &[&str]) -> Result<String> {
+        let mut params = vec![];
+        for _ in 0..self.u.int_in_range(0..=3)? {
+            let name = self.pick(names)?;
+            params.push(format!("{name}: {}", self.ty(0)?));
+        }
+
+        Ok(params.join(", "))
+    }
+
+    fn block(&mut self, depth: usize) -> Result<()> {
+        self.indent += 1;
+        for _ in 0..self.u.int_in_range(0..=5)? {
+            self.statement(depth + 1)?;
+        }
+        self.indent -= 1;
+
+        Ok(())
+    }
+
+    fn statement(&mut self, depth: usize) -> Result<()> {
+        let nested = depth < MAX_DEPTH;
+
+        match self.u.int_in_range(0..=6)? {
+            0 => {
+                self.write_indent();
+                self.definition(depth)?;
+            }
+            1 => {
+                let target = self.place(depth)?;
+                let op = self.pick(ASSIGN_OPS)?;
+                let value = self.value(depth)?;
+                self.line(&format!("{target} {op} {value};"));
+            }
+            2 if nested => {
+                let condition = self.expression(depth)?;
+                self.line(&format!("while {condition} {{"));
+                self.block(depth)?;
+                self.line("}");
+            }
+            3 if nested => {
+                let condition = self.expression(depth)?;
+                self.line(&format!("if {condition} {{"));
+                self.block(depth)?;
+                if self.u.arbitrary()? {
+                    self.line("} else {");
+                    self.block(depth)?;
+                }
+                self.line("}");
+            }
+            4 => {
+                let value = self.expression(depth)?;
+                self.line(&format!("return {value};"));
+            }
+            _ => {
+                let call = self.call(depth)?;
+                self.line(&format!("{call};"));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn definition(&mut self, depth: usize) -> Result<()> {
+        let keyword = if self.u.ratio(1, 4)? { "const" } else { "var" };
+        let name = self.pick(VARIABLES)?;
+        let ty = self.ty(0)?;
+        let value = self.value(depth)?;
+        writeln!(self.out, "{keyword} {name}: {ty} = {value};").unwrap();
+
+        Ok(())
+    }
+
+    /// What can be assigned to: a variable with fields and indices.
+    fn place(&mut self, depth: usize) -> Result<String> {
+        let mut place = self.pick(VARIABLES)?.to_string();
+        for _ in 0..self.u.int_in_range(0..=2)? {
+            place += &format!(".{}", self.pick(FIELDS)?);
+        }
+        for _ in 0..self.u.int_in_range(0..=2)? {
+            place += &format!("[{}]", self.binary(depth + 1, INDEX_OPS)?);
+        }
+
+        Ok(place)
+    }
+
+    fn ty(&mut self, depth: usize) -> Result<String> {
+        Ok(match self.u.int_in_range(0..=5)? {
+            0 => self.pick(STRUCTS)?.to_string(),
+            1 if self.u.ratio(1, 3)? => format!("float<{}>", self.pick(UNITS)?),
+            1 => "float".to_string(),
+            2 => "char".to_string(),
+            3 => "bool".to_string(),
+            4 if depth < MAX_ARRAY_DEPTH => {
+                let len = self.u.int_in_range(0..=4)?;
+                format!("[{}; {len}]", self.ty(depth + 1)?)
+            }
+            5 if self.u.ratio(1, 3)? => self.pick(FIXED)?.to_string(),
+            5 if self.u.ratio(1, 2)? => self.pick(SIZED)?.to_string(),
+            _ => "int".to_string(),
+        })
+    }
+
+    /// What a variable can be initialized with: an expression, an array or a struct literal.
+    fn value(&mut self, depth: usize) -> Result<String> {
+        Ok(match self.u.int_in_range(0..=5)? {
+            0 if depth < MAX_DEPTH => format!("[{}]", self.inputs(depth + 1)?),
+            1 if depth < MAX_DEPTH => {
+                // the parser needs a comma after the last field
+                let mut fields = String::new();
+                for _ in 0..self.u.int_in_range(0..=2)? {
+                    let name = self.pick(FIELDS)?;
+                    fields += &format!("{name}: {}, ", self.value(depth + 1)?);
+                }
+                format!("{{ {fields}}}")
+            }
+            _ => self.expression(depth)?,
+        })
+    }
+
+    fn inputs(&mut self, depth: usize) -> Result<String> {
+        let mut inputs = vec![];
+        for _ in 0..self.u.int_in_range(0..=3)? {
+            inputs.push(match depth < MAX_DEPTH && self.u.ratio(1, 5)? {
+                true => format!("[{}]", self.inputs(depth + 1)?),
+                false => self.expression(depth)?,
+            });
+        }
+
+        Ok(inputs.join(", "))
+    }
+
+    fn call(&mut self, depth: usize) -> Result<String> {
+        let name = match self.u.int_in_range(0..=3)? {
+            0 => self.pick(BUILTINS)?,
+            1 => {
+                if self.u.arbitrary()? {
+                    "assert"
+                } else {
+                    "assert_eq"
+                }
+            }
+            _ => self.pick(FUNCTIONS)?,
+        };
+
+        Ok(format!("{name}({})", self.inputs(depth + 1)?))
+    }
+
+    /// A conditional, which is what `if`, `while` and `return` take and what can be
+    /// parenthesized.
+    fn expression(&mut self, depth: usize) -> Result<String> {
+        let lhs = self.arithmetic(depth)?;
+        if self.u.ratio(1, 4)? {
+            let op = self.pick(COMPARISONS)?;
+            return Ok(format!("{lhs} {op} {}", self.arithmetic(depth)?));
+        }
+
+        Ok(lhs)
+    }
+
+    /// An expression without a comparison at its top, which comparisons do not chain.
+    fn arithmetic(&mut self, depth: usize) -> Result<String> {
+        self.binary(depth, BINARY_OPS)
+    }
+
+    /// Factors joined by the operators `ops`.
+    fn binary(&mut self, depth: usize, ops: &[&str]) -> Result<String> {
+        if depth >= MAX_DEPTH || self.u.ratio(1, 2)? {
+            return self.factor(depth);
+        }
+
+        let lhs = self.factor(depth + 1)?;
+        let op = self.pick(ops)?;
+        let rhs = self.binary(depth + 1, ops)?;
+
+        Ok(format!("{lhs} {op} {rhs}"))
+    }
+
+    fn factor(&mut self, depth: usize) -> Result<String> {
+        let nested = depth < MAX_DEPTH;
+
+        let mut factor = match self.u.int_in_range(0..=9)? {
+            0 if self.u.ratio(1, 4)? => {
+                let number = self.u.int_in_range(0..=1000_u32)?;
+                format!("{number}{}", self.pick(SIZED)?)
+            }
+            0 => self.u.int_in_range(0..=1000_u32)?.to_string(),
+            1 => {
+                let mut literal = format!(
+                    "{}.{}",
+                    self.u.int_in_range(0..=99_u32)?,
+                    self.u.int_in_range(0..=99_u32)?
+                );
+                if self.u.ratio(1, 4)? {
+                    literal += &format!(" {}", self.pick(&UNITS[..5])?);
+                }
+                literal
+            }
+            2 => self.pick(&["true", "false"])?.to_string(),
+            3 => format!("{:?}", self.pick(&['a', 'z', ' ', '\n', '\''])?),
+            4 if nested => format!("({})", self.expression(depth + 1)?),
+            5 if nested => format!(
+                "{}{}",
+                self.pick(&["-", "!", "~"])?,
+                self.factor(depth + 1)?
+            ),
+            6 if nested => self.call(depth)?,
+            7 if nested => {
+                let (module, item) = (self.pick(MODULES)?, self.pick(FUNCTIONS)?);
+                format!("{module}::{item}({})", self.inputs(depth + 1)?)
+            }
+            _ => self.place(depth)?,
+        };
+
+        if self.u.ratio(1, 8)? {
+            // `as float` followed by `<` would be read as the start of a unit
+            factor = match self.ty(0)?.as_str() {
+                "float" => format!("({factor} as float)"),
+                ty => format!("{factor} as {ty}"),
+            };
+        }
+
+        Ok(factor)
+    }
+
+    fn pick<T: Copy>(&mut self, choices: &[T]) -> Result<T> {
+        self.u.choose(choices).copied()
+    }
+
+    fn line(&mut self, line: &str) {
+        self.write_indent();
+        self.out += line;
+        self.out.push('\n');
+    }
+
+    fn write_indent(&mut self) {
+        self.out += &"    ".repeat(self.indent);
+    }
+}
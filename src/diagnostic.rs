@@ -0,0 +1,133 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::lexer::{LexError, Span};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stage {
+    Lexical,
+    Syntax,
+    Semantic,
+}
+
+/// An error found while compiling, along with where it was found when that is known.
+/// Semantic errors also carry the numeric code reported by the checker.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub stage: Stage,
+    pub code: Option<usize>,
+    pub message: String,
+    pub span: Option<Span>,
+    pub file: Option<PathBuf>,
+}
+
+impl Diagnostic {
+    pub fn syntax(message: &str, span: Span) -> Self {
+        Self {
+            stage: Stage::Syntax,
+            code: None,
+            message: message
+                .strip_prefix("syntax error: ")
+                .unwrap_or(message)
+                .to_string(),
+            span: Some(span),
+            file: None,
+        }
+    }
+
+    pub fn semantic(code: usize) -> Self {
+        Self {
+            stage: Stage::Semantic,
+            code: Some(code),
+            message: crate::source::describe_error(code).to_string(),
+            span: None,
+            file: None,
+        }
+    }
+
+    pub fn with_file(mut self, file: impl Into<PathBuf>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Replaces the generic description of the error with a more specific one.
+    pub fn with_message(mut self, message: String) -> Self {
+        self.message = message;
+        self
+    }
+
+    /// The diagnostic as a JSON object, for editors and CI. Fields that are not known are null.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "stage": self.stage.to_string(),
+            "code": self.code,
+            "message": self.message,
+            "file": self.file.as_ref().map(|file| file.display().to_string()),
+            "line": self.span.map(|span| span.line),
+            "column": self.span.map(|span| span.column),
+            "start": self.span.map(|span| span.start),
+            "end": self.span.map(|span| span.end),
+        })
+    }
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(e: LexError) -> Self {
+        Self {
+            stage: Stage::Lexical,
+            code: None,
+            message: e.message(),
+            span: Some(e.span),
+            file: None,
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Stage::Lexical => "lexical",
+            Stage::Syntax => "syntax",
+            Stage::Semantic => "semantic",
+        })
+    }
+}
+
+/// Writes the `file:line:column: ` a message is prefixed with, or the part of it that is known.
+pub(crate) fn write_location(
+    f: &mut fmt::Formatter<'_>,
+    file: Option<&Path>,
+    span: Option<Span>,
+) -> fmt::Result {
+    if let Some(file) = file {
+        write!(f, "{}:", file.display())?;
+    }
+
+    if let Some(span) = span {
+        write!(f, "{}:{}:", span.line, span.column)?;
+    }
+
+    if file.is_some() || span.is_some() {
+        write!(f, " ")?;
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_location(f, self.file.as_deref(), self.span)?;
+
+        match self.code {
+            Some(code) => write!(f, "{} error {code}: {}", self.stage, self.message),
+            None => write!(f, "{} error: {}", self.stage, self.message),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! Renders the node graph of a [`Compilation`] as a Graphviz or Mermaid diagram.
+//!
+//! Each node is drawn with its exported variables, and each dependency as an edge from the
+//! dependent node to the node it depends on. Nodes only share data through exported variables,
+//! as the language has no channels, so there are no channels to draw.
+
+use crate::Compilation;
+
+/// The node graph in Graphviz DOT syntax.
+pub fn to_dot(compilation: &Compilation) -> String {
+    let mut text = String::from("digraph karma {\n    rankdir=LR;\n    node [shape=record];\n\n");
+
+    for (node_id, exports) in nodes(compilation) {
+        if exports.is_empty() {
+            text.push_str(&format!("    \"{node_id}\" [label=\"{node_id}\"];\n"));
+        } else {
+            let exports: String = exports
+                .iter()
+                .map(|(var_id, t)| format!("{var_id}: {}\\l", escape_record(t)))
+                .collect();
+            text.push_str(&format!(
+                "    \"{node_id}\" [label=\"{{{node_id}|{exports}}}\"];\n"
+            ));
+        }
+    }
+
+    text.push('\n');
+    for (node_id, dependency) in edges(compilation) {
+        text.push_str(&format!(
+            "    \"{node_id}\" -> \"{dependency}\" [label=\"depends on\"];\n"
+        ));
+    }
+
+    text.push_str("}\n");
+    text
+}
+
+/// The node graph as a Mermaid flowchart. Mermaid has no quoted ids, so each node's id is its
+/// name behind a `node_` prefix, which no Mermaid keyword starts with, and the name is shown in
+/// its label.
+pub fn to_mermaid(compilation: &Compilation) -> String {
+    let mut text = String::from("flowchart LR\n");
+
+    for (node_id, exports) in nodes(compilation) {
+        let mut label = escape_mermaid(node_id);
+        for (var_id, t) in exports {
+            label.push_str(&format!("<br/>{var_id}: {}", escape_mermaid(t)));
+        }
+
+        text.push_str(&format!("    node_{node_id}[\"{label}\"]\n"));
+    }
+
+    for (node_id, dependency) in edges(compilation) {
+        text.push_str(&format!(
+            "    node_{node_id} -->|\"depends on\"| node_{dependency}\n"
+        ));
+    }
+
+    text
+}
+
+fn nodes(compilation: &Compilation) -> Vec<(&str, &[(String, String)])> {
+    compilation
+        .graph
+        .keys()
+        .map(|node_id| {
+            let exports = compilation
+                .exports
+                .get(node_id)
+                .map_or(&[][..], |exports| exports.as_slice());
+            (node_id.as_str(), exports)
+        })
+        .collect()
+}
+
+fn edges(compilation: &Compilation) -> Vec<(&str, &str)> {
+    compilation
+        .graph
+        .iter()
+        .flat_map(|(node_id, dependencies)| {
+            dependencies
+                .iter()
+                .map(move |dependency| (node_id.as_str(), dependency.as_str()))
+        })
+        .collect()
+}
+
+/// Escapes the characters that have a meaning inside a DOT record label.
+fn escape_record(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if matches!(c, '{' | '}' | '|' | '<' | '>' | '"') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// Escapes the characters that have a meaning inside a quoted Mermaid label, as the entity codes
+/// Mermaid reads in their place.
+fn escape_mermaid(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("#lt;"),
+            '>' => escaped.push_str("#gt;"),
+            '"' => escaped.push_str("#quot;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
@@ -0,0 +1,201 @@
+//! Fixed-point numbers, as the VM computes them.
+//!
+//! A `fixed<I, F>` number is an integer counting steps of 2^-F, of which only the low I + F
+//! bits are used, the sign included. Everything is computed with integers, so results are the
+//! same on every host, with or without floating point hardware.
+
+/// The layout of a fixed-point type and what happens to results outside its range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Format {
+    /// The number of bits, I + F.
+    pub bits: u8,
+    /// The number of fractional bits, F.
+    pub frac: u8,
+    /// Whether results outside the range wrap around rather than saturate.
+    pub wrap: bool,
+}
+
+impl Format {
+    /// The format of the type `t`, like `fixed<16, 16>` or `fixed<8, 8, wrap>`, or `None` when
+    /// `t` is not a fixed-point type that fits in 32 bits with at least one integer bit.
+    pub fn of_type(t: &str) -> Option<Format> {
+        let mut params = t.strip_prefix("fixed<")?.strip_suffix('>')?.split(", ");
+        let int: u8 = params.next()?.parse().ok()?;
+        let frac: u8 = params.next()?.parse().ok()?;
+        let wrap = match params.next() {
+            None => false,
+            Some("wrap") => true,
+            Some(_) => return None,
+        };
+
+        let bits = int.checked_add(frac)?;
+        (int >= 1 && bits <= 32).then_some(Format { bits, frac, wrap })
+    }
+
+    /// The operand bytes of the instructions that take a format.
+    pub fn encode(self) -> [u8; 2] {
+        [self.bits | if self.wrap { 0x80 } else { 0 }, self.frac]
+    }
+
+    pub fn decode(bytes: [u8; 2]) -> Option<Format> {
+        let format = Format {
+            bits: bytes[0] & 0x7F,
+            frac: bytes[1],
+            wrap: bytes[0] & 0x80 != 0,
+        };
+
+        ((1..=32).contains(&format.bits) && format.frac < format.bits).then_some(format)
+    }
+
+    /// `value` brought into the range of the format.
+    pub fn fit(self, value: i64) -> i32 {
+        let shift = 64 - self.bits as u32;
+        if self.wrap {
+            // keeps the low bits and extends their sign
+            ((value << shift) >> shift) as i32
+        } else {
+            let max = (1_i64 << (self.bits - 1)) - 1;
+            value.clamp(-max - 1, max) as i32
+        }
+    }
+
+    pub fn add(self, a: i32, b: i32) -> i32 {
+        self.fit(a as i64 + b as i64)
+    }
+
+    pub fn sub(self, a: i32, b: i32) -> i32 {
+        self.fit(a as i64 - b as i64)
+    }
+
+    /// The product, rounded down.
+    pub fn mul(self, a: i32, b: i32) -> i32 {
+        self.fit((a as i64 * b as i64) >> self.frac)
+    }
+
+    /// The quotient, rounded toward zero, or `None` when `b` is zero.
+    pub fn div(self, a: i32, b: i32) -> Option<i32> {
+        (b != 0).then(|| self.fit(((a as i64) << self.frac) / b as i64))
+    }
+
+    pub fn neg(self, a: i32) -> i32 {
+        self.fit(-(a as i64))
+    }
+
+    pub fn of_int(self, value: i32) -> i32 {
+        self.fit((value as i64) << self.frac)
+    }
+
+    /// The value nearest to `value`.
+    pub fn of_float(self, value: f64) -> i32 {
+        self.fit((value * (1_u64 << self.frac) as f64).round() as i64)
+    }
+
+    /// `value`, which has `from_frac` fractional bits, in this format, rounded down.
+    pub fn convert(self, value: i32, from_frac: u8) -> i32 {
+        let value = value as i64;
+        self.fit(match self.frac.checked_sub(from_frac) {
+            Some(shift) => value << shift,
+            None => value >> (from_frac - self.frac),
+        })
+    }
+}
+
+/// The integer part of `value`, which has `frac` fractional bits, rounded toward zero.
+pub fn to_int(value: i32, frac: u8) -> i32 {
+    (value as i64 / (1_i64 << frac)) as i32
+}
+
+pub fn to_float(value: i32, frac: u8) -> f32 {
+    (value as f64 / (1_u64 << frac) as f64) as f32
+}
+
+/// The exact decimal value of `value`, which has `frac` fractional bits.
+pub fn to_string(value: i32, frac: u8) -> String {
+    let magnitude = (value as i64).unsigned_abs();
+    let mask = (1_u64 << frac) - 1;
+
+    let sign = if value < 0 { "-" } else { "" };
+    let mut text = format!("{sign}{}", magnitude >> frac);
+
+    // every fractional bit adds one decimal digit, so this ends
+    let mut rest = magnitude & mask;
+    if rest != 0 {
+        text.push('.');
+    }
+    while rest != 0 {
+        rest *= 10;
+        text.push(char::from(b'0' + (rest >> frac) as u8));
+        rest &= mask;
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const Q16_16: Format = Format {
+        bits: 32,
+        frac: 16,
+        wrap: false,
+    };
+    const Q4_4_WRAP: Format = Format {
+        bits: 8,
+        frac: 4,
+        wrap: true,
+    };
+
+    #[test]
+    fn formats_are_read_from_types() {
+        assert_eq!(Format::of_type("fixed<16, 16>"), Some(Q16_16));
+        assert_eq!(Format::of_type("fixed<4, 4, wrap>"), Some(Q4_4_WRAP));
+        assert_eq!(Format::decode(Q4_4_WRAP.encode()), Some(Q4_4_WRAP));
+
+        assert_eq!(Format::of_type("fixed<16, 17>"), None);
+        assert_eq!(Format::of_type("fixed<0, 8>"), None);
+        assert_eq!(Format::of_type("fixed<8, 8, round>"), None);
+        assert_eq!(Format::of_type("int"), None);
+    }
+
+    #[test]
+    fn arithmetic_saturates_or_wraps() {
+        let one = Q16_16.of_int(1);
+        assert_eq!(one, 0x10000);
+        assert_eq!(
+            Q16_16.mul(Q16_16.of_float(1.5), Q16_16.of_float(-2.25)),
+            Q16_16.of_float(-3.375)
+        );
+        assert_eq!(Q16_16.div(one, Q16_16.of_int(3)), Some(0x5555));
+        assert_eq!(Q16_16.div(one, 0), None);
+
+        assert_eq!(Q16_16.add(i32::MAX, one), i32::MAX);
+        assert_eq!(Q16_16.neg(i32::MIN), i32::MAX);
+        assert_eq!(Q16_16.of_int(40000), i32::MAX);
+
+        // 7.5 + 1 is 8.5, which wraps around to -7.5 in four integer bits
+        assert_eq!(
+            Q4_4_WRAP.add(Q4_4_WRAP.of_float(7.5), Q4_4_WRAP.of_int(1)),
+            Q4_4_WRAP.of_float(-7.5)
+        );
+        assert_eq!(Q4_4_WRAP.of_int(-9), Q4_4_WRAP.of_int(7));
+    }
+
+    #[test]
+    fn converts_and_prints_exactly() {
+        assert_eq!(to_int(Q16_16.of_float(-2.75), 16), -2);
+        assert_eq!(to_float(Q16_16.of_float(0.375), 16), 0.375);
+        assert_eq!(
+            Q4_4_WRAP.convert(Q16_16.of_float(1.96875), 16),
+            Q4_4_WRAP.of_float(1.9375)
+        );
+        assert_eq!(
+            Q16_16.convert(Q4_4_WRAP.of_float(-0.5), 4),
+            Q16_16.of_float(-0.5)
+        );
+
+        assert_eq!(to_string(Q16_16.of_float(-3.375), 16), "-3.375");
+        assert_eq!(to_string(1, 16), "0.0000152587890625");
+        assert_eq!(to_string(Q16_16.of_int(12), 16), "12");
+    }
+}
@@ -0,0 +1,395 @@
+//! The canonical layout of Karma source, as printed by `karma fmt`.
+//!
+//! The source is lexed and parsed, and every token is printed again with spacing and line
+//! breaks chosen from the grammar symbol it was parsed as. Literals keep their spelling and
+//! comments stay next to the tokens they were written beside. Lines are not wrapped, so a
+//! call or expression stays on one line however long it is.
+
+use crate::{
+    diagnostic::Diagnostic,
+    lexer::{Lexer, Span, Token},
+    parser::{GrammarSymbol, Parser},
+};
+
+const INDENT: &str = "    ";
+
+/// Formats `source`, which must parse. Formatting formatted source gives the same source.
+pub fn format_source(source: &str) -> Result<String, Diagnostic> {
+    let chars: Vec<char> = source.chars().collect();
+
+    let mut lexer = Lexer::from_source(source);
+    let mut tokens = vec![];
+    while let Some(token) = lexer.next_token()? {
+        tokens.push((token, lexer.token_span()));
+    }
+
+    let mut parser = Parser::new(Lexer::from_source(source));
+    parser.parse()?;
+    let parents = parser.parse_tree.terminal_parents();
+    if parents.len() != tokens.len() {
+        // the first token the parse tree does not hold, or the end of the source
+        let span = match tokens.get(parents.len()) {
+            Some((_, span)) => *span,
+            None => lexer.token_span(),
+        };
+        return Err(Diagnostic::syntax(
+            "not every token is part of the parse tree",
+            span,
+        ));
+    }
+
+    let words = tokens
+        .into_iter()
+        .zip(parents)
+        .map(|((token, span), parent)| Word {
+            text: chars[span.start..span.end].iter().collect(),
+            token,
+            span,
+            parent,
+        })
+        .collect();
+
+    let mut printer = Printer {
+        chars: &chars,
+        words,
+        comments: lexer.comments().to_vec(),
+        next_comment: 0,
+        open: vec![],
+        closed: None,
+        out: String::new(),
+        pending_indent: None,
+        prev_end: 0,
+        after_open: false,
+        after_line_comment: false,
+        after_leading_comment: false,
+        blank_pending: false,
+    };
+
+    Ok(printer.print())
+}
+
+struct Word {
+    token: Token,
+    text: String,
+    span: Span,
+    /// The grammar symbol the token was parsed as part of.
+    parent: GrammarSymbol,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    /// The body of a node or module.
+    Node,
+    /// The body of a function or statement.
+    Block,
+    /// The fields of a struct declaration.
+    Struct,
+    /// The fields of a struct literal.
+    Literal,
+    /// Parentheses and brackets.
+    Group,
+}
+
+/// An open delimiter, and whether what it encloses is laid out one item per line.
+#[derive(Clone, Copy)]
+struct Open {
+    kind: Kind,
+    breaks: bool,
+}
+
+enum Gap {
+    Inline { space: bool },
+    Line,
+}
+
+struct Printer<'a> {
+    chars: &'a [char],
+    words: Vec<Word>,
+    comments: Vec<(Span, String)>,
+    next_comment: usize,
+    open: Vec<Open>,
+    /// The delimiter closed by the previous word, if it closed one.
+    closed: Option<Open>,
+    out: String,
+    /// The indentation to write before the next text, when a line was just broken.
+    pending_indent: Option<usize>,
+    /// Where the last token or comment printed ends in the source.
+    prev_end: usize,
+    after_open: bool,
+    after_line_comment: bool,
+    /// Whether the line so far is a block comment, which the next token may share.
+    after_leading_comment: bool,
+    blank_pending: bool,
+}
+
+impl Printer<'_> {
+    fn print(&mut self) -> String {
+        for i in 0..self.words.len() {
+            self.print_word(i);
+        }
+
+        self.flush_comments(usize::MAX, 0);
+
+        let mut out = std::mem::take(&mut self.out);
+        out.truncate(out.trim_end().len());
+        if !out.is_empty() {
+            out.push('\n');
+        }
+
+        out
+    }
+
+    fn print_word(&mut self, i: usize) {
+        let gap = match i {
+            0 => Gap::Line,
+            _ => self.gap(i),
+        };
+        let word = &self.words[i];
+        let (start, end) = (word.span.start, word.span.end);
+        let closes = matches!(
+            word.token,
+            Token::RightBrace | Token::RightParen | Token::RightBracket
+        );
+
+        let comment_indent = match gap {
+            Gap::Inline { .. } => self.level() + 1,
+            _ => self.level(),
+        };
+        self.flush_comments(start, comment_indent);
+
+        self.closed = None;
+        if closes {
+            self.closed = self.open.pop();
+        }
+
+        match gap {
+            Gap::Line if self.after_leading_comment && self.newlines(self.prev_end, start) == 0 => {
+                self.write(" ");
+            }
+            Gap::Line => {
+                let allow_blank = !self.after_open && !closes;
+                self.break_line(start, allow_blank, self.level());
+            }
+            Gap::Inline { space } => {
+                if self.after_line_comment {
+                    self.break_line(start, false, self.level() + 1);
+                } else if space {
+                    self.write(" ");
+                }
+            }
+        }
+
+        if !self.is_dropped_comma(i) {
+            let text = self.words[i].text.clone();
+            self.write(&text);
+        }
+        self.prev_end = end;
+
+        if let Some(open) = self.opens(i) {
+            self.open.push(open);
+            self.after_open = open.breaks;
+        }
+
+        // declarations get an empty line between them, as do the items of a node or module
+        // that have bodies
+        let top = self.open.last().map(|open| open.kind);
+        self.blank_pending = match self.closed.map(|open| open.kind) {
+            Some(Kind::Node) => top.is_none(),
+            Some(Kind::Block | Kind::Struct) => top == Some(Kind::Node),
+            _ => false,
+        };
+
+        // the last field of a struct or struct literal gets a trailing comma
+        let before_close = self
+            .words
+            .get(i + 1)
+            .is_some_and(|next| next.token == Token::RightBrace);
+        let fields = self
+            .open
+            .last()
+            .is_some_and(|open| matches!(open.kind, Kind::Struct | Kind::Literal));
+        if before_close && fields && !matches!(self.words[i].token, Token::Comma | Token::LeftBrace)
+        {
+            self.write(",");
+        }
+    }
+
+    /// Decides what separates word `i` from the word before it.
+    fn gap(&self, i: usize) -> Gap {
+        let prev = &self.words[i - 1];
+        let word = &self.words[i];
+        let top = self.open.last().copied();
+        let breaks = top.is_some_and(|open| open.breaks);
+
+        match prev.token {
+            // the parameters of a fixed-point type stay on one line
+            Token::Comma
+                if matches!(prev.parent, GrammarSymbol::Type | GrammarSymbol::FixedMode) => {}
+            Token::LeftBrace | Token::Comma if breaks => return Gap::Line,
+            Token::Semicolon if prev.parent != GrammarSymbol::Type => return Gap::Line,
+            Token::RightBrace
+                if self.closed.is_some_and(|open| {
+                    matches!(open.kind, Kind::Node | Kind::Block | Kind::Struct)
+                }) =>
+            {
+                return match word.token {
+                    Token::Else => Gap::Inline { space: true },
+                    _ => Gap::Line,
+                };
+            }
+            _ => {}
+        }
+
+        if word.token == Token::RightBrace && breaks {
+            return Gap::Line;
+        }
+
+        Gap::Inline {
+            space: spaced(prev, word),
+        }
+    }
+
+    /// The delimiter word `i` opens, if it opens one.
+    fn opens(&self, i: usize) -> Option<Open> {
+        let word = &self.words[i];
+        let kind = match (&word.token, &word.parent) {
+            (Token::LeftParen | Token::LeftBracket, _) => Kind::Group,
+            (Token::LeftBrace, GrammarSymbol::Item) => Kind::Struct,
+            (Token::LeftBrace, GrammarSymbol::Value) => Kind::Literal,
+            (Token::LeftBrace, GrammarSymbol::NodeBlock) => Kind::Node,
+            (Token::LeftBrace, _) => Kind::Block,
+            _ => return None,
+        };
+
+        // braces always enclose one item per line, unless there is nothing between them
+        let empty = self.words.get(i + 1).is_some_and(|next| {
+            next.token == Token::RightBrace && !self.has_comments(word.span.end, next.span.start)
+        });
+
+        Some(Open {
+            kind,
+            breaks: kind != Kind::Group && !empty,
+        })
+    }
+
+    /// Trailing commas are dropped from lists kept on one line.
+    fn is_dropped_comma(&self, i: usize) -> bool {
+        self.words[i].token == Token::Comma
+            && self
+                .words
+                .get(i + 1)
+                .is_some_and(|next| matches!(next.token, Token::RightParen | Token::RightBracket))
+    }
+
+    /// Prints the comments that start before `before`. Comments that were written on a line
+    /// of their own get one here too, indented by `indent`.
+    fn flush_comments(&mut self, before: usize, indent: usize) {
+        while let Some((span, text)) = self.comments.get(self.next_comment).cloned() {
+            if span.start >= before {
+                break;
+            }
+            self.next_comment += 1;
+
+            let own_line = self.out.is_empty() || self.newlines(self.prev_end, span.start) > 0;
+            if own_line {
+                let allow_blank = !self.after_open;
+                self.break_line(span.start, allow_blank, indent);
+            } else {
+                self.write(" ");
+            }
+
+            let line_comment = text.starts_with("//");
+            self.write(if line_comment { text.trim_end() } else { &text });
+            self.prev_end = span.end;
+            self.after_line_comment = line_comment;
+            self.after_leading_comment = own_line && !line_comment;
+        }
+    }
+
+    /// Ends the current line. When `allow_blank`, an empty line is left if the source had one
+    /// here or one is due between declarations.
+    fn break_line(&mut self, next_start: usize, allow_blank: bool, indent: usize) {
+        if !self.out.is_empty() {
+            let blank =
+                allow_blank && (self.blank_pending || self.newlines(self.prev_end, next_start) > 1);
+
+            self.out.truncate(self.out.trim_end_matches(' ').len());
+            self.out.push('\n');
+            if blank {
+                self.out.push('\n');
+            }
+        }
+
+        self.pending_indent = Some(indent);
+        self.blank_pending = false;
+        self.after_line_comment = false;
+    }
+
+    fn write(&mut self, text: &str) {
+        if let Some(indent) = self.pending_indent.take() {
+            self.out.push_str(&INDENT.repeat(indent));
+        }
+
+        self.out.push_str(text);
+        self.after_open = false;
+        self.after_leading_comment = false;
+    }
+
+    /// The number of lines the enclosing delimiters indent by.
+    fn level(&self) -> usize {
+        self.open.iter().filter(|open| open.breaks).count()
+    }
+
+    fn newlines(&self, start: usize, end: usize) -> usize {
+        let end = end.min(self.chars.len());
+        let start = start.min(end);
+
+        self.chars[start..end]
+            .iter()
+            .filter(|c| **c == '\n')
+            .count()
+    }
+
+    fn has_comments(&self, start: usize, end: usize) -> bool {
+        self.comments
+            .iter()
+            .any(|(span, _)| span.start >= start && span.start < end)
+    }
+}
+
+/// Whether a space separates two words printed on the same line.
+fn spaced(prev: &Word, word: &Word) -> bool {
+    let in_unit = |word: &Word| {
+        matches!(
+            word.parent,
+            GrammarSymbol::UnitType
+                | GrammarSymbol::Unit
+                | GrammarSymbol::UnitPower
+                | GrammarSymbol::UnitExponent
+                | GrammarSymbol::UnitRest
+        )
+    };
+
+    match (&prev.token, &word.token) {
+        // units are written without spaces, like `float<m/s^2>`
+        _ if in_unit(word) => false,
+        (token, _) if in_unit(prev) && *token != Token::Greater => false,
+        // and so are the brackets of fixed-point types, like `fixed<16, 16>`
+        (_, Token::Less | Token::Greater) if word.parent == GrammarSymbol::Type => false,
+        (Token::Less, _) if prev.parent == GrammarSymbol::Type => false,
+        (
+            _,
+            Token::Comma | Token::Semicolon | Token::RightParen | Token::RightBracket | Token::Dot,
+        ) => false,
+        (_, Token::DoubleColon) | (Token::DoubleColon, _) => false,
+        (Token::LeftParen | Token::LeftBracket | Token::Dot, _) => false,
+        (Token::LeftBrace, Token::RightBrace) => false,
+        // but two minus signs in a row are kept apart, as `--` reads like a decrement
+        (Token::Sub, Token::Sub) => true,
+        (Token::Sub | Token::Not | Token::BitwiseNot, _) => prev.parent != GrammarSymbol::Factor,
+        (Token::ID(_), Token::LeftParen | Token::LeftBracket) => false,
+        (Token::RightBracket, Token::LeftBracket) => false,
+        (_, Token::Colon) => word.parent == GrammarSymbol::OptIDList,
+        _ => true,
+    }
+}
@@ -0,0 +1,253 @@
+//! Quaternions and rigid transforms, as the VM computes them.
+//!
+//! A quaternion is stored as `[w, x, y, z]` and a rigid transform as a rotation followed by a
+//! translation. Rotations are applied as `q v q⁻¹`, so quaternions need not be normalised.
+
+pub type Quat = [f32; 4];
+pub type Vec3 = [f32; 3];
+
+pub const IDENTITY: Quat = [1.0, 0.0, 0.0, 0.0];
+
+/// The rotation by `angle` radians about `axis`, or `None` when the axis is zero.
+pub fn axis_angle(axis: Vec3, angle: f32) -> Option<Quat> {
+    let len = dot3(axis, axis).sqrt();
+    if len == 0.0 {
+        return None;
+    }
+
+    let (sin, cos) = (angle / 2.0).sin_cos();
+    let [x, y, z] = axis.map(|c| c / len * sin);
+    Some([cos, x, y, z])
+}
+
+/// The rotation by `roll` about x, then `pitch` about y, then `yaw` about z, all in radians and
+/// about the fixed axes.
+pub fn euler(roll: f32, pitch: f32, yaw: f32) -> Quat {
+    let (sr, cr) = (roll / 2.0).sin_cos();
+    let (sp, cp) = (pitch / 2.0).sin_cos();
+    let (sy, cy) = (yaw / 2.0).sin_cos();
+
+    [
+        cr * cp * cy + sr * sp * sy,
+        sr * cp * cy - cr * sp * sy,
+        cr * sp * cy + sr * cp * sy,
+        cr * cp * sy - sr * sp * cy,
+    ]
+}
+
+/// The Hamilton product `a b`, which rotates by `b` and then by `a`.
+pub fn mul(a: Quat, b: Quat) -> Quat {
+    let [aw, ax, ay, az] = a;
+    let [bw, bx, by, bz] = b;
+
+    [
+        aw * bw - ax * bx - ay * by - az * bz,
+        aw * bx + ax * bw + ay * bz - az * by,
+        aw * by - ax * bz + ay * bw + az * bx,
+        aw * bz + ax * by - ay * bx + az * bw,
+    ]
+}
+
+/// The inverse of `q`, or `None` when it is zero.
+pub fn inverse(q: Quat) -> Option<Quat> {
+    let norm = dot4(q, q);
+    if norm == 0.0 {
+        return None;
+    }
+
+    let [w, x, y, z] = q;
+    Some([w / norm, -x / norm, -y / norm, -z / norm])
+}
+
+/// `q` scaled to unit length, or `None` when it is zero.
+pub fn normalize(q: Quat) -> Option<Quat> {
+    let len = dot4(q, q).sqrt();
+    (len != 0.0).then(|| q.map(|c| c / len))
+}
+
+/// `v` rotated by `q`, or `None` when `q` is zero.
+pub fn rotate(q: Quat, v: Vec3) -> Option<Vec3> {
+    let [x, y, z] = v;
+    let [_, x, y, z] = mul(mul(q, [0.0, x, y, z]), inverse(q)?);
+    Some([x, y, z])
+}
+
+/// The rotation `t` of the way from `a` to `b` along the shorter arc between them.
+pub fn slerp(a: Quat, b: Quat, t: f32) -> Option<Quat> {
+    let (a, mut b) = (normalize(a)?, normalize(b)?);
+
+    let mut cos = dot4(a, b);
+    if cos < 0.0 {
+        b = b.map(|c| -c);
+        cos = -cos;
+    }
+
+    // nearly parallel rotations are interpolated linearly, where the sine below vanishes
+    if cos > 0.9995 {
+        return normalize(std::array::from_fn(|i| a[i] + t * (b[i] - a[i])));
+    }
+
+    let theta = cos.acos();
+    let sin = theta.sin();
+    let (wa, wb) = (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin);
+    Some(std::array::from_fn(|i| wa * a[i] + wb * b[i]))
+}
+
+/// A rotation followed by a translation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub rotation: Quat,
+    pub translation: Vec3,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        rotation: IDENTITY,
+        translation: [0.0; 3],
+    };
+
+    /// `v` moved by the transform.
+    pub fn apply(self, v: Vec3) -> Option<Vec3> {
+        let [x, y, z] = rotate(self.rotation, v)?;
+        let [tx, ty, tz] = self.translation;
+        Some([x + tx, y + ty, z + tz])
+    }
+
+    /// The transform that applies `other` and then `self`.
+    pub fn compose(self, other: Transform) -> Option<Transform> {
+        Some(Transform {
+            rotation: mul(self.rotation, other.rotation),
+            translation: self.apply(other.translation)?,
+        })
+    }
+
+    /// The transform that undoes `self`, or `None` when its rotation is zero.
+    pub fn inverse(self) -> Option<Transform> {
+        let rotation = inverse(self.rotation)?;
+        let [x, y, z] = rotate(rotation, self.translation)?;
+
+        Some(Transform {
+            rotation,
+            translation: [-x, -y, -z],
+        })
+    }
+}
+
+fn dot3(a: Vec3, b: Vec3) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn dot4(a: Quat, b: Quat) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_2, FRAC_PI_4, PI};
+
+    fn assert_close<const N: usize>(actual: [f32; N], expected: [f32; N]) {
+        let close = actual
+            .iter()
+            .zip(expected)
+            .all(|(a, e)| (a - e).abs() < 1e-5);
+        assert!(close, "{actual:?} is not close to {expected:?}");
+    }
+
+    #[test]
+    fn axis_angle_matches_the_half_angle_formula() {
+        let q = axis_angle([0.0, 0.0, 2.0], FRAC_PI_2).unwrap();
+        assert_close(q, [FRAC_1_SQRT_2, 0.0, 0.0, FRAC_1_SQRT_2]);
+
+        assert_eq!(axis_angle([0.0; 3], 1.0), None);
+    }
+
+    #[test]
+    fn euler_angles_compose_about_the_fixed_axes() {
+        assert_close(
+            euler(0.0, 0.0, FRAC_PI_2),
+            axis_angle([0.0, 0.0, 1.0], FRAC_PI_2).unwrap(),
+        );
+        assert_close(euler(PI, 0.0, 0.0), [0.0, 1.0, 0.0, 0.0]);
+
+        let roll = axis_angle([1.0, 0.0, 0.0], 0.3).unwrap();
+        let pitch = axis_angle([0.0, 1.0, 0.0], -0.7).unwrap();
+        let yaw = axis_angle([0.0, 0.0, 1.0], 1.9).unwrap();
+        assert_close(euler(0.3, -0.7, 1.9), mul(yaw, mul(pitch, roll)));
+    }
+
+    #[test]
+    fn the_hamilton_product_of_the_units() {
+        let [i, j, k] = [
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+
+        assert_eq!(mul(i, j), k);
+        assert_eq!(mul(j, i), k.map(|c| -c));
+        assert_eq!(mul(k, k), [-1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(mul(IDENTITY, i), i);
+    }
+
+    #[test]
+    fn rotates_vectors() {
+        let q = axis_angle([0.0, 0.0, 1.0], FRAC_PI_2).unwrap();
+        assert_close(rotate(q, [1.0, 0.0, 0.0]).unwrap(), [0.0, 1.0, 0.0]);
+
+        // scaling a quaternion does not change the rotation it stands for
+        let scaled = q.map(|c| c * 3.0);
+        assert_close(rotate(scaled, [0.0, 2.0, 5.0]).unwrap(), [-2.0, 0.0, 5.0]);
+
+        let q = axis_angle([1.0, 1.0, 1.0], 2.0 * PI / 3.0).unwrap();
+        assert_close(rotate(q, [1.0, 0.0, 0.0]).unwrap(), [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn inverts_and_normalises() {
+        let q = [1.0, 2.0, 3.0, 4.0];
+        assert_close(mul(q, inverse(q).unwrap()), IDENTITY);
+        assert_close(
+            normalize([0.0, 3.0, 0.0, 4.0]).unwrap(),
+            [0.0, 0.6, 0.0, 0.8],
+        );
+
+        assert_eq!(inverse([0.0; 4]), None);
+        assert_eq!(normalize([0.0; 4]), None);
+    }
+
+    #[test]
+    fn slerp_follows_the_shorter_arc() {
+        let yaw = axis_angle([0.0, 0.0, 1.0], FRAC_PI_2).unwrap();
+        let halfway = slerp(IDENTITY, yaw, 0.5).unwrap();
+        assert_close(halfway, axis_angle([0.0, 0.0, 1.0], FRAC_PI_4).unwrap());
+
+        assert_close(slerp(IDENTITY, yaw, 0.0).unwrap(), IDENTITY);
+        assert_close(slerp(IDENTITY, yaw, 1.0).unwrap(), yaw);
+
+        // `-yaw` is the same rotation, so it is reached the same way
+        let flipped = slerp(IDENTITY, yaw.map(|c| -c), 0.5).unwrap();
+        assert_close(flipped, halfway);
+    }
+
+    #[test]
+    fn composes_and_inverts_transforms() {
+        let a = Transform {
+            rotation: axis_angle([0.0, 0.0, 1.0], FRAC_PI_2).unwrap(),
+            translation: [1.0, 0.0, 0.0],
+        };
+        let b = Transform {
+            rotation: IDENTITY,
+            translation: [0.0, 2.0, 0.0],
+        };
+
+        assert_close(a.apply([1.0, 0.0, 0.0]).unwrap(), [1.0, 1.0, 0.0]);
+
+        let ab = a.compose(b).unwrap();
+        assert_close(ab.apply([0.0; 3]).unwrap(), [-1.0, 0.0, 0.0]);
+
+        let identity = a.compose(a.inverse().unwrap()).unwrap();
+        assert_close(identity.rotation, IDENTITY);
+        assert_close(identity.translation, [0.0; 3]);
+    }
+}
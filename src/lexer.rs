@@ -1,20 +1,28 @@
 use phf::phf_map;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use serde::Serialize;
+use std::{cell::Cell, fmt, fs, io};
 
 static KEYWORDS: phf::Map<&'static str, Token> = phf_map! {
     "node" => Token::Node,
+    "module" => Token::Module, "pub" => Token::Pub, "use" => Token::Use,
     "export" => Token::Export,
     "var" => Token::Var, "const" => Token::Const,
-    "fn" => Token::Fn,
+    "fn" => Token::Fn, "test" => Token::Test,
     "while" => Token::While,
     "true" => Token::True, "false" => Token::False,
     "if" => Token::If, "else" => Token::Else,
     "return" => Token::Return,
     "struct" => Token::Struct,
+    "as" => Token::As,
     "int" => Token::Int, "float" => Token::FloatKW, "bool" => Token::Bool, "char" => Token::Char,
+    "fixed" => Token::Fixed,
 };
 
+/// The types a number can be suffixed with to give it that type.
+const SUFFIXES: [&str; 10] = [
+    "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64",
+];
+
 static SYMBOLS: phf::Map<&'static str, Token> = phf_map! {
     "=" => Token::Assign,
     "+" => Token::Add, "-" => Token::Sub, "*" => Token::Mul, "/" => Token::Div,
@@ -23,21 +31,30 @@ static SYMBOLS: phf::Map<&'static str, Token> = phf_map! {
     ";" => Token::Semicolon, ":" => Token::Colon, "::" => Token::DoubleColon,
     "->" => Token::Arrow, "." => Token::Dot, "," => Token::Comma,
     "==" => Token::Equals, "!" => Token::Not, "<" => Token::Less, ">" => Token::Greater, "<=" => Token::Leq, ">=" => Token::Geq, "!=" => Token::Neq,
-    "&&" => Token::LogicalAnd, "||" => Token::LogicalOr, "&" => Token::BitwiseAnd, "|" => Token::BitwiseOr,
+    "&&" => Token::LogicalAnd, "||" => Token::LogicalOr, "&" => Token::BitwiseAnd, "|" => Token::BitwiseOr, "~" => Token::BitwiseNot, "^" => Token::BitwiseXor,
+    "%" => Token::Mod, "<<" => Token::ShiftLeft, ">>" => Token::ShiftRight,
+    "%=" => Token::ModAssign, "&=" => Token::AndAssign, "|=" => Token::OrAssign, "^=" => Token::XorAssign,
+    "<<=" => Token::ShlAssign, ">>=" => Token::ShrAssign,
 };
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize)]
 pub enum Token {
     ID(String),
     Integer(i32),
     Float(f32),
+    /// A number with a type suffix, like `255u8`, without separators.
+    SizedNumber(String),
     Character(char),
     StringLiteral(String),
     Node,
+    Module,
+    Pub,
+    Use,
     Export,
     Var,
     Const,
     Fn,
+    Test,
     While,
     True,
     False,
@@ -48,10 +65,17 @@ pub enum Token {
     Mul,
     Sub,
     Div,
+    Mod,
     AddAssign,
     MulAssign,
     SubAssign,
     DivAssign,
+    ModAssign,
+    AndAssign,
+    OrAssign,
+    XorAssign,
+    ShlAssign,
+    ShrAssign,
     LeftParen,
     RightParen,
     LeftBracket,
@@ -75,46 +99,376 @@ pub enum Token {
     LogicalOr,
     BitwiseAnd,
     BitwiseOr,
+    BitwiseNot,
+    BitwiseXor,
+    ShiftLeft,
+    ShiftRight,
     Return,
     Struct,
+    As,
     Int,
     FloatKW,
     Bool,
     Char,
+    Fixed,
+}
+
+/// A range of characters in the source, along with the line and column it starts on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LexErrorKind {
+    UnknownCharacter(char),
+    IntegerOverflow(String),
+    MalformedInteger(String),
+    MalformedFloat(String),
+    FloatOverflow(String),
+    EmptyCharLiteral,
+    UnterminatedCharLiteral,
+    UnterminatedString,
+    UnterminatedComment,
+    InvalidEscape(char),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub span: Span,
+}
+
+impl LexError {
+    pub fn message(&self) -> String {
+        match &self.kind {
+            LexErrorKind::UnknownCharacter(c) => format!("unknown character `{c}`"),
+            LexErrorKind::IntegerOverflow(s) => {
+                format!("integer literal `{s}` does not fit in an int")
+            }
+            LexErrorKind::MalformedInteger(s) => format!("malformed integer literal `{s}`"),
+            LexErrorKind::MalformedFloat(s) => format!("malformed float literal `{s}`"),
+            LexErrorKind::FloatOverflow(s) => {
+                format!("float literal `{s}` does not fit in a float")
+            }
+            LexErrorKind::EmptyCharLiteral => "empty character literal".to_string(),
+            LexErrorKind::UnterminatedCharLiteral => "unterminated character literal".to_string(),
+            LexErrorKind::UnterminatedString => "unterminated string literal".to_string(),
+            LexErrorKind::UnterminatedComment => "unterminated block comment".to_string(),
+            LexErrorKind::InvalidEscape(c) => format!("invalid escape sequence `\\{c}`"),
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: lexical error: {}",
+            self.span.line,
+            self.span.column,
+            self.message()
+        )
+    }
 }
 
 pub struct Lexer {
     chars: Vec<char>,
     curr: usize,
+    token_start: usize,
+    doc_comments: Vec<(usize, String)>,
+    comments: Vec<(Span, String)>,
+    /// The last position a span was taken at, with its line and the start of that line, so
+    /// each span only counts the lines since the one before it.
+    position: Cell<Position>,
+    /// Whether the last token was a `-`, after which `2147483648` is the magnitude of `i32::MIN`.
+    after_minus: bool,
+}
+
+#[derive(Clone, Copy)]
+struct Position {
+    offset: usize,
+    line: usize,
+    line_start: usize,
+}
+
+impl Position {
+    const START: Self = Self {
+        offset: 0,
+        line: 1,
+        line_start: 0,
+    };
 }
 
 impl Lexer {
-    pub fn new(filename: &str) -> Self {
-        let f = BufReader::new(File::open(filename).expect("Could not open file"));
+    pub fn new(filename: &str) -> io::Result<Self> {
+        let source = fs::read_to_string(filename)?;
+
+        Ok(Self::from_source(&source))
+    }
+
+    pub fn from_source(source: &str) -> Self {
+        Self {
+            chars: source.chars().collect(),
+            curr: 0,
+            token_start: 0,
+            doc_comments: vec![],
+            comments: vec![],
+            position: Cell::new(Position::START),
+            after_minus: false,
+        }
+    }
+
+    /// Returns the `///` comments seen so far together with the line each one starts on.
+    pub fn doc_comments(&self) -> &[(usize, String)] {
+        &self.doc_comments
+    }
+
+    /// Returns every comment seen so far, line and block, as written in the source.
+    pub fn comments(&self) -> &[(Span, String)] {
+        &self.comments
+    }
+
+    /// Returns the span of the token most recently returned by `next_token`.
+    pub fn token_span(&self) -> Span {
+        self.span(self.token_start, self.curr)
+    }
+
+    /// Runs `f` on the lexer and then puts it back where it was, so the tokens `f` reads are
+    /// read again by the next call to `next_token`.
+    pub fn lookahead<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let (curr, token_start) = (self.curr, self.token_start);
+        let (doc_comments, comments) = (self.doc_comments.len(), self.comments.len());
+        let (position, after_minus) = (self.position.get(), self.after_minus);
+
+        let result = f(self);
+
+        self.position.set(position);
+        self.after_minus = after_minus;
+        self.curr = curr;
+        self.token_start = token_start;
+        self.doc_comments.truncate(doc_comments);
+        self.comments.truncate(comments);
+
+        result
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        let mut position = self.position.get();
+        if start < position.offset {
+            position = Position::START;
+        }
+
+        for (i, c) in self.chars[position.offset..start].iter().enumerate() {
+            if *c == '\n' {
+                position.line += 1;
+                position.line_start = position.offset + i + 1;
+            }
+        }
+        position.offset = start;
+        self.position.set(position);
+
+        Span {
+            start,
+            end,
+            line: position.line,
+            column: start - position.line_start + 1,
+        }
+    }
+
+    fn error(&self, kind: LexErrorKind, start: usize, end: usize) -> LexError {
+        LexError {
+            kind,
+            span: self.span(start, end),
+        }
+    }
+
+    fn record_comment(&mut self, end: usize) {
+        let comment = self.chars[self.curr..end].iter().collect::<String>();
+        self.comments
+            .push((self.span(self.curr, end), comment.clone()));
+
+        if comment.starts_with("///") && !comment.starts_with("////") {
+            let line = self.span(self.curr, end).line;
+            self.doc_comments
+                .push((line, comment[3..].trim().to_string()));
+        }
+    }
+
+    fn symbol(&mut self, end: usize) -> Result<Option<Token>, LexError> {
+        let attr = self.chars[self.curr..end].iter().collect::<String>();
 
-        let mut chars = vec![];
+        match SYMBOLS.get(attr.as_str()) {
+            Some(token) => {
+                self.curr = end;
+                Ok(Some(token.clone()))
+            }
+            None => Err(self.error(
+                LexErrorKind::UnknownCharacter(self.chars[self.curr]),
+                self.curr,
+                end,
+            )),
+        }
+    }
+
+    /// Returns the end of the run of identifier characters starting at `idx`, used to report
+    /// the whole of a malformed literal like `0b102` or `12ab`.
+    fn word_end(&self, mut idx: usize) -> usize {
+        while idx < self.chars.len()
+            && (self.chars[idx].is_ascii_alphanumeric() || self.chars[idx] == '_')
+        {
+            idx += 1;
+        }
+
+        idx
+    }
+
+    /// The type suffix between `end` and `word_end`, like the `u8` of `255u8`, if that is one.
+    fn suffix(&self, end: usize, word_end: usize) -> Option<String> {
+        let suffix = self.chars[end..word_end].iter().collect::<String>();
+        SUFFIXES.contains(&suffix.as_str()).then_some(suffix)
+    }
+
+    /// Decimal literals have to fit in an `i32`, except that `2147483648` may follow a `-` so
+    /// that `-2147483648` can be written; it wraps to `i32::MIN`, which negating leaves as it
+    /// is. Hex, binary and octal literals may use all 32 bits and are reinterpreted as an `i32`,
+    /// so `0xFFFFFFFF` is `-1`. Suffixed literals are checked against their type by the checker
+    /// instead.
+    fn integer(&mut self, end: usize, radix: u32) -> Result<Option<Token>, LexError> {
+        let attr = self.chars[self.curr..end].iter().collect::<String>();
+        let digits = attr
+            .chars()
+            .skip(if radix == 10 { 0 } else { 2 })
+            .filter(|c| *c != '_')
+            .collect::<String>();
+
+        let word_end = self.word_end(end);
+        let suffix = self.suffix(end, word_end);
+        let float_suffix = suffix
+            .as_ref()
+            .is_some_and(|suffix| suffix.starts_with('f'));
+        if digits.is_empty()
+            || (word_end != end && suffix.is_none())
+            || (float_suffix && radix != 10)
+        {
+            let attr = self.chars[self.curr..word_end].iter().collect::<String>();
+            return Err(self.error(LexErrorKind::MalformedInteger(attr), self.curr, word_end));
+        }
+
+        if let Some(suffix) = suffix {
+            let prefix = &attr[..if radix == 10 { 0 } else { 2 }];
+            self.curr = word_end;
+            return Ok(Some(Token::SizedNumber(format!(
+                "{prefix}{digits}{suffix}"
+            ))));
+        }
+
+        let val = if radix == 10 {
+            match digits.parse::<i32>() {
+                Err(_) if self.after_minus && digits == "2147483648" => Some(i32::MIN),
+                val => val.ok(),
+            }
+        } else {
+            u32::from_str_radix(&digits, radix).ok().map(|v| v as i32)
+        };
+
+        match val {
+            Some(val) => {
+                self.curr = end;
+                Ok(Some(Token::Integer(val)))
+            }
+            None => Err(self.error(LexErrorKind::IntegerOverflow(attr), self.curr, end)),
+        }
+    }
+
+    fn float(&mut self, end: usize) -> Result<Option<Token>, LexError> {
+        let attr = self.chars[self.curr..end].iter().collect::<String>();
+        let text = attr.chars().filter(|c| *c != '_').collect::<String>();
+
+        let word_end = self.word_end(end);
+        let suffix = self
+            .suffix(end, word_end)
+            .filter(|suffix| suffix.starts_with('f'));
+        if text.ends_with('.')
+            || attr.contains("_.")
+            || attr.contains("._")
+            || text.ends_with(['e', 'E'])
+            || text.contains(".e")
+            || text.contains(".E")
+            || (word_end != end && suffix.is_none())
+            || self.chars.get(end) == Some(&'.')
+        {
+            let attr = self.chars[self.curr..word_end].iter().collect::<String>();
+            return Err(self.error(LexErrorKind::MalformedFloat(attr), self.curr, word_end));
+        }
+
+        if let Some(suffix) = suffix {
+            self.curr = word_end;
+            return Ok(Some(Token::SizedNumber(text + &suffix)));
+        }
 
-        for line in f.lines() {
-            for c in line.expect("lines failed").chars() {
-                chars.push(c);
+        match text.parse::<f32>() {
+            Ok(val) if val.is_finite() => {
+                self.curr = end;
+                Ok(Some(Token::Float(val)))
             }
+            Ok(_) => Err(self.error(LexErrorKind::FloatOverflow(attr), self.curr, end)),
+            Err(_) => Err(self.error(LexErrorKind::MalformedFloat(attr), self.curr, end)),
         }
+    }
 
-        Self { chars, curr: 0 }
+    fn escape(&self, idx: usize) -> Result<char, LexError> {
+        match self.chars[idx] {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '0' => Ok('\0'),
+            '\\' => Ok('\\'),
+            '\'' => Ok('\''),
+            '"' => Ok('"'),
+            c => Err(self.error(LexErrorKind::InvalidEscape(c), idx - 1, idx + 1)),
+        }
     }
 
-    pub fn next_token(&mut self) -> Result<Option<Token>, String> {
+    pub fn next_token(&mut self) -> Result<Option<Token>, LexError> {
+        let token = self.lex_token()?;
+        self.after_minus = token == Some(Token::Sub);
+
+        Ok(token)
+    }
+
+    fn lex_token(&mut self) -> Result<Option<Token>, LexError> {
         let mut forward = self.curr;
         let mut state = 0;
+        let mut comment_depth = 0;
+        let mut character = ' ';
+        let mut radix = 10;
 
         while forward < self.chars.len() {
             let c = self.chars[forward];
 
             match state {
                 0 => {
+                    self.token_start = forward;
+
+                    let leading_dot = c == '.'
+                        && self
+                            .chars
+                            .get(forward + 1)
+                            .is_some_and(|next| next.is_ascii_digit());
+
+                    if leading_dot {
+                        state = 6;
+                        forward += 1;
+                        continue;
+                    }
+
                     match c {
                         ' ' | '\t' | '\n' | '\r' | '{' | '}' | '(' | ')' | '[' | ']' | ';'
-                        | '.' | ',' => {
+                        | '.' | ',' | '~' => {
                             self.curr = forward + 1;
                         }
                         _ => {}
@@ -148,11 +502,14 @@ impl Lexer {
                         ',' => {
                             return Ok(Some(Token::Comma));
                         }
+                        '~' => {
+                            return Ok(Some(Token::BitwiseNot));
+                        }
                         _ => {}
                     }
 
                     state = match c {
-                        '+' | '*' | '=' | '!' | '<' | '>' => 1,
+                        '+' | '*' | '=' | '!' | '%' | '^' => 1,
                         ':' => 2,
                         '-' => 3,
                         '_' => 4,
@@ -161,6 +518,7 @@ impl Lexer {
                         '|' => 9,
                         '/' => 10,
                         '\'' => 12,
+                        '<' | '>' => 13,
                         _ => 0,
                     };
 
@@ -171,19 +529,17 @@ impl Lexer {
                     if c.is_ascii_digit() {
                         state = 5;
                     }
+
+                    if state == 0 && !c.is_whitespace() {
+                        return Err(self.error(
+                            LexErrorKind::UnknownCharacter(c),
+                            forward,
+                            forward + 1,
+                        ));
+                    }
                 }
                 1 => {
-                    if c == '=' {
-                        let attr = self.chars[self.curr..forward + 1]
-                            .into_iter()
-                            .collect::<String>();
-                        self.curr = forward + 1;
-                        return Ok(Some(SYMBOLS[attr.as_str()].clone()));
-                    } else {
-                        let attr = String::from(self.chars[forward - 1]);
-                        self.curr = forward;
-                        return Ok(Some(SYMBOLS[attr.as_str()].clone()));
-                    }
+                    return self.symbol(forward + if c == '=' { 1 } else { 0 });
                 }
                 2 => {
                     self.curr = forward + if c == ':' { 1 } else { 0 };
@@ -206,121 +562,202 @@ impl Lexer {
                         _ => Token::Sub,
                     }));
                 }
-                4 => {
-                    if !(c.is_ascii_alphanumeric() || c == '_') {
-                        let attr = self.chars[self.curr..forward]
-                            .into_iter()
-                            .collect::<String>();
-                        self.curr = forward;
+                4 if !(c.is_ascii_alphanumeric() || c == '_') => {
+                    let attr = self.chars[self.curr..forward].iter().collect::<String>();
+                    self.curr = forward;
 
-                        if KEYWORDS.contains_key(attr.as_str()) {
-                            return Ok(Some(KEYWORDS[attr.as_str()].clone()));
-                        } else {
-                            return Ok(Some(Token::ID(attr)));
-                        }
+                    if KEYWORDS.contains_key(attr.as_str()) {
+                        return Ok(Some(KEYWORDS[attr.as_str()].clone()));
+                    } else {
+                        return Ok(Some(Token::ID(attr)));
                     }
                 }
                 5 => {
                     if c == '.' {
                         state = 6;
-                    } else if !(c.is_ascii_digit()) {
-                        let attr = self.chars[self.curr..forward]
-                            .into_iter()
-                            .collect::<String>();
-                        let val: i32 = attr.parse().expect("Failed to convert to float");
-                        self.curr = forward;
-                        return Ok(Some(Token::Integer(val)));
+                    } else if c == 'e' || c == 'E' {
+                        state = 18;
+                    } else if forward == self.curr + 1
+                        && self.chars[self.curr] == '0'
+                        && matches!(c, 'x' | 'b' | 'o')
+                    {
+                        radix = match c {
+                            'x' => 16,
+                            'b' => 2,
+                            _ => 8,
+                        };
+                        state = 17;
+                    } else if !(c.is_ascii_digit() || c == '_') {
+                        return self.integer(forward, 10);
                     }
                 }
                 6 => {
-                    if !(c.is_ascii_digit()) {
-                        let attr = self.chars[self.curr..forward]
-                            .into_iter()
-                            .collect::<String>();
-                        let val: f32 = attr.parse().expect("Failed to convert to float");
-                        self.curr = forward;
-                        return Ok(Some(Token::Float(val)));
+                    if c == 'e' || c == 'E' {
+                        state = 18;
+                    } else if !(c.is_ascii_digit() || c == '_') {
+                        return self.float(forward);
                     }
                 }
-                7 => {
-                    if c == '"' {
+                7 => match c {
+                    '\\' if forward + 1 < self.chars.len() => {
+                        self.escape(forward + 1)?;
+                        forward += 1;
+                    }
+                    '\n' => {
+                        return Err(self.error(
+                            LexErrorKind::UnterminatedString,
+                            self.curr,
+                            forward,
+                        ));
+                    }
+                    '"' => {
                         let attr = self.chars[self.curr..forward + 1]
-                            .into_iter()
+                            .iter()
                             .collect::<String>();
                         self.curr = forward + 1;
                         return Ok(Some(Token::StringLiteral(attr)));
                     }
-                }
+                    _ => {}
+                },
                 8 => {
                     self.curr = forward
                         + match c {
-                            '&' => 1,
+                            '&' | '=' => 1,
                             _ => 0,
                         };
 
                     return Ok(Some(match c {
                         '&' => Token::LogicalAnd,
+                        '=' => Token::AndAssign,
                         _ => Token::BitwiseAnd,
                     }));
                 }
                 9 => {
                     self.curr = forward
                         + match c {
-                            '|' => 1,
+                            '|' | '=' => 1,
                             _ => 0,
                         };
 
                     return Ok(Some(match c {
                         '|' => Token::LogicalOr,
+                        '=' => Token::OrAssign,
                         _ => Token::BitwiseOr,
                     }));
                 }
                 10 => {
-                    if c == '=' {
-                        let attr = self.chars[self.curr..forward + 1]
-                            .into_iter()
-                            .collect::<String>();
-                        self.curr = forward + 1;
-                        return Ok(Some(SYMBOLS[attr.as_str()].clone()));
-                    } else if c == '/' {
+                    if c == '/' {
                         state = 11;
+                    } else if c == '*' {
+                        comment_depth = 1;
+                        state = 15;
                     } else {
-                        let attr = String::from(self.chars[forward - 1]);
-                        self.curr = forward;
-                        return Ok(Some(SYMBOLS[attr.as_str()].clone()));
+                        return self.symbol(forward + if c == '=' { 1 } else { 0 });
                     }
                 }
-                11 => {
-                    if c == '\n' {
-                        self.curr = forward + 1;
-                        state = 0;
+                11 if c == '\n' => {
+                    self.record_comment(forward);
+
+                    self.curr = forward + 1;
+                    state = 0;
+                }
+                12 => match c {
+                    '\'' => {
+                        return Err(self.error(
+                            LexErrorKind::EmptyCharLiteral,
+                            self.curr,
+                            forward + 1,
+                        ));
+                    }
+                    '\n' => {
+                        return Err(self.error(
+                            LexErrorKind::UnterminatedCharLiteral,
+                            self.curr,
+                            forward,
+                        ));
+                    }
+                    '\\' if forward + 1 < self.chars.len() => {
+                        character = self.escape(forward + 1)?;
+                        forward += 1;
+                        state = 16;
+                    }
+                    _ => {
+                        character = c;
+                        state = 16;
+                    }
+                },
+                13 => {
+                    if c == self.chars[self.curr] {
+                        state = 14;
+                    } else {
+                        return self.symbol(forward + if c == '=' { 1 } else { 0 });
                     }
                 }
-                12 => {
-                    if c == '\'' {
-                        if forward - self.curr != 2 {
-                            return Err(String::from(
-                                "Error: characters must be one character length",
-                            ));
+                14 => {
+                    return self.symbol(forward + if c == '=' { 1 } else { 0 });
+                }
+                15 => {
+                    let next = self.chars.get(forward + 1);
+                    if c == '*' && next == Some(&'/') {
+                        comment_depth -= 1;
+                        forward += 1;
+
+                        if comment_depth == 0 {
+                            self.record_comment(forward + 1);
+                            self.curr = forward + 1;
+                            state = 0;
                         }
-                        let attr = Token::Character(self.chars[self.curr + 1]);
-                        self.curr = forward + 1;
-                        return Ok(Some(attr));
+                    } else if c == '/' && next == Some(&'*') {
+                        comment_depth += 1;
+                        forward += 1;
                     }
                 }
+                16 => {
+                    if c != '\'' {
+                        return Err(self.error(
+                            LexErrorKind::UnterminatedCharLiteral,
+                            self.curr,
+                            forward,
+                        ));
+                    }
 
+                    self.curr = forward + 1;
+                    return Ok(Some(Token::Character(character)));
+                }
+                17 if !(c.is_digit(radix) || c == '_') => {
+                    return self.integer(forward, radix);
+                }
+                18 => {
+                    if c == '+' || c == '-' || c.is_ascii_digit() {
+                        state = 19;
+                    } else {
+                        return self.float(forward);
+                    }
+                }
+                19 if !(c.is_ascii_digit() || c == '_') => {
+                    return self.float(forward);
+                }
                 _ => {}
             }
 
             forward += 1;
         }
 
+        let end = self.chars.len();
+
         match state {
+            1 | 10 | 13 | 14 => self.symbol(end),
+            2 => {
+                self.curr = end;
+                Ok(Some(Token::Colon))
+            }
+            3 => {
+                self.curr = end;
+                Ok(Some(Token::Sub))
+            }
             4 => {
-                let attr = self.chars[self.curr..self.chars.len()]
-                    .into_iter()
-                    .collect::<String>();
-                self.curr = self.chars.len();
+                let attr = self.chars[self.curr..end].iter().collect::<String>();
+                self.curr = end;
 
                 if KEYWORDS.contains_key(attr.as_str()) {
                     Ok(Some(KEYWORDS[attr.as_str()].clone()))
@@ -328,30 +765,357 @@ impl Lexer {
                     Ok(Some(Token::ID(attr)))
                 }
             }
-            5 => {
-                let attr = self.chars[self.curr..self.chars.len()]
-                    .into_iter()
-                    .collect::<String>();
-                let val: i32 = attr.parse().expect("Failed to convert to float");
-                self.curr = self.chars.len();
-                Ok(Some(Token::Integer(val)))
+            5 => self.integer(end, 10),
+            6 | 18 | 19 => self.float(end),
+            17 => self.integer(end, radix),
+            7 => Err(self.error(LexErrorKind::UnterminatedString, self.curr, end)),
+            8 => {
+                self.curr = end;
+                Ok(Some(Token::BitwiseAnd))
             }
-            6 => {
-                let attr = self.chars[self.curr..self.chars.len()]
-                    .into_iter()
-                    .collect::<String>();
-                let val: f32 = attr.parse().expect("Failed to convert to float");
-                self.curr = self.chars.len();
-                Ok(Some(Token::Float(val)))
+            9 => {
+                self.curr = end;
+                Ok(Some(Token::BitwiseOr))
             }
-            7 => {
-                let attr = self.chars[self.curr..self.chars.len()]
-                    .into_iter()
-                    .collect::<String>();
-                self.curr = self.chars.len();
-                Ok(Some(Token::StringLiteral(attr)))
+            11 => {
+                self.record_comment(end);
+
+                self.curr = end;
+                Ok(None)
+            }
+            12 | 16 => Err(self.error(LexErrorKind::UnterminatedCharLiteral, self.curr, end)),
+            15 => Err(self.error(LexErrorKind::UnterminatedComment, self.curr, end)),
+            _ => {
+                self.token_start = end;
+                self.curr = end;
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex(source: &str) -> Result<Vec<Token>, LexError> {
+        let mut lexer = Lexer::from_source(source);
+        let mut tokens = vec![];
+        while let Some(token) = lexer.next_token()? {
+            tokens.push(token);
+        }
+
+        Ok(tokens)
+    }
+
+    fn id(name: &str) -> Token {
+        Token::ID(name.to_string())
+    }
+
+    #[test]
+    fn line_comments_end_at_the_newline() {
+        assert_eq!(lex("a // b\nc"), Ok(vec![id("a"), id("c")]));
+        assert_eq!(lex("a // b"), Ok(vec![id("a")]));
+    }
+
+    #[test]
+    fn block_comments_nest() {
+        assert_eq!(lex("a /* b /* c */ d */ e"), Ok(vec![id("a"), id("e")]));
+        assert_eq!(lex("a /* b\nc */ d"), Ok(vec![id("a"), id("d")]));
+    }
+
+    #[test]
+    fn doc_comments_are_recorded_with_their_line() {
+        let mut lexer =
+            Lexer::from_source("/// first\nnode\n  /// second\n//// not a doc\n// nor this");
+        while lexer.next_token().unwrap().is_some() {}
+
+        assert_eq!(
+            lexer.doc_comments(),
+            [(1, "first".to_string()), (3, "second".to_string())]
+        );
+    }
+
+    fn error(source: &str) -> (LexErrorKind, usize, usize, usize, usize) {
+        let error = lex(source).unwrap_err();
+        let Span {
+            start,
+            end,
+            line,
+            column,
+        } = error.span;
+
+        (error.kind, start, end, line, column)
+    }
+
+    #[test]
+    fn unterminated_comments_and_strings_are_errors() {
+        use LexErrorKind::*;
+
+        assert_eq!(error("a /* b /* c */"), (UnterminatedComment, 2, 14, 1, 3));
+        assert_eq!(error("x\n\"abc\nd\""), (UnterminatedString, 2, 6, 2, 1));
+        assert_eq!(error("\"abc"), (UnterminatedString, 0, 4, 1, 1));
+    }
+
+    #[test]
+    fn every_lexical_error_has_a_span() {
+        use LexErrorKind::*;
+
+        assert_eq!(error("a $ b"), (UnknownCharacter('$'), 2, 3, 1, 3));
+        assert_eq!(
+            error("x = 2147483648;"),
+            (IntegerOverflow("2147483648".to_string()), 4, 14, 1, 5)
+        );
+        assert_eq!(
+            error("  1.2.3"),
+            (MalformedFloat("1.2".to_string()), 2, 5, 1, 3)
+        );
+        assert_eq!(error("''"), (EmptyCharLiteral, 0, 2, 1, 1));
+        assert_eq!(error("'ab'"), (UnterminatedCharLiteral, 0, 2, 1, 1));
+        assert_eq!(error("'\\q'"), (InvalidEscape('q'), 1, 3, 1, 2));
+    }
+
+    #[test]
+    fn errors_are_printed_with_their_position() {
+        let error = lex("\n  @").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "2:3: lexical error: unknown character `@`"
+        );
+    }
+
+    fn lex_error(source: &str) -> LexErrorKind {
+        lex(source).expect_err("expected a lexical error").kind
+    }
+
+    #[test]
+    fn decimal_integers() {
+        assert_eq!(
+            lex("0 42 2147483647").unwrap(),
+            vec![
+                Token::Integer(0),
+                Token::Integer(42),
+                Token::Integer(i32::MAX),
+            ]
+        );
+    }
+
+    #[test]
+    fn hex_integers() {
+        assert_eq!(
+            lex("0xFF 0x7fffffff").unwrap(),
+            vec![Token::Integer(255), Token::Integer(i32::MAX)]
+        );
+    }
+
+    #[test]
+    fn hex_integers_use_all_32_bits() {
+        assert_eq!(
+            lex("0xFFFFFFFF 0x8000_0000").unwrap(),
+            vec![Token::Integer(-1), Token::Integer(i32::MIN)]
+        );
+    }
+
+    #[test]
+    fn binary_integers() {
+        assert_eq!(
+            lex("0b1010_0000 0b1").unwrap(),
+            vec![Token::Integer(160), Token::Integer(1)]
+        );
+    }
+
+    #[test]
+    fn octal_integers() {
+        assert_eq!(
+            lex("0o755 0o0").unwrap(),
+            vec![Token::Integer(493), Token::Integer(0)]
+        );
+    }
+
+    #[test]
+    fn digit_separators() {
+        assert_eq!(
+            lex("1_000_000 2.5_5").unwrap(),
+            vec![Token::Integer(1_000_000), Token::Float(2.55)]
+        );
+    }
+
+    #[test]
+    fn separators_do_not_touch_the_point() {
+        assert_eq!(
+            lex_error("2_.5"),
+            LexErrorKind::MalformedFloat("2_.5".to_string())
+        );
+        assert_eq!(
+            lex_error("2._5"),
+            LexErrorKind::MalformedFloat("2._5".to_string())
+        );
+    }
+
+    #[test]
+    fn float_exponents() {
+        assert_eq!(
+            lex("1.5e-3 2E+2 1e3 6.02e23").unwrap(),
+            vec![
+                Token::Float(1.5e-3),
+                Token::Float(2e2),
+                Token::Float(1e3),
+                Token::Float(6.02e23),
+            ]
+        );
+    }
+
+    #[test]
+    fn leading_dot_floats() {
+        assert_eq!(
+            lex(".5 x.y").unwrap(),
+            vec![
+                Token::Float(0.5),
+                Token::ID("x".to_string()),
+                Token::Dot,
+                Token::ID("y".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn literals_followed_by_symbols() {
+        assert_eq!(
+            lex("0xF;1.5)").unwrap(),
+            vec![
+                Token::Integer(15),
+                Token::Semicolon,
+                Token::Float(1.5),
+                Token::RightParen,
+            ]
+        );
+    }
+
+    #[test]
+    fn suffixed_numbers() {
+        let sized = |text: &str| Token::SizedNumber(text.to_string());
+
+        assert_eq!(
+            lex("255u8 0xFF_FFi64 1_000f64 2.5e3f32 4000000000u32").unwrap(),
+            vec![
+                sized("255u8"),
+                sized("0xFFFFi64"),
+                sized("1000f64"),
+                sized("2.5e3f32"),
+                sized("4000000000u32"),
+            ]
+        );
+
+        assert_eq!(
+            lex_error("1.5u8"),
+            LexErrorKind::MalformedFloat("1.5u8".to_string())
+        );
+        assert_eq!(
+            lex_error("0b1f64"),
+            LexErrorKind::MalformedInteger("0b1f64".to_string())
+        );
+        assert_eq!(
+            lex_error("1u128"),
+            LexErrorKind::MalformedInteger("1u128".to_string())
+        );
+    }
+
+    #[test]
+    fn decimal_overflow() {
+        assert_eq!(
+            lex_error("2147483648"),
+            LexErrorKind::IntegerOverflow("2147483648".to_string())
+        );
+    }
+
+    #[test]
+    fn minimum_int_after_minus() {
+        assert_eq!(
+            lex("-2147483648 - -2147483648").unwrap(),
+            vec![
+                Token::Sub,
+                Token::Integer(i32::MIN),
+                Token::Sub,
+                Token::Sub,
+                Token::Integer(i32::MIN),
+            ]
+        );
+        assert_eq!(
+            lex_error("x 2147483648"),
+            LexErrorKind::IntegerOverflow("2147483648".to_string())
+        );
+    }
+
+    #[test]
+    fn radix_overflow() {
+        assert_eq!(
+            lex_error("0x1_0000_0000"),
+            LexErrorKind::IntegerOverflow("0x1_0000_0000".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_radix_digits() {
+        assert_eq!(
+            lex_error("0b102"),
+            LexErrorKind::MalformedInteger("0b102".to_string())
+        );
+        assert_eq!(
+            lex_error("0xFG"),
+            LexErrorKind::MalformedInteger("0xFG".to_string())
+        );
+        assert_eq!(
+            lex_error("0x"),
+            LexErrorKind::MalformedInteger("0x".to_string())
+        );
+    }
+
+    #[test]
+    fn malformed_floats() {
+        assert_eq!(
+            lex_error("1.;"),
+            LexErrorKind::MalformedFloat("1.".to_string())
+        );
+        assert_eq!(
+            lex_error("1e;"),
+            LexErrorKind::MalformedFloat("1e".to_string())
+        );
+        assert_eq!(
+            lex_error("1.2.3"),
+            LexErrorKind::MalformedFloat("1.2".to_string())
+        );
+    }
+
+    #[test]
+    fn float_overflow() {
+        assert_eq!(
+            lex_error("1e39"),
+            LexErrorKind::FloatOverflow("1e39".to_string())
+        );
+    }
+
+    #[test]
+    fn error_spans() {
+        let error = lex("var x: int =\n    0b2;").unwrap_err();
+
+        assert_eq!(error.span.line, 2);
+        assert_eq!(error.span.column, 5);
+    }
+
+    #[test]
+    fn token_spans_follow_lines_and_lookahead() {
+        let mut lexer = Lexer::from_source("a\n  b /* c\n */ d\ne");
+        let mut positions = vec![];
+        while let Some(token) = lexer.next_token().unwrap() {
+            if token == Token::ID("d".to_string()) {
+                lexer.lookahead(|lexer| lexer.next_token()).unwrap();
             }
-            _ => Ok(None),
+
+            let span = lexer.token_span();
+            positions.push((span.line, span.column));
         }
+
+        assert_eq!(positions, [(1, 1), (2, 3), (3, 5), (4, 1)]);
+        assert_eq!(lexer.comments()[0].0.line, 2);
     }
 }
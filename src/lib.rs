@@ -0,0 +1,405 @@
+//! The Karma compiler as a library.
+//!
+//! `compile_str`, `compile_file` and `compile_project` run the whole pipeline and return a
+//! [`Compilation`] holding the generated bytecode and node graph in memory, or the diagnostics
+//! that stopped compilation. [`vm`] runs and disassembles the bytecode.
+
+pub mod diagnostic;
+pub mod diagram;
+mod fixed;
+pub mod format;
+mod geometry;
+pub mod lexer;
+pub mod lsp;
+pub mod parser;
+pub mod project;
+pub mod repl;
+mod sized;
+pub mod source;
+pub mod testing;
+mod units;
+pub mod vm;
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub use diagnostic::{Diagnostic, Stage};
+pub use project::Manifest;
+
+use lexer::{Lexer, Span};
+use parser::{AbstractSyntaxTree, ParseTree, Parser, SyntaxTreeNode};
+use project::MANIFEST_FILE;
+use source::{SemanticError, Source};
+
+/// A place in the sources: the file, when there is one, and where in it.
+pub type Location = (Option<PathBuf>, Option<Span>);
+
+#[derive(Debug, Default)]
+pub struct Compilation {
+    pub diagnostics: Vec<Diagnostic>,
+    /// The bytecode of each node, keyed by node name.
+    pub bytecode: BTreeMap<String, Vec<u8>>,
+    /// The nodes each node depends on, keyed by node name.
+    pub graph: BTreeMap<String, Vec<String>>,
+    /// The nodes in the order they should be started, dependencies first.
+    pub order: Vec<String>,
+    /// The name and type of each node's exported variables, keyed by node name.
+    pub exports: BTreeMap<String, Vec<(String, String)>>,
+    /// The checked signatures and locals of each node's functions, keyed by node name.
+    pub ir: BTreeMap<String, String>,
+    /// The syntax tree of the whole program, when it parsed.
+    pub ast: Option<AbstractSyntaxTree>,
+    /// The parse tree of each source that parsed, with its file when it has one.
+    pub parse_trees: Vec<(Option<PathBuf>, ParseTree)>,
+    /// The bytecode of each test function, keyed by node and then by test name.
+    pub tests: BTreeMap<String, BTreeMap<String, Vec<u8>>>,
+    /// Where each assertion is written, in the order their instructions number them.
+    pub assertions: Vec<Location>,
+    /// Where the name of each test function is written, keyed by node and then by test name.
+    pub test_locations: BTreeMap<String, BTreeMap<String, Location>>,
+}
+
+impl Compilation {
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// Writes `<node>.k` for every node and `graph.json` into `dir`, creating it if needed.
+    pub fn write_to(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        for (node_id, bytes) in &self.bytecode {
+            fs::write(dir.join(format!("{node_id}.k")), bytes)?;
+        }
+
+        fs::write(dir.join("graph.json"), self.graph_json())
+    }
+
+    /// The node graph as JSON: the dependencies of each node under `edges` and the startup
+    /// order under `order`.
+    pub fn graph_json(&self) -> String {
+        serde_json::json!({ "edges": self.graph, "order": self.order }).to_string()
+    }
+}
+
+pub fn compile_str(source: &str) -> Compilation {
+    compile_sources(vec![(None, source.to_string())], &[], None)
+}
+
+pub fn compile_file(path: impl AsRef<Path>) -> io::Result<Compilation> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+
+    Ok(compile_sources(
+        vec![(Some(path.to_path_buf()), source)],
+        &[],
+        None,
+    ))
+}
+
+/// Compiles a directory as a single program, so nodes in one file can depend on nodes declared
+/// in another. When the directory has a `karma.toml` the sources it lists are compiled and its
+/// entry nodes are checked, otherwise every `.krm` file under the directory is.
+pub fn compile_project(dir: impl AsRef<Path>) -> io::Result<Compilation> {
+    let manifest = Manifest::load(dir.as_ref())?;
+
+    let (files, entry) = match &manifest {
+        Some(manifest) => (manifest.source_files()?, manifest.entry.clone()),
+        None => {
+            let mut files = vec![];
+            project::find_sources(dir.as_ref(), &mut files)?;
+            files.sort();
+            (files, vec![])
+        }
+    };
+
+    let mut sources = vec![];
+    for file in files {
+        let source = fs::read_to_string(&file)?;
+        sources.push((Some(file), source));
+    }
+
+    let manifest_file = dir.as_ref().join(MANIFEST_FILE);
+    Ok(compile_sources(sources, &entry, Some(&manifest_file)))
+}
+
+/// Parses each source, checks the nodes they declare against each other and compiles them as
+/// one program. Every name in `entry` must be a declared node, and is reported against
+/// `manifest_file` when it is not.
+fn compile_sources(
+    sources: Vec<(Option<PathBuf>, String)>,
+    entry: &[String],
+    manifest_file: Option<&Path>,
+) -> Compilation {
+    check_sources(sources, entry, manifest_file).0
+}
+
+/// Compiles the sources like [`compile_sources`], also giving back the checked program when it
+/// checks.
+fn check_sources(
+    sources: Vec<(Option<PathBuf>, String)>,
+    entry: &[String],
+    manifest_file: Option<&Path>,
+) -> (Compilation, Option<Source>) {
+    let mut program = AbstractSyntaxTree::new();
+    let mut parse_trees = vec![];
+    let mut files = vec![];
+    let mut assertions = vec![];
+    let mut diagnostics = vec![];
+
+    for (file, source) in sources {
+        match parse(&source) {
+            Ok((parse_tree, ast)) => {
+                let mut spans = vec![];
+                Source::assertion_spans(&ast, &mut spans);
+                assertions.extend(spans.into_iter().map(|span| (file.clone(), span)));
+
+                program = append_nodes(program, ast.clone());
+                files.push((file.clone(), ast));
+                parse_trees.push((file, parse_tree));
+            }
+            Err(diagnostic) => diagnostics.push(match &file {
+                Some(file) => diagnostic.with_file(file),
+                None => diagnostic,
+            }),
+        }
+    }
+
+    if !diagnostics.is_empty() {
+        let compilation = Compilation {
+            diagnostics,
+            parse_trees,
+            ..Default::default()
+        };
+        return (compilation, None);
+    }
+
+    diagnostics.extend(project::check_nodes(&files));
+
+    let mut items = vec![];
+    declared_items(&program, &mut items);
+    for node in entry {
+        if !items
+            .iter()
+            .any(|(name, item)| name == node && item.node == SyntaxTreeNode::DeclareNode)
+        {
+            let diagnostic = Diagnostic::semantic(25)
+                .with_message(format!("entry node `{node}` is not declared"));
+            diagnostics.push(match manifest_file {
+                Some(file) => diagnostic.with_file(file),
+                None => diagnostic,
+            });
+        }
+    }
+
+    if !diagnostics.is_empty() {
+        let compilation = Compilation {
+            diagnostics,
+            ast: Some(program),
+            parse_trees,
+            ..Default::default()
+        };
+        return (compilation, None);
+    }
+
+    let (compilation, source) = check_and_generate(program, &files);
+    let test_locations = compilation
+        .tests
+        .iter()
+        .map(|(node, tests)| {
+            let locations = tests
+                .keys()
+                .map(|test| (test.clone(), test_location(&files, node, test)))
+                .collect();
+            (node.clone(), locations)
+        })
+        .collect();
+
+    let compilation = Compilation {
+        parse_trees,
+        assertions,
+        test_locations,
+        ..compilation
+    };
+    (compilation, source)
+}
+
+fn parse(source: &str) -> Result<(ParseTree, AbstractSyntaxTree), Diagnostic> {
+    let mut parser = Parser::new(Lexer::from_source(source));
+    parser.parse()?;
+    parser.generate_ast();
+
+    Ok((std::mem::take(&mut parser.parse_tree), parser.ast))
+}
+
+/// Checks the program `ast` and generates its bytecode, giving back the checked program when
+/// it checks. `files` pairs the syntax tree of each source with its file, if it has one, to
+/// tell where a semantic error is.
+fn check_and_generate(
+    ast: AbstractSyntaxTree,
+    files: &[(Option<PathBuf>, AbstractSyntaxTree)],
+) -> (Compilation, Option<Source>) {
+    let source = match Source::new(ast.clone()) {
+        Ok(source) => source,
+        Err(error) => {
+            let compilation = Compilation {
+                diagnostics: vec![locate(&error, files)],
+                ast: Some(ast),
+                ..Default::default()
+            };
+            return (compilation, None);
+        }
+    };
+
+    let mut tests = BTreeMap::new();
+    for (node_id, test_ids) in source.tests() {
+        let node_tests: &mut BTreeMap<_, _> = tests.entry(node_id.clone()).or_default();
+        for test_id in test_ids {
+            if let Some(bytes) = source.generate_test_bytecode(node_id, test_id) {
+                node_tests.insert(test_id.clone(), bytes);
+            }
+        }
+    }
+
+    let compilation = Compilation {
+        diagnostics: vec![],
+        bytecode: source.generate_bytecode().into_iter().collect(),
+        graph: source.graph().clone().into_iter().collect(),
+        order: source.startup_order().to_vec(),
+        exports: source.exports().into_iter().collect(),
+        ir: source.ir().into_iter().collect(),
+        ast: Some(ast),
+        tests,
+        ..Default::default()
+    };
+    (compilation, Some(source))
+}
+
+/// Chains the node sequence `rest` onto the end of the node sequence `program`.
+fn append_nodes(mut program: AbstractSyntaxTree, rest: AbstractSyntaxTree) -> AbstractSyntaxTree {
+    if program.node != SyntaxTreeNode::NodeSeq {
+        return rest;
+    }
+
+    let tail = program
+        .children
+        .pop()
+        .expect("node sequence without a tail");
+    program.children.push(append_nodes(tail, rest));
+
+    program
+}
+
+/// The diagnostic of `error`, where the checker placed it, or else at the name of the function
+/// it was found in, or of the node or module. Of the declarations an error can be in, the last
+/// one is searched first, which is the one in error when a name is declared again.
+fn locate(error: &SemanticError, files: &[(Option<PathBuf>, AbstractSyntaxTree)]) -> Diagnostic {
+    let diagnostic = Diagnostic::semantic(error.code);
+
+    let mut items = files.iter().rev().flat_map(|(file, ast)| {
+        let mut items = vec![];
+        declared_items(ast, &mut items);
+        items
+            .into_iter()
+            .rev()
+            .filter(|(name, _)| error.item.as_ref().is_none_or(|item| item == name))
+            .map(move |(_, item)| (file, item))
+    });
+
+    let found = match (error.span, &error.function) {
+        (Some(span), _) => items
+            .find(|(_, item)| contains_span(item, span))
+            .map(|(file, _)| (file, span)),
+        (None, Some(function)) => items.find_map(|(file, item)| {
+            let mut spans = vec![];
+            function_spans(item, function, &mut spans);
+            spans.pop().flatten().map(|span| (file, span))
+        }),
+        (None, None) if error.item.is_some() => items
+            .next()
+            .and_then(|(file, item)| item_name(item).span.map(|span| (file, span))),
+        (None, None) => None,
+    };
+
+    match found {
+        Some((Some(file), span)) => diagnostic.with_span(span).with_file(file),
+        Some((None, span)) => diagnostic.with_span(span),
+        None => diagnostic,
+    }
+}
+
+/// Lists the nodes and modules declared in the node sequence `ast` by name, in order.
+fn declared_items<'a>(
+    ast: &'a AbstractSyntaxTree,
+    items: &mut Vec<(&'a str, &'a AbstractSyntaxTree)>,
+) {
+    match ast.node {
+        SyntaxTreeNode::NodeSeq => {
+            for child in &ast.children {
+                declared_items(child, items);
+            }
+        }
+        SyntaxTreeNode::DeclareNode | SyntaxTreeNode::DeclareModule => {
+            if let SyntaxTreeNode::Identifier(name) = &item_name(ast).node {
+                items.push((name, ast));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The file declaring the test function `test` of the node `node` and where its name is
+/// written there. `files` pairs the syntax tree of each source with its file, if it has one.
+fn test_location(
+    files: &[(Option<PathBuf>, AbstractSyntaxTree)],
+    node: &str,
+    test: &str,
+) -> Location {
+    for (file, ast) in files {
+        let mut items = vec![];
+        declared_items(ast, &mut items);
+
+        for (name, item) in items {
+            if name == node && item.node == SyntaxTreeNode::DeclareNode {
+                let mut spans = vec![];
+                function_spans(item, test, &mut spans);
+                if let Some(span) = spans.pop() {
+                    return (file.clone(), span);
+                }
+            }
+        }
+    }
+
+    (None, None)
+}
+
+/// The name of the node or module `item`.
+fn item_name(item: &AbstractSyntaxTree) -> &AbstractSyntaxTree {
+    match item.node {
+        SyntaxTreeNode::DeclareNode => &item.children[0].children[0],
+        _ => &item.children[0],
+    }
+}
+
+/// Adds where the name of each function called `function` in `ast` is written to `spans`.
+fn function_spans(ast: &AbstractSyntaxTree, function: &str, spans: &mut Vec<Option<Span>>) {
+    if ast.node == SyntaxTreeNode::DeclareFunc {
+        if ast.children[0].node == SyntaxTreeNode::Identifier(function.to_string()) {
+            spans.push(ast.children[0].span);
+        }
+        return;
+    }
+
+    for child in &ast.children {
+        function_spans(child, function, spans);
+    }
+}
+
+/// Whether a node of `ast` is written at `span`.
+fn contains_span(ast: &AbstractSyntaxTree, span: Span) -> bool {
+    ast.span == Some(span) || ast.children.iter().any(|child| contains_span(child, span))
+}
@@ -0,0 +1,1253 @@
+//! A language server for `.krm` files, speaking the Language Server Protocol over stdio.
+//!
+//! Diagnostics are published when a document is opened or saved. Definitions, hovers,
+//! completions and document symbols come from an [`Outline`] of the document's syntax tree, and
+//! a document that does not parse keeps the outline it had when it last did. Hovers show the
+//! types the checker inferred when the program last checked.
+
+use std::{
+    collections::{HashMap, LinkedList},
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    lexer::{Lexer, Span, Token},
+    parser::{AbstractSyntaxTree, SyntaxTreeNode},
+    project::{self, MANIFEST_FILE},
+    source::{ScopeElem, Source},
+    Diagnostic, Manifest,
+};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+const BUILTINS: [&str; 27] = [
+    "print_int",
+    "print_float",
+    "print_bool",
+    "print_char",
+    "print_fixed",
+    "println",
+    "assert",
+    "assert_eq",
+    "vec2",
+    "vec3",
+    "vec4",
+    "mat2",
+    "mat3",
+    "mat4",
+    "dot",
+    "cross",
+    "norm",
+    "transpose",
+    "inverse",
+    "quat",
+    "transform3",
+    "axis_angle",
+    "euler",
+    "slerp",
+    "normalize",
+    "rotation",
+    "translation",
+];
+
+const KEYWORDS: [&str; 22] = [
+    "node", "module", "pub", "use", "export", "var", "const", "fn", "test", "while", "if", "else",
+    "return", "struct", "as", "true", "false", "int", "float", "bool", "char", "fixed",
+];
+
+/// The number types that are names rather than keywords.
+const SIZED_TYPES: [&str; 10] = [
+    "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64",
+];
+
+/// Serves requests read from `input` until the client sends `exit` or closes the stream.
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut server = Server::default();
+
+    while let Some(message) = read_message(&mut input)? {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+
+        if method == "exit" {
+            break;
+        }
+
+        match message.get("id") {
+            // responses to requests the server never sends
+            Some(_) if method.is_empty() => {}
+            Some(id) => {
+                let response = match server.request(method, params) {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err((code, message)) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": code, "message": message },
+                    }),
+                };
+                write_message(&mut output, &response)?;
+            }
+            None => {
+                for (method, params) in server.notify(method, params) {
+                    let notification =
+                        json!({ "jsonrpc": "2.0", "method": method, "params": params });
+                    write_message(&mut output, &notification)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message without a Content-Length header",
+        ));
+    };
+
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
+
+#[derive(Default)]
+struct Server {
+    /// The text of each open document, keyed by URI.
+    documents: HashMap<String, String>,
+    /// The last outline of each document that parsed, with the text it was made from, keyed
+    /// by URI.
+    outlines: HashMap<String, (String, Outline)>,
+    /// The program each document was last checked in, when it checked, keyed by URI. A program
+    /// is checked when one of its documents is opened or saved.
+    checked: HashMap<String, Rc<Source>>,
+}
+
+impl Server {
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": {
+                        "openClose": true,
+                        "change": 1,
+                        "save": { "includeText": false },
+                    },
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": { "triggerCharacters": [":"] },
+                    "documentSymbolProvider": true,
+                },
+                "serverInfo": { "name": "karma", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => Ok(Value::Null),
+            "textDocument/definition" => {
+                let (uri, offset) = self.position(params)?;
+                let project = self.project(&uri);
+
+                Ok(match project.resolve(&uri, offset) {
+                    Some(target) => {
+                        let (file, span) = project.definition(target);
+                        json!({ "uri": file.uri, "range": file.index.range(span) })
+                    }
+                    None => Value::Null,
+                })
+            }
+            "textDocument/hover" => {
+                let (uri, offset) = self.position(params)?;
+                let project = self.project(&uri);
+                let checked = self.checked.get(&uri).map(Rc::as_ref);
+
+                Ok(match project.resolve(&uri, offset) {
+                    Some(target) => json!({
+                        "contents": {
+                            "kind": "markdown",
+                            "value": format!("```karma\n{}\n```", project.describe(target, checked)),
+                        },
+                    }),
+                    None => Value::Null,
+                })
+            }
+            "textDocument/completion" => {
+                let (uri, offset) = self.position(params)?;
+                Ok(Value::Array(self.project(&uri).complete(&uri, offset)))
+            }
+            "textDocument/documentSymbol" => {
+                let uri = document_uri(params)?;
+                Ok(Value::Array(self.file(&uri).symbols()))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unsupported method `{method}`"))),
+        }
+    }
+
+    /// Handles a notification, returning the notifications to send back.
+    fn notify(&mut self, method: &str, params: &Value) -> Vec<(&'static str, Value)> {
+        let Ok(uri) = document_uri(params) else {
+            return vec![];
+        };
+
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+                self.diagnostics(&uri)
+            }
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes
+                    .and_then(|c| c.last())
+                    .and_then(|c| c["text"].as_str())
+                {
+                    self.documents.insert(uri, text.to_string());
+                }
+                vec![]
+            }
+            "textDocument/didSave" => {
+                if let Some(text) = params["text"].as_str() {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+                self.diagnostics(&uri)
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                self.outlines.remove(&uri);
+                self.checked.remove(&uri);
+                vec![(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                )]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// The text of a document, from the editor when it is open and from disk otherwise.
+    fn text(&self, uri: &str) -> String {
+        match self.documents.get(uri) {
+            Some(text) => text.clone(),
+            None => uri_to_path(uri)
+                .and_then(|path| fs::read_to_string(path).ok())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn position(&self, params: &Value) -> Result<(String, usize), (i64, String)> {
+        let uri = document_uri(params)?;
+        let position = &params["position"];
+        let (Some(line), Some(character)) =
+            (position["line"].as_u64(), position["character"].as_u64())
+        else {
+            return Err((INVALID_PARAMS, "missing position".to_string()));
+        };
+
+        let index = LineIndex::new(&self.text(&uri));
+        Ok((uri, index.offset(line as usize, character as usize)))
+    }
+
+    /// The document with its outline. A document that does not parse keeps the outline it had
+    /// when it last did.
+    fn file(&mut self, uri: &str) -> File {
+        let text = self.text(uri);
+        let outline = match self.outlines.get(uri) {
+            Some((outlined, outline)) if *outlined == text => outline.clone(),
+            cached => match Outline::new(&text) {
+                Some(outline) => {
+                    self.outlines
+                        .insert(uri.to_string(), (text.clone(), outline.clone()));
+                    outline
+                }
+                None => cached
+                    .map(|(_, outline)| outline.clone())
+                    .unwrap_or_default(),
+            },
+        };
+
+        File::new(uri.to_string(), text, outline)
+    }
+
+    /// The files compiled together with the document: the sources of the project it belongs
+    /// to, or the document alone when it is not part of one.
+    fn project(&mut self, uri: &str) -> Project {
+        let mut files = vec![];
+        let mut manifest_dir = None;
+
+        if let Some(dir) = uri_to_path(uri).as_deref().and_then(project_dir) {
+            for path in project::project_sources(&dir).unwrap_or_default() {
+                files.push(self.file(&path_to_uri(&path)));
+            }
+            manifest_dir = Some(dir);
+        }
+
+        if !files.iter().any(|file| file.uri == uri) {
+            files.push(self.file(uri));
+        }
+
+        Project {
+            files,
+            manifest_dir,
+        }
+    }
+
+    /// Compiles the document's project and publishes the diagnostics of each of its files.
+    fn diagnostics(&mut self, uri: &str) -> Vec<(&'static str, Value)> {
+        let project = self.project(uri);
+
+        let (sources, entry, manifest_file) = match &project.manifest_dir {
+            Some(dir) => {
+                let entry = Manifest::load(dir)
+                    .ok()
+                    .flatten()
+                    .map(|manifest| manifest.entry)
+                    .unwrap_or_default();
+                let sources = project
+                    .files
+                    .iter()
+                    .map(|file| (uri_to_path(&file.uri), file.text.clone()))
+                    .collect();
+                (sources, entry, Some(dir.join(MANIFEST_FILE)))
+            }
+            None => (vec![(None, self.text(uri))], vec![], None),
+        };
+
+        let (compilation, source) = crate::check_sources(sources, &entry, manifest_file.as_deref());
+        if let Some(source) = source.map(Rc::new) {
+            for file in &project.files {
+                self.checked.insert(file.uri.clone(), Rc::clone(&source));
+            }
+        }
+
+        let mut published: Vec<(String, Vec<Value>)> = project
+            .files
+            .iter()
+            .map(|file| (file.uri.clone(), vec![]))
+            .collect();
+        for diagnostic in &compilation.diagnostics {
+            // diagnostics without a file belong to the document that was saved
+            let file_uri = match &diagnostic.file {
+                Some(path) if manifest_file.as_deref() != Some(path.as_path()) => path_to_uri(path),
+                _ => uri.to_string(),
+            };
+
+            let Some(file) = project.files.iter().find(|file| file.uri == file_uri) else {
+                continue;
+            };
+            if let Some((_, diagnostics)) = published.iter_mut().find(|(u, _)| *u == file_uri) {
+                diagnostics.push(to_lsp_diagnostic(diagnostic, file));
+            }
+        }
+
+        published
+            .into_iter()
+            .map(|(uri, diagnostics)| {
+                (
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": diagnostics }),
+                )
+            })
+            .collect()
+    }
+}
+
+fn document_uri(params: &Value) -> Result<String, (i64, String)> {
+    params["textDocument"]["uri"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or((INVALID_PARAMS, "missing textDocument.uri".to_string()))
+}
+
+/// The diagnostic as the protocol publishes it. One that is not about a place in `file`, like an
+/// entry node missing from the manifest, is shown at the first node or module of the file.
+fn to_lsp_diagnostic(diagnostic: &Diagnostic, file: &File) -> Value {
+    let span = diagnostic.span.or_else(|| {
+        file.outline
+            .items
+            .iter()
+            .find(|item| item.container.is_none())
+            .map(|item| item.name_span)
+    });
+    let range = match span {
+        Some(span) => file.index.range(span),
+        None => json!({
+            "start": { "line": 0, "character": 0 },
+            "end": { "line": 0, "character": 0 },
+        }),
+    };
+
+    let mut lsp_diagnostic = json!({
+        "range": range,
+        "severity": 1,
+        "source": "karma",
+        "message": format!("{} error: {}", diagnostic.stage, diagnostic.message),
+    });
+    if let Some(code) = diagnostic.code {
+        lsp_diagnostic["code"] = json!(code);
+    }
+
+    lsp_diagnostic
+}
+
+/// The nearest directory above `path` holding a manifest.
+fn project_dir(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .find(|dir| dir.join(MANIFEST_FILE).is_file())
+        .map(Path::to_path_buf)
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let bytes = path.as_bytes();
+    let mut decoded = vec![];
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut uri = String::from("file://");
+
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+
+    uri
+}
+
+/// Converts between character offsets and LSP positions. Characters are counted as `char`s,
+/// which matches the protocol's UTF-16 columns outside of the astral planes.
+struct LineIndex {
+    starts: Vec<usize>,
+    len: usize,
+}
+
+impl LineIndex {
+    fn new(text: &str) -> Self {
+        let mut starts = vec![0];
+        let mut len = 0;
+
+        for (i, c) in text.chars().enumerate() {
+            if c == '\n' {
+                starts.push(i + 1);
+            }
+            len = i + 1;
+        }
+
+        Self { starts, len }
+    }
+
+    fn offset(&self, line: usize, character: usize) -> usize {
+        let Some(&start) = self.starts.get(line) else {
+            return self.len;
+        };
+        let end = self.starts.get(line + 1).map_or(self.len, |next| next - 1);
+
+        (start + character).min(end)
+    }
+
+    fn position(&self, offset: usize) -> Value {
+        let line = self.starts.partition_point(|start| *start <= offset) - 1;
+        json!({ "line": line, "character": offset - self.starts[line] })
+    }
+
+    fn range(&self, span: Span) -> Value {
+        json!({ "start": self.position(span.start), "end": self.position(span.end) })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ItemKind {
+    Node,
+    Module,
+    Function,
+    Struct,
+    Export,
+}
+
+/// A node or module, or something declared in one.
+#[derive(Clone)]
+struct Item {
+    kind: ItemKind,
+    name: String,
+    name_span: Span,
+    /// From the first keyword to the closing brace or semicolon.
+    span: Span,
+    /// The node or module the item is declared in.
+    container: Option<usize>,
+    /// The declaration as written, without its body.
+    detail: String,
+    public: bool,
+    /// The nodes a node depends on.
+    dependencies: Vec<(String, Span)>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum LocalKind {
+    Param,
+    Var,
+    Const,
+}
+
+/// A parameter or variable of a function.
+#[derive(Clone)]
+struct Local {
+    kind: LocalKind,
+    name: String,
+    name_span: Span,
+    declared_type: String,
+    function: usize,
+    /// Where the local can be used: the body of its function for a parameter, and from its name
+    /// to the end of the block declaring it for a variable.
+    scope: Span,
+}
+
+impl Local {
+    fn visible_at(&self, offset: usize) -> bool {
+        self.scope.start <= offset && offset <= self.scope.end
+    }
+}
+
+/// A `use module::item;` in a node or module.
+#[derive(Clone)]
+struct Import {
+    module: String,
+    name: String,
+    container: Option<usize>,
+}
+
+/// The declarations in a document, found from its syntax tree.
+#[derive(Clone, Default)]
+struct Outline {
+    items: Vec<Item>,
+    locals: Vec<Local>,
+    imports: Vec<Import>,
+}
+
+impl Outline {
+    /// The outline of `text`, or `None` when it does not parse.
+    fn new(text: &str) -> Option<Self> {
+        let (_, ast) = crate::parse(text).ok()?;
+        let chars: Vec<char> = text.chars().collect();
+
+        let mut outline = Self::default();
+        outline.add_containers(&ast, &chars);
+        Some(outline)
+    }
+
+    /// Adds the nodes and modules of the node sequence `ast`, with what they declare.
+    fn add_containers(&mut self, ast: &AbstractSyntaxTree, chars: &[char]) {
+        match &ast.node {
+            SyntaxTreeNode::NodeSeq => {
+                for child in &ast.children {
+                    self.add_containers(child, chars);
+                }
+            }
+            SyntaxTreeNode::DeclareNode | SyntaxTreeNode::DeclareModule => {
+                let (kind, name, dependencies) = match &ast.node {
+                    SyntaxTreeNode::DeclareNode => {
+                        let header = &ast.children[0];
+                        let mut dependencies = vec![];
+                        dependency_names(&header.children[1], &mut dependencies);
+                        (ItemKind::Node, &header.children[0], dependencies)
+                    }
+                    _ => (ItemKind::Module, &ast.children[0], vec![]),
+                };
+                let body = &ast.children[1];
+
+                let container = self.items.len();
+                if let Some(item) = Item::new(kind, name, ast, body.span, None, chars) {
+                    self.items.push(Item {
+                        dependencies,
+                        ..item
+                    });
+                    self.add_items(body, container, chars);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Adds what the statement sequence `ast` of the node or module `container` declares.
+    fn add_items(&mut self, ast: &AbstractSyntaxTree, container: usize, chars: &[char]) {
+        let mut statements = ast;
+        while statements.node == SyntaxTreeNode::TLStmtSeq {
+            let statement = &statements.children[0];
+            let (public, declaration) = match &statement.node {
+                SyntaxTreeNode::Public | SyntaxTreeNode::Test => (
+                    statement.node == SyntaxTreeNode::Public,
+                    &statement.children[0],
+                ),
+                _ => (false, statement),
+            };
+            // a public item starts at `pub`, and a test at its `fn`
+            let span = if public { statement } else { declaration };
+
+            match &declaration.node {
+                SyntaxTreeNode::DeclareFunc => {
+                    let body = &declaration.children[3];
+                    let function = self.items.len();
+                    let item = Item::new(
+                        ItemKind::Function,
+                        &declaration.children[0],
+                        span,
+                        body.span,
+                        Some(container),
+                        chars,
+                    );
+
+                    if let Some(item) = item {
+                        let span = item.span;
+                        self.items.push(Item { public, ..item });
+                        self.add_params(&declaration.children[1], function, span, chars);
+                        self.add_locals(body, function, span.end, chars);
+                    }
+                }
+                SyntaxTreeNode::DeclareStruct => {
+                    let item = Item::new(
+                        ItemKind::Struct,
+                        &declaration.children[0],
+                        span,
+                        None,
+                        Some(container),
+                        chars,
+                    );
+                    self.items.extend(item.map(|item| Item { public, ..item }));
+                }
+                SyntaxTreeNode::Export => {
+                    let definition = &declaration.children[0];
+                    let item = Item::new(
+                        ItemKind::Export,
+                        &definition.children[0],
+                        declaration,
+                        None,
+                        Some(container),
+                        chars,
+                    );
+
+                    // shown without its initial value
+                    self.items.extend(item.map(|item| Item {
+                        detail: match (declaration.span, definition.children[1].span) {
+                            (Some(start), Some(end)) => slice(chars, start.start, end.end),
+                            _ => item.detail.clone(),
+                        },
+                        ..item
+                    }));
+                }
+                SyntaxTreeNode::Use => {
+                    if let (SyntaxTreeNode::Identifier(module), SyntaxTreeNode::Identifier(name)) =
+                        (&declaration.children[0].node, &declaration.children[1].node)
+                    {
+                        self.imports.push(Import {
+                            module: module.clone(),
+                            name: name.clone(),
+                            container: Some(container),
+                        });
+                    }
+                }
+                _ => {}
+            }
+
+            statements = &statements.children[1];
+        }
+    }
+
+    /// Adds the parameters in the list `ast` of the function `function`, which can be used
+    /// anywhere in `scope`.
+    fn add_params(
+        &mut self,
+        ast: &AbstractSyntaxTree,
+        function: usize,
+        scope: Span,
+        chars: &[char],
+    ) {
+        let mut list = ast;
+        while list.node == SyntaxTreeNode::ParamList {
+            let param = &list.children[0];
+            if let Some(local) = Local::new(LocalKind::Param, param, function, scope, chars) {
+                self.locals.push(local);
+            }
+            list = &list.children[1];
+        }
+    }
+
+    /// Adds the variables the statements `ast` of the function `function` declare, in a block
+    /// that ends at `end` unless `ast` is a block of its own.
+    fn add_locals(
+        &mut self,
+        ast: &AbstractSyntaxTree,
+        function: usize,
+        end: usize,
+        chars: &[char],
+    ) {
+        match &ast.node {
+            // the statements are read one after another, so the stack does not grow with the
+            // length of a block
+            SyntaxTreeNode::StmtSeq => {
+                let end = ast.span.map_or(end, |span| span.end);
+                let mut statements = ast;
+                while statements.node == SyntaxTreeNode::StmtSeq {
+                    self.add_locals(&statements.children[0], function, end, chars);
+                    statements = &statements.children[1];
+                }
+            }
+            SyntaxTreeNode::DeclareVar | SyntaxTreeNode::DeclareConst => {
+                let kind = match ast.node {
+                    SyntaxTreeNode::DeclareVar => LocalKind::Var,
+                    _ => LocalKind::Const,
+                };
+                let Some(start) = ast.children[0].span else {
+                    return;
+                };
+                let scope = Span { end, ..start };
+
+                if let Some(local) = Local::new(kind, ast, function, scope, chars) {
+                    self.locals.push(local);
+                }
+            }
+            SyntaxTreeNode::WhileLoop | SyntaxTreeNode::IfStmt => {
+                let end = ast.span.map_or(end, |span| span.end);
+                for body in ast.children.iter().skip(1) {
+                    self.add_locals(body, function, end, chars);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Item {
+    /// The item named by the identifier `name`, written at the span of `declaration` with its
+    /// body at `body`, or `None` when the declaration is not written in the source.
+    fn new(
+        kind: ItemKind,
+        name: &AbstractSyntaxTree,
+        declaration: &AbstractSyntaxTree,
+        body: Option<Span>,
+        container: Option<usize>,
+        chars: &[char],
+    ) -> Option<Self> {
+        let (SyntaxTreeNode::Identifier(id), Some(name_span), Some(span)) =
+            (&name.node, name.span, declaration.span)
+        else {
+            return None;
+        };
+
+        Some(Self {
+            kind,
+            name: id.clone(),
+            name_span,
+            span,
+            container,
+            detail: slice(chars, span.start, body.map_or(span.end, |body| body.start)),
+            public: false,
+            dependencies: vec![],
+        })
+    }
+}
+
+impl Local {
+    /// The local declared by `declaration`, a parameter or a variable whose first two children
+    /// are its name and its type.
+    fn new(
+        kind: LocalKind,
+        declaration: &AbstractSyntaxTree,
+        function: usize,
+        scope: Span,
+        chars: &[char],
+    ) -> Option<Self> {
+        let [name, declared_type, ..] = declaration.children.as_slice() else {
+            return None;
+        };
+        let (SyntaxTreeNode::Identifier(id), Some(name_span)) = (&name.node, name.span) else {
+            return None;
+        };
+
+        Some(Self {
+            kind,
+            name: id.clone(),
+            name_span,
+            declared_type: declared_type
+                .span
+                .map_or(String::new(), |span| slice(chars, span.start, span.end)),
+            function,
+            scope,
+        })
+    }
+}
+
+/// The characters from `start` to `end`, with each run of whitespace made a single space.
+fn slice(chars: &[char], start: usize, end: usize) -> String {
+    let end = end.min(chars.len());
+    let text: String = chars[start.min(end)..end].iter().collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Adds the names in the dependency list `ast` of a node header to `dependencies`.
+fn dependency_names(ast: &AbstractSyntaxTree, dependencies: &mut Vec<(String, Span)>) {
+    match (&ast.node, ast.span) {
+        (SyntaxTreeNode::Identifier(name), Some(span)) => dependencies.push((name.clone(), span)),
+        (SyntaxTreeNode::NodeList, _) => {
+            for child in &ast.children {
+                dependency_names(child, dependencies);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A source file with its outline.
+struct File {
+    uri: String,
+    text: String,
+    index: LineIndex,
+    /// The tokens of the file, up to the first that does not lex, to tell what is at a
+    /// position.
+    tokens: Vec<(Token, Span)>,
+    outline: Outline,
+}
+
+impl File {
+    fn new(uri: String, text: String, outline: Outline) -> Self {
+        let mut lexer = Lexer::from_source(&text);
+        let mut tokens = vec![];
+        while let Ok(Some(token)) = lexer.next_token() {
+            tokens.push((token, lexer.token_span()));
+        }
+
+        Self {
+            index: LineIndex::new(&text),
+            tokens,
+            outline,
+            uri,
+            text,
+        }
+    }
+
+    /// The nodes and modules of the file, with what they declare nested inside them.
+    fn symbols(&self) -> Vec<Value> {
+        let items = &self.outline.items;
+        let symbol = |item: &Item, children: Vec<Value>| {
+            json!({
+                "name": item.name,
+                "detail": item.detail,
+                "kind": match item.kind {
+                    ItemKind::Node => 5,
+                    ItemKind::Module => 2,
+                    ItemKind::Function => 12,
+                    ItemKind::Struct => 23,
+                    ItemKind::Export => 13,
+                },
+                "range": self.index.range(item.span),
+                "selectionRange": self.index.range(item.name_span),
+                "children": children,
+            })
+        };
+
+        items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.container.is_none())
+            .map(|(i, item)| {
+                let children = items
+                    .iter()
+                    .filter(|child| child.container == Some(i))
+                    .map(|child| symbol(child, vec![]))
+                    .collect();
+                symbol(item, children)
+            })
+            .collect()
+    }
+
+    /// The innermost item of one of `kinds` whose span holds `offset`.
+    fn item_at(&self, offset: usize, kinds: &[ItemKind]) -> Option<usize> {
+        self.outline
+            .items
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, item)| {
+                kinds.contains(&item.kind) && item.span.start <= offset && offset <= item.span.end
+            })
+            .map(|(i, _)| i)
+    }
+
+    /// The identifier under `offset`, which may be just past its end.
+    fn identifier_at(&self, offset: usize) -> Option<usize> {
+        self.tokens.iter().position(|(token, span)| {
+            matches!(token, Token::ID(_)) && span.start <= offset && offset <= span.end
+        })
+    }
+
+    /// The names in scope at `offset` inside a function body.
+    fn scope_at(&self, offset: usize) -> LinkedList<ScopeElem> {
+        let mut stack = LinkedList::new();
+        let outline = &self.outline;
+
+        let Some(function) = self.item_at(offset, &[ItemKind::Function]) else {
+            return stack;
+        };
+        let container = outline.items[function].container;
+
+        for item in outline
+            .items
+            .iter()
+            .filter(|item| item.container == container)
+        {
+            if item.kind == ItemKind::Function {
+                stack.push_back(ScopeElem::Func(item.name.clone()));
+            }
+        }
+        for import in outline.imports.iter().filter(|i| i.container == container) {
+            stack.push_back(ScopeElem::Func(import.name.clone()));
+        }
+
+        // a variable is in scope once its declaration is written
+        for local in &outline.locals {
+            if local.function != function || !local.visible_at(offset) {
+                continue;
+            }
+
+            match local.kind {
+                LocalKind::Param => stack.push_back(ScopeElem::Variable(local.name.clone())),
+                LocalKind::Var if local.name_span.end < offset => {
+                    stack.push_back(ScopeElem::Variable(local.name.clone()))
+                }
+                LocalKind::Const if local.name_span.end < offset => {
+                    stack.push_back(ScopeElem::Const(local.name.clone()))
+                }
+                _ => {}
+            }
+        }
+
+        stack
+    }
+}
+
+/// What a name refers to: an item or a local of one of the project's files.
+#[derive(Clone, Copy)]
+enum Target {
+    Item(usize, usize),
+    Local(usize, usize),
+}
+
+struct Project {
+    files: Vec<File>,
+    /// The directory holding the manifest, when the files make up a project.
+    manifest_dir: Option<PathBuf>,
+}
+
+impl Project {
+    fn file(&self, uri: &str) -> Option<usize> {
+        self.files.iter().position(|file| file.uri == uri)
+    }
+
+    /// Resolves the identifier at `offset` in the document `uri`.
+    fn resolve(&self, uri: &str, offset: usize) -> Option<Target> {
+        let f = self.file(uri)?;
+        let file = &self.files[f];
+        let outline = &file.outline;
+
+        let i = file.identifier_at(offset)?;
+        let Token::ID(name) = &file.tokens[i].0 else {
+            return None;
+        };
+        let token = |i: Option<usize>| i.and_then(|i| file.tokens.get(i)).map(|t| &t.0);
+
+        // `module::item` or `Node::export`
+        if let (Some(Token::DoubleColon), Some(Token::ID(qualifier))) =
+            (token(i.checked_sub(1)), token(i.checked_sub(2)))
+        {
+            return self.qualified(qualifier, name);
+        }
+        if token(Some(i + 1)) == Some(&Token::DoubleColon) {
+            return self.container(name);
+        }
+
+        // a dependency in a node header
+        let in_header = outline.items.iter().any(|item| {
+            item.dependencies
+                .iter()
+                .any(|(_, span)| *span == file.tokens[i].1)
+        });
+        if in_header {
+            return self.container(name);
+        }
+
+        if let Some(function) = file.item_at(offset, &[ItemKind::Function]) {
+            let local = outline.locals.iter().enumerate().rev().find(|(_, local)| {
+                local.function == function && &local.name == name && local.visible_at(offset)
+            });
+            if let Some((l, _)) = local {
+                return Some(Target::Local(f, l));
+            }
+        }
+
+        let container = file.item_at(offset, &[ItemKind::Node, ItemKind::Module]);
+        let item = outline.items.iter().position(|item| {
+            item.container.is_some() && item.container == container && &item.name == name
+        });
+        if let Some(item) = item {
+            return Some(Target::Item(f, item));
+        }
+
+        let import = outline
+            .imports
+            .iter()
+            .find(|import| import.container == container && &import.name == name);
+        if let Some(import) = import {
+            return self.qualified(&import.module, name);
+        }
+
+        self.container(name)
+    }
+
+    /// The node or module named `name`.
+    fn container(&self, name: &str) -> Option<Target> {
+        self.files.iter().enumerate().find_map(|(f, file)| {
+            file.outline
+                .items
+                .iter()
+                .position(|item| item.container.is_none() && item.name == name)
+                .map(|item| Target::Item(f, item))
+        })
+    }
+
+    /// The item `name` declared in the node or module `qualifier`.
+    fn qualified(&self, qualifier: &str, name: &str) -> Option<Target> {
+        let Some(Target::Item(f, container)) = self.container(qualifier) else {
+            return None;
+        };
+
+        self.files[f]
+            .outline
+            .items
+            .iter()
+            .position(|item| item.container == Some(container) && item.name == name)
+            .map(|item| Target::Item(f, item))
+    }
+
+    /// The file `target` is declared in and the span of its name.
+    fn definition(&self, target: Target) -> (&File, Span) {
+        match target {
+            Target::Item(f, item) => (&self.files[f], self.files[f].outline.items[item].name_span),
+            Target::Local(f, local) => (
+                &self.files[f],
+                self.files[f].outline.locals[local].name_span,
+            ),
+        }
+    }
+
+    /// The declaration of `target`, with the types of locals as the checker inferred them in
+    /// `checked`, the program last checked with the file.
+    fn describe(&self, target: Target, checked: Option<&Source>) -> String {
+        let (outline, local) = match target {
+            Target::Item(f, i) => {
+                let items = &self.files[f].outline.items;
+                let mut lines = vec![items[i].detail.as_str()];
+
+                // a node is shown with its exports
+                if items[i].kind == ItemKind::Node {
+                    lines.extend(
+                        items
+                            .iter()
+                            .filter(|item| item.container == Some(i))
+                            .filter(|item| item.kind == ItemKind::Export)
+                            .map(|item| item.detail.as_str()),
+                    );
+                }
+
+                return lines.join("\n");
+            }
+            Target::Local(f, l) => (&self.files[f].outline, &self.files[f].outline.locals[l]),
+        };
+
+        let function = &outline.items[local.function];
+        let node = function
+            .container
+            .map(|c| &outline.items[c])
+            .filter(|c| c.kind == ItemKind::Node);
+        let inferred = match (node, checked) {
+            (Some(node), Some(source)) => {
+                // the checker renames a variable declared again in a later block, and never a
+                // parameter
+                let name = match local.kind {
+                    LocalKind::Param => Some(local.name.clone()),
+                    _ => source.local_name(&node.name, &function.name, local.name_span),
+                };
+                name.and_then(|name| source.variable_type(&node.name, &function.name, &name))
+            }
+            _ => None,
+        };
+        let local_type = inferred.unwrap_or_else(|| local.declared_type.clone());
+
+        match local.kind {
+            LocalKind::Param => format!("{}: {local_type}", local.name),
+            LocalKind::Var => format!("var {}: {local_type}", local.name),
+            LocalKind::Const => format!("const {}: {local_type}", local.name),
+        }
+    }
+
+    /// The completions at `offset` in the document `uri`.
+    fn complete(&self, uri: &str, offset: usize) -> Vec<Value> {
+        let Some(f) = self.file(uri) else {
+            return vec![];
+        };
+        let file = &self.files[f];
+        let outline = &file.outline;
+        let mut completions: Vec<Value> = vec![];
+        let mut add = |label: &str, kind: u8, detail: &str| {
+            if !completions.iter().any(|c| c["label"] == label) {
+                completions.push(json!({ "label": label, "kind": kind, "detail": detail }));
+            }
+        };
+
+        // after `qualifier::`, only what the node or module offers
+        let before: Vec<&Token> = file
+            .tokens
+            .iter()
+            .filter(|(_, span)| span.end <= offset)
+            .map(|(token, _)| token)
+            .collect();
+        let qualifier = match before.as_slice() {
+            [.., Token::ID(qualifier), Token::DoubleColon] => Some(qualifier),
+            [.., Token::ID(qualifier), Token::DoubleColon, Token::ID(_)] => Some(qualifier),
+            _ => None,
+        };
+        if let Some(qualifier) = qualifier {
+            if let Some(Target::Item(q, container)) = self.container(qualifier) {
+                let items = &self.files[q].outline.items;
+                let is_module = items[container].kind == ItemKind::Module;
+
+                for item in items
+                    .iter()
+                    .filter(|item| item.container == Some(container))
+                {
+                    let offered = match item.kind {
+                        ItemKind::Export => !is_module,
+                        _ => is_module && item.public,
+                    };
+                    if offered {
+                        add(&item.name, completion_kind(item.kind), &item.detail);
+                    }
+                }
+            }
+            return completions;
+        }
+
+        let function = file.item_at(offset, &[ItemKind::Function]);
+        for elem in file.scope_at(offset) {
+            let (name, kind) = match &elem {
+                ScopeElem::Variable(name) => (name, 6),
+                ScopeElem::Const(name) => (name, 21),
+                ScopeElem::Func(name) => (name, 3),
+                _ => continue,
+            };
+            let detail = match kind {
+                3 => outline
+                    .items
+                    .iter()
+                    .find(|item| item.kind == ItemKind::Function && &item.name == name)
+                    .map_or(String::new(), |item| item.detail.clone()),
+                _ => outline
+                    .locals
+                    .iter()
+                    .find(|local| {
+                        Some(local.function) == function
+                            && &local.name == name
+                            && local.visible_at(offset)
+                    })
+                    .map_or(String::new(), |local| local.declared_type.clone()),
+            };
+            add(name, kind, &detail);
+        }
+
+        let container = file.item_at(offset, &[ItemKind::Node, ItemKind::Module]);
+        for item in &outline.items {
+            if item.container.is_some() && item.container == container {
+                add(&item.name, completion_kind(item.kind), &item.detail);
+            }
+        }
+        for file in &self.files {
+            for item in file.outline.items.iter().filter(|i| i.container.is_none()) {
+                add(&item.name, completion_kind(item.kind), &item.detail);
+            }
+        }
+        for builtin in BUILTINS {
+            add(builtin, 3, "builtin");
+        }
+        for keyword in KEYWORDS {
+            add(keyword, 14, "");
+        }
+        for t in SIZED_TYPES {
+            add(t, 14, "");
+        }
+
+        completions
+    }
+}
+
+fn completion_kind(kind: ItemKind) -> u8 {
+    match kind {
+        ItemKind::Node => 7,
+        ItemKind::Module => 9,
+        ItemKind::Function => 3,
+        ItemKind::Struct => 22,
+        ItemKind::Export => 6,
+    }
+}
@@ -1,23 +1,521 @@
-mod lexer;
-mod parser;
-mod source;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
-use crate::lexer::Lexer;
-use crate::parser::Parser;
-use crate::source::Source;
+use clap::{Parser, Subcommand, ValueEnum};
+use karma::{
+    diagram, format, lsp,
+    project::{self, MANIFEST_FILE},
+    repl, testing, vm, Compilation, Diagnostic, Manifest,
+};
 
-fn main() {
-    let filename = std::env::args().nth(1).expect("no path passed");
-    let lexer = Lexer::new(&filename);
+#[derive(Parser)]
+#[command(
+    name = "karma",
+    version,
+    about = "Compiler for the Karma programming language",
+    after_help = "Exit codes: 0 on success, 1 when the program does not compile or cannot be \
+                  read, 2 for invalid arguments and 3 when a running node or a test fails."
+)]
+struct Cli {
+    /// How errors are reported. `json` prints one JSON object per line on stderr.
+    #[arg(long, value_enum, global = true, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
 
-    let mut parser = Parser::new(lexer);
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check a program for errors without writing anything
+    Check {
+        /// A `.krm` file, or a project directory or its `karma.toml`
+        path: PathBuf,
+    },
+    /// Compile a program and write its outputs
+    Build {
+        /// A `.krm` file, or a project directory or its `karma.toml`
+        path: PathBuf,
+        /// The directory the outputs are written to. Defaults to the manifest's `out-dir`, or
+        /// `comp`
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+        /// Only write the outputs of this node
+        #[arg(long)]
+        node: Option<String>,
+        /// The outputs to write, separated by commas
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_values_t = [Emit::Bytecode, Emit::Graph]
+        )]
+        emit: Vec<Emit>,
+    },
+    /// Compile a program and run one of its nodes
+    Run {
+        /// A `.krm` file, or a project directory or its `karma.toml`
+        path: PathBuf,
+        /// The node to run, required when the program has more than one node and the manifest
+        /// does not name a single entry node
+        #[arg(long)]
+        node: Option<String>,
+    },
+    /// Compile a program and run its tests
+    Test {
+        /// A `.krm` file, or a project directory or its `karma.toml`
+        path: PathBuf,
+        /// Only run the tests whose name, as `Node::test`, contains this
+        filter: Option<String>,
+    },
+    /// Print the instructions of a compiled `.k` file, or of the nodes of a program
+    Disasm {
+        /// A `.k` file, a `.krm` file, or a project directory or its `karma.toml`
+        path: PathBuf,
+        /// Only print the instructions of this node
+        #[arg(long)]
+        node: Option<String>,
+    },
+    /// Print the node graph of a program as a diagram
+    Graph {
+        /// A `.krm` file, or a project directory or its `karma.toml`
+        path: PathBuf,
+        /// The diagram language to print
+        #[arg(long, value_enum, default_value_t = GraphFormat::Dot)]
+        format: GraphFormat,
+    },
+    /// Print the syntax tree of a program
+    Ast {
+        /// A `.krm` file, or a project directory or its `karma.toml`
+        path: PathBuf,
+    },
+    /// Rewrite source files in the canonical style
+    ///
+    /// Long lines are not wrapped: a call or expression is kept on one line however long it is.
+    Fmt {
+        /// `.krm` files, or project directories or their `karma.toml`
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Only report the files that are not formatted, failing if there are any
+        #[arg(long)]
+        check: bool,
+    },
+    /// Run a language server speaking the Language Server Protocol over stdio
+    Lsp,
+    /// Evaluate statements and expressions interactively
+    Repl,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum MessageFormat {
+    Human,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum GraphFormat {
+    /// Graphviz DOT
+    Dot,
+    /// A Mermaid flowchart
+    Mermaid,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Emit {
+    /// The syntax tree of the program as an indented tree, as `ast.txt`
+    Ast,
+    /// The syntax tree of the program as JSON, as `ast.json`
+    AstJson,
+    /// The parse tree of each source file as an indented tree, as `parse_tree.txt`
+    ParseTree,
+    /// The checked signatures and locals of each node, as `<node>.ir`
+    Ir,
+    /// The bytecode of each node, as `<node>.k`
+    Bytecode,
+    /// The dependencies between nodes and their startup order, as `graph.json`
+    Graph,
+}
+
+enum Failure {
+    Compile(Vec<Diagnostic>),
+    Io(PathBuf, io::Error),
+    Usage(String),
+    Runtime(vm::RuntimeError),
+    Unformatted(Vec<PathBuf>),
+    /// The tests that failed, which are already listed with the results of every test.
+    Tests(Vec<testing::TestResult>),
+}
+
+impl Failure {
+    fn exit_code(&self) -> ExitCode {
+        ExitCode::from(match self {
+            Failure::Compile(_) | Failure::Io(..) | Failure::Unformatted(_) => 1,
+            Failure::Usage(_) => 2,
+            Failure::Runtime(_) | Failure::Tests(_) => 3,
+        })
+    }
+
+    fn report(&self, format: MessageFormat) {
+        let messages = match self {
+            Failure::Compile(diagnostics) => diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.to_string(), diagnostic.to_json()))
+                .collect(),
+            Failure::Io(path, e) => {
+                let message = format!("{}: {e}", path.display());
+                vec![(
+                    format!("error: {message}"),
+                    serde_json::json!({ "stage": "io", "message": message }),
+                )]
+            }
+            Failure::Usage(message) => vec![(
+                format!("error: {message}"),
+                serde_json::json!({ "stage": "usage", "message": message }),
+            )],
+            Failure::Runtime(e) => vec![(
+                e.to_string(),
+                serde_json::json!({ "stage": "runtime", "message": e.message, "pc": e.pc }),
+            )],
+            Failure::Unformatted(files) => files
+                .iter()
+                .map(|file| {
+                    let file = file.display().to_string();
+                    (
+                        format!("error: {file} is not formatted"),
+                        serde_json::json!({
+                            "stage": "format",
+                            "message": "not formatted",
+                            "file": file,
+                        }),
+                    )
+                })
+                .collect(),
+            // the human output of each failure is printed with the test results
+            Failure::Tests(_) if format == MessageFormat::Human => vec![],
+            Failure::Tests(tests) => tests
+                .iter()
+                .filter_map(|test| {
+                    let failure = test.failure.as_ref()?;
+                    let json = serde_json::json!({
+                        "stage": "test",
+                        "message": failure.message,
+                        "test": test.qualified_name(),
+                        "file": failure.file.as_ref().map(|file| file.display().to_string()),
+                        "line": failure.span.map(|span| span.line),
+                        "column": failure.span.map(|span| span.column),
+                        "start": failure.span.map(|span| span.start),
+                        "end": failure.span.map(|span| span.end),
+                    });
+                    Some((failure.to_string(), json))
+                })
+                .collect(),
+        };
+
+        for (human, json) in messages {
+            match format {
+                MessageFormat::Human => eprintln!("{human}"),
+                MessageFormat::Json => eprintln!("{json}"),
+            }
+        }
+    }
+}
 
-    match parser.parse() {
-        Ok(_) => {
-            parser.generate_ast();
-            let source = Source::new(parser).expect("could not build source");
-            source.compile().expect("could not compile");
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match execute(cli.command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => {
+            failure.report(cli.message_format);
+            failure.exit_code()
         }
-        Err(s) => println!("{s}"),
     }
 }
+
+fn execute(command: Command) -> Result<(), Failure> {
+    match command {
+        Command::Check { path } => {
+            checked(compile(&path)?)?;
+        }
+        Command::Build {
+            path,
+            out_dir,
+            node,
+            emit,
+        } => {
+            let mut compilation = checked(compile(&path)?)?;
+            let out_dir = match out_dir {
+                Some(out_dir) => out_dir,
+                None => manifest(&path)?.map_or_else(|| PathBuf::from("comp"), |m| m.out_dir),
+            };
+
+            if let Some(node) = &node {
+                select_node(&compilation, Some(node))?;
+                compilation.bytecode.retain(|node_id, _| node_id == node);
+                compilation.ir.retain(|node_id, _| node_id == node);
+                compilation.graph.retain(|node_id, _| node_id == node);
+                compilation.order.retain(|node_id| node_id == node);
+            }
+
+            write_outputs(&compilation, &out_dir, &emit)
+                .map_err(|e| Failure::Io(out_dir.clone(), e))?;
+        }
+        Command::Run { path, node } => {
+            let compilation = checked(compile(&path)?)?;
+            let node = match (node, manifest(&path)?) {
+                (None, Some(manifest)) if manifest.entry.len() == 1 => {
+                    Some(manifest.entry[0].clone())
+                }
+                (node, _) => node,
+            };
+            let node = select_node(&compilation, node.as_deref())?;
+
+            vm::run(&compilation.bytecode[&node], &mut io::stdout().lock())
+                .map_err(Failure::Runtime)?;
+        }
+        Command::Test { path, filter } => {
+            let compilation = checked(compile(&path)?)?;
+            let results = testing::run_tests(&compilation, filter.as_deref());
+            let total: usize = compilation.tests.values().map(|tests| tests.len()).sum();
+
+            println!(
+                "running {} test{}",
+                results.len(),
+                if results.len() == 1 { "" } else { "s" }
+            );
+            for result in &results {
+                let outcome = if result.failure.is_some() {
+                    "FAILED"
+                } else {
+                    "ok"
+                };
+                println!("test {} ... {outcome}", result.qualified_name());
+            }
+
+            let failed: Vec<_> = results.iter().filter(|r| r.failure.is_some()).collect();
+            if !failed.is_empty() {
+                println!("\nfailures:");
+                for result in &failed {
+                    println!("\n---- {} ----", result.qualified_name());
+                    if !result.output.is_empty() {
+                        println!("{}", result.output.trim_end());
+                    }
+                    println!("{}", result.failure.as_ref().unwrap());
+                }
+            }
+
+            println!(
+                "\ntest result: {}. {} passed; {} failed; {} filtered out",
+                if failed.is_empty() { "ok" } else { "FAILED" },
+                results.len() - failed.len(),
+                failed.len(),
+                total - results.len()
+            );
+
+            if !failed.is_empty() {
+                return Err(Failure::Tests(failed.into_iter().cloned().collect()));
+            }
+        }
+        Command::Disasm { path, node } => {
+            if path.extension().is_some_and(|ext| ext == "k") {
+                let bytes = fs::read(&path).map_err(|e| Failure::Io(path.clone(), e))?;
+                print!("{}", vm::disassemble(&bytes).map_err(Failure::Runtime)?);
+                return Ok(());
+            }
+
+            let compilation = checked(compile(&path)?)?;
+            let nodes = match node {
+                Some(node) => vec![select_node(&compilation, Some(&node))?],
+                None => compilation.bytecode.keys().cloned().collect(),
+            };
+
+            for node in nodes {
+                println!("{node}:");
+                print!(
+                    "{}",
+                    vm::disassemble(&compilation.bytecode[&node]).map_err(Failure::Runtime)?
+                );
+            }
+        }
+        Command::Graph { path, format } => {
+            let compilation = checked(compile(&path)?)?;
+
+            print!(
+                "{}",
+                match format {
+                    GraphFormat::Dot => diagram::to_dot(&compilation),
+                    GraphFormat::Mermaid => diagram::to_mermaid(&compilation),
+                }
+            );
+        }
+        Command::Ast { path } => {
+            let compilation = compile(&path)?;
+
+            // the tree is printed even when the program has semantic errors
+            match compilation.ast {
+                Some(ast) => print!("{ast}"),
+                None => return Err(Failure::Compile(compilation.diagnostics)),
+            }
+        }
+        Command::Fmt { paths, check } => {
+            let mut diagnostics = vec![];
+            let mut unformatted = vec![];
+
+            for file in source_files(&paths)? {
+                let source = fs::read_to_string(&file).map_err(|e| Failure::Io(file.clone(), e))?;
+                let formatted = match format::format_source(&source) {
+                    Ok(formatted) => formatted,
+                    Err(diagnostic) => {
+                        diagnostics.push(diagnostic.with_file(&file));
+                        continue;
+                    }
+                };
+
+                if formatted == source {
+                    continue;
+                }
+
+                if check {
+                    unformatted.push(file);
+                } else {
+                    fs::write(&file, formatted).map_err(|e| Failure::Io(file.clone(), e))?;
+                }
+            }
+
+            if !diagnostics.is_empty() {
+                return Err(Failure::Compile(diagnostics));
+            }
+            if !unformatted.is_empty() {
+                return Err(Failure::Unformatted(unformatted));
+            }
+        }
+        Command::Lsp => {
+            lsp::serve(io::stdin().lock(), io::stdout().lock())
+                .map_err(|e| Failure::Io(PathBuf::from("stdio"), e))?;
+        }
+        Command::Repl => {
+            repl::run(io::stdin().lock(), io::stdout().lock())
+                .map_err(|e| Failure::Io(PathBuf::from("stdio"), e))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the project directory `path` names, if it names a directory or a manifest.
+fn project_dir(path: &Path) -> Option<&Path> {
+    if path.is_dir() {
+        Some(path)
+    } else if path.file_name().is_some_and(|name| name == MANIFEST_FILE) {
+        Some(path.parent().unwrap_or(Path::new(".")))
+    } else {
+        None
+    }
+}
+
+fn manifest(path: &Path) -> Result<Option<Manifest>, Failure> {
+    match project_dir(path) {
+        Some(dir) => Manifest::load(dir).map_err(|e| Failure::Io(path.to_path_buf(), e)),
+        None => Ok(None),
+    }
+}
+
+fn compile(path: &Path) -> Result<Compilation, Failure> {
+    let compilation = match project_dir(path) {
+        Some(dir) => karma::compile_project(dir),
+        None => karma::compile_file(path),
+    };
+
+    compilation.map_err(|e| Failure::Io(path.to_path_buf(), e))
+}
+
+/// Lists the source files `paths` name, expanding projects into their sources.
+fn source_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, Failure> {
+    let mut files = vec![];
+
+    for path in paths {
+        match project_dir(path) {
+            Some(dir) => files
+                .extend(project::project_sources(dir).map_err(|e| Failure::Io(path.clone(), e))?),
+            None => files.push(path.clone()),
+        }
+    }
+
+    Ok(files)
+}
+
+fn checked(compilation: Compilation) -> Result<Compilation, Failure> {
+    if compilation.is_ok() {
+        Ok(compilation)
+    } else {
+        Err(Failure::Compile(compilation.diagnostics))
+    }
+}
+
+/// Resolves the node a command acts on, which may only be left out when there is one node.
+fn select_node(compilation: &Compilation, node: Option<&str>) -> Result<String, Failure> {
+    match node {
+        Some(node) if compilation.bytecode.contains_key(node) => Ok(node.to_string()),
+        Some(node) => Err(Failure::Usage(format!("no node named `{node}`"))),
+        None if compilation.bytecode.len() == 1 => {
+            Ok(compilation.bytecode.keys().next().unwrap().clone())
+        }
+        None => {
+            let nodes: Vec<&str> = compilation.bytecode.keys().map(String::as_str).collect();
+            Err(Failure::Usage(format!(
+                "the program has nodes {}, pick one with --node",
+                nodes.join(", ")
+            )))
+        }
+    }
+}
+
+fn write_outputs(compilation: &Compilation, dir: &Path, emit: &[Emit]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    for emit in emit {
+        match emit {
+            Emit::Ast => {
+                if let Some(ast) = &compilation.ast {
+                    fs::write(dir.join("ast.txt"), ast.to_string())?;
+                }
+            }
+            Emit::AstJson => {
+                if let Some(ast) = &compilation.ast {
+                    let json = serde_json::to_string(ast).map_err(io::Error::other)?;
+                    fs::write(dir.join("ast.json"), json)?;
+                }
+            }
+            Emit::ParseTree => {
+                let mut text = String::new();
+                for (file, parse_tree) in &compilation.parse_trees {
+                    if compilation.parse_trees.len() > 1 {
+                        if let Some(file) = file {
+                            text += &format!("# {}\n", file.display());
+                        }
+                    }
+                    text += &parse_tree.to_string();
+                }
+                fs::write(dir.join("parse_tree.txt"), text)?;
+            }
+            Emit::Ir => {
+                for (node_id, ir) in &compilation.ir {
+                    fs::write(dir.join(format!("{node_id}.ir")), ir)?;
+                }
+            }
+            Emit::Bytecode => {
+                for (node_id, bytes) in &compilation.bytecode {
+                    fs::write(dir.join(format!("{node_id}.k")), bytes)?;
+                }
+            }
+            Emit::Graph => {
+                fs::write(dir.join("graph.json"), compilation.graph_json())?;
+            }
+        }
+    }
+
+    Ok(())
+}
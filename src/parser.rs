@@ -1,13 +1,24 @@
-use crate::lexer::{Lexer, Token};
-use std::collections::{HashMap, LinkedList};
-
-#[derive(Clone, Debug, PartialEq)]
+use crate::diagnostic::Diagnostic;
+use crate::lexer::{Lexer, Span, Token};
+use crate::units::{self, Unit};
+use serde::{Serialize, Serializer};
+use std::{
+    collections::{HashMap, LinkedList},
+    fmt,
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum SyntaxTreeNode {
     NodeSeq,
     DeclareNode,
+    DeclareModule,
     NodeHeader,
     NodeList,
     TLStmtSeq,
+    Export,
+    Public,
+    Test,
+    Use,
     DeclareStruct,
     FieldList,
     Field,
@@ -24,6 +35,7 @@ pub enum SyntaxTreeNode {
     WhileLoop,
     IfStmt,
     Assign,
+    CompoundAssign,
     Index,
     FnCall,
     InputList,
@@ -31,6 +43,15 @@ pub enum SyntaxTreeNode {
     SubOp,
     MulOp,
     DivOp,
+    ModOp,
+    ShlOp,
+    ShrOp,
+    BitAndOp,
+    BitXorOp,
+    BitOrOp,
+    NegOp,
+    NotOp,
+    BitNotOp,
     OrOp,
     AndOp,
     CompEq,
@@ -39,8 +60,12 @@ pub enum SyntaxTreeNode {
     CompGreater,
     CompLeq,
     CompGeq,
+    Cast,
     Integer(i32),
     Float(f32),
+    /// A number with a type suffix, like `255u8`. Unlike a second `String` variant, a boxed
+    /// `str` fits beside `Identifier` without making every node larger.
+    SizedNumber(Box<str>),
     Character(char),
     Identifier(String),
     True,
@@ -48,10 +73,13 @@ pub enum SyntaxTreeNode {
     Null,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct AbstractSyntaxTree {
     pub node: SyntaxTreeNode,
     pub children: Vec<AbstractSyntaxTree>,
+    /// Where the node is written in the source, from its first token to its last.
+    #[serde(skip)]
+    pub span: Option<Span>,
 }
 
 impl AbstractSyntaxTree {
@@ -59,7 +87,30 @@ impl AbstractSyntaxTree {
         Self {
             node: SyntaxTreeNode::Null,
             children: vec![],
+            span: None,
+        }
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        writeln!(f, "{}{:?}", "  ".repeat(depth), self.node)?;
+        for child in &self.children {
+            child.fmt_indented(f, depth + 1)?;
         }
+
+        Ok(())
+    }
+}
+
+impl Default for AbstractSyntaxTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Prints one node per line, indented by its depth in the tree.
+impl fmt::Display for AbstractSyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
     }
 }
 
@@ -68,6 +119,39 @@ pub struct ParseTree {
     node_list: Vec<GrammarSymbol>,
     adj_list: HashMap<usize, Vec<usize>>,
     parents_list: HashMap<usize, usize>,
+    /// The span of each node that covers a terminal, by index.
+    spans: HashMap<usize, Span>,
+}
+
+impl Default for ParseTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A node of the parse tree with its children nested inside it, which is how the tree is
+/// serialized so that the output does not depend on how the tree is stored.
+#[derive(Serialize)]
+struct ParseNode<'a> {
+    symbol: &'a GrammarSymbol,
+    children: Vec<ParseNode<'a>>,
+}
+
+impl Serialize for ParseTree {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.nested(0).serialize(serializer)
+    }
+}
+
+/// Prints one symbol per line, indented by its depth in the tree.
+impl fmt::Display for ParseTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.node_list.is_empty() {
+            self.fmt_indented(f, 0, 0)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl ParseTree {
@@ -76,17 +160,100 @@ impl ParseTree {
             node_list: vec![],
             adj_list: HashMap::new(),
             parents_list: HashMap::new(),
+            spans: HashMap::new(),
         }
     }
 
     pub fn set_root(&mut self, sym: GrammarSymbol) {
-        if self.node_list.len() == 0 {
+        if self.node_list.is_empty() {
             self.node_list.push(sym);
         } else {
             self.node_list[0] = sym;
         }
     }
 
+    fn nested(&self, idx: usize) -> Option<ParseNode<'_>> {
+        let symbol = self.node_list.get(idx)?;
+        let children = self
+            .get_children(idx)
+            .into_iter()
+            .filter_map(|child| self.nested(child))
+            .collect();
+
+        Some(ParseNode { symbol, children })
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, idx: usize, depth: usize) -> fmt::Result {
+        writeln!(f, "{}{:?}", "  ".repeat(depth), self.node_list[idx])?;
+        for child in self.get_children(idx) {
+            self.fmt_indented(f, child, depth + 1)?;
+        }
+
+        Ok(())
+    }
+
+    /// Gives the terminals the spans they were read at, in the order they were read, which is
+    /// the order they appear in the tree, and every other node the span from its first terminal
+    /// to its last.
+    fn set_spans(&mut self, spans: Vec<Span>) {
+        let mut order = vec![];
+        let mut stack = vec![0];
+
+        while let Some(idx) = stack.pop() {
+            order.push(idx);
+            stack.extend(self.get_children(idx).into_iter().rev());
+        }
+
+        let terminals = order
+            .iter()
+            .filter(|idx| matches!(self.node_list[**idx], GrammarSymbol::Terminal(_)));
+        self.spans = terminals.copied().zip(spans).collect();
+
+        // every child comes after its parent in the order, so going back through it covers the
+        // children of a node before the node
+        for &idx in order.iter().rev() {
+            let children = self.get_children(idx);
+            let first = children.iter().find_map(|child| self.spans.get(child));
+            let last = children
+                .iter()
+                .rev()
+                .find_map(|child| self.spans.get(child));
+
+            if let (Some(first), Some(last)) = (first, last) {
+                let span = Span {
+                    end: last.end,
+                    ..*first
+                };
+                self.spans.insert(idx, span);
+            }
+        }
+    }
+
+    /// The span from the first terminal under the node `idx` to the last, if anything under it
+    /// was read.
+    pub fn span(&self, idx: usize) -> Option<Span> {
+        self.spans.get(&idx).copied()
+    }
+
+    /// The symbol each terminal was derived from, in the order the terminals were read.
+    pub fn terminal_parents(&self) -> Vec<GrammarSymbol> {
+        let mut parents = vec![];
+        if self.node_list.is_empty() {
+            return parents;
+        }
+
+        let mut stack = vec![(0, 0)];
+
+        while let Some((idx, parent)) = stack.pop() {
+            if let GrammarSymbol::Terminal(_) = self.node_list[idx] {
+                parents.push(self.node_list[parent].clone());
+            }
+            stack.extend(self.get_children(idx).into_iter().rev().map(|c| (c, idx)));
+        }
+
+        parents
+    }
+
     pub fn get_node(&self, idx: usize) -> GrammarSymbol {
         self.node_list[idx].clone()
     }
@@ -152,16 +319,24 @@ pub struct Parser {
     pub ast: AbstractSyntaxTree,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum GrammarSymbol {
     Terminal(Token),
     Empty,
     End,
     Array,
     ArrLen,
+    AssignOp,
     AssignOrFnCall,
+    BitAnd,
+    BitAnd1,
+    BitOr,
+    BitOr1,
+    BitXor,
+    BitXor1,
     Block,
     BoolExpr,
+    Cast,
     BoolTerm,
     BoolTerm1,
     Comparison,
@@ -180,21 +355,28 @@ pub enum GrammarSymbol {
     IDRest,
     InputList,
     InputRest,
+    Item,
+    ModuleNT,
     NodeBlock,
     NodeHeader,
     NodeList,
     NodeNT,
     NodeRest,
     Primitive,
+    OptCast,
     OptElse,
+    OptField,
     OptIDList,
     OptIndex,
+    OptUnit,
     Param,
     ParamList,
     ParamRest,
     Positive,
     Program,
     ReturnType,
+    Shift,
+    Shift1,
     Stmt,
     StmtList,
     Term,
@@ -202,6 +384,13 @@ pub enum GrammarSymbol {
     TLStmt,
     TLStmtList,
     Type,
+    Unit,
+    UnitExponent,
+    UnitPower,
+    UnitRest,
+    UnitType,
+    FixedBits,
+    FixedMode,
 }
 
 impl Parser {
@@ -213,7 +402,7 @@ impl Parser {
         }
     }
 
-    pub fn parse(&mut self) -> Result<(), String> {
+    pub fn parse(&mut self) -> Result<(), Diagnostic> {
         let mut stack: LinkedList<GrammarSymbol> = LinkedList::new();
 
         stack.push_back(GrammarSymbol::End);
@@ -221,6 +410,7 @@ impl Parser {
 
         self.parse_tree.set_root(GrammarSymbol::Program);
         let mut idx = 0;
+        let mut spans = vec![];
 
         loop {
             let token = self.lexer.next_token()?;
@@ -231,28 +421,25 @@ impl Parser {
 
                 match top.unwrap() {
                     GrammarSymbol::Terminal(t) => {
-                        if std::mem::discriminant(&token) == std::mem::discriminant(&Some(t)) {
+                        if std::mem::discriminant(&token)
+                            == std::mem::discriminant(&Some(t.clone()))
+                        {
+                            spans.push(self.lexer.token_span());
                             break;
                         } else {
-                            return Err("syntax error 1".to_string());
+                            return Err(self.syntax_error(format!(
+                                "syntax error: expected {t:?}, found {token:?}"
+                            )));
                         }
                     }
                     GrammarSymbol::Empty => {}
-                    GrammarSymbol::End => {
-                        if token == None {
-                            break;
-                        } else {
-                            // println!("{}", token.unwrap(),);
-                            return Err("syntax error 3".to_string());
-                        }
-                    }
+                    // `Program` only ends at the end of input
+                    GrammarSymbol::End => break,
                     nt => {
                         let production: Vec<GrammarSymbol> = match nt {
                             GrammarSymbol::Terminal(_)
                             | GrammarSymbol::Empty
-                            | GrammarSymbol::End => {
-                                return Err(format!("syntax error: {:?} {:?}", nt, token));
-                            }
+                            | GrammarSymbol::End => unreachable!("matched above"),
                             GrammarSymbol::Array => match token {
                                 Some(Token::LeftBracket) => {
                                     vec![
@@ -261,11 +448,7 @@ impl Parser {
                                         GrammarSymbol::Terminal(Token::RightBracket),
                                     ]
                                 }
-                                _ => {
-                                    return Err(
-                                        "syntax error: expected array assignment".to_string()
-                                    )
-                                }
+                                _ => unreachable!("`Value` only expands `Array` on `[`"),
                             },
                             GrammarSymbol::ArrLen => {
                                 match token {
@@ -273,15 +456,28 @@ impl Parser {
                                         vec![GrammarSymbol::Terminal(Token::Integer(i))]
                                     }
                                     _ => {
-                                        return Err("syntax error: expected integer constant for array length".to_string());
+                                        return Err(self.syntax_error("syntax error: expected integer constant for array length".to_string()));
                                     }
                                 }
                             }
                             GrammarSymbol::AssignOrFnCall => match token {
-                                Some(Token::Assign) | Some(Token::LeftBracket) => {
+                                Some(Token::Assign)
+                                | Some(Token::AddAssign)
+                                | Some(Token::SubAssign)
+                                | Some(Token::MulAssign)
+                                | Some(Token::DivAssign)
+                                | Some(Token::ModAssign)
+                                | Some(Token::AndAssign)
+                                | Some(Token::OrAssign)
+                                | Some(Token::XorAssign)
+                                | Some(Token::ShlAssign)
+                                | Some(Token::ShrAssign)
+                                | Some(Token::LeftBracket)
+                                | Some(Token::Dot) => {
                                     vec![
+                                        GrammarSymbol::OptField,
                                         GrammarSymbol::OptIndex,
-                                        GrammarSymbol::Terminal(Token::Assign),
+                                        GrammarSymbol::AssignOp,
                                         GrammarSymbol::Value,
                                         GrammarSymbol::Terminal(Token::Semicolon),
                                     ]
@@ -295,11 +491,58 @@ impl Parser {
                                     ]
                                 }
                                 _ => {
-                                    println!("{:?}", token);
-                                    return Err(
+                                    return Err(self.syntax_error(
                                         "syntax error: expected function call or assignment"
                                             .to_string(),
-                                    );
+                                    ));
+                                }
+                            },
+                            GrammarSymbol::OptField => match token {
+                                Some(Token::Dot) => {
+                                    vec![
+                                        GrammarSymbol::Terminal(Token::Dot),
+                                        GrammarSymbol::ID,
+                                        GrammarSymbol::OptField,
+                                    ]
+                                }
+                                Some(Token::Assign)
+                                | Some(Token::AddAssign)
+                                | Some(Token::SubAssign)
+                                | Some(Token::MulAssign)
+                                | Some(Token::DivAssign)
+                                | Some(Token::ModAssign)
+                                | Some(Token::AndAssign)
+                                | Some(Token::OrAssign)
+                                | Some(Token::XorAssign)
+                                | Some(Token::ShlAssign)
+                                | Some(Token::ShrAssign)
+                                | Some(Token::LeftBracket) => {
+                                    vec![]
+                                }
+                                _ => {
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected field or assignment".to_string(),
+                                    ));
+                                }
+                            },
+                            GrammarSymbol::AssignOp => match token {
+                                Some(Token::Assign)
+                                | Some(Token::AddAssign)
+                                | Some(Token::SubAssign)
+                                | Some(Token::MulAssign)
+                                | Some(Token::DivAssign)
+                                | Some(Token::ModAssign)
+                                | Some(Token::AndAssign)
+                                | Some(Token::OrAssign)
+                                | Some(Token::XorAssign)
+                                | Some(Token::ShlAssign)
+                                | Some(Token::ShrAssign) => {
+                                    vec![GrammarSymbol::Terminal(token.clone().unwrap())]
+                                }
+                                _ => {
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected assignment operator".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::Block => match token {
@@ -309,37 +552,49 @@ impl Parser {
                                     GrammarSymbol::Terminal(Token::RightBrace),
                                 ],
                                 _ => {
-                                    return Err("syntax error: expected {".to_string());
+                                    return Err(
+                                        self.syntax_error("syntax error: expected {".to_string())
+                                    );
                                 }
                             },
                             GrammarSymbol::BoolExpr => match token {
                                 Some(Token::ID(_))
                                 | Some(Token::Sub)
+                                | Some(Token::Not)
+                                | Some(Token::BitwiseNot)
                                 | Some(Token::Integer(_))
                                 | Some(Token::Float(_))
+                                | Some(Token::SizedNumber(..))
                                 | Some(Token::Character(_))
                                 | Some(Token::True)
                                 | Some(Token::False)
                                 | Some(Token::LeftParen) => {
-                                    vec![GrammarSymbol::Expression, GrammarSymbol::Comparison]
+                                    vec![GrammarSymbol::BitOr, GrammarSymbol::Comparison]
                                 }
                                 _ => {
-                                    return Err("syntax error: expected expression 1".to_string());
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected expression 1".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::BoolTerm => match token {
                                 Some(Token::ID(_))
                                 | Some(Token::Sub)
+                                | Some(Token::Not)
+                                | Some(Token::BitwiseNot)
                                 | Some(Token::LeftParen)
                                 | Some(Token::Integer(_))
                                 | Some(Token::Float(_))
+                                | Some(Token::SizedNumber(..))
                                 | Some(Token::Character(_))
                                 | Some(Token::True)
                                 | Some(Token::False) => {
                                     vec![GrammarSymbol::BoolExpr, GrammarSymbol::BoolTerm1]
                                 }
                                 _ => {
-                                    return Err("syntax error: expected expression 2".to_string());
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected expression 2".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::BoolTerm1 => match token {
@@ -347,7 +602,7 @@ impl Parser {
                                     vec![
                                         GrammarSymbol::Terminal(Token::LogicalAnd),
                                         GrammarSymbol::BoolExpr,
-                                        GrammarSymbol::Conditional1,
+                                        GrammarSymbol::BoolTerm1,
                                     ]
                                 }
                                 Some(Token::LeftBrace)
@@ -359,36 +614,247 @@ impl Parser {
                                     vec![]
                                 }
                                 _ => {
-                                    return Err("syntax error: expected expression 3".to_string());
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected expression 3".to_string(),
+                                    ));
+                                }
+                            },
+                            GrammarSymbol::BitOr => match token {
+                                Some(Token::ID(_))
+                                | Some(Token::Sub)
+                                | Some(Token::Not)
+                                | Some(Token::BitwiseNot)
+                                | Some(Token::Integer(_))
+                                | Some(Token::Float(_))
+                                | Some(Token::SizedNumber(..))
+                                | Some(Token::Character(_))
+                                | Some(Token::True)
+                                | Some(Token::False)
+                                | Some(Token::LeftParen) => {
+                                    vec![GrammarSymbol::BitXor, GrammarSymbol::BitOr1]
+                                }
+                                _ => {
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected expression 10".to_string(),
+                                    ));
+                                }
+                            },
+                            GrammarSymbol::BitOr1 => match token {
+                                Some(Token::BitwiseOr) => {
+                                    vec![
+                                        GrammarSymbol::Terminal(Token::BitwiseOr),
+                                        GrammarSymbol::BitXor,
+                                        GrammarSymbol::BitOr1,
+                                    ]
+                                }
+                                Some(Token::Equals)
+                                | Some(Token::Neq)
+                                | Some(Token::Less)
+                                | Some(Token::Greater)
+                                | Some(Token::Leq)
+                                | Some(Token::Geq)
+                                | Some(Token::LeftBrace)
+                                | Some(Token::LogicalAnd)
+                                | Some(Token::LogicalOr)
+                                | Some(Token::Semicolon)
+                                | Some(Token::Comma)
+                                | Some(Token::RightParen)
+                                | Some(Token::RightBracket) => {
+                                    vec![]
+                                }
+                                _ => unreachable!("`BitXor1` lets nothing else through"),
+                            },
+                            GrammarSymbol::BitXor => match token {
+                                Some(Token::ID(_))
+                                | Some(Token::Sub)
+                                | Some(Token::Not)
+                                | Some(Token::BitwiseNot)
+                                | Some(Token::Integer(_))
+                                | Some(Token::Float(_))
+                                | Some(Token::SizedNumber(..))
+                                | Some(Token::Character(_))
+                                | Some(Token::True)
+                                | Some(Token::False)
+                                | Some(Token::LeftParen) => {
+                                    vec![GrammarSymbol::BitAnd, GrammarSymbol::BitXor1]
+                                }
+                                _ => {
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected expression 12".to_string(),
+                                    ));
+                                }
+                            },
+                            GrammarSymbol::BitXor1 => match token {
+                                Some(Token::BitwiseXor) => {
+                                    vec![
+                                        GrammarSymbol::Terminal(Token::BitwiseXor),
+                                        GrammarSymbol::BitAnd,
+                                        GrammarSymbol::BitXor1,
+                                    ]
+                                }
+                                Some(Token::BitwiseOr)
+                                | Some(Token::Equals)
+                                | Some(Token::Neq)
+                                | Some(Token::Less)
+                                | Some(Token::Greater)
+                                | Some(Token::Leq)
+                                | Some(Token::Geq)
+                                | Some(Token::LeftBrace)
+                                | Some(Token::LogicalAnd)
+                                | Some(Token::LogicalOr)
+                                | Some(Token::Semicolon)
+                                | Some(Token::Comma)
+                                | Some(Token::RightParen)
+                                | Some(Token::RightBracket) => {
+                                    vec![]
+                                }
+                                _ => unreachable!("`BitAnd1` lets nothing else through"),
+                            },
+                            GrammarSymbol::BitAnd => match token {
+                                Some(Token::ID(_))
+                                | Some(Token::Sub)
+                                | Some(Token::Not)
+                                | Some(Token::BitwiseNot)
+                                | Some(Token::Integer(_))
+                                | Some(Token::Float(_))
+                                | Some(Token::SizedNumber(..))
+                                | Some(Token::Character(_))
+                                | Some(Token::True)
+                                | Some(Token::False)
+                                | Some(Token::LeftParen) => {
+                                    vec![GrammarSymbol::Shift, GrammarSymbol::BitAnd1]
+                                }
+                                _ => {
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected expression 14".to_string(),
+                                    ));
+                                }
+                            },
+                            GrammarSymbol::BitAnd1 => match token {
+                                Some(Token::BitwiseAnd) => {
+                                    vec![
+                                        GrammarSymbol::Terminal(Token::BitwiseAnd),
+                                        GrammarSymbol::Shift,
+                                        GrammarSymbol::BitAnd1,
+                                    ]
+                                }
+                                Some(Token::BitwiseXor)
+                                | Some(Token::BitwiseOr)
+                                | Some(Token::Equals)
+                                | Some(Token::Neq)
+                                | Some(Token::Less)
+                                | Some(Token::Greater)
+                                | Some(Token::Leq)
+                                | Some(Token::Geq)
+                                | Some(Token::LeftBrace)
+                                | Some(Token::LogicalAnd)
+                                | Some(Token::LogicalOr)
+                                | Some(Token::Semicolon)
+                                | Some(Token::Comma)
+                                | Some(Token::RightParen)
+                                | Some(Token::RightBracket) => {
+                                    vec![]
+                                }
+                                _ => unreachable!("`Shift1` lets nothing else through"),
+                            },
+                            GrammarSymbol::Shift => match token {
+                                Some(Token::ID(_))
+                                | Some(Token::Sub)
+                                | Some(Token::Not)
+                                | Some(Token::BitwiseNot)
+                                | Some(Token::Integer(_))
+                                | Some(Token::Float(_))
+                                | Some(Token::SizedNumber(..))
+                                | Some(Token::Character(_))
+                                | Some(Token::True)
+                                | Some(Token::False)
+                                | Some(Token::LeftParen) => {
+                                    vec![GrammarSymbol::Expression, GrammarSymbol::Shift1]
+                                }
+                                _ => {
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected expression 16".to_string(),
+                                    ));
+                                }
+                            },
+                            GrammarSymbol::Shift1 => match token {
+                                Some(Token::ShiftLeft) => {
+                                    vec![
+                                        GrammarSymbol::Terminal(Token::ShiftLeft),
+                                        GrammarSymbol::Expression,
+                                        GrammarSymbol::Shift1,
+                                    ]
+                                }
+                                Some(Token::ShiftRight) => {
+                                    vec![
+                                        GrammarSymbol::Terminal(Token::ShiftRight),
+                                        GrammarSymbol::Expression,
+                                        GrammarSymbol::Shift1,
+                                    ]
+                                }
+                                Some(Token::BitwiseAnd)
+                                | Some(Token::BitwiseXor)
+                                | Some(Token::BitwiseOr)
+                                | Some(Token::Equals)
+                                | Some(Token::Neq)
+                                | Some(Token::Less)
+                                | Some(Token::Greater)
+                                | Some(Token::Leq)
+                                | Some(Token::Geq)
+                                | Some(Token::LeftBrace)
+                                | Some(Token::LogicalAnd)
+                                | Some(Token::LogicalOr)
+                                | Some(Token::Semicolon)
+                                | Some(Token::Comma)
+                                | Some(Token::RightParen)
+                                | Some(Token::RightBracket) => {
+                                    vec![]
+                                }
+                                _ => unreachable!("`Expression1` lets nothing else through"),
+                            },
+                            GrammarSymbol::Cast => match token {
+                                Some(Token::ID(_))
+                                | Some(Token::Sub)
+                                | Some(Token::Not)
+                                | Some(Token::BitwiseNot)
+                                | Some(Token::Integer(_))
+                                | Some(Token::Float(_))
+                                | Some(Token::SizedNumber(..))
+                                | Some(Token::Character(_))
+                                | Some(Token::True)
+                                | Some(Token::False)
+                                | Some(Token::LeftParen) => {
+                                    vec![GrammarSymbol::Factor, GrammarSymbol::OptCast]
+                                }
+                                _ => {
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected expression 9".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::Comparison => match token {
                                 Some(Token::Equals) => vec![
                                     GrammarSymbol::Terminal(Token::Equals),
-                                    GrammarSymbol::Expression,
-                                ],
-                                Some(Token::Neq) => vec![
-                                    GrammarSymbol::Terminal(Token::Neq),
-                                    GrammarSymbol::Expression,
-                                ],
-                                Some(Token::Less) => vec![
-                                    GrammarSymbol::Terminal(Token::Less),
-                                    GrammarSymbol::Expression,
+                                    GrammarSymbol::BitOr,
                                 ],
+                                Some(Token::Neq) => {
+                                    vec![GrammarSymbol::Terminal(Token::Neq), GrammarSymbol::BitOr]
+                                }
+                                Some(Token::Less) => {
+                                    vec![GrammarSymbol::Terminal(Token::Less), GrammarSymbol::BitOr]
+                                }
                                 Some(Token::Greater) => {
                                     vec![
                                         GrammarSymbol::Terminal(Token::Greater),
-                                        GrammarSymbol::Expression,
+                                        GrammarSymbol::BitOr,
                                     ]
                                 }
-                                Some(Token::Leq) => vec![
-                                    GrammarSymbol::Terminal(Token::Leq),
-                                    GrammarSymbol::Expression,
-                                ],
-                                Some(Token::Geq) => vec![
-                                    GrammarSymbol::Terminal(Token::Geq),
-                                    GrammarSymbol::Expression,
-                                ],
+                                Some(Token::Leq) => {
+                                    vec![GrammarSymbol::Terminal(Token::Leq), GrammarSymbol::BitOr]
+                                }
+                                Some(Token::Geq) => {
+                                    vec![GrammarSymbol::Terminal(Token::Geq), GrammarSymbol::BitOr]
+                                }
                                 Some(Token::LogicalAnd)
                                 | Some(Token::LogicalOr)
                                 | Some(Token::LeftBrace)
@@ -398,26 +864,26 @@ impl Parser {
                                 | Some(Token::RightBracket) => {
                                     vec![]
                                 }
-                                _ => {
-                                    return Err(
-                                        "syntax error: expected comparison symbol".to_string()
-                                    );
-                                }
+                                _ => unreachable!("`BitOr1` lets nothing else through"),
                             },
                             GrammarSymbol::Conditional => match token {
                                 Some(Token::ID(_))
                                 | Some(Token::LeftParen)
                                 | Some(Token::Sub)
+                                | Some(Token::Not)
+                                | Some(Token::BitwiseNot)
                                 | Some(Token::Integer(_))
                                 | Some(Token::Float(_))
+                                | Some(Token::SizedNumber(..))
                                 | Some(Token::Character(_))
                                 | Some(Token::True)
                                 | Some(Token::False) => {
                                     vec![GrammarSymbol::BoolTerm, GrammarSymbol::Conditional1]
                                 }
                                 _ => {
-                                    println!("{:?}", token);
-                                    return Err("syntax error: expected expression 4".to_string());
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected expression 4".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::Conditional1 => match token {
@@ -435,16 +901,17 @@ impl Parser {
                                 | Some(Token::RightBracket) => {
                                     vec![]
                                 }
-                                _ => {
-                                    return Err("syntax error: expected expression 5".to_string());
-                                }
+                                _ => unreachable!("`BoolTerm1` lets nothing else through"),
                             },
                             GrammarSymbol::Value => match token {
                                 Some(Token::ID(_))
                                 | Some(Token::LeftParen)
                                 | Some(Token::Sub)
+                                | Some(Token::Not)
+                                | Some(Token::BitwiseNot)
                                 | Some(Token::Integer(_))
                                 | Some(Token::Float(_))
+                                | Some(Token::SizedNumber(..))
                                 | Some(Token::Character(_))
                                 | Some(Token::True)
                                 | Some(Token::False) => {
@@ -461,9 +928,9 @@ impl Parser {
                                     ]
                                 }
                                 _ => {
-                                    return Err(
-                                        "syntax error: expected conditional or array".to_string()
-                                    );
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected conditional or array".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::Definition => match token {
@@ -490,16 +957,19 @@ impl Parser {
                                     ]
                                 }
                                 _ => {
-                                    return Err(
-                                        "syntax error: expected variable definition".to_string()
-                                    );
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected variable definition".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::Expression => match token {
                                 Some(Token::ID(_))
                                 | Some(Token::Sub)
+                                | Some(Token::Not)
+                                | Some(Token::BitwiseNot)
                                 | Some(Token::Integer(_))
                                 | Some(Token::Float(_))
+                                | Some(Token::SizedNumber(..))
                                 | Some(Token::Character(_))
                                 | Some(Token::True)
                                 | Some(Token::False)
@@ -507,7 +977,9 @@ impl Parser {
                                     vec![GrammarSymbol::Term, GrammarSymbol::Expression1]
                                 }
                                 _ => {
-                                    return Err("syntax error: expected expression 6".to_string());
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected expression 6".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::Expression1 => match token {
@@ -523,7 +995,12 @@ impl Parser {
                                 | Some(Token::LogicalAnd)
                                 | Some(Token::LogicalOr)
                                 | Some(Token::Comma)
-                                | Some(Token::RightBracket) => {
+                                | Some(Token::RightBracket)
+                                | Some(Token::ShiftLeft)
+                                | Some(Token::ShiftRight)
+                                | Some(Token::BitwiseAnd)
+                                | Some(Token::BitwiseXor)
+                                | Some(Token::BitwiseOr) => {
                                     vec![]
                                 }
                                 Some(Token::Sub) => {
@@ -540,31 +1017,46 @@ impl Parser {
                                         GrammarSymbol::Expression1,
                                     ]
                                 }
-                                _ => {
-                                    return Err("syntax error: expected expression 7".to_string());
-                                }
+                                _ => unreachable!("`Term1` lets nothing else through"),
                             },
                             GrammarSymbol::Factor => match token {
                                 Some(Token::LeftParen) => {
                                     vec![
                                         GrammarSymbol::Terminal(Token::LeftParen),
-                                        GrammarSymbol::Expression,
+                                        GrammarSymbol::Conditional,
                                         GrammarSymbol::Terminal(Token::RightParen),
                                     ]
                                 }
                                 Some(Token::ID(_)) => {
                                     vec![GrammarSymbol::ID, GrammarSymbol::IDRest]
                                 }
-                                Some(Token::Sub)
-                                | Some(Token::Integer(_))
+                                Some(Token::Sub) => {
+                                    vec![GrammarSymbol::Terminal(Token::Sub), GrammarSymbol::Factor]
+                                }
+                                Some(Token::Not) => {
+                                    vec![GrammarSymbol::Terminal(Token::Not), GrammarSymbol::Factor]
+                                }
+                                Some(Token::BitwiseNot) => {
+                                    vec![
+                                        GrammarSymbol::Terminal(Token::BitwiseNot),
+                                        GrammarSymbol::Factor,
+                                    ]
+                                }
+                                Some(Token::Integer(_))
                                 | Some(Token::Float(_))
+                                | Some(Token::SizedNumber(..))
                                 | Some(Token::True)
                                 | Some(Token::False) => {
                                     vec![GrammarSymbol::Primitive]
                                 }
-                                _ => {
-                                    return Err("syntax error: expected expression 8".to_string());
-                                }
+                                Some(Token::Character(c)) => {
+                                    vec![GrammarSymbol::Terminal(Token::Character(c))]
+                                }
+                                _ => {
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected expression 8".to_string(),
+                                    ));
+                                }
                             },
                             GrammarSymbol::Field => match token {
                                 Some(Token::ID(_)) => {
@@ -575,9 +1067,9 @@ impl Parser {
                                     ]
                                 }
                                 _ => {
-                                    return Err(
-                                        "syntax error: expected field definition".to_string()
-                                    );
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected field definition".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::FieldList => match token {
@@ -588,9 +1080,9 @@ impl Parser {
                                     vec![]
                                 }
                                 _ => {
-                                    return Err(
-                                        "syntax error: expected field definition".to_string()
-                                    );
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected field definition".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::FieldRest => match token {
@@ -604,9 +1096,9 @@ impl Parser {
                                     vec![]
                                 }
                                 _ => {
-                                    return Err(
-                                        "syntax error: expected field definition".to_string()
-                                    );
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected field definition".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::Func => match token {
@@ -623,9 +1115,9 @@ impl Parser {
                                     ]
                                 }
                                 _ => {
-                                    return Err(
-                                        "syntax error: expected function declaration".to_string()
-                                    );
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected function declaration".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::ID => match token {
@@ -633,8 +1125,9 @@ impl Parser {
                                     vec![GrammarSymbol::Terminal(Token::ID(id.clone()))]
                                 }
                                 _ => {
-                                    println!("{:?}", stack);
-                                    return Err("syntax error: expected identifier".to_string());
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected identifier".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::IDRest => match token {
@@ -662,7 +1155,8 @@ impl Parser {
                                         GrammarSymbol::IDRest,
                                     ]
                                 }
-                                Some(Token::Mul)
+                                Some(Token::As)
+                                | Some(Token::Mul)
                                 | Some(Token::Div)
                                 | Some(Token::Add)
                                 | Some(Token::Sub)
@@ -678,23 +1172,31 @@ impl Parser {
                                 | Some(Token::LogicalAnd)
                                 | Some(Token::LogicalOr)
                                 | Some(Token::Comma)
-                                | Some(Token::RightBracket) => {
+                                | Some(Token::RightBracket)
+                                | Some(Token::Mod)
+                                | Some(Token::ShiftLeft)
+                                | Some(Token::ShiftRight)
+                                | Some(Token::BitwiseAnd)
+                                | Some(Token::BitwiseXor)
+                                | Some(Token::BitwiseOr) => {
                                     // println!("T' -> `");
                                     vec![]
                                 }
                                 _ => {
-                                    println!("{token:?}");
-                                    return Err(
+                                    return Err(self.syntax_error(
                                         "syntax error: expected identifier or function call"
                                             .to_string(),
-                                    );
+                                    ));
                                 }
                             },
                             GrammarSymbol::InputList => match token {
                                 Some(Token::ID(_))
                                 | Some(Token::Sub)
+                                | Some(Token::Not)
+                                | Some(Token::BitwiseNot)
                                 | Some(Token::Integer(_))
                                 | Some(Token::Float(_))
+                                | Some(Token::SizedNumber(..))
                                 | Some(Token::Character(_))
                                 | Some(Token::True)
                                 | Some(Token::False)
@@ -706,8 +1208,9 @@ impl Parser {
                                     vec![]
                                 }
                                 _ => {
-                                    println!("{token:?}");
-                                    return Err("syntax error: expected valid input".to_string());
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected valid input".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::InputRest => match token {
@@ -722,9 +1225,18 @@ impl Parser {
                                     vec![]
                                 }
                                 _ => {
-                                    return Err("syntax error: expected comma".to_string());
+                                    return Err(self
+                                        .syntax_error("syntax error: expected comma".to_string()));
                                 }
                             },
+                            GrammarSymbol::ModuleNT => match token {
+                                Some(Token::Module) => vec![
+                                    GrammarSymbol::Terminal(Token::Module),
+                                    GrammarSymbol::ID,
+                                    GrammarSymbol::NodeBlock,
+                                ],
+                                _ => unreachable!("`Program` only expands `ModuleNT` on `module`"),
+                            },
                             GrammarSymbol::NodeBlock => match token {
                                 Some(Token::LeftBrace) => vec![
                                     GrammarSymbol::Terminal(Token::LeftBrace),
@@ -732,7 +1244,9 @@ impl Parser {
                                     GrammarSymbol::Terminal(Token::RightBrace),
                                 ],
                                 _ => {
-                                    return Err("syntax error: expected {".to_string());
+                                    return Err(
+                                        self.syntax_error("syntax error: expected {".to_string())
+                                    );
                                 }
                             },
                             GrammarSymbol::NodeHeader => match token {
@@ -740,9 +1254,9 @@ impl Parser {
                                     vec![GrammarSymbol::ID, GrammarSymbol::OptIDList]
                                 }
                                 _ => {
-                                    return Err(
-                                        "syntax error: expected identifier for node".to_string()
-                                    );
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected identifier for node".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::NodeList => match token {
@@ -750,8 +1264,10 @@ impl Parser {
                                     vec![GrammarSymbol::ID, GrammarSymbol::NodeRest]
                                 }
                                 _ => {
-                                    return Err("syntax error: expected list of node identifiers"
-                                        .to_string());
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected list of node identifiers"
+                                            .to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::NodeNT => match token {
@@ -763,9 +1279,7 @@ impl Parser {
                                     ]
                                     // println!("N -> node Nh NB");
                                 }
-                                _ => {
-                                    return Err("syntax error: expected node keyword".to_string());
-                                }
+                                _ => unreachable!("`Program` only expands `NodeNT` on `node`"),
                             },
                             GrammarSymbol::NodeRest => match token {
                                 Some(Token::Comma) => vec![
@@ -774,9 +1288,9 @@ impl Parser {
                                 ],
                                 Some(Token::LeftBrace) => vec![],
                                 _ => {
-                                    return Err(
-                                        "syntax error: expected comma for node list".to_string()
-                                    );
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected comma for node list".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::Primitive => match token {
@@ -786,17 +1300,49 @@ impl Parser {
                                 Some(Token::False) => {
                                     vec![GrammarSymbol::Terminal(Token::False)]
                                 }
-                                Some(Token::Sub) => {
+                                Some(Token::Integer(_))
+                                | Some(Token::Float(_))
+                                | Some(Token::SizedNumber(..)) => {
+                                    vec![GrammarSymbol::Positive]
+                                }
+                                _ => unreachable!("`Factor` only expands `Primitive` on a literal"),
+                            },
+                            GrammarSymbol::OptCast => match token {
+                                Some(Token::As) => {
                                     vec![
-                                        GrammarSymbol::Terminal(Token::Sub),
-                                        GrammarSymbol::Positive,
+                                        GrammarSymbol::Terminal(Token::As),
+                                        GrammarSymbol::Type,
+                                        GrammarSymbol::OptCast,
                                     ]
                                 }
-                                Some(Token::Integer(_)) | Some(Token::Float(_)) => {
-                                    vec![GrammarSymbol::Positive]
+                                Some(Token::Mul)
+                                | Some(Token::Div)
+                                | Some(Token::Add)
+                                | Some(Token::Sub)
+                                | Some(Token::RightParen)
+                                | Some(Token::Semicolon)
+                                | Some(Token::Equals)
+                                | Some(Token::Neq)
+                                | Some(Token::Less)
+                                | Some(Token::Greater)
+                                | Some(Token::Leq)
+                                | Some(Token::Geq)
+                                | Some(Token::LeftBrace)
+                                | Some(Token::LogicalAnd)
+                                | Some(Token::LogicalOr)
+                                | Some(Token::Comma)
+                                | Some(Token::RightBracket)
+                                | Some(Token::Mod)
+                                | Some(Token::ShiftLeft)
+                                | Some(Token::ShiftRight)
+                                | Some(Token::BitwiseAnd)
+                                | Some(Token::BitwiseXor)
+                                | Some(Token::BitwiseOr) => {
+                                    vec![]
                                 }
                                 _ => {
-                                    return Err("syntax error: expected number".to_string());
+                                    return Err(self
+                                        .syntax_error("syntax error: expected cast".to_string()));
                                 }
                             },
                             GrammarSymbol::OptElse => match token {
@@ -813,7 +1359,9 @@ impl Parser {
                                     vec![]
                                 }
                                 _ => {
-                                    return Err("syntax error: expected else keyword".to_string());
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected else keyword".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::OptIDList => match token {
@@ -823,7 +1371,9 @@ impl Parser {
                                 ],
                                 Some(Token::LeftBrace) => vec![],
                                 _ => {
-                                    return Err("syntax error: expected : or {".to_string());
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected : or {".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::OptIndex => match token {
@@ -836,6 +1386,17 @@ impl Parser {
                                     ]
                                 }
                                 Some(Token::Assign)
+                                | Some(Token::AddAssign)
+                                | Some(Token::SubAssign)
+                                | Some(Token::MulAssign)
+                                | Some(Token::DivAssign)
+                                | Some(Token::ModAssign)
+                                | Some(Token::AndAssign)
+                                | Some(Token::OrAssign)
+                                | Some(Token::XorAssign)
+                                | Some(Token::ShlAssign)
+                                | Some(Token::ShrAssign)
+                                | Some(Token::As)
                                 | Some(Token::Mul)
                                 | Some(Token::Div)
                                 | Some(Token::Add)
@@ -852,12 +1413,19 @@ impl Parser {
                                 | Some(Token::LogicalAnd)
                                 | Some(Token::LogicalOr)
                                 | Some(Token::Comma)
-                                | Some(Token::RightBracket) => {
+                                | Some(Token::RightBracket)
+                                | Some(Token::Mod)
+                                | Some(Token::ShiftLeft)
+                                | Some(Token::ShiftRight)
+                                | Some(Token::BitwiseAnd)
+                                | Some(Token::BitwiseXor)
+                                | Some(Token::BitwiseOr) => {
                                     vec![]
                                 }
                                 _ => {
-                                    println!("{token:?}");
-                                    return Err("syntax error: expected array index".to_string());
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected array index".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::Param => match token {
@@ -866,9 +1434,9 @@ impl Parser {
                                     GrammarSymbol::Terminal(Token::Colon),
                                     GrammarSymbol::Type,
                                 ],
-                                _ => {
-                                    return Err("syntax error: expected parameter name".to_string());
-                                }
+                                _ => unreachable!(
+                                    "`ParamList` only expands `Param` on an identifier"
+                                ),
                             },
                             GrammarSymbol::ParamList => match token {
                                 Some(Token::ID(_)) => {
@@ -876,7 +1444,9 @@ impl Parser {
                                 }
                                 Some(Token::RightParen) | Some(Token::RightBrace) => vec![],
                                 _ => {
-                                    return Err("syntax error: expected parameter list".to_string());
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected parameter list".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::ParamRest => match token {
@@ -886,10 +1456,9 @@ impl Parser {
                                 ],
                                 Some(Token::RightParen) | Some(Token::RightBrace) => vec![],
                                 _ => {
-                                    println!("{token:?}");
-                                    return Err(
-                                        "syntax error: expected comma for param list".to_string()
-                                    );
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected comma for param list".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::Positive => match token {
@@ -897,28 +1466,44 @@ impl Parser {
                                     vec![GrammarSymbol::Terminal(Token::Integer(num))]
                                 }
                                 Some(Token::Float(num)) => {
-                                    vec![GrammarSymbol::Terminal(Token::Float(num))]
+                                    vec![
+                                        GrammarSymbol::Terminal(Token::Float(num)),
+                                        GrammarSymbol::OptUnit,
+                                    ]
+                                }
+                                Some(Token::SizedNumber(ref text)) => {
+                                    vec![GrammarSymbol::Terminal(Token::SizedNumber(text.clone()))]
                                 }
                                 _ => {
-                                    return Err("syntax error: expected number".to_string());
+                                    unreachable!("`Primitive` only expands `Positive` on a number")
                                 }
                             },
+                            // a unit written after a float literal, as in `3.0 m`
+                            GrammarSymbol::OptUnit => match token {
+                                Some(Token::ID(_)) => vec![GrammarSymbol::ID],
+                                _ => vec![],
+                            },
                             GrammarSymbol::Program => match token {
                                 Some(Token::Node) => {
                                     vec![GrammarSymbol::NodeNT, GrammarSymbol::Program]
                                     // println!("P -> N P");
                                 }
+                                Some(Token::Module) => {
+                                    vec![GrammarSymbol::ModuleNT, GrammarSymbol::Program]
+                                }
                                 None => {
                                     vec![]
                                     // println!("P -> `");
                                 }
                                 _ => {
-                                    return Err("syntax error: expected node keyword".to_string());
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected node or module keyword".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::ReturnType => match token {
                                 Some(Token::ID(_)) | Some(Token::Int) | Some(Token::FloatKW)
-                                | Some(Token::Bool) | Some(Token::Char) => {
+                                | Some(Token::Bool) | Some(Token::Char) | Some(Token::Fixed) => {
                                     vec![GrammarSymbol::Type]
                                 }
                                 Some(Token::LeftParen) => vec![
@@ -927,7 +1512,9 @@ impl Parser {
                                 ],
                                 Some(Token::Not) => vec![GrammarSymbol::Terminal(Token::Not)],
                                 _ => {
-                                    return Err("syntax error: invalid return type".to_string());
+                                    return Err(self.syntax_error(
+                                        "syntax error: invalid return type".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::Stmt => match token {
@@ -962,9 +1549,9 @@ impl Parser {
                                         GrammarSymbol::Terminal(Token::Semicolon),
                                     ]
                                 }
-                                _ => {
-                                    return Err("syntax error: invalid statement".to_string());
-                                }
+                                _ => unreachable!(
+                                    "`StmtList` only expands `Stmt` on a token that starts one"
+                                ),
                             },
                             GrammarSymbol::StmtList => match token {
                                 Some(Token::Var) | Some(Token::Const) | Some(Token::While)
@@ -975,38 +1562,49 @@ impl Parser {
                                     vec![]
                                 }
                                 _ => {
-                                    return Err("syntax error: unrecognized statement".to_string());
+                                    return Err(self.syntax_error(
+                                        "syntax error: unrecognized statement".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::Term => match token {
                                 Some(Token::ID(_))
                                 | Some(Token::Sub)
+                                | Some(Token::Not)
+                                | Some(Token::BitwiseNot)
                                 | Some(Token::Integer(_))
                                 | Some(Token::Float(_))
+                                | Some(Token::SizedNumber(..))
+                                | Some(Token::Character(_))
                                 | Some(Token::True)
                                 | Some(Token::False)
                                 | Some(Token::LeftParen) => {
-                                    vec![GrammarSymbol::Factor, GrammarSymbol::Term1]
-                                }
-                                Some(Token::Character(c)) => {
-                                    vec![GrammarSymbol::Terminal(Token::Character(c))]
+                                    vec![GrammarSymbol::Cast, GrammarSymbol::Term1]
                                 }
                                 _ => {
-                                    return Err("syntax error: expected term".to_string());
+                                    return Err(self
+                                        .syntax_error("syntax error: expected term".to_string()));
                                 }
                             },
                             GrammarSymbol::Term1 => match token {
                                 Some(Token::Mul) => {
                                     vec![
                                         GrammarSymbol::Terminal(Token::Mul),
-                                        GrammarSymbol::Factor,
+                                        GrammarSymbol::Cast,
                                         GrammarSymbol::Term1,
                                     ]
                                 }
                                 Some(Token::Div) => {
                                     vec![
                                         GrammarSymbol::Terminal(Token::Div),
-                                        GrammarSymbol::Factor,
+                                        GrammarSymbol::Cast,
+                                        GrammarSymbol::Term1,
+                                    ]
+                                }
+                                Some(Token::Mod) => {
+                                    vec![
+                                        GrammarSymbol::Terminal(Token::Mod),
+                                        GrammarSymbol::Cast,
                                         GrammarSymbol::Term1,
                                     ]
                                 }
@@ -1024,13 +1622,15 @@ impl Parser {
                                 | Some(Token::LogicalAnd)
                                 | Some(Token::LogicalOr)
                                 | Some(Token::Comma)
-                                | Some(Token::RightBracket) => {
+                                | Some(Token::RightBracket)
+                                | Some(Token::ShiftLeft)
+                                | Some(Token::ShiftRight)
+                                | Some(Token::BitwiseAnd)
+                                | Some(Token::BitwiseXor)
+                                | Some(Token::BitwiseOr) => {
                                     vec![]
                                 }
-                                _ => {
-                                    println!("{:?}", token.unwrap());
-                                    return Err("syntax error: expected term 2".to_string());
-                                }
+                                _ => unreachable!("`OptCast` lets nothing else through"),
                             },
                             GrammarSymbol::TLStmt => match token {
                                 Some(Token::Export) => {
@@ -1039,6 +1639,29 @@ impl Parser {
                                         GrammarSymbol::Definition,
                                     ]
                                 }
+                                Some(Token::Pub) => {
+                                    vec![GrammarSymbol::Terminal(Token::Pub), GrammarSymbol::Item]
+                                }
+                                Some(Token::Test) => {
+                                    vec![GrammarSymbol::Terminal(Token::Test), GrammarSymbol::Func]
+                                }
+                                Some(Token::Use) => {
+                                    vec![
+                                        GrammarSymbol::Terminal(Token::Use),
+                                        GrammarSymbol::ID,
+                                        GrammarSymbol::Terminal(Token::DoubleColon),
+                                        GrammarSymbol::ID,
+                                        GrammarSymbol::Terminal(Token::Semicolon),
+                                    ]
+                                }
+                                Some(Token::Struct) | Some(Token::Fn) => {
+                                    vec![GrammarSymbol::Item]
+                                }
+                                _ => unreachable!(
+                                    "`TLStmtList` only expands `TLStmt` on a token that starts one"
+                                ),
+                            },
+                            GrammarSymbol::Item => match token {
                                 Some(Token::Struct) => {
                                     vec![
                                         GrammarSymbol::Terminal(Token::Struct),
@@ -1052,22 +1675,23 @@ impl Parser {
                                     vec![GrammarSymbol::Func]
                                 }
                                 _ => {
-                                    return Err(
-                                        "syntax error: invalid top level statement".to_string()
-                                    );
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected function or struct".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::TLStmtList => match token {
-                                Some(Token::Fn) | Some(Token::Export) | Some(Token::Struct) => {
+                                Some(Token::Fn) | Some(Token::Export) | Some(Token::Struct)
+                                | Some(Token::Pub) | Some(Token::Use) | Some(Token::Test) => {
                                     vec![GrammarSymbol::TLStmt, GrammarSymbol::TLStmtList]
                                 }
                                 Some(Token::RightBrace) => {
                                     vec![]
                                 }
                                 _ => {
-                                    return Err(
-                                        "syntax error: invalid top level statement 2".to_string()
-                                    );
+                                    return Err(self.syntax_error(
+                                        "syntax error: invalid top level statement 2".to_string(),
+                                    ));
                                 }
                             },
                             GrammarSymbol::Type => match token {
@@ -1078,7 +1702,10 @@ impl Parser {
                                     vec![GrammarSymbol::Terminal(Token::Int)]
                                 }
                                 Some(Token::FloatKW) => {
-                                    vec![GrammarSymbol::Terminal(Token::FloatKW)]
+                                    vec![
+                                        GrammarSymbol::Terminal(Token::FloatKW),
+                                        GrammarSymbol::UnitType,
+                                    ]
                                 }
                                 Some(Token::Bool) => {
                                     vec![GrammarSymbol::Terminal(Token::Bool)]
@@ -1086,6 +1713,15 @@ impl Parser {
                                 Some(Token::Char) => {
                                     vec![GrammarSymbol::Terminal(Token::Char)]
                                 }
+                                Some(Token::Fixed) => vec![
+                                    GrammarSymbol::Terminal(Token::Fixed),
+                                    GrammarSymbol::Terminal(Token::Less),
+                                    GrammarSymbol::FixedBits,
+                                    GrammarSymbol::Terminal(Token::Comma),
+                                    GrammarSymbol::FixedBits,
+                                    GrammarSymbol::FixedMode,
+                                    GrammarSymbol::Terminal(Token::Greater),
+                                ],
                                 Some(Token::LeftBracket) => {
                                     vec![
                                         GrammarSymbol::Terminal(Token::LeftBracket),
@@ -1096,12 +1732,97 @@ impl Parser {
                                     ]
                                 }
                                 _ => {
-                                    return Err("syntax error: expected type".to_string());
+                                    return Err(self
+                                        .syntax_error("syntax error: expected type".to_string()));
+                                }
+                            },
+                            GrammarSymbol::UnitType => match token {
+                                Some(Token::Less) if !self.in_cast(idx) || self.unit_follows() => {
+                                    vec![
+                                        GrammarSymbol::Terminal(Token::Less),
+                                        GrammarSymbol::Unit,
+                                        GrammarSymbol::UnitRest,
+                                        GrammarSymbol::Terminal(Token::Greater),
+                                    ]
+                                }
+                                _ => vec![],
+                            },
+                            GrammarSymbol::Unit => match token {
+                                Some(Token::ID(_)) => {
+                                    vec![GrammarSymbol::ID, GrammarSymbol::UnitPower]
+                                }
+                                // `1` stands for no unit, as in `1/s`
+                                Some(Token::Integer(1)) => {
+                                    vec![GrammarSymbol::Terminal(Token::Integer(1))]
+                                }
+                                _ => {
+                                    return Err(self
+                                        .syntax_error("syntax error: expected unit".to_string()));
+                                }
+                            },
+                            GrammarSymbol::UnitPower => match token {
+                                Some(Token::BitwiseXor) => vec![
+                                    GrammarSymbol::Terminal(Token::BitwiseXor),
+                                    GrammarSymbol::UnitExponent,
+                                ],
+                                _ => vec![],
+                            },
+                            GrammarSymbol::UnitExponent => match token {
+                                Some(Token::Integer(i)) => {
+                                    vec![GrammarSymbol::Terminal(Token::Integer(i))]
+                                }
+                                _ => {
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected integer exponent for unit"
+                                            .to_string(),
+                                    ));
+                                }
+                            },
+                            GrammarSymbol::UnitRest => match token {
+                                Some(Token::Mul) => vec![
+                                    GrammarSymbol::Terminal(Token::Mul),
+                                    GrammarSymbol::Unit,
+                                    GrammarSymbol::UnitRest,
+                                ],
+                                Some(Token::Div) => vec![
+                                    GrammarSymbol::Terminal(Token::Div),
+                                    GrammarSymbol::Unit,
+                                    GrammarSymbol::UnitRest,
+                                ],
+                                Some(Token::Greater) => vec![],
+                                _ => {
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected `*`, `/` or `>` in unit"
+                                            .to_string(),
+                                    ));
+                                }
+                            },
+                            GrammarSymbol::FixedBits => match token {
+                                Some(Token::Integer(i)) => {
+                                    vec![GrammarSymbol::Terminal(Token::Integer(i))]
+                                }
+                                _ => {
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected number of bits".to_string(),
+                                    ));
+                                }
+                            },
+                            // saturating is the default, and `wrap` or `sat` chooses explicitly
+                            GrammarSymbol::FixedMode => match token {
+                                Some(Token::Comma) => {
+                                    vec![GrammarSymbol::Terminal(Token::Comma), GrammarSymbol::ID]
+                                }
+                                Some(Token::Greater) => vec![],
+                                _ => {
+                                    return Err(self.syntax_error(
+                                        "syntax error: expected `,` or `>` in fixed-point type"
+                                            .to_string(),
+                                    ));
                                 }
                             },
                         };
 
-                        if production.len() == 0 {
+                        if production.is_empty() {
                             self.parse_tree.add_child(idx, GrammarSymbol::Empty);
                             idx = self.parse_tree.get_next_nt_sibling(idx);
                             continue;
@@ -1129,7 +1850,7 @@ impl Parser {
                         });
 
                         if has_nt {
-                            idx = next_idx as usize;
+                            idx = next_idx;
                         } else {
                             idx = self.parse_tree.get_next_nt_sibling(idx);
                         }
@@ -1137,17 +1858,199 @@ impl Parser {
                 }
             }
 
-            if stack.is_empty() && token == None {
+            if stack.is_empty() && token.is_none() {
+                self.parse_tree.set_spans(spans);
                 return Ok(());
             }
         }
     }
 
+    fn syntax_error(&self, message: String) -> Diagnostic {
+        Diagnostic::syntax(&message, self.lexer.token_span())
+    }
+
+    /// Whether the parse node `idx` is part of the type of a cast, where a `<` after the type
+    /// may compare rather than start a unit.
+    fn in_cast(&self, idx: usize) -> bool {
+        let parent = |idx: usize| self.parse_tree.parents_list.get(&idx).copied();
+
+        parent(idx)
+            .and_then(parent)
+            .is_some_and(|idx| self.parse_tree.get_node(idx) == GrammarSymbol::OptCast)
+    }
+
+    /// Whether the tokens after the `<` that follows `float` spell a unit closed by `>`. When
+    /// they do not, the `<` compares, as in `d as float < limit`.
+    fn unit_follows(&mut self) -> bool {
+        self.lexer.lookahead(|lexer| {
+            // whether a unit is expected next, rather than `^`, `*`, `/` or `>`
+            let mut expect_unit = true;
+            let mut after_id = false;
+            loop {
+                match (expect_unit, lexer.next_token()) {
+                    (true, Ok(Some(Token::ID(_)))) => (expect_unit, after_id) = (false, true),
+                    (true, Ok(Some(Token::Integer(1)))) => (expect_unit, after_id) = (false, false),
+                    (false, Ok(Some(Token::BitwiseXor))) if after_id => {
+                        if !matches!(lexer.next_token(), Ok(Some(Token::Integer(_)))) {
+                            return false;
+                        }
+                        after_id = false;
+                    }
+                    (false, Ok(Some(Token::Mul | Token::Div))) => expect_unit = true,
+                    (false, Ok(Some(Token::Greater))) => return true,
+                    _ => return false,
+                }
+            }
+        })
+    }
+
+    /// The unit written at the parse node `idx`, which is a `UnitType`, `Unit` or `UnitRest`;
+    /// an empty one has no unit.
+    fn unit(&self, idx: usize) -> Unit {
+        let children = self.parse_tree.get_children(idx);
+        let child = |i: usize| self.parse_tree.get_node(children[i]);
+
+        match (self.parse_tree.get_node(idx), child(0)) {
+            (GrammarSymbol::UnitType, GrammarSymbol::Terminal(Token::Less)) => {
+                self.unit(children[1]).mul(&self.unit(children[2]))
+            }
+            (GrammarSymbol::Unit, GrammarSymbol::ID) => {
+                let SyntaxTreeNode::Identifier(symbol) =
+                    self.build_ast_from_parse_node(children[0]).node
+                else {
+                    return Unit::default();
+                };
+
+                let power = self.parse_tree.get_children(children[1]);
+                let exponent = match power.get(1).map(|&i| self.parse_tree.get_children(i)) {
+                    Some(exponent) => match self.parse_tree.get_node(exponent[0]) {
+                        GrammarSymbol::Terminal(Token::Integer(i)) => i,
+                        _ => 1,
+                    },
+                    None => 1,
+                };
+
+                Unit::symbol(&symbol).pow(exponent)
+            }
+            (GrammarSymbol::UnitRest, GrammarSymbol::Terminal(Token::Mul)) => {
+                self.unit(children[1]).mul(&self.unit(children[2]))
+            }
+            // like division of numbers, `/` only divides by the symbol after it
+            (GrammarSymbol::UnitRest, GrammarSymbol::Terminal(Token::Div)) => {
+                self.unit(children[1]).pow(-1).mul(&self.unit(children[2]))
+            }
+            _ => Unit::default(),
+        }
+    }
+
+    /// The fixed-point type written at the parse node `idx`, which is a `Type`, like
+    /// `fixed<16, 16>` or `fixed<8, 8, wrap>`; saturating is left unwritten.
+    fn fixed_type(&self, idx: usize) -> String {
+        let children = self.parse_tree.get_children(idx);
+        let bits = |i: usize| match self.build_ast_from_parse_node(children[i]).node {
+            SyntaxTreeNode::Integer(bits) => bits,
+            _ => 0,
+        };
+        let mode = match self.parse_tree.get_children(children[5]).get(1) {
+            Some(&mode) => match self.build_ast_from_parse_node(mode).node {
+                SyntaxTreeNode::Identifier(mode) if mode != "sat" => format!(", {mode}"),
+                _ => String::new(),
+            },
+            None => String::new(),
+        };
+
+        format!("fixed<{}, {}{mode}>", bits(2), bits(4))
+    }
+
+    /// `i32` and `f32` are written `int` and `float` everywhere else.
+    fn alias_type(node: &mut SyntaxTreeNode) {
+        if let SyntaxTreeNode::Identifier(t) = node {
+            match t.as_str() {
+                "i32" => *t = "int".to_string(),
+                "f32" => *t = "float".to_string(),
+                _ => {}
+            }
+        }
+    }
+
+    /// `operand` with a minus sign in front of it, where the sign of a number literal is
+    /// folded into the literal. Integers wrap, so `-2147483648` is `i32::MIN`.
+    fn negated(operand: AbstractSyntaxTree) -> AbstractSyntaxTree {
+        let mut tree = AbstractSyntaxTree::new();
+
+        match operand.node {
+            SyntaxTreeNode::Integer(num) => {
+                tree.node = SyntaxTreeNode::Integer(num.wrapping_neg());
+            }
+            SyntaxTreeNode::Float(num) => {
+                tree.node = SyntaxTreeNode::Float(-num);
+                tree.children = operand.children;
+            }
+            SyntaxTreeNode::SizedNumber(text) => {
+                tree.node = SyntaxTreeNode::SizedNumber(match text.strip_prefix('-') {
+                    Some(text) => text.into(),
+                    None => format!("-{text}").into(),
+                });
+            }
+            _ => {
+                tree.node = SyntaxTreeNode::NegOp;
+                tree.children = vec![operand];
+            }
+        }
+
+        tree
+    }
+
     pub fn generate_ast(&mut self) {
         self.ast = self.build_ast_from_parse_node(0);
     }
 
     pub fn build_ast_from_parse_node(&self, idx: usize) -> AbstractSyntaxTree {
+        // expressions nest deeply, so they are built by a function of their own whose frame
+        // holds only what expressions need
+        let mut tree = match self.parse_tree.get_node(idx) {
+            GrammarSymbol::Expression
+            | GrammarSymbol::Expression1
+            | GrammarSymbol::Shift
+            | GrammarSymbol::Shift1
+            | GrammarSymbol::BitAnd
+            | GrammarSymbol::BitAnd1
+            | GrammarSymbol::BitXor
+            | GrammarSymbol::BitXor1
+            | GrammarSymbol::BitOr
+            | GrammarSymbol::BitOr1
+            | GrammarSymbol::Term
+            | GrammarSymbol::Term1
+            | GrammarSymbol::Cast
+            | GrammarSymbol::Factor
+            | GrammarSymbol::Primitive
+            | GrammarSymbol::Positive
+            | GrammarSymbol::IDRest
+            | GrammarSymbol::Conditional
+            | GrammarSymbol::Conditional1
+            | GrammarSymbol::BoolTerm
+            | GrammarSymbol::BoolTerm1
+            | GrammarSymbol::BoolExpr
+            | GrammarSymbol::Comparison
+            | GrammarSymbol::Terminal(
+                Token::Equals | Token::Neq | Token::Less | Token::Greater | Token::Leq | Token::Geq,
+            )
+            | GrammarSymbol::InputList
+            | GrammarSymbol::InputRest => self.build_expression_ast(idx),
+            _ => self.build_statement_ast(idx),
+        };
+
+        // a tree built from part of the node, like a statement that starts with a name, covers
+        // the whole node
+        if let Some(span) = self.parse_tree.span(idx) {
+            tree.span = Some(span);
+        }
+
+        tree
+    }
+
+    /// Builds the tree of a program, an item, a type or a statement.
+    fn build_statement_ast(&self, idx: usize) -> AbstractSyntaxTree {
         let mut tree = AbstractSyntaxTree::new();
         let node = self.parse_tree.get_node(idx);
         let children = self.parse_tree.get_children(idx);
@@ -1197,8 +2100,16 @@ impl Parser {
                     self.build_ast_from_parse_node(children[2]),
                 ];
             }
+            GrammarSymbol::ModuleNT => {
+                tree.node = SyntaxTreeNode::DeclareModule;
+
+                tree.children = vec![
+                    self.build_ast_from_parse_node(children[1]),
+                    self.build_ast_from_parse_node(children[2]),
+                ];
+            }
             GrammarSymbol::Program => match self.parse_tree.get_node(children[0]) {
-                GrammarSymbol::NodeNT => {
+                GrammarSymbol::NodeNT | GrammarSymbol::ModuleNT => {
                     tree.node = SyntaxTreeNode::NodeSeq;
 
                     tree.children = vec![
@@ -1225,6 +2136,31 @@ impl Parser {
                 _ => {}
             },
             GrammarSymbol::TLStmt => match self.parse_tree.get_node(children[0]) {
+                GrammarSymbol::Item => {
+                    tree = self.build_ast_from_parse_node(children[0]);
+                }
+                GrammarSymbol::Terminal(Token::Export) => {
+                    tree.node = SyntaxTreeNode::Export;
+                    tree.children = vec![self.build_ast_from_parse_node(children[1])];
+                }
+                GrammarSymbol::Terminal(Token::Pub) => {
+                    tree.node = SyntaxTreeNode::Public;
+                    tree.children = vec![self.build_ast_from_parse_node(children[1])];
+                }
+                GrammarSymbol::Terminal(Token::Test) => {
+                    tree.node = SyntaxTreeNode::Test;
+                    tree.children = vec![self.build_ast_from_parse_node(children[1])];
+                }
+                GrammarSymbol::Terminal(Token::Use) => {
+                    tree.node = SyntaxTreeNode::Use;
+                    tree.children = vec![
+                        self.build_ast_from_parse_node(children[1]),
+                        self.build_ast_from_parse_node(children[3]),
+                    ];
+                }
+                _ => {}
+            },
+            GrammarSymbol::Item => match self.parse_tree.get_node(children[0]) {
                 GrammarSymbol::Func => {
                     tree = self.build_ast_from_parse_node(children[0]);
                 }
@@ -1330,12 +2266,14 @@ impl Parser {
             GrammarSymbol::Type => match self.parse_tree.get_node(children[0]) {
                 GrammarSymbol::ID => {
                     tree = self.build_ast_from_parse_node(children[0]);
+                    Self::alias_type(&mut tree.node);
                 }
                 GrammarSymbol::Terminal(Token::Int) => {
                     tree.node = SyntaxTreeNode::Identifier("int".to_string());
                 }
                 GrammarSymbol::Terminal(Token::FloatKW) => {
-                    tree.node = SyntaxTreeNode::Identifier("float".to_string());
+                    let unit = self.unit(children[1]);
+                    tree.node = SyntaxTreeNode::Identifier(units::with_unit("float", &unit));
                 }
                 GrammarSymbol::Terminal(Token::Bool) => {
                     tree.node = SyntaxTreeNode::Identifier("bool".to_string());
@@ -1343,6 +2281,9 @@ impl Parser {
                 GrammarSymbol::Terminal(Token::Char) => {
                     tree.node = SyntaxTreeNode::Identifier("char".to_string());
                 }
+                GrammarSymbol::Terminal(Token::Fixed) => {
+                    tree.node = SyntaxTreeNode::Identifier(self.fixed_type(idx));
+                }
                 GrammarSymbol::Terminal(Token::LeftBracket) => {
                     let t = self.build_ast_from_parse_node(children[1]);
                     let t = match t.node {
@@ -1364,12 +2305,13 @@ impl Parser {
             GrammarSymbol::Array => {
                 tree = self.build_ast_from_parse_node(children[1]);
             }
-            GrammarSymbol::ArrLen => match self.parse_tree.get_node(children[0]) {
-                GrammarSymbol::Terminal(Token::Integer(i)) => {
+            GrammarSymbol::ArrLen | GrammarSymbol::FixedBits => {
+                if let GrammarSymbol::Terminal(Token::Integer(i)) =
+                    self.parse_tree.get_node(children[0])
+                {
                     tree.node = SyntaxTreeNode::Integer(i);
                 }
-                _ => {}
-            },
+            }
             GrammarSymbol::OptIndex => match self.parse_tree.get_node(children[0]) {
                 GrammarSymbol::Terminal(Token::LeftBracket) => {
                     tree.node = SyntaxTreeNode::Index;
@@ -1388,17 +2330,32 @@ impl Parser {
             GrammarSymbol::Block => {
                 tree = self.build_ast_from_parse_node(children[1]);
             }
-            GrammarSymbol::StmtList => match self.parse_tree.get_node(children[0]) {
-                GrammarSymbol::Empty => {}
-                _ => {
-                    tree.node = SyntaxTreeNode::StmtSeq;
+            // the statements are built one after another rather than each list inside the
+            // one before, so the stack does not grow with the length of a block
+            GrammarSymbol::StmtList => {
+                let mut statements = vec![];
+                let mut list = idx;
+                loop {
+                    let children = self.parse_tree.get_children(list);
+                    if self.parse_tree.get_node(children[0]) == GrammarSymbol::Empty {
+                        break;
+                    }
 
-                    tree.children = vec![
+                    statements.push((
                         self.build_ast_from_parse_node(children[0]),
-                        self.build_ast_from_parse_node(children[1]),
-                    ];
+                        self.parse_tree.span(list),
+                    ));
+                    list = children[1];
                 }
-            },
+
+                for (statement, span) in statements.into_iter().rev() {
+                    tree = AbstractSyntaxTree {
+                        node: SyntaxTreeNode::StmtSeq,
+                        children: vec![statement, tree],
+                        span,
+                    };
+                }
+            }
             GrammarSymbol::Value => match self.parse_tree.get_node(children[0]) {
                 GrammarSymbol::Terminal(Token::LeftBrace) => {
                     tree = self.build_ast_from_parse_node(children[1]);
@@ -1431,8 +2388,12 @@ impl Parser {
                 GrammarSymbol::ID => {
                     tree = self.build_ast_from_parse_node(children[1]);
 
-                    tree.children
-                        .insert(0, self.build_ast_from_parse_node(children[0]));
+                    let mut id = self.build_ast_from_parse_node(children[0]);
+                    if let SyntaxTreeNode::Identifier(name) = id.node {
+                        id.node = SyntaxTreeNode::Identifier(name + &self.field_path(children[1]));
+                    }
+
+                    tree.children.insert(0, id);
                 }
                 GrammarSymbol::Terminal(Token::Return) => {
                     tree.node = SyntaxTreeNode::ReturnValue;
@@ -1477,16 +2438,58 @@ impl Parser {
 
                     tree.children = vec![self.build_ast_from_parse_node(children[1])];
                 }
-                GrammarSymbol::OptIndex => {
-                    tree.node = SyntaxTreeNode::Assign;
+                GrammarSymbol::OptField => {
+                    let op_idx = self.parse_tree.get_children(children[2])[0];
+                    let op = match self.parse_tree.get_node(op_idx) {
+                        GrammarSymbol::Terminal(Token::AddAssign) => SyntaxTreeNode::AddOp,
+                        GrammarSymbol::Terminal(Token::SubAssign) => SyntaxTreeNode::SubOp,
+                        GrammarSymbol::Terminal(Token::MulAssign) => SyntaxTreeNode::MulOp,
+                        GrammarSymbol::Terminal(Token::DivAssign) => SyntaxTreeNode::DivOp,
+                        GrammarSymbol::Terminal(Token::ModAssign) => SyntaxTreeNode::ModOp,
+                        GrammarSymbol::Terminal(Token::AndAssign) => SyntaxTreeNode::BitAndOp,
+                        GrammarSymbol::Terminal(Token::OrAssign) => SyntaxTreeNode::BitOrOp,
+                        GrammarSymbol::Terminal(Token::XorAssign) => SyntaxTreeNode::BitXorOp,
+                        GrammarSymbol::Terminal(Token::ShlAssign) => SyntaxTreeNode::ShlOp,
+                        GrammarSymbol::Terminal(Token::ShrAssign) => SyntaxTreeNode::ShrOp,
+                        _ => SyntaxTreeNode::Null,
+                    };
 
-                    tree.children = vec![
-                        self.build_ast_from_parse_node(children[0]),
-                        self.build_ast_from_parse_node(children[2]),
-                    ];
+                    if op == SyntaxTreeNode::Null {
+                        tree.node = SyntaxTreeNode::Assign;
+
+                        tree.children = vec![
+                            self.build_ast_from_parse_node(children[1]),
+                            self.build_ast_from_parse_node(children[3]),
+                        ];
+                    } else {
+                        let mut op_tree = AbstractSyntaxTree::new();
+                        op_tree.node = op;
+
+                        tree.node = SyntaxTreeNode::CompoundAssign;
+
+                        tree.children = vec![
+                            self.build_ast_from_parse_node(children[1]),
+                            op_tree,
+                            self.build_ast_from_parse_node(children[3]),
+                        ];
+                    }
                 }
                 _ => {}
             },
+            e => {
+                println!("unresolved {:?}", e);
+            }
+        };
+
+        tree
+    }
+
+    /// Builds the tree of an expression or a list of call arguments.
+    fn build_expression_ast(&self, idx: usize) -> AbstractSyntaxTree {
+        let mut tree = AbstractSyntaxTree::new();
+        let children = self.parse_tree.get_children(idx);
+
+        match self.parse_tree.get_node(idx) {
             GrammarSymbol::Expression => {
                 let subtree = self.build_ast_from_parse_node(children[1]);
 
@@ -1496,7 +2499,7 @@ impl Parser {
                         tree.children
                             .insert(0, self.build_ast_from_parse_node(children[0]));
 
-                        tree = Self::rebalance_expression_tree(tree);
+                        tree = Self::rebalance_tree(tree, self.count_operators(children[1]));
                     }
                     _ => {
                         tree = self.build_ast_from_parse_node(children[0]);
@@ -1548,34 +2551,212 @@ impl Parser {
                     tree.node = SyntaxTreeNode::Null;
                 }
             },
-            GrammarSymbol::Term => match self.parse_tree.get_node(children[0]) {
-                GrammarSymbol::Factor => {
-                    let subtree = self.build_ast_from_parse_node(children[1]);
+            GrammarSymbol::Shift => {
+                let subtree = self.build_ast_from_parse_node(children[1]);
+
+                match subtree.node {
+                    SyntaxTreeNode::ShlOp | SyntaxTreeNode::ShrOp => {
+                        tree = subtree;
+                        tree.children
+                            .insert(0, self.build_ast_from_parse_node(children[0]));
+
+                        tree = Self::rebalance_tree(tree, self.count_operators(children[1]));
+                    }
+                    _ => {
+                        tree = self.build_ast_from_parse_node(children[0]);
+                    }
+                }
+            }
+            GrammarSymbol::Shift1 => match self.parse_tree.get_node(children[0]) {
+                GrammarSymbol::Terminal(Token::ShiftLeft) => {
+                    let mut subtree = self.build_ast_from_parse_node(children[2]);
 
                     match subtree.node {
-                        SyntaxTreeNode::MulOp | SyntaxTreeNode::DivOp => {
-                            tree = subtree;
-                            tree.children
-                                .insert(0, self.build_ast_from_parse_node(children[0]));
+                        SyntaxTreeNode::ShlOp | SyntaxTreeNode::ShrOp => {
+                            tree.node = SyntaxTreeNode::ShlOp;
+
+                            subtree
+                                .children
+                                .insert(0, self.build_ast_from_parse_node(children[1]));
 
-                            tree = Self::rebalance_term_tree(tree);
+                            tree.children = vec![subtree];
                         }
                         _ => {
-                            tree = self.build_ast_from_parse_node(children[0]);
+                            tree.node = SyntaxTreeNode::ShlOp;
+
+                            tree.children = vec![self.build_ast_from_parse_node(children[1])];
                         }
                     }
                 }
-                GrammarSymbol::Terminal(Token::Character(c)) => {
-                    tree.node = SyntaxTreeNode::Character(c);
+                GrammarSymbol::Terminal(Token::ShiftRight) => {
+                    let mut subtree = self.build_ast_from_parse_node(children[2]);
+
+                    match subtree.node {
+                        SyntaxTreeNode::ShlOp | SyntaxTreeNode::ShrOp => {
+                            tree.node = SyntaxTreeNode::ShrOp;
+
+                            subtree
+                                .children
+                                .insert(0, self.build_ast_from_parse_node(children[1]));
+
+                            tree.children = vec![subtree];
+                        }
+                        _ => {
+                            tree.node = SyntaxTreeNode::ShrOp;
+
+                            tree.children = vec![self.build_ast_from_parse_node(children[1])];
+                        }
+                    }
+                }
+                _ => {
+                    tree.node = SyntaxTreeNode::Null;
+                }
+            },
+            GrammarSymbol::BitAnd => {
+                let subtree = self.build_ast_from_parse_node(children[1]);
+
+                match subtree.node {
+                    SyntaxTreeNode::BitAndOp => {
+                        tree = subtree;
+                        tree.children
+                            .insert(0, self.build_ast_from_parse_node(children[0]));
+
+                        tree = Self::rebalance_tree(tree, self.count_operators(children[1]));
+                    }
+                    _ => {
+                        tree = self.build_ast_from_parse_node(children[0]);
+                    }
+                }
+            }
+            GrammarSymbol::BitAnd1 => match self.parse_tree.get_node(children[0]) {
+                GrammarSymbol::Terminal(Token::BitwiseAnd) => {
+                    let mut subtree = self.build_ast_from_parse_node(children[2]);
+
+                    match subtree.node {
+                        SyntaxTreeNode::BitAndOp => {
+                            tree.node = SyntaxTreeNode::BitAndOp;
+
+                            subtree
+                                .children
+                                .insert(0, self.build_ast_from_parse_node(children[1]));
+
+                            tree.children = vec![subtree];
+                        }
+                        _ => {
+                            tree.node = SyntaxTreeNode::BitAndOp;
+
+                            tree.children = vec![self.build_ast_from_parse_node(children[1])];
+                        }
+                    }
+                }
+                _ => {
+                    tree.node = SyntaxTreeNode::Null;
+                }
+            },
+            GrammarSymbol::BitXor => {
+                let subtree = self.build_ast_from_parse_node(children[1]);
+
+                match subtree.node {
+                    SyntaxTreeNode::BitXorOp => {
+                        tree = subtree;
+                        tree.children
+                            .insert(0, self.build_ast_from_parse_node(children[0]));
+
+                        tree = Self::rebalance_tree(tree, self.count_operators(children[1]));
+                    }
+                    _ => {
+                        tree = self.build_ast_from_parse_node(children[0]);
+                    }
+                }
+            }
+            GrammarSymbol::BitXor1 => match self.parse_tree.get_node(children[0]) {
+                GrammarSymbol::Terminal(Token::BitwiseXor) => {
+                    let mut subtree = self.build_ast_from_parse_node(children[2]);
+
+                    match subtree.node {
+                        SyntaxTreeNode::BitXorOp => {
+                            tree.node = SyntaxTreeNode::BitXorOp;
+
+                            subtree
+                                .children
+                                .insert(0, self.build_ast_from_parse_node(children[1]));
+
+                            tree.children = vec![subtree];
+                        }
+                        _ => {
+                            tree.node = SyntaxTreeNode::BitXorOp;
+
+                            tree.children = vec![self.build_ast_from_parse_node(children[1])];
+                        }
+                    }
+                }
+                _ => {
+                    tree.node = SyntaxTreeNode::Null;
                 }
-                _ => {}
             },
+            GrammarSymbol::BitOr => {
+                let subtree = self.build_ast_from_parse_node(children[1]);
+
+                match subtree.node {
+                    SyntaxTreeNode::BitOrOp => {
+                        tree = subtree;
+                        tree.children
+                            .insert(0, self.build_ast_from_parse_node(children[0]));
+
+                        tree = Self::rebalance_tree(tree, self.count_operators(children[1]));
+                    }
+                    _ => {
+                        tree = self.build_ast_from_parse_node(children[0]);
+                    }
+                }
+            }
+            GrammarSymbol::BitOr1 => match self.parse_tree.get_node(children[0]) {
+                GrammarSymbol::Terminal(Token::BitwiseOr) => {
+                    let mut subtree = self.build_ast_from_parse_node(children[2]);
+
+                    match subtree.node {
+                        SyntaxTreeNode::BitOrOp => {
+                            tree.node = SyntaxTreeNode::BitOrOp;
+
+                            subtree
+                                .children
+                                .insert(0, self.build_ast_from_parse_node(children[1]));
+
+                            tree.children = vec![subtree];
+                        }
+                        _ => {
+                            tree.node = SyntaxTreeNode::BitOrOp;
+
+                            tree.children = vec![self.build_ast_from_parse_node(children[1])];
+                        }
+                    }
+                }
+                _ => {
+                    tree.node = SyntaxTreeNode::Null;
+                }
+            },
+            GrammarSymbol::Term => {
+                let subtree = self.build_ast_from_parse_node(children[1]);
+
+                match subtree.node {
+                    SyntaxTreeNode::MulOp | SyntaxTreeNode::DivOp | SyntaxTreeNode::ModOp => {
+                        tree = subtree;
+                        tree.children
+                            .insert(0, self.build_ast_from_parse_node(children[0]));
+
+                        tree = Self::rebalance_tree(tree, self.count_operators(children[1]));
+                    }
+                    _ => {
+                        tree = self.build_ast_from_parse_node(children[0]);
+                    }
+                }
+            }
             GrammarSymbol::Term1 => match self.parse_tree.get_node(children[0]) {
                 GrammarSymbol::Terminal(Token::Mul) => {
                     let mut subtree = self.build_ast_from_parse_node(children[2]);
 
                     match subtree.node {
-                        SyntaxTreeNode::MulOp | SyntaxTreeNode::DivOp => {
+                        SyntaxTreeNode::MulOp | SyntaxTreeNode::DivOp | SyntaxTreeNode::ModOp => {
                             tree.node = SyntaxTreeNode::MulOp;
 
                             subtree
@@ -1595,7 +2776,7 @@ impl Parser {
                     let mut subtree = self.build_ast_from_parse_node(children[2]);
 
                     match subtree.node {
-                        SyntaxTreeNode::MulOp | SyntaxTreeNode::DivOp => {
+                        SyntaxTreeNode::MulOp | SyntaxTreeNode::DivOp | SyntaxTreeNode::ModOp => {
                             tree.node = SyntaxTreeNode::DivOp;
 
                             subtree
@@ -1611,10 +2792,43 @@ impl Parser {
                         }
                     }
                 }
+                GrammarSymbol::Terminal(Token::Mod) => {
+                    let mut subtree = self.build_ast_from_parse_node(children[2]);
+
+                    match subtree.node {
+                        SyntaxTreeNode::MulOp | SyntaxTreeNode::DivOp | SyntaxTreeNode::ModOp => {
+                            tree.node = SyntaxTreeNode::ModOp;
+
+                            subtree
+                                .children
+                                .insert(0, self.build_ast_from_parse_node(children[1]));
+
+                            tree.children = vec![subtree];
+                        }
+                        _ => {
+                            tree.node = SyntaxTreeNode::ModOp;
+
+                            tree.children = vec![self.build_ast_from_parse_node(children[1])];
+                        }
+                    }
+                }
                 _ => {
                     tree.node = SyntaxTreeNode::Null;
                 }
             },
+            GrammarSymbol::Cast => {
+                tree = self.build_ast_from_parse_node(children[0]);
+
+                let mut opt_cast = self.parse_tree.get_children(children[1]);
+                while opt_cast.len() > 1 {
+                    let mut cast = AbstractSyntaxTree::new();
+                    cast.node = SyntaxTreeNode::Cast;
+                    cast.children = vec![tree, self.build_ast_from_parse_node(opt_cast[1])];
+
+                    tree = cast;
+                    opt_cast = self.parse_tree.get_children(opt_cast[2]);
+                }
+            }
             GrammarSymbol::Factor => match self.parse_tree.get_node(children[0]) {
                 GrammarSymbol::Primitive => {
                     tree = self.build_ast_from_parse_node(children[0]);
@@ -1653,17 +2867,23 @@ impl Parser {
                 GrammarSymbol::Terminal(Token::LeftParen) => {
                     tree = self.build_ast_from_parse_node(children[1]);
                 }
+                GrammarSymbol::Terminal(Token::Character(c)) => {
+                    tree.node = SyntaxTreeNode::Character(c);
+                }
+                GrammarSymbol::Terminal(Token::Sub) => {
+                    tree = Self::negated(self.build_ast_from_parse_node(children[1]));
+                }
+                GrammarSymbol::Terminal(Token::Not) => {
+                    tree.node = SyntaxTreeNode::NotOp;
+                    tree.children = vec![self.build_ast_from_parse_node(children[1])];
+                }
+                GrammarSymbol::Terminal(Token::BitwiseNot) => {
+                    tree.node = SyntaxTreeNode::BitNotOp;
+                    tree.children = vec![self.build_ast_from_parse_node(children[1])];
+                }
                 _ => {}
             },
             GrammarSymbol::Primitive => match self.parse_tree.get_node(children[0]) {
-                GrammarSymbol::Terminal(Token::Sub) => {
-                    let subtree = self.build_ast_from_parse_node(children[1]);
-                    tree.node = match subtree.node {
-                        SyntaxTreeNode::Integer(num) => SyntaxTreeNode::Integer(-1 * num),
-                        SyntaxTreeNode::Float(num) => SyntaxTreeNode::Float(-1.0 * num),
-                        _ => SyntaxTreeNode::Null,
-                    };
-                }
                 GrammarSymbol::Positive => {
                     tree = self.build_ast_from_parse_node(children[0]);
                 }
@@ -1679,8 +2899,17 @@ impl Parser {
                 GrammarSymbol::Terminal(Token::Integer(num)) => {
                     tree.node = SyntaxTreeNode::Integer(num);
                 }
+                GrammarSymbol::Terminal(Token::SizedNumber(text)) => {
+                    tree.node = SyntaxTreeNode::SizedNumber(text.into());
+                }
                 GrammarSymbol::Terminal(Token::Float(num)) => {
                     tree.node = SyntaxTreeNode::Float(num);
+
+                    // the unit of a literal is kept as its child
+                    let unit = self.parse_tree.get_children(children[1]);
+                    if self.parse_tree.get_node(unit[0]) == GrammarSymbol::ID {
+                        tree.children = vec![self.build_ast_from_parse_node(unit[0])];
+                    }
                 }
                 _ => {}
             },
@@ -1711,7 +2940,7 @@ impl Parser {
                         tree.children
                             .insert(0, self.build_ast_from_parse_node(children[0]));
 
-                        tree = Self::rebalance_comparison_tree(tree);
+                        tree = Self::rebalance_tree(tree, self.count_operators(children[1]));
                     }
                     _ => {
                         tree = self.build_ast_from_parse_node(children[0]);
@@ -1752,7 +2981,7 @@ impl Parser {
                         tree.children
                             .insert(0, self.build_ast_from_parse_node(children[0]));
 
-                        tree = Self::rebalance_bool_term_tree(tree);
+                        tree = Self::rebalance_tree(tree, self.count_operators(children[1]));
                     }
                     _ => {
                         tree = self.build_ast_from_parse_node(children[0]);
@@ -1848,139 +3077,61 @@ impl Parser {
                 }
                 _ => {}
             },
-            e => {
-                println!("unresolved {:?}", e);
-            }
+            _ => unreachable!("only expressions are built here"),
         };
 
         tree
     }
 
-    fn rebalance_expression_tree(mut tree: AbstractSyntaxTree) -> AbstractSyntaxTree {
-        let mut stack = LinkedList::new();
+    fn field_path(&self, idx: usize) -> String {
+        let mut path = String::new();
 
-        let mut root = tree;
+        let mut field = match self.parse_tree.get_children(idx).first() {
+            Some(&first) if self.parse_tree.get_node(first) == GrammarSymbol::OptField => first,
+            _ => return path,
+        };
 
-        while root.children.len() > 1 {
-            if root.node != SyntaxTreeNode::AddOp && root.node != SyntaxTreeNode::SubOp {
+        loop {
+            let children = self.parse_tree.get_children(field);
+            if children.len() < 3 {
                 break;
             }
 
-            let mut subtree = root.clone();
-            subtree.children.remove(1);
-            stack.push_back(subtree);
-
-            root = root.children[1].clone();
-        }
-
-        stack.push_back(root);
-
-        tree = stack.pop_front().unwrap();
-        while !stack.is_empty() {
-            let mut front = stack.pop_front().unwrap();
-
-            if front.children.len() > 0
-                && (front.node == SyntaxTreeNode::AddOp || front.node == SyntaxTreeNode::SubOp)
+            if let SyntaxTreeNode::Identifier(name) =
+                self.build_ast_from_parse_node(children[1]).node
             {
-                tree.children.push(front.children[0].clone());
-
-                front.children = vec![tree];
-
-                tree = front;
-            } else {
-                tree.children.push(front);
-            }
-        }
-
-        tree
-    }
-
-    fn rebalance_term_tree(mut tree: AbstractSyntaxTree) -> AbstractSyntaxTree {
-        let mut stack = LinkedList::new();
-
-        let mut root = tree;
-
-        while root.children.len() > 1 {
-            if root.node != SyntaxTreeNode::MulOp && root.node != SyntaxTreeNode::DivOp {
-                break;
+                path.push('.');
+                path.push_str(&name);
             }
 
-            let mut subtree = root.clone();
-            subtree.children.remove(1);
-            stack.push_back(subtree);
-
-            root = root.children[1].clone();
+            field = children[2];
         }
 
-        stack.push_back(root);
-
-        tree = stack.pop_front().unwrap();
-        while !stack.is_empty() {
-            let mut front = stack.pop_front().unwrap();
-
-            if front.children.len() > 0
-                && (front.node == SyntaxTreeNode::MulOp || front.node == SyntaxTreeNode::DivOp)
-            {
-                tree.children.push(front.children[0].clone());
-
-                front.children = vec![tree];
-
-                tree = front;
-            } else {
-                tree.children.push(front);
-            }
-        }
-
-        tree
+        path
     }
 
-    fn rebalance_comparison_tree(mut tree: AbstractSyntaxTree) -> AbstractSyntaxTree {
-        let mut stack = LinkedList::new();
-
-        let mut root = tree;
+    fn count_operators(&self, mut idx: usize) -> usize {
+        let mut count = 0;
 
-        while root.children.len() > 1 {
-            if root.node != SyntaxTreeNode::OrOp {
+        loop {
+            let children = self.parse_tree.get_children(idx);
+            if children.len() < 3 {
                 break;
             }
 
-            let mut subtree = root.clone();
-            subtree.children.remove(1);
-            stack.push_back(subtree);
-
-            root = root.children[1].clone();
+            count += 1;
+            idx = children[2];
         }
 
-        stack.push_back(root);
-
-        tree = stack.pop_front().unwrap();
-        while !stack.is_empty() {
-            let mut front = stack.pop_front().unwrap();
-
-            if front.children.len() > 0 && front.node == SyntaxTreeNode::OrOp {
-                tree.children.push(front.children[0].clone());
-
-                front.children = vec![tree];
-
-                tree = front;
-            } else {
-                tree.children.push(front);
-            }
-        }
-
-        tree
+        count
     }
 
-    fn rebalance_bool_term_tree(mut tree: AbstractSyntaxTree) -> AbstractSyntaxTree {
+    fn rebalance_tree(mut tree: AbstractSyntaxTree, operators: usize) -> AbstractSyntaxTree {
         let mut stack = LinkedList::new();
 
         let mut root = tree;
 
-        while root.children.len() > 1 {
-            if root.node != SyntaxTreeNode::AndOp {
-                break;
-            }
-
+        for _ in 0..operators {
             let mut subtree = root.clone();
             subtree.children.remove(1);
             stack.push_back(subtree);
@@ -1988,23 +3139,17 @@ impl Parser {
             root = root.children[1].clone();
         }
 
-        stack.push_back(root);
-
         tree = stack.pop_front().unwrap();
-        while !stack.is_empty() {
-            let mut front = stack.pop_front().unwrap();
+        while let Some(mut front) = stack.pop_front() {
+            tree.children.push(front.children[0].clone());
 
-            if front.children.len() > 0 && front.node == SyntaxTreeNode::AndOp {
-                tree.children.push(front.children[0].clone());
+            front.children = vec![tree];
 
-                front.children = vec![tree];
-
-                tree = front;
-            } else {
-                tree.children.push(front);
-            }
+            tree = front;
         }
 
+        tree.children.push(root);
+
         tree
     }
 }
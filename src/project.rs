@@ -0,0 +1,314 @@
+//! Projects made of many source files, described by a `karma.toml` manifest.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    diagnostic::Diagnostic,
+    lexer::Span,
+    parser::{AbstractSyntaxTree, SyntaxTreeNode},
+};
+
+pub const MANIFEST_FILE: &str = "karma.toml";
+
+/// The `[project]` table of a `karma.toml` manifest:
+///
+/// ```toml
+/// [project]
+/// name = "rover"
+/// sources = ["nodes", "drivers/motor.krm"]
+/// out-dir = "build"
+/// entry = ["Planner"]
+/// ```
+///
+/// Paths are relative to the directory holding the manifest. Every `.krm` file under a listed
+/// directory is a source.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default = "Manifest::default_sources")]
+    pub sources: Vec<PathBuf>,
+    #[serde(default = "Manifest::default_out_dir")]
+    pub out_dir: PathBuf,
+    /// The nodes meant to be run, which must be declared somewhere in the sources.
+    #[serde(default)]
+    pub entry: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestFile {
+    project: Manifest,
+}
+
+impl Manifest {
+    fn default_sources() -> Vec<PathBuf> {
+        vec![PathBuf::from(".")]
+    }
+
+    fn default_out_dir() -> PathBuf {
+        PathBuf::from("comp")
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str::<ManifestFile>(text)
+            .map(|file| file.project)
+            .map_err(|e| e.message().to_string())
+    }
+
+    /// Reads `dir/karma.toml` if there is one, with its paths resolved against `dir`.
+    pub fn load(dir: &Path) -> io::Result<Option<Self>> {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let text = fs::read_to_string(&path)?;
+        let mut manifest = Self::parse(&text).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {e}", path.display()),
+            )
+        })?;
+
+        manifest.sources = manifest
+            .sources
+            .iter()
+            .map(|source| dir.join(source))
+            .collect();
+        manifest.out_dir = dir.join(&manifest.out_dir);
+
+        Ok(Some(manifest))
+    }
+
+    /// Lists the `.krm` files the manifest names, sorted and without repeats.
+    pub fn source_files(&self) -> io::Result<Vec<PathBuf>> {
+        let mut files = vec![];
+
+        for source in &self.sources {
+            if source.is_dir() {
+                find_sources(source, &mut files)?;
+            } else if source.is_file() {
+                files.push(source.clone());
+            } else {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("source {} does not exist", source.display()),
+                ));
+            }
+        }
+
+        files.sort();
+        files.dedup();
+
+        Ok(files)
+    }
+}
+
+/// Lists the sources of the project in `dir`: the ones its manifest names, or every `.krm` file
+/// under `dir` when it has no manifest.
+pub fn project_sources(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    match Manifest::load(dir)? {
+        Some(manifest) => manifest.source_files(),
+        None => {
+            let mut files = vec![];
+            find_sources(dir, &mut files)?;
+            files.sort();
+            Ok(files)
+        }
+    }
+}
+
+/// Collects every `.krm` file under `dir`.
+pub(crate) fn find_sources(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            find_sources(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "krm") {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// A node declaration as written in a source file.
+struct NodeHeader<'a> {
+    name: &'a str,
+    span: Span,
+    dependencies: Vec<(&'a str, Span)>,
+}
+
+/// Adds the headers of the nodes declared in the node sequence `ast` to `headers`, in order.
+fn node_headers<'a>(ast: &'a AbstractSyntaxTree, headers: &mut Vec<NodeHeader<'a>>) {
+    match &ast.node {
+        SyntaxTreeNode::NodeSeq => {
+            for child in &ast.children {
+                node_headers(child, headers);
+            }
+        }
+        SyntaxTreeNode::DeclareNode => {
+            let header = &ast.children[0];
+            if let (SyntaxTreeNode::Identifier(name), Some(span)) =
+                (&header.children[0].node, header.children[0].span)
+            {
+                let mut dependencies = vec![];
+                node_dependencies(&header.children[1], &mut dependencies);
+                headers.push(NodeHeader {
+                    name,
+                    span,
+                    dependencies,
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Adds the names in the dependency list `ast` of a node header to `dependencies`.
+fn node_dependencies<'a>(ast: &'a AbstractSyntaxTree, dependencies: &mut Vec<(&'a str, Span)>) {
+    match (&ast.node, ast.span) {
+        (SyntaxTreeNode::Identifier(name), Some(span)) => dependencies.push((name, span)),
+        (SyntaxTreeNode::NodeList, _) => {
+            for child in &ast.children {
+                node_dependencies(child, dependencies);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Reports nodes declared twice, nodes that depend on themselves, dependencies on nodes that
+/// are not declared anywhere and cycles of dependencies. `files` pairs the syntax tree of each
+/// source with its file, if it has one.
+pub(crate) fn check_nodes(files: &[(Option<PathBuf>, AbstractSyntaxTree)]) -> Vec<Diagnostic> {
+    let files: Vec<_> = files
+        .iter()
+        .map(|(file, ast)| {
+            let mut headers = vec![];
+            node_headers(ast, &mut headers);
+            (file, headers)
+        })
+        .collect();
+
+    let mut diagnostics = vec![];
+    let mut declared: HashMap<&str, (&Option<PathBuf>, &NodeHeader)> = HashMap::new();
+    let mut declaration_order = vec![];
+
+    for (file, headers) in &files {
+        for header in headers {
+            match declared.get(header.name) {
+                Some((first_file, first)) => {
+                    let location = location(first_file, first.span);
+                    let diagnostic = Diagnostic::semantic(1).with_message(format!(
+                        "node `{}` is already declared at {location}",
+                        header.name
+                    ));
+                    diagnostics.push(at(diagnostic, file, header.span));
+                }
+                None => {
+                    declared.insert(header.name, (file, header));
+                    declaration_order.push(header.name);
+                }
+            }
+        }
+    }
+
+    for (file, headers) in &files {
+        for header in headers {
+            for &(dependency, span) in &header.dependencies {
+                let diagnostic = if dependency == header.name {
+                    Diagnostic::semantic(30)
+                        .with_message(format!("node `{dependency}` depends on itself"))
+                } else if !declared.contains_key(dependency) {
+                    Diagnostic::semantic(25).with_message(format!(
+                        "node `{}` depends on `{dependency}`, which is not declared",
+                        header.name
+                    ))
+                } else {
+                    continue;
+                };
+
+                diagnostics.push(at(diagnostic, file, span));
+            }
+        }
+    }
+
+    // a depth-first search from every node, reporting each edge back to a node still on the
+    // search path at the place that edge is written
+    let mut finished = HashSet::new();
+    for root in declaration_order {
+        if finished.contains(root) {
+            continue;
+        }
+
+        let mut path = vec![root];
+        let mut next_edge = vec![0];
+
+        while let Some(&id) = path.last() {
+            let (file, header) = declared[id];
+            let edge = next_edge.last_mut().unwrap();
+
+            let Some(&(dependency, span)) = header.dependencies.get(*edge) else {
+                finished.insert(id);
+                path.pop();
+                next_edge.pop();
+                continue;
+            };
+            *edge += 1;
+
+            if dependency == id
+                || !declared.contains_key(dependency)
+                || finished.contains(dependency)
+            {
+                continue;
+            }
+
+            match path.iter().position(|node| *node == dependency) {
+                Some(start) => {
+                    let mut cycle: Vec<String> = path[start..]
+                        .iter()
+                        .map(|node| format!("`{node}`"))
+                        .collect();
+                    cycle.push(format!("`{dependency}`"));
+
+                    let diagnostic = Diagnostic::semantic(31).with_message(format!(
+                        "nodes {} depend on each other in a cycle",
+                        cycle.join(" -> ")
+                    ));
+                    diagnostics.push(at(diagnostic, file, span));
+                }
+                None => {
+                    path.push(dependency);
+                    next_edge.push(0);
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
+
+fn at(diagnostic: Diagnostic, file: &Option<PathBuf>, span: Span) -> Diagnostic {
+    let diagnostic = diagnostic.with_span(span);
+    match file {
+        Some(file) => diagnostic.with_file(file),
+        None => diagnostic,
+    }
+}
+
+fn location(file: &Option<PathBuf>, span: Span) -> String {
+    match file {
+        Some(file) => format!("{}:{}:{}", file.display(), span.line, span.column),
+        None => format!("{}:{}", span.line, span.column),
+    }
+}
@@ -0,0 +1,350 @@
+//! The interactive session behind `karma repl`.
+//!
+//! Every entry is compiled into the `main` function of an implicit node. Statements are kept,
+//! so the variables they declare stay alive for the entries after them. The program is run
+//! again from the start for each entry, and only the output it had not printed before is shown.
+//! An expression is evaluated after the kept statements and printed with its type.
+
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    parser::{AbstractSyntaxTree, SyntaxTreeNode},
+    source::Source,
+    vm, Diagnostic,
+};
+
+/// The implicit node entries are compiled into.
+const NODE: &str = "Repl";
+
+/// The variable an expression is stored in so it can be printed.
+const VALUE: &str = "__value";
+
+const HELP: &str = "\
+Statements are kept for later entries. Expressions are evaluated and printed with their type.
+:type <expr>      print the type of an expression
+:ast <expr>       print the syntax tree of an expression
+:bytecode <expr>  print the instructions an expression compiles to
+:help             print this message
+:quit             end the session
+";
+
+/// Reads entries from `input` until it ends or `:quit` is entered, writing a prompt before each
+/// entry and what it printed after. An entry that opens more braces than it closes continues on
+/// the next line.
+pub fn run(input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut session = Session::new();
+    let mut lines = input.lines();
+
+    loop {
+        write!(output, "> ")?;
+        output.flush()?;
+
+        let Some(line) = lines.next() else {
+            writeln!(output)?;
+            break;
+        };
+        let mut entry = line?;
+
+        while depth(&entry) > 0 {
+            write!(output, ". ")?;
+            output.flush()?;
+
+            match lines.next() {
+                Some(line) => {
+                    entry.push('\n');
+                    entry += &line?;
+                }
+                None => break,
+            }
+        }
+
+        if matches!(entry.trim(), ":quit" | ":q") {
+            break;
+        }
+
+        match session.eval(&entry) {
+            Ok(text) if text.is_empty() || text.ends_with('\n') => write!(output, "{text}")?,
+            Ok(text) => writeln!(output, "{text}")?,
+            Err(message) => writeln!(output, "{message}")?,
+        }
+    }
+
+    Ok(())
+}
+
+/// The statements entered so far, and how much of what they print has been shown.
+#[derive(Default)]
+pub struct Session {
+    statements: Vec<String>,
+    printed: usize,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Evaluates one entry, returning what it prints or why it could not be evaluated. A
+    /// statement that fails is not kept.
+    pub fn eval(&mut self, entry: &str) -> Result<String, String> {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            return Ok(String::new());
+        }
+
+        if let Some(command) = entry.strip_prefix(':') {
+            let (name, expr) = command
+                .split_once(char::is_whitespace)
+                .unwrap_or((command, ""));
+
+            return match name {
+                "type" => Ok(format!("{}\n", self.type_of(&self.expression(expr)?)?)),
+                "ast" => Ok(self.expression(expr)?.to_string()),
+                "bytecode" => self.bytecode(expr),
+                "help" => Ok(HELP.to_string()),
+                _ => Err(format!("unknown command `:{name}`, see `:help`")),
+            };
+        }
+
+        match crate::parse(&self.program(&[entry])) {
+            Ok(_) => self.execute(entry),
+            Err(diagnostic) if entry.ends_with(';') || entry.ends_with('}') => {
+                Err(describe(diagnostic))
+            }
+            Err(_) => self.evaluate(entry),
+        }
+    }
+
+    /// Runs the statement `entry` after the kept statements, and keeps it if it compiles and
+    /// runs.
+    fn execute(&mut self, entry: &str) -> Result<String, String> {
+        let out = run_program(&self.program(&[entry]))?;
+        let text = String::from_utf8_lossy(&out[self.printed.min(out.len())..]).into_owned();
+
+        self.statements.push(entry.to_string());
+        self.printed = out.len();
+
+        Ok(text)
+    }
+
+    /// Runs the expression `expr` after the kept statements and prints its value and type.
+    fn evaluate(&self, expr: &str) -> Result<String, String> {
+        let value_type = self.type_of(&self.expression(expr)?)?;
+
+        // calls to functions that return nothing are run for what they print
+        let statements = match value_type.as_str() {
+            "" => vec![format!("{expr};")],
+            _ => {
+                let mut statements = vec![format!("var {VALUE}: {value_type} = {expr};")];
+                statements.extend(print_value(VALUE, &value_type));
+                statements
+            }
+        };
+        let statements: Vec<&str> = statements.iter().map(String::as_str).collect();
+
+        let out = run_program(&self.program(&statements))?;
+        let mut text = String::from_utf8_lossy(&out[self.printed.min(out.len())..]).into_owned();
+        if !value_type.is_empty() {
+            text += &format!(": {value_type}\n");
+        }
+
+        Ok(text)
+    }
+
+    /// Prints the instructions `expr` compiles to after the kept statements.
+    fn bytecode(&self, expr: &str) -> Result<String, String> {
+        let ast = self.expression(expr)?;
+        self.type_of(&ast)?;
+
+        let bytes = self
+            .checked()?
+            .expression_bytecode(NODE, &ast)
+            .expect("the implicit node has a main function");
+
+        vm::disassemble(&bytes).map_err(|e| e.to_string())
+    }
+
+    /// Parses `expr` as the value of a variable declared after the kept statements.
+    fn expression(&self, expr: &str) -> Result<AbstractSyntaxTree, String> {
+        if expr.trim().is_empty() {
+            return Err("expected an expression".to_string());
+        }
+
+        let declaration = format!("var {VALUE}: int = {expr};");
+        let (_, program) = crate::parse(&self.program(&[&declaration])).map_err(describe)?;
+
+        last_statement(&program)
+            .filter(|statement| {
+                statement.node == SyntaxTreeNode::DeclareVar
+                    && statement.children[0].node == SyntaxTreeNode::Identifier(VALUE.to_string())
+            })
+            .map(|statement| statement.children[2].clone())
+            .ok_or_else(|| "expected a single expression".to_string())
+    }
+
+    /// The type of the expression `ast` after the kept statements.
+    fn type_of(&self, ast: &AbstractSyntaxTree) -> Result<String, String> {
+        self.checked()?
+            .expression_type(NODE, "main", ast)
+            .map_err(|code| describe(Diagnostic::semantic(code)))
+    }
+
+    /// Checks the program made of the kept statements.
+    fn checked(&self) -> Result<Source, String> {
+        let (_, program) = crate::parse(&self.program(&[])).map_err(describe)?;
+
+        Source::new(program).map_err(|error| describe(Diagnostic::semantic(error.code)))
+    }
+
+    /// The source of the implicit node, with `extra` after the kept statements.
+    fn program(&self, extra: &[&str]) -> String {
+        let body: Vec<&str> = self
+            .statements
+            .iter()
+            .map(String::as_str)
+            .chain(extra.iter().copied())
+            .collect();
+
+        format!(
+            "node {NODE} {{\n    fn main() -> () {{\n{}\n    }}\n}}\n",
+            body.join("\n")
+        )
+    }
+}
+
+/// Compiles and runs the implicit node, returning everything it printed.
+fn run_program(source: &str) -> Result<Vec<u8>, String> {
+    let compilation = crate::compile_str(source);
+    if let Some(diagnostic) = compilation.diagnostics.into_iter().next() {
+        return Err(describe(diagnostic));
+    }
+
+    let mut out = vec![];
+    vm::run(&compilation.bytecode[NODE], &mut out).map_err(|e| e.to_string())?;
+
+    Ok(out)
+}
+
+/// The statements that print the value of `access`, of type `value_type`. Arrays are printed
+/// element by element between brackets.
+fn print_value(access: &str, value_type: &str) -> Vec<String> {
+    match value_type {
+        "char" => vec![
+            "print_char('\\'');".to_string(),
+            format!("print_char({access});"),
+            "print_char('\\'');".to_string(),
+        ],
+        "int" | "float" | "bool" => vec![format!("print_{value_type}({access});")],
+        "i8" | "i16" | "i64" | "u8" | "u16" | "u32" | "u64" => {
+            vec![format!("print_int({access});")]
+        }
+        "f64" => vec![format!("print_float({access});")],
+        // the unit is shown with the type
+        _ if value_type.starts_with("float<") => vec![format!("print_float({access});")],
+        _ if value_type.starts_with("fixed<") => vec![format!("print_fixed({access});")],
+        "vec2" | "vec3" | "vec4" | "mat2" | "mat3" | "mat4" | "quat" | "transform3" => {
+            let n: usize = value_type[3..].parse().unwrap_or(0);
+            // the components of vectors are picked out with unit vectors, since only variables
+            // have fields
+            let unit = |i: usize| {
+                let elems: Vec<&str> = (0..n).map(|j| if i == j { "1" } else { "0" }).collect();
+                format!("vec{n}({})", elems.join(", "))
+            };
+            let elems: Vec<Vec<String>> = match value_type {
+                "quat" => ["w", "x", "y", "z"]
+                    .iter()
+                    .map(|c| vec![format!("print_float({access}.{c});")])
+                    .collect(),
+                "transform3" => {
+                    let rotation = format!("{access}_rotation");
+                    let mut statements =
+                        vec![format!("var {rotation}: quat = rotation({access});")];
+                    statements.extend(print_value(&rotation, "quat"));
+
+                    vec![
+                        statements,
+                        print_value(&format!("translation({access})"), "vec3"),
+                    ]
+                }
+                _ if value_type.starts_with("vec") => (0..n)
+                    .map(|i| vec![format!("print_float(dot({access}, {}));", unit(i))])
+                    .collect(),
+                _ => (0..n * n)
+                    .map(|k| {
+                        let (row, col) = (unit(k / n), unit(k % n));
+                        vec![format!("print_float(dot({row}, {access} * {col}));")]
+                    })
+                    .collect(),
+            };
+
+            let mut statements: Vec<String> = format!("{value_type}(")
+                .chars()
+                .map(|c| format!("print_char('{c}');"))
+                .collect();
+            for (i, elem) in elems.into_iter().enumerate() {
+                if i > 0 {
+                    statements.push("print_char(',');".to_string());
+                    statements.push("print_char(' ');".to_string());
+                }
+                statements.extend(elem);
+            }
+            statements.push("print_char(')');".to_string());
+
+            statements
+        }
+        _ => {
+            let Some((elem_type, len)) = value_type
+                .strip_prefix('[')
+                .and_then(|t| t.strip_suffix(']'))
+                .and_then(|t| t.rsplit_once("; "))
+            else {
+                return vec![];
+            };
+            let len: usize = len.parse().unwrap_or(0);
+
+            let mut statements = vec!["print_char('[');".to_string()];
+            for i in 0..len {
+                if i > 0 {
+                    statements.push("print_char(',');".to_string());
+                    statements.push("print_char(' ');".to_string());
+                }
+                statements.extend(print_value(&format!("{access}[{i}]"), elem_type));
+            }
+            statements.push("print_char(']');".to_string());
+
+            statements
+        }
+    }
+}
+
+/// The last statement of the `main` function of the implicit node.
+fn last_statement(program: &AbstractSyntaxTree) -> Option<&AbstractSyntaxTree> {
+    let node = program.children.first()?;
+    let main = node.children.get(1)?.children.first()?;
+    let mut statements = main.children.get(3)?;
+
+    while statements.children.get(1)?.node == SyntaxTreeNode::StmtSeq {
+        statements = &statements.children[1];
+    }
+
+    statements.children.first()
+}
+
+/// Diagnostics are reported without a position, which would point into the implicit node.
+fn describe(diagnostic: Diagnostic) -> String {
+    Diagnostic {
+        span: None,
+        file: None,
+        ..diagnostic
+    }
+    .to_string()
+}
+
+/// How many more braces `entry` opens than it closes.
+fn depth(entry: &str) -> i32 {
+    entry.chars().fold(0, |depth, c| match c {
+        '{' => depth + 1,
+        '}' => depth - 1,
+        _ => depth,
+    })
+}
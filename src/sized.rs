@@ -0,0 +1,270 @@
+//! Integers and floats of explicit width, as the VM computes them.
+//!
+//! `i8`…`i64` and `u8`…`u64` wrap around at their width, like `int`, which is `i32`. `f64` is
+//! a double precision `float`, which is `f32`. Integers are computed as `i128`, which holds
+//! every value of every width, and then brought back into the range of their type.
+
+/// A numeric type and its width in bits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Signed(u8),
+    Unsigned(u8),
+    Float(u8),
+}
+
+/// A value of some kind, wide enough for every kind.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Number {
+    Int(i128),
+    Float(f64),
+}
+
+impl Kind {
+    pub const INT: Kind = Kind::Signed(32);
+    pub const FLOAT: Kind = Kind::Float(32);
+
+    /// The kind of the type `t`, counting `int` and `float` as `i32` and `f32`, or `None` when
+    /// `t` is not a numeric type.
+    pub fn of_type(t: &str) -> Option<Kind> {
+        let (class, bits) = match t {
+            "int" => return Some(Kind::INT),
+            "float" => return Some(Kind::FLOAT),
+            _ => t.split_at_checked(1)?,
+        };
+
+        let bits = match bits {
+            "8" => 8,
+            "16" => 16,
+            "32" => 32,
+            "64" => 64,
+            _ => return None,
+        };
+
+        match class {
+            "i" => Some(Kind::Signed(bits)),
+            "u" => Some(Kind::Unsigned(bits)),
+            "f" if bits >= 32 => Some(Kind::Float(bits)),
+            _ => None,
+        }
+    }
+
+    /// The name of the type, where `i32` and `f32` are `int` and `float`.
+    pub fn type_name(self) -> String {
+        match self {
+            Kind::INT => "int".to_string(),
+            Kind::FLOAT => "float".to_string(),
+            kind => kind.to_string(),
+        }
+    }
+
+    pub fn bits(self) -> u8 {
+        match self {
+            Kind::Signed(bits) | Kind::Unsigned(bits) | Kind::Float(bits) => bits,
+        }
+    }
+
+    /// The number of bytes a value takes in memory.
+    pub fn size(self) -> u32 {
+        self.bits() as u32 / 8
+    }
+
+    pub fn is_int(self) -> bool {
+        !matches!(self, Kind::Float(_))
+    }
+
+    /// The operand byte of the instructions that take a kind: the class in the high nibble
+    /// and the base 2 logarithm of the size in the low one.
+    pub fn encode(self) -> u8 {
+        let class = match self {
+            Kind::Signed(_) => 0x00,
+            Kind::Unsigned(_) => 0x10,
+            Kind::Float(_) => 0x20,
+        };
+
+        class | self.size().trailing_zeros() as u8
+    }
+
+    pub fn decode(byte: u8) -> Option<Kind> {
+        let bits = 8_u8
+            .checked_shl((byte & 0x0F) as u32)
+            .filter(|bits| *bits <= 64)?;
+
+        match byte >> 4 {
+            0 => Some(Kind::Signed(bits)),
+            1 => Some(Kind::Unsigned(bits)),
+            2 if bits >= 32 => Some(Kind::Float(bits)),
+            _ => None,
+        }
+    }
+
+    /// Whether `value` is in the range of the kind.
+    pub fn contains(self, value: i128) -> bool {
+        self.wrap(value) == value
+    }
+
+    /// `value` wrapped around into the range of an integer kind.
+    pub fn wrap(self, value: i128) -> i128 {
+        let shift = 128 - self.bits() as u32;
+        match self {
+            // keeps the low bits and extends their sign
+            Kind::Signed(_) => (value << shift) >> shift,
+            _ => ((value << shift) as u128 >> shift) as i128,
+        }
+    }
+
+    /// `value` converted into the kind. Integers wrap around, floats saturate at the range of
+    /// an integer kind and round toward zero, and NaN becomes zero.
+    pub fn convert(self, value: Number) -> Number {
+        match (self, value) {
+            (Kind::Float(32), Number::Int(value)) => Number::Float(value as f32 as f64),
+            (Kind::Float(32), Number::Float(value)) => Number::Float(value as f32 as f64),
+            (Kind::Float(_), Number::Int(value)) => Number::Float(value as f64),
+            (Kind::Float(_), value) => value,
+            (kind, Number::Int(value)) => Number::Int(kind.wrap(value)),
+            (kind, Number::Float(value)) => {
+                let bits = kind.bits() as u32;
+                let (min, max) = match kind {
+                    Kind::Signed(_) => (-(1_i128 << (bits - 1)), (1_i128 << (bits - 1)) - 1),
+                    _ => (0, (1_i128 << bits) - 1),
+                };
+                Number::Int((value as i128).clamp(min, max))
+            }
+        }
+    }
+
+    /// The literal `text`, like `255`, `0xFF` or `1.5e3`, as a value of the kind, or `None`
+    /// when it is not a number of the kind or does not fit in it. Like `int` literals, hex,
+    /// binary and octal ones may use every bit and are reinterpreted in the kind.
+    pub fn literal(self, text: &str) -> Option<Number> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text),
+        };
+
+        if let Kind::Float(_) = self {
+            let value: f64 = digits.parse().ok()?;
+            let value = if negative { -value } else { value };
+            return value
+                .is_finite()
+                .then(|| self.convert(Number::Float(value)));
+        }
+
+        let radix = match digits.get(..2) {
+            Some("0x") => 16,
+            Some("0b") => 2,
+            Some("0o") => 8,
+            _ => 10,
+        };
+        let value = match radix {
+            10 => digits.parse::<i128>().ok()?,
+            _ => {
+                let value = u128::from_str_radix(&digits[2..], radix).ok()?;
+                if value >> self.bits() != 0 {
+                    return None;
+                }
+                self.wrap(value as i128)
+            }
+        };
+        let value = if negative { -value } else { value };
+
+        self.contains(value).then_some(Number::Int(value))
+    }
+}
+
+/// Splits a suffixed literal like `255u8` into its digits and the kind of its suffix, which
+/// starts at the last `i`, `u` or `f` since no digit after it is a letter.
+pub fn split_literal(text: &str) -> Option<(&str, Kind)> {
+    let at = text.rfind(['i', 'u', 'f'])?;
+    Some((&text[..at], Kind::of_type(&text[at..])?))
+}
+
+impl std::fmt::Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let class = match self {
+            Kind::Signed(_) => "i",
+            Kind::Unsigned(_) => "u",
+            Kind::Float(_) => "f",
+        };
+
+        write!(f, "{class}{}", self.bits())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds_are_read_from_types() {
+        assert_eq!(Kind::of_type("u8"), Some(Kind::Unsigned(8)));
+        assert_eq!(Kind::of_type("i64"), Some(Kind::Signed(64)));
+        assert_eq!(Kind::of_type("int"), Some(Kind::INT));
+        assert_eq!(
+            Kind::of_type("f64").map(Kind::type_name).as_deref(),
+            Some("f64")
+        );
+        assert_eq!(
+            Kind::of_type("f32").map(Kind::type_name).as_deref(),
+            Some("float")
+        );
+
+        assert_eq!(Kind::of_type("f16"), None);
+        assert_eq!(Kind::of_type("u128"), None);
+        assert_eq!(Kind::of_type("char"), None);
+
+        for t in [
+            "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64",
+        ] {
+            let kind = Kind::of_type(t).unwrap();
+            assert_eq!(Kind::decode(kind.encode()), Some(kind));
+        }
+        assert_eq!(Kind::decode(0x04), None);
+    }
+
+    #[test]
+    fn integers_wrap_at_their_width() {
+        assert_eq!(Kind::Unsigned(8).wrap(256 + 44), 44);
+        assert_eq!(Kind::Unsigned(8).wrap(-1), 255);
+        assert_eq!(Kind::Signed(8).wrap(128), -128);
+        assert_eq!(Kind::Unsigned(64).wrap(-1), u64::MAX as i128);
+        assert_eq!(Kind::Signed(64).wrap(u64::MAX as i128), -1);
+
+        assert_eq!(
+            Kind::Unsigned(16).convert(Number::Float(1e9)),
+            Number::Int(65535)
+        );
+        assert_eq!(
+            Kind::Signed(8).convert(Number::Float(-3.9)),
+            Number::Int(-3)
+        );
+        assert_eq!(
+            Kind::Signed(32).convert(Number::Float(f64::NAN)),
+            Number::Int(0)
+        );
+    }
+
+    #[test]
+    fn literals_must_fit_their_kind() {
+        assert_eq!(Kind::Unsigned(8).literal("255"), Some(Number::Int(255)));
+        assert_eq!(Kind::Unsigned(8).literal("256"), None);
+        assert_eq!(Kind::Unsigned(8).literal("-1"), None);
+        assert_eq!(Kind::Signed(8).literal("-128"), Some(Number::Int(-128)));
+        assert_eq!(Kind::Signed(8).literal("0xFF"), Some(Number::Int(-1)));
+        assert_eq!(Kind::Signed(8).literal("0x1FF"), None);
+        assert_eq!(
+            Kind::Unsigned(64).literal("18446744073709551615"),
+            Some(Number::Int(u64::MAX as i128))
+        );
+
+        assert_eq!(Kind::Float(64).literal("0.1"), Some(Number::Float(0.1)));
+        assert_eq!(Kind::Float(64).literal("-2"), Some(Number::Float(-2.0)));
+        assert_eq!(Kind::Float(64).literal("1e400"), None);
+
+        assert_eq!(split_literal("0xffu8"), Some(("0xff", Kind::Unsigned(8))));
+        assert_eq!(
+            split_literal("-1.5e3f64"),
+            Some(("-1.5e3", Kind::Float(64)))
+        );
+        assert_eq!(split_literal("255"), None);
+    }
+}
@@ -1,12 +1,16 @@
-use std::{
-    collections::{HashMap, HashSet, LinkedList},
-    io::Write,
+use std::collections::{hash_map::Entry, BTreeSet, HashMap, HashSet, LinkedList};
+
+use crate::{
+    fixed::Format,
+    lexer::Span,
+    parser::{AbstractSyntaxTree, SyntaxTreeNode},
+    project,
+    sized::{self, Kind, Number},
+    units::{self, Unit},
 };
 
-use crate::parser::{AbstractSyntaxTree, Parser, SyntaxTreeNode};
-
 #[derive(Clone, Debug, PartialEq)]
-enum ScopeElem {
+pub(crate) enum ScopeElem {
     IfScope,
     WhileScope,
     ElseScope,
@@ -15,6 +19,8 @@ enum ScopeElem {
     Func(String),
 }
 
+type FunctionSignature = (String, String, Vec<(String, String)>);
+
 #[derive(Debug, Clone)]
 enum TLElement {
     Function(
@@ -23,33 +29,253 @@ enum TLElement {
         HashSet<(String, String)>,
         AbstractSyntaxTree,
     ),
+    #[allow(dead_code)]
     Struct(Vec<(String, String)>),
-    Export,
+    Export(String),
+}
+
+/// The items declared in a `module` block, which nodes share through `use module::item;`.
+#[derive(Debug, Clone, Default)]
+struct Module {
+    items: HashMap<String, TLElement>,
+    public: HashSet<String>,
+    /// The module and item of each `use`, and where it is written.
+    imports: Vec<(String, String, Option<Span>)>,
 }
 
 pub struct Source {
     graph: HashMap<String, Vec<String>>,
+    order: Vec<String>,
     symbol_table: HashMap<String, HashMap<String, TLElement>>,
+    /// The test functions of each node, in the order they are declared.
+    tests: HashMap<String, Vec<String>>,
+}
+
+/// An error found by `Source::new`, with the node or module and the function it was found in
+/// when the check that found it looks at one, and where it is written when that is known.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SemanticError {
+    pub code: usize,
+    /// The node or module the error is in.
+    pub item: Option<String>,
+    /// The function the error is in, inside `item` when that is known.
+    pub function: Option<String>,
+    /// The statement the error is in, or the name whose declaration is in error.
+    pub span: Option<Span>,
+}
+
+impl SemanticError {
+    /// The `error` in the node or module `item`.
+    fn in_item(error: impl Into<Self>, item: &str) -> Self {
+        Self {
+            item: Some(item.to_string()),
+            ..error.into()
+        }
+    }
+
+    /// The `error` in the function `fn_id` of the node or module `item`. Module functions
+    /// linked into a node are named `module::function`, and are reported in their module.
+    fn in_function(error: impl Into<Self>, item: &str, fn_id: &str) -> Self {
+        let (item, function) = fn_id.split_once("::").unwrap_or((item, fn_id));
+
+        Self {
+            item: Some(item.to_string()).filter(|item| !item.is_empty()),
+            function: Some(function.to_string()),
+            ..error.into()
+        }
+    }
+
+    /// The error written at `span`, unless a check below already placed it more precisely.
+    fn at(self, span: Option<Span>) -> Self {
+        Self {
+            span: self.span.or(span),
+            ..self
+        }
+    }
+}
+
+impl From<usize> for SemanticError {
+    fn from(code: usize) -> Self {
+        Self {
+            code,
+            ..Default::default()
+        }
+    }
+}
+
+/// Describes the numeric error codes of the errors returned by `Source::new`.
+pub fn describe_error(code: usize) -> &'static str {
+    match code {
+        1 => "a node with this name is already declared",
+        2 => "a function or struct with this name is already declared in the node",
+        3 => "a parameter with this name is already declared",
+        4 => "a constant is declared with a name that is already in scope",
+        5 => "a variable is declared with a name that is already in scope",
+        6 => "use of an undeclared variable",
+        7 => "call to an undeclared function",
+        8 => "the initial value does not match the declared type",
+        9 => "the assigned value does not match the type of the variable",
+        10 => "the operands of a comparison have incompatible types",
+        11 => "the operator cannot be applied to operands of these types",
+        12 => "the variable has no known type",
+        13 => "no function matches the types of the arguments",
+        14 => "a function without a return type returns a value",
+        15 => "the returned value does not match the return type",
+        16 => "assignment to something that is not a variable in scope",
+        18 => "a function returning `!` returns",
+        19 => "a function returning `!` does not loop forever",
+        20 => "not every path through the function returns a value",
+        21 => "array elements have different types",
+        22 => "array indices must be non-negative integers",
+        23 => "only arrays can be indexed, and only with integers",
+        24 => "the value cannot be cast to this type",
+        25 => "reference to an undeclared node",
+        26 => "use of an undeclared module",
+        27 => "the module has no public item with this name",
+        28 => "a module with this name is already declared",
+        29 => "only items declared in a module can be public",
+        30 => "a node depends on itself",
+        31 => "the dependencies between nodes form a cycle",
+        32 => "a test function takes parameters or returns a value",
+        33 => "tests can only be declared in nodes",
+        34 => "an assertion is used as a value",
+        35 => "the element type of an empty array cannot be inferred",
+        36 => "the type has no field with this name",
+        37 => "the units of the operands do not match",
+        38 => "a fixed-point type has more than 32 bits, no integer bit, or a mode other than `wrap` or `sat`",
+        39 => "the literal does not fit in its type",
+        40 => "the condition of an `if` or `while` is not a `bool`",
+        _ => "unknown error",
+    }
 }
 
 impl Source {
-    pub fn new(parser: Parser) -> Result<Self, usize> {
+    pub fn new(mut ast: AbstractSyntaxTree) -> Result<Self, SemanticError> {
+        Self::number_assertions(&mut ast, &mut 0);
+
+        let mut tests = HashMap::new();
+        Self::collect_tests(&mut tests, &ast, None)?;
+
+        // the program may not have come from files checked by `compile_project`
+        if let Some(diagnostic) = project::check_nodes(&[(None, ast.clone())])
+            .into_iter()
+            .next()
+        {
+            return Err(SemanticError {
+                code: diagnostic.code.unwrap_or_default(),
+                span: diagnostic.span,
+                ..Default::default()
+            });
+        }
+
         let mut graph = HashMap::new();
-        Self::create_node_graph(&mut graph, parser.ast.clone());
+        Self::create_node_graph(&mut graph, ast.clone());
+        let order = Self::order_nodes(&graph);
+
+        let mut modules = HashMap::new();
+        Self::seed_modules(&mut modules, ast.clone())?;
+        let linked = Self::link_modules(&mut modules)?;
 
         let mut symbol_table = HashMap::new();
-        Self::seed_symbol_table(&mut symbol_table, parser.ast.clone())?;
+        Self::seed_symbol_table(&mut symbol_table, &modules, &linked, ast)?;
 
         // println!("{symbol_table:?}");
 
+        // module functions are checked once on their own, so errors are found even in
+        // functions no node imports
+        Self::check_semantics(&mut HashMap::from([(String::new(), linked)]))?;
         Self::check_semantics(&mut symbol_table)?;
 
         Ok(Self {
             graph,
+            order,
             symbol_table,
+            tests,
         })
     }
 
+    /// Gives every call to `assert` or `assert_eq` the number of the assertion as an extra
+    /// child. Assertions are numbered in the order they are written, which is the order their
+    /// instructions report when they fail.
+    fn number_assertions(ast: &mut AbstractSyntaxTree, next: &mut u32) {
+        if is_assertion_call(ast) {
+            ast.children.push(AbstractSyntaxTree {
+                node: SyntaxTreeNode::Integer(*next as i32),
+                children: vec![],
+                span: None,
+            });
+            *next += 1;
+        }
+
+        for child in ast.children.iter_mut() {
+            Self::number_assertions(child, next);
+        }
+    }
+
+    /// Where each call to `assert` or `assert_eq` in `ast` is written, in the order
+    /// `number_assertions` numbers them.
+    pub fn assertion_spans(ast: &AbstractSyntaxTree, spans: &mut Vec<Option<Span>>) {
+        if is_assertion_call(ast) {
+            spans.push(ast.span);
+        }
+
+        for child in &ast.children {
+            Self::assertion_spans(child, spans);
+        }
+    }
+
+    /// Lists the test functions of each node. Tests take no parameters and return nothing.
+    fn collect_tests(
+        tests: &mut HashMap<String, Vec<String>>,
+        ast: &AbstractSyntaxTree,
+        node_id: Option<&str>,
+    ) -> Result<(), SemanticError> {
+        match &ast.node {
+            SyntaxTreeNode::DeclareNode => {
+                if let SyntaxTreeNode::Identifier(id) = &ast.children[0].children[0].node {
+                    Self::collect_tests(tests, &ast.children[1], Some(id))?;
+                }
+            }
+            SyntaxTreeNode::DeclareModule => {
+                if let SyntaxTreeNode::Identifier(id) = &ast.children[0].node {
+                    if let Err(mut error) = Self::collect_tests(tests, &ast.children[1], None) {
+                        error.item = Some(id.clone());
+                        return Err(error);
+                    }
+                }
+            }
+            SyntaxTreeNode::Test => {
+                let func = &ast.children[0];
+                let SyntaxTreeNode::Identifier(id) = &func.children[0].node else {
+                    return Ok(());
+                };
+                let Some(node_id) = node_id else {
+                    return Err(SemanticError::in_function(33, "", id).at(func.children[0].span));
+                };
+
+                if func.children[1].node != SyntaxTreeNode::Null
+                    || func.children[2].children[0].node != SyntaxTreeNode::Void
+                {
+                    return Err(
+                        SemanticError::in_function(32, node_id, id).at(func.children[0].span)
+                    );
+                }
+
+                tests
+                    .entry(node_id.to_string())
+                    .or_default()
+                    .push(id.clone());
+            }
+            _ => {
+                for child in &ast.children {
+                    Self::collect_tests(tests, child, node_id)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn create_node_graph(graph: &mut HashMap<String, Vec<String>>, ast: AbstractSyntaxTree) {
         match ast.node {
             SyntaxTreeNode::NodeSeq => {
@@ -72,10 +298,47 @@ impl Source {
 
                 Self::add_dependencies(graph, &id, children[1].clone());
             }
-            _ => {
-                return;
+            _ => {}
+        }
+    }
+
+    /// Orders the nodes, whose dependencies `project::check_nodes` has checked, so each one
+    /// starts after everything it depends on. Nodes that could start at the same time are
+    /// ordered by name.
+    fn order_nodes(graph: &HashMap<String, Vec<String>>) -> Vec<String> {
+        let mut waiting_on = HashMap::new();
+        let mut dependents: HashMap<&String, Vec<&String>> = HashMap::new();
+
+        for (id, dependencies) in graph {
+            let dependencies: HashSet<&String> = dependencies.iter().collect();
+
+            for dependency in &dependencies {
+                dependents.entry(dependency).or_default().push(id);
+            }
+
+            waiting_on.insert(id, dependencies.len());
+        }
+
+        let mut ready: BTreeSet<&String> = waiting_on
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(id, _)| *id)
+            .collect();
+        let mut order = vec![];
+
+        while let Some(id) = ready.pop_first() {
+            order.push(id.clone());
+
+            for dependent in dependents.get(id).into_iter().flatten() {
+                let count = waiting_on.get_mut(dependent).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert(dependent);
+                }
             }
         }
+
+        order
     }
 
     fn add_dependencies(
@@ -89,9 +352,7 @@ impl Source {
                 dependencies.push(dependency);
                 graph.insert(id.clone(), dependencies);
             }
-            SyntaxTreeNode::Null => {
-                return;
-            }
+            SyntaxTreeNode::Null => {}
             _ => {
                 Self::add_dependencies(graph, id, ast.children[0].clone());
                 Self::add_dependencies(graph, id, ast.children[1].clone());
@@ -101,8 +362,10 @@ impl Source {
 
     fn seed_symbol_table(
         symbol_table: &mut HashMap<String, HashMap<String, TLElement>>,
+        modules: &HashMap<String, Module>,
+        linked: &HashMap<String, TLElement>,
         ast: AbstractSyntaxTree,
-    ) -> Result<(), usize> {
+    ) -> Result<(), SemanticError> {
         match ast.node {
             SyntaxTreeNode::DeclareNode => {
                 let header = ast.children[0].clone();
@@ -111,27 +374,235 @@ impl Source {
                     _ => "".to_string(),
                 };
 
-                if symbol_table.contains_key(&id) {
-                    return Err(1);
+                Self::seed_node(symbol_table, modules, linked, ast.children[1].clone(), &id)
+                    .map_err(|error| SemanticError::in_item(error, &id))?;
+            }
+            SyntaxTreeNode::DeclareModule => {}
+            _ => {
+                for child in ast.children.clone() {
+                    Self::seed_symbol_table(symbol_table, modules, linked, child)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the node `id`, whose body is `ast`, and the module items it imports to the symbol
+    /// table.
+    fn seed_node(
+        symbol_table: &mut HashMap<String, HashMap<String, TLElement>>,
+        modules: &HashMap<String, Module>,
+        linked: &HashMap<String, TLElement>,
+        ast: AbstractSyntaxTree,
+        id: &str,
+    ) -> Result<(), SemanticError> {
+        let mut statements = &ast;
+        while statements.node == SyntaxTreeNode::TLStmtSeq {
+            let statement = &statements.children[0];
+            if statement.node == SyntaxTreeNode::Public {
+                return Err(SemanticError::from(29).at(statement.span));
+            }
+            statements = &statements.children[1];
+        }
+
+        Self::sst_node(symbol_table, ast.clone(), id.to_string())?;
+
+        let mut imports = vec![];
+        Self::imports(&mut imports, ast);
+
+        let node_tl = symbol_table.entry(id.to_string()).or_default();
+        for (module_id, item_id, span) in imports {
+            Self::resolve_import(modules, &module_id, &item_id)
+                .map_err(|code| SemanticError::from(code).at(span))?;
+
+            if node_tl.contains_key(&item_id) {
+                return Err(SemanticError::from(2).at(span));
+            }
+
+            let item = modules[&module_id].items[&item_id].clone();
+            Self::link_item(node_tl, linked, &item);
+            node_tl.insert(item_id, item);
+        }
+
+        Ok(())
+    }
+
+    fn seed_modules(
+        modules: &mut HashMap<String, Module>,
+        ast: AbstractSyntaxTree,
+    ) -> Result<(), SemanticError> {
+        match ast.node {
+            SyntaxTreeNode::DeclareModule => {
+                let id = match ast.children[0].clone().node {
+                    SyntaxTreeNode::Identifier(id) => id,
+                    _ => "".to_string(),
+                };
+
+                if modules.contains_key(&id) {
+                    return Err(SemanticError::in_item(28, &id).at(ast.children[0].span));
                 }
 
-                Self::sst_node(symbol_table, ast.children[1].clone(), id)?;
+                let mut items = HashMap::new();
+                Self::sst_node(&mut items, ast.children[1].clone(), id.clone())
+                    .map_err(|error| SemanticError::in_item(error, &id))?;
+
+                let mut module = Module {
+                    items: items.remove(&id).unwrap_or_default(),
+                    ..Default::default()
+                };
+                Self::public_items(&mut module.public, ast.children[1].clone());
+                Self::imports(&mut module.imports, ast.children[1].clone());
+
+                modules.insert(id, module);
             }
+            SyntaxTreeNode::DeclareNode => {}
             _ => {
                 for child in ast.children.clone() {
-                    Self::seed_symbol_table(symbol_table, child)?;
+                    Self::seed_modules(modules, child)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn public_items(public: &mut HashSet<String>, ast: AbstractSyntaxTree) {
+        match ast.node {
+            SyntaxTreeNode::Public => {
+                if let SyntaxTreeNode::Identifier(id) = ast.children[0].children[0].clone().node {
+                    public.insert(id);
+                }
+            }
+            SyntaxTreeNode::TLStmtSeq => {
+                for child in ast.children {
+                    Self::public_items(public, child);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn imports(imports: &mut Vec<(String, String, Option<Span>)>, ast: AbstractSyntaxTree) {
+        match ast.node {
+            SyntaxTreeNode::Use => {
+                if let (
+                    SyntaxTreeNode::Identifier(module_id),
+                    SyntaxTreeNode::Identifier(item_id),
+                ) = (ast.children[0].clone().node, ast.children[1].clone().node)
+                {
+                    imports.push((module_id, item_id, ast.span));
+                }
+            }
+            SyntaxTreeNode::TLStmtSeq => {
+                for child in ast.children {
+                    Self::imports(imports, child);
                 }
             }
+            _ => {}
+        }
+    }
+
+    fn resolve_import(
+        modules: &HashMap<String, Module>,
+        module_id: &str,
+        item_id: &str,
+    ) -> Result<(), usize> {
+        let Some(module) = modules.get(module_id) else {
+            return Err(26);
+        };
+
+        if !module.public.contains(item_id) || !module.items.contains_key(item_id) {
+            return Err(27);
         }
 
         Ok(())
     }
 
+    /// Qualifies every call between module functions as `module::function` and returns all
+    /// module items under their qualified names.
+    fn link_modules(
+        modules: &mut HashMap<String, Module>,
+    ) -> Result<HashMap<String, TLElement>, SemanticError> {
+        let snapshot = modules.clone();
+        let mut linked = HashMap::new();
+
+        for (module_id, module) in modules.iter_mut() {
+            let mut qualified = HashMap::new();
+            for item_id in module.items.keys() {
+                qualified.insert(item_id.clone(), format!("{module_id}::{item_id}"));
+            }
+            for (import_module, item_id, span) in &module.imports {
+                Self::resolve_import(&snapshot, import_module, item_id)
+                    .map_err(|code| SemanticError::in_item(code, module_id).at(*span))?;
+                qualified.insert(item_id.clone(), format!("{import_module}::{item_id}"));
+            }
+
+            for (item_id, item) in module.items.iter_mut() {
+                if let TLElement::Function(_, _, _, tree) = item {
+                    Self::qualify_calls(tree, &qualified);
+                }
+
+                linked.insert(format!("{module_id}::{item_id}"), item.clone());
+            }
+        }
+
+        Ok(linked)
+    }
+
+    fn qualify_calls(ast: &mut AbstractSyntaxTree, qualified: &HashMap<String, String>) {
+        if ast.node == SyntaxTreeNode::FnCall {
+            if let SyntaxTreeNode::Identifier(id) = &ast.children[0].node {
+                if let Some(name) = qualified.get(id) {
+                    ast.children[0].node = SyntaxTreeNode::Identifier(name.clone());
+                }
+            }
+        }
+
+        for child in ast.children.iter_mut() {
+            Self::qualify_calls(child, qualified);
+        }
+    }
+
+    /// Adds the module functions `item` calls, directly or not, to a node's symbol table.
+    fn link_item(
+        node_tl: &mut HashMap<String, TLElement>,
+        linked: &HashMap<String, TLElement>,
+        item: &TLElement,
+    ) {
+        let mut calls = vec![];
+        if let TLElement::Function(_, _, _, tree) = item {
+            Self::module_calls(&mut calls, tree);
+        }
+
+        for call in calls {
+            if let Entry::Vacant(entry) = node_tl.entry(call) {
+                let callee = linked[entry.key()].clone();
+                entry.insert(callee.clone());
+                Self::link_item(node_tl, linked, &callee);
+            }
+        }
+    }
+
+    fn module_calls(calls: &mut Vec<String>, ast: &AbstractSyntaxTree) {
+        if ast.node == SyntaxTreeNode::FnCall {
+            if let SyntaxTreeNode::Identifier(id) = &ast.children[0].node {
+                if id.contains("::") {
+                    calls.push(id.clone());
+                }
+            }
+        }
+
+        for child in &ast.children {
+            Self::module_calls(calls, child);
+        }
+    }
+
     fn sst_node(
         symbol_table: &mut HashMap<String, HashMap<String, TLElement>>,
         ast: AbstractSyntaxTree,
         node_id: String,
-    ) -> Result<(), usize> {
+    ) -> Result<(), SemanticError> {
         match ast.node {
             SyntaxTreeNode::DeclareStruct => {
                 let id = match ast.children[0].clone().node {
@@ -149,12 +620,34 @@ impl Source {
                 };
 
                 if map.contains_key(&id) {
-                    return Err(2);
+                    return Err(SemanticError::from(2).at(ast.children[0].span));
                 }
 
                 map.insert(id, entry);
                 symbol_table.insert(node_id, map);
             }
+            SyntaxTreeNode::Export => {
+                let definition = ast.children[0].clone();
+                let id = match definition.children[0].clone().node {
+                    SyntaxTreeNode::Identifier(id) => id,
+                    _ => "".to_string(),
+                };
+                let t = match definition.children[1].clone().node {
+                    SyntaxTreeNode::Identifier(t) => t,
+                    _ => "".to_string(),
+                };
+
+                if !fixed_types_valid(&t) {
+                    return Err(SemanticError::from(38).at(ast.span));
+                }
+
+                let map = symbol_table.entry(node_id).or_default();
+                if map.contains_key(&id) {
+                    return Err(SemanticError::from(2).at(definition.children[0].span));
+                }
+
+                map.insert(id, TLElement::Export(t));
+            }
             SyntaxTreeNode::DeclareFunc => {
                 let id = match ast.children[0].clone().node {
                     SyntaxTreeNode::Identifier(id) => id,
@@ -178,7 +671,7 @@ impl Source {
                 };
 
                 if map.contains_key(&id) {
-                    return Err(2);
+                    return Err(SemanticError::from(2).at(ast.children[0].span));
                 }
 
                 map.insert(id, entry);
@@ -194,77 +687,62 @@ impl Source {
         Ok(())
     }
 
-    fn sst_func(ast: AbstractSyntaxTree) -> Result<Vec<(String, String)>, usize> {
-        match ast.node {
-            SyntaxTreeNode::ParamList => {
-                let param = Self::sst_func(ast.children[0].clone())?;
-                let mut rest = Self::sst_func(ast.children[1].clone())?;
-
-                for p in param {
-                    if rest.contains(&p) {
-                        return Err(3);
-                    }
-                    rest.push(p);
-                }
+    /// Lists the parameters of a function, last first. A repeated parameter is reported where
+    /// it is repeated.
+    fn sst_func(ast: AbstractSyntaxTree) -> Result<Vec<(String, String)>, SemanticError> {
+        let mut params = vec![];
+        let mut list = &ast;
+
+        while list.node == SyntaxTreeNode::ParamList {
+            let param = &list.children[0];
+            let id = match &param.children[0].node {
+                SyntaxTreeNode::Identifier(id) => id.clone(),
+                _ => "".to_string(),
+            };
+            let t = match &param.children[1].node {
+                SyntaxTreeNode::Identifier(t) => t.clone(),
+                _ => "".to_string(),
+            };
 
-                Ok(rest)
+            if params.contains(&(id.clone(), t.clone())) {
+                return Err(SemanticError::from(3).at(param.children[0].span));
             }
-            SyntaxTreeNode::Param => {
-                let id = match ast.children[0].clone().node {
-                    SyntaxTreeNode::Identifier(id) => id,
-                    _ => "".to_string(),
-                };
-
-                let t = match ast.children[1].clone().node {
-                    SyntaxTreeNode::Identifier(id) => id,
-                    _ => "".to_string(),
-                };
+            params.push((id, t));
 
-                Ok(vec![(id, t)])
-            }
-            _ => Ok(vec![]),
+            list = &list.children[1];
         }
+
+        params.reverse();
+        Ok(params)
     }
 
     fn check_semantics(
         symbol_table: &mut HashMap<String, HashMap<String, TLElement>>,
-    ) -> Result<(), usize> {
+    ) -> Result<(), SemanticError> {
         let mut functions = vec![];
         for (_, node_tl) in symbol_table.clone() {
             for (tl_id, tl_elem) in node_tl {
-                match tl_elem {
-                    TLElement::Function(ret, params, _, _) => {
-                        functions.push((tl_id, ret, params));
-                    }
-                    _ => {}
+                if let TLElement::Function(ret, params, _, _) = tl_elem {
+                    functions.push((tl_id, ret, params));
                 }
             }
         }
 
-        for (_, node_tl) in symbol_table {
-            for (_, tl_elem) in node_tl {
-                match tl_elem {
-                    TLElement::Function(ret, _, set, tree) => {
-                        let mut stack = LinkedList::new();
-                        for (func_name, _, _) in functions.clone() {
-                            stack.push_back(ScopeElem::Func(func_name.clone()));
-                        }
-
-                        for (var_id, _) in set.clone() {
-                            stack.push_back(ScopeElem::Variable(var_id));
-                        }
-
-                        Self::check_semantics_helper(&mut stack, set, tree.clone())?;
+        for (node_id, node_tl) in symbol_table.iter_mut() {
+            // a struct of a module is also known by its own name, as it is written once imported
+            let mut structs = HashMap::new();
+            for (tl_id, tl_elem) in node_tl.iter() {
+                if let TLElement::Struct(fields) = tl_elem {
+                    let name = tl_id.rsplit("::").next().unwrap_or(tl_id);
+                    structs.insert(name.to_string(), fields.clone());
+                    structs.insert(tl_id.clone(), fields.clone());
+                }
+            }
 
-                        Self::check_types(functions.clone(), set.clone(), tree.clone())?;
-                        Self::check_return(
-                            functions.clone(),
-                            set.clone(),
-                            tree.clone(),
-                            ret.clone(),
-                        )?;
-                    }
-                    _ => {}
+            for (fn_id, tl_elem) in node_tl.iter_mut() {
+                if let TLElement::Function(ret, _, set, tree) = tl_elem {
+                    Self::check_function(&functions, &structs, ret, set, tree)
+                        .map_err(|error| SemanticError::in_function(error, node_id, fn_id))?;
                 }
             }
         }
@@ -272,57 +750,346 @@ impl Source {
         Ok(())
     }
 
-    fn check_semantics_helper(
-        stack: &mut LinkedList<ScopeElem>,
-        var_set: &mut HashSet<(String, String)>,
-        ast: AbstractSyntaxTree,
-    ) -> Result<(), usize> {
-        let children = ast.children.clone();
+    /// Checks a function returning `ret`, whose body is `tree` and whose parameters are in
+    /// `set`, and adds its locals to `set`. `structs` are the structs it can use, by name.
+    fn check_function(
+        functions: &[FunctionSignature],
+        structs: &HashMap<String, Vec<(String, String)>>,
+        ret: &str,
+        set: &mut HashSet<(String, String)>,
+        tree: &mut AbstractSyntaxTree,
+    ) -> Result<(), SemanticError> {
+        if !set.iter().all(|(_, t)| fixed_types_valid(t)) || !fixed_types_valid(ret) {
+            return Err(38.into());
+        }
 
-        match ast.node {
-            SyntaxTreeNode::DeclareConst => {
-                Self::check_semantics_helper(stack, var_set, children[2].clone())?;
+        Self::flatten_structs(structs, &mut vec![HashMap::new()], tree)?;
 
-                let id = match children[0].clone().node {
-                    SyntaxTreeNode::Identifier(id) => id,
-                    _ => "".to_string(),
-                };
+        let mut stack = LinkedList::new();
+        for (func_name, _, _) in functions {
+            stack.push_back(ScopeElem::Func(func_name.clone()));
+        }
 
-                let t = match children[1].clone().node {
-                    SyntaxTreeNode::Identifier(i) => i,
-                    _ => "".to_string(),
+        for (var_id, _) in set.clone() {
+            stack.push_back(ScopeElem::Variable(var_id));
+        }
+
+        let mut scopes = vec![set.iter().map(|(id, _)| (id.clone(), id.clone())).collect()];
+        let mut declared = set.iter().map(|(id, _)| (id.clone(), 1)).collect();
+        Self::rename_locals(tree, &mut scopes, &mut declared);
+
+        Self::check_semantics_helper(&mut stack, set, tree.clone())?;
+        Self::type_literals(functions, set, ret, tree, None);
+        Self::widen_stored_values(functions, set, tree);
+
+        Self::check_types(functions.to_vec(), set.clone(), tree.clone())?;
+        Self::check_return(
+            functions.to_vec(),
+            set.clone(),
+            tree.clone(),
+            ret.to_string(),
+        )
+    }
+
+    /// Declares each field of a local of a struct type as a local of its own, named like `p.x`,
+    /// and reads fields through those locals, so that the rest of the checker and the code
+    /// generator never see a struct. `locals` maps the struct locals in scope to their struct,
+    /// innermost scope last. A struct local is initialised with a struct literal naming each of
+    /// its fields once.
+    fn flatten_structs(
+        structs: &HashMap<String, Vec<(String, String)>>,
+        locals: &mut Vec<HashMap<String, String>>,
+        ast: &mut AbstractSyntaxTree,
+    ) -> Result<(), SemanticError> {
+        match &ast.node {
+            SyntaxTreeNode::StmtSeq => {
+                let span = ast.children[0].span;
+                Self::flatten_structs(structs, locals, &mut ast.children[0])
+                    .map_err(|error| error.at(span))?;
+
+                let statement = &ast.children[0];
+                let declared = match (&statement.node, &statement.children[..]) {
+                    (
+                        SyntaxTreeNode::DeclareVar | SyntaxTreeNode::DeclareConst,
+                        [name, t, value],
+                    ) => match (&name.node, &t.node) {
+                        (SyntaxTreeNode::Identifier(id), SyntaxTreeNode::Identifier(t))
+                            if structs.contains_key(t) =>
+                        {
+                            let mut fields = vec![];
+                            Self::declare_fields(
+                                structs,
+                                locals.last_mut().unwrap(),
+                                statement,
+                                id,
+                                t,
+                                value,
+                                &mut fields,
+                            )
+                            .map_err(|code| SemanticError::from(code).at(span))?;
+                            Some(fields)
+                        }
+                        _ => None,
+                    },
+                    _ => None,
                 };
 
-                for elem in stack.clone() {
-                    if elem == ScopeElem::Const(id.clone())
-                        || elem == ScopeElem::Variable(id.clone())
-                    {
-                        return Err(4);
+                // the fields are declared one after another in place of the struct
+                if let Some(mut fields) = declared {
+                    let mut rest = ast.children[1].clone();
+                    while fields.len() > 1 {
+                        rest = AbstractSyntaxTree {
+                            node: SyntaxTreeNode::StmtSeq,
+                            children: vec![fields.pop().unwrap(), rest],
+                            span: statement.span,
+                        };
                     }
+                    ast.children = vec![fields.pop().unwrap(), rest];
                 }
 
-                var_set.insert((id.clone(), t.clone()));
-
-                stack.push_back(ScopeElem::Const(id));
+                Self::flatten_structs(structs, locals, &mut ast.children[1])
             }
-            SyntaxTreeNode::DeclareVar => {
-                Self::check_semantics_helper(stack, var_set, children[2].clone())?;
+            SyntaxTreeNode::WhileLoop | SyntaxTreeNode::IfStmt => {
+                Self::flatten_structs(structs, locals, &mut ast.children[0])?;
 
-                let id = match children[0].clone().node {
-                    SyntaxTreeNode::Identifier(id) => id,
-                    _ => "".to_string(),
-                };
+                for body in ast.children.iter_mut().skip(1) {
+                    locals.push(HashMap::new());
+                    Self::flatten_structs(structs, locals, body)?;
+                    locals.pop();
+                }
 
-                let t = match children[1].clone().node {
-                    SyntaxTreeNode::Identifier(i) => i,
-                    _ => "".to_string(),
+                Ok(())
+            }
+            SyntaxTreeNode::Identifier(id) => {
+                let mut id = id.clone();
+                let struct_of = |locals: &[HashMap<String, String>], id: &str| {
+                    locals.iter().rev().find_map(|scope| scope.get(id)).cloned()
                 };
 
-                for elem in stack.clone() {
+                // `p.x` is read as `p` with the field as its child
+                while let Some(struct_id) = struct_of(locals, &id) {
+                    let Some(SyntaxTreeNode::Identifier(field)) =
+                        ast.children.first().map(|child| child.node.clone())
+                    else {
+                        break;
+                    };
+                    if !structs[&struct_id].iter().any(|(name, _)| *name == field) {
+                        return Err(36.into());
+                    }
+
+                    id = format!("{id}.{field}");
+                    ast.node = SyntaxTreeNode::Identifier(id.clone());
+                    ast.children = ast.children[0].children.clone();
+                }
+
+                for child in ast.children.iter_mut() {
+                    Self::flatten_structs(structs, locals, child)?;
+                }
+
+                Ok(())
+            }
+            _ => {
+                for child in ast.children.iter_mut() {
+                    Self::flatten_structs(structs, locals, child)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds to `fields` a declaration like `declare` of each field of the local `id` of the
+    /// struct `struct_id`, given the struct literal `value`, and adds the local to `scope`.
+    /// Fields of a struct type are declared field by field in turn.
+    fn declare_fields(
+        structs: &HashMap<String, Vec<(String, String)>>,
+        scope: &mut HashMap<String, String>,
+        declare: &AbstractSyntaxTree,
+        id: &str,
+        struct_id: &str,
+        value: &AbstractSyntaxTree,
+        fields: &mut Vec<AbstractSyntaxTree>,
+    ) -> Result<(), usize> {
+        let mut values = HashMap::new();
+        let mut list = value;
+        if list.node != SyntaxTreeNode::FieldList {
+            return Err(8);
+        }
+        while list.node == SyntaxTreeNode::FieldList {
+            let SyntaxTreeNode::Identifier(field) = &list.children[0].children[0].node else {
+                return Err(8);
+            };
+            if values
+                .insert(field.clone(), &list.children[0].children[1])
+                .is_some()
+            {
+                return Err(8);
+            }
+
+            list = &list.children[1];
+        }
+
+        let declared = &structs[struct_id];
+        if values
+            .keys()
+            .any(|field| !declared.iter().any(|(name, _)| name == field))
+        {
+            return Err(36);
+        }
+
+        scope.insert(id.to_string(), struct_id.to_string());
+        for (field, t) in declared {
+            let value = *values.get(field).ok_or(8_usize)?;
+            let name = format!("{id}.{field}");
+
+            if structs.contains_key(t) {
+                Self::declare_fields(structs, scope, declare, &name, t, value, fields)?;
+                continue;
+            }
+
+            let identifier = |id: String| AbstractSyntaxTree {
+                node: SyntaxTreeNode::Identifier(id),
+                children: vec![],
+                span: declare.span,
+            };
+            fields.push(AbstractSyntaxTree {
+                node: declare.node.clone(),
+                children: vec![identifier(name), identifier(t.clone()), value.clone()],
+                span: declare.span,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Gives every local declared again after the scope of an earlier one has ended a name of
+    /// its own, like `x#1`, so the locals of a function are told apart by their name alone.
+    /// `scopes` maps the names in scope to what they are renamed to, innermost last, and
+    /// `declared` counts how many times each name has been declared.
+    fn rename_locals(
+        ast: &mut AbstractSyntaxTree,
+        scopes: &mut Vec<HashMap<String, String>>,
+        declared: &mut HashMap<String, usize>,
+    ) {
+        match &ast.node {
+            SyntaxTreeNode::DeclareVar | SyntaxTreeNode::DeclareConst => {
+                Self::rename_locals(&mut ast.children[2], scopes, declared);
+
+                let SyntaxTreeNode::Identifier(id) = ast.children[0].node.clone() else {
+                    return;
+                };
+                // declaring a name that is still in scope is an error the checker reports
+                if scopes.iter().any(|scope| scope.contains_key(&id)) {
+                    return;
+                }
+
+                let count = declared.entry(id.clone()).or_insert(0);
+                let renamed = match *count {
+                    0 => id.clone(),
+                    n => format!("{id}#{n}"),
+                };
+                *count += 1;
+
+                ast.children[0].node = SyntaxTreeNode::Identifier(renamed.clone());
+                scopes.last_mut().unwrap().insert(id, renamed);
+            }
+            SyntaxTreeNode::WhileLoop | SyntaxTreeNode::IfStmt => {
+                Self::rename_locals(&mut ast.children[0], scopes, declared);
+
+                for body in ast.children.iter_mut().skip(1) {
+                    scopes.push(HashMap::new());
+                    Self::rename_locals(body, scopes, declared);
+                    scopes.pop();
+                }
+            }
+            SyntaxTreeNode::Identifier(id) => {
+                // the fields of a struct are locals of their own, and those of a vector are
+                // renamed with it
+                let in_scope = |id: &str| scopes.iter().rev().find_map(|scope| scope.get(id));
+                let renamed = match (in_scope(id), id.split_once('.')) {
+                    (Some(renamed), _) => Some(renamed.clone()),
+                    (None, Some((base, field))) => {
+                        in_scope(base).map(|renamed| format!("{renamed}.{field}"))
+                    }
+                    (None, None) => None,
+                };
+                if let Some(renamed) = renamed {
+                    ast.node = SyntaxTreeNode::Identifier(renamed);
+                }
+
+                match ast.children.first_mut() {
+                    // the names of fields are not locals
+                    Some(child) if matches!(child.node, SyntaxTreeNode::Identifier(_)) => {
+                        for grandchild in child.children.iter_mut() {
+                            Self::rename_locals(grandchild, scopes, declared);
+                        }
+                    }
+                    Some(child) => Self::rename_locals(child, scopes, declared),
+                    None => {}
+                }
+            }
+            SyntaxTreeNode::FnCall => Self::rename_locals(&mut ast.children[1], scopes, declared),
+            SyntaxTreeNode::Cast => Self::rename_locals(&mut ast.children[0], scopes, declared),
+            SyntaxTreeNode::FieldList | SyntaxTreeNode::Float(_) => {}
+            _ => {
+                for child in ast.children.iter_mut() {
+                    Self::rename_locals(child, scopes, declared);
+                }
+            }
+        }
+    }
+
+    fn check_semantics_helper(
+        stack: &mut LinkedList<ScopeElem>,
+        var_set: &mut HashSet<(String, String)>,
+        ast: AbstractSyntaxTree,
+    ) -> Result<(), SemanticError> {
+        let children = ast.children.clone();
+
+        match ast.node {
+            SyntaxTreeNode::DeclareConst => {
+                Self::check_semantics_helper(stack, var_set, children[2].clone())?;
+
+                let id = match children[0].clone().node {
+                    SyntaxTreeNode::Identifier(id) => id,
+                    _ => "".to_string(),
+                };
+
+                let t = match children[1].clone().node {
+                    SyntaxTreeNode::Identifier(i) => i,
+                    _ => "".to_string(),
+                };
+
+                for elem in stack.clone() {
+                    if elem == ScopeElem::Const(id.clone())
+                        || elem == ScopeElem::Variable(id.clone())
+                    {
+                        return Err(4.into());
+                    }
+                }
+
+                var_set.insert((id.clone(), t.clone()));
+
+                stack.push_back(ScopeElem::Const(id));
+            }
+            SyntaxTreeNode::DeclareVar => {
+                Self::check_semantics_helper(stack, var_set, children[2].clone())?;
+
+                let id = match children[0].clone().node {
+                    SyntaxTreeNode::Identifier(id) => id,
+                    _ => "".to_string(),
+                };
+
+                let t = match children[1].clone().node {
+                    SyntaxTreeNode::Identifier(i) => i,
+                    _ => "".to_string(),
+                };
+
+                for elem in stack.clone() {
                     if elem == ScopeElem::Variable(id.clone())
                         || elem == ScopeElem::Const(id.clone())
                     {
-                        return Err(5);
+                        return Err(5.into());
                     }
                 }
 
@@ -330,24 +1097,34 @@ impl Source {
 
                 stack.push_back(ScopeElem::Variable(id));
             }
-            SyntaxTreeNode::Assign => {
+            SyntaxTreeNode::Assign | SyntaxTreeNode::CompoundAssign => {
                 Self::check_semantics_helper(stack, var_set, children[1].clone())?;
-                Self::check_semantics_helper(stack, var_set, children[2].clone())?;
+                Self::check_semantics_helper(stack, var_set, children.last().unwrap().clone())?;
 
                 let id = match children[0].clone().node {
                     SyntaxTreeNode::Identifier(id) => id,
                     _ => "".to_string(),
                 };
 
+                // the field of a vector is assigned to through the vector, and the field of a
+                // struct is a local of its own
+                let id = match id.split_once('.') {
+                    Some((base, _)) if !stack.contains(&ScopeElem::Variable(id.clone())) => {
+                        base.to_string()
+                    }
+                    _ => id,
+                };
+
                 for elem in stack.clone() {
                     if elem == ScopeElem::Variable(id.clone()) {
                         return Ok(());
                     }
                 }
 
-                return Err(16);
+                return Err(16.into());
             }
             SyntaxTreeNode::WhileLoop => {
+                Self::check_semantics_helper(stack, var_set, children[0].clone())?;
                 stack.push_back(ScopeElem::WhileScope);
 
                 Self::check_semantics_helper(stack, var_set, children[1].clone())?;
@@ -361,6 +1138,7 @@ impl Source {
                 }
             }
             SyntaxTreeNode::IfStmt => {
+                Self::check_semantics_helper(stack, var_set, children[0].clone())?;
                 stack.push_back(ScopeElem::IfScope);
 
                 Self::check_semantics_helper(stack, var_set, children[1].clone())?;
@@ -401,6 +1179,12 @@ impl Source {
             | SyntaxTreeNode::SubOp
             | SyntaxTreeNode::MulOp
             | SyntaxTreeNode::DivOp
+            | SyntaxTreeNode::ModOp
+            | SyntaxTreeNode::ShlOp
+            | SyntaxTreeNode::ShrOp
+            | SyntaxTreeNode::BitAndOp
+            | SyntaxTreeNode::BitXorOp
+            | SyntaxTreeNode::BitOrOp
             | SyntaxTreeNode::AndOp
             | SyntaxTreeNode::OrOp
             | SyntaxTreeNode::CompEq
@@ -414,8 +1198,15 @@ impl Source {
                 Self::check_semantics_helper(stack, var_set, children[1].clone())?;
             }
             SyntaxTreeNode::Identifier(id) => {
-                if children.len() > 0 {
-                    Self::check_semantics_helper(stack, var_set, children[0].clone())?;
+                match children.first().map(|child| &child.node) {
+                    // a field is looked up in the type of the variable rather than in scope
+                    Some(SyntaxTreeNode::Identifier(_)) => {
+                        for child in children[0].children.clone() {
+                            Self::check_semantics_helper(stack, var_set, child)?;
+                        }
+                    }
+                    Some(_) => Self::check_semantics_helper(stack, var_set, children[0].clone())?,
+                    None => {}
                 }
 
                 for elem in stack.clone() {
@@ -426,8 +1217,7 @@ impl Source {
                     }
                 }
 
-                println!("{id}");
-                return Err(6);
+                return Err(6.into());
             }
             SyntaxTreeNode::FnCall => {
                 Self::check_semantics_helper(stack, var_set, children[1].clone())?;
@@ -441,7 +1231,10 @@ impl Source {
                     || id == "print_float"
                     || id == "print_bool"
                     || id == "print_char"
+                    || id == "print_fixed"
                     || id == "println"
+                    || is_assertion(&id)
+                    || is_linear_algebra(&id)
                 {
                     return Ok(());
                 }
@@ -452,12 +1245,20 @@ impl Source {
                     }
                 }
 
-                return Err(7);
+                return Err(7.into());
+            }
+            SyntaxTreeNode::Cast => {
+                Self::check_semantics_helper(stack, var_set, children[0].clone())?;
             }
-            SyntaxTreeNode::FieldList => {
-                println!("field list");
+            // the child of a literal is its unit rather than a variable
+            SyntaxTreeNode::FieldList | SyntaxTreeNode::Float(_) => {
                 return Ok(());
             }
+            SyntaxTreeNode::StmtSeq => {
+                Self::check_semantics_helper(stack, var_set, children[0].clone())
+                    .map_err(|error| error.at(children[0].span))?;
+                Self::check_semantics_helper(stack, var_set, children[1].clone())?;
+            }
             _ => {
                 for child in children {
                     Self::check_semantics_helper(stack, var_set, child)?;
@@ -468,11 +1269,242 @@ impl Source {
         Ok(())
     }
 
+    /// Casts the `int` values stored in `float` variables, array elements and components to
+    /// `float`, so `var f: float = 1;` stores `1.0` the way `f = 1 as float;` would.
+    fn widen_stored_values(
+        functions: &[FunctionSignature],
+        var_set: &HashSet<(String, String)>,
+        ast: &mut AbstractSyntaxTree,
+    ) {
+        let target = match &ast.node {
+            SyntaxTreeNode::DeclareVar | SyntaxTreeNode::DeclareConst => {
+                match &ast.children[1].node {
+                    SyntaxTreeNode::Identifier(t) => Some(t.clone()),
+                    _ => None,
+                }
+            }
+            SyntaxTreeNode::Assign => {
+                Self::get_type(functions.to_vec(), var_set.clone(), ast.children[0].clone())
+                    .and_then(|t| Self::get_indexed(t, ast.children[1].clone()))
+                    .ok()
+            }
+            SyntaxTreeNode::StmtSeq | SyntaxTreeNode::WhileLoop | SyntaxTreeNode::IfStmt => {
+                for child in ast.children.iter_mut() {
+                    Self::widen_stored_values(functions, var_set, child);
+                }
+                return;
+            }
+            _ => return,
+        };
+
+        let value = &mut ast.children[2];
+        if target.as_deref() == Some("float")
+            && Self::get_type(functions.to_vec(), var_set.clone(), value.clone()).as_deref()
+                == Ok("int")
+        {
+            *value = AbstractSyntaxTree {
+                node: SyntaxTreeNode::Cast,
+                children: vec![
+                    value.clone(),
+                    AbstractSyntaxTree {
+                        node: SyntaxTreeNode::Identifier("float".to_string()),
+                        children: vec![],
+                        span: None,
+                    },
+                ],
+                span: value.span,
+            };
+        }
+    }
+
+    /// Gives the literals without a suffix the sized type their context expects, so `x + 1` is
+    /// `x + 1u8` when `x` is a `u8`. `expected` is the type the value of `ast` should have, and
+    /// `ret` the return type of the function it is in.
+    fn type_literals(
+        functions: &[FunctionSignature],
+        var_set: &HashSet<(String, String)>,
+        ret: &str,
+        ast: &mut AbstractSyntaxTree,
+        expected: Option<String>,
+    ) {
+        if ast.node == SyntaxTreeNode::FnCall && Self::type_arguments(functions, var_set, ret, ast)
+        {
+            return;
+        }
+
+        let mut contexts = Self::literal_contexts(functions, var_set, ret, ast, expected);
+        contexts.resize(ast.children.len(), None);
+
+        for (child, expected) in ast.children.iter_mut().zip(contexts) {
+            Self::type_literals(functions, var_set, ret, child, expected);
+        }
+    }
+
+    /// The sized type of the expression `ast` once its literals are typed, like `u8` for
+    /// `x % 4` when `x` is a `u8`.
+    fn sized_type(
+        functions: &[FunctionSignature],
+        var_set: &HashSet<(String, String)>,
+        ast: &AbstractSyntaxTree,
+    ) -> Option<String> {
+        if let Ok(t) = Self::get_type(functions.to_vec(), var_set.clone(), ast.clone()) {
+            return sized_kind(&t).is_some().then_some(t);
+        }
+
+        match ast.node {
+            SyntaxTreeNode::AddOp
+            | SyntaxTreeNode::SubOp
+            | SyntaxTreeNode::MulOp
+            | SyntaxTreeNode::DivOp
+            | SyntaxTreeNode::ModOp
+            | SyntaxTreeNode::ShlOp
+            | SyntaxTreeNode::ShrOp
+            | SyntaxTreeNode::BitAndOp
+            | SyntaxTreeNode::BitXorOp
+            | SyntaxTreeNode::BitOrOp
+            | SyntaxTreeNode::NegOp
+            | SyntaxTreeNode::BitNotOp => ast
+                .children
+                .iter()
+                .take(2)
+                .find_map(|child| Self::sized_type(functions, var_set, child)),
+            _ => None,
+        }
+    }
+
+    /// Types the literals among the arguments of the call `call` by the parameters of the
+    /// function it calls, and returns whether there is such a function.
+    fn type_arguments(
+        functions: &[FunctionSignature],
+        var_set: &HashSet<(String, String)>,
+        ret: &str,
+        call: &mut AbstractSyntaxTree,
+    ) -> bool {
+        let [callee, args] = &mut call.children[..] else {
+            return false;
+        };
+        let arity = flatten_inputs(args.clone()).len();
+        let Some((_, _, params)) = functions.iter().find(|(id, _, params)| {
+            callee.node == SyntaxTreeNode::Identifier(id.clone()) && params.len() == arity
+        }) else {
+            return false;
+        };
+
+        let mut list = args;
+        for (_, t) in params {
+            Self::type_literals(
+                functions,
+                var_set,
+                ret,
+                &mut list.children[0],
+                Some(t.clone()),
+            );
+            list = &mut list.children[1];
+        }
+
+        true
+    }
+
+    /// Rewrites `ast` into a suffixed literal if it is an unsuffixed one of a kind `expected`
+    /// can be, and returns the type each child is expected to have.
+    fn literal_contexts(
+        functions: &[FunctionSignature],
+        var_set: &HashSet<(String, String)>,
+        ret: &str,
+        ast: &mut AbstractSyntaxTree,
+        expected: Option<String>,
+    ) -> Vec<Option<String>> {
+        let type_of = |ast: &AbstractSyntaxTree| {
+            Self::get_type(functions.to_vec(), var_set.clone(), ast.clone()).ok()
+        };
+        // the operands of a binary operator take the type of the one that has a sized type
+        let operands = |ast: &AbstractSyntaxTree, expected: Option<String>| {
+            let t = ast
+                .children
+                .iter()
+                .take(2)
+                .find_map(|child| Self::sized_type(functions, var_set, child))
+                .or(expected);
+            vec![t.clone(), t]
+        };
+        let expected = expected.filter(|t| sized_kind(t).is_some() || t.starts_with('['));
+        let children = &ast.children;
+
+        match &ast.node {
+            SyntaxTreeNode::Integer(num) => {
+                if let Some(kind) = expected.as_deref().and_then(sized_kind) {
+                    if kind.is_int() {
+                        ast.node = SyntaxTreeNode::SizedNumber(format!("{num}{kind}").into());
+                    }
+                }
+                vec![]
+            }
+            // a float literal with a unit is a `float`
+            SyntaxTreeNode::Float(num) if children.is_empty() => {
+                if let Some(kind @ Kind::Float(_)) = expected.as_deref().and_then(sized_kind) {
+                    ast.node = SyntaxTreeNode::SizedNumber(format!("{num}{kind}").into());
+                }
+                vec![]
+            }
+            SyntaxTreeNode::DeclareVar | SyntaxTreeNode::DeclareConst => {
+                let t = match &children[1].node {
+                    SyntaxTreeNode::Identifier(t) => Some(t.clone()),
+                    _ => None,
+                };
+                vec![None, None, t]
+            }
+            SyntaxTreeNode::Assign => {
+                let t = type_of(&children[0])
+                    .and_then(|t| Self::get_indexed(t, children[1].clone()).ok());
+                vec![None, None, t]
+            }
+            SyntaxTreeNode::CompoundAssign => {
+                let (target, _) = Self::desugar_compound_assign(ast.clone());
+                vec![None, None, None, type_of(&target)]
+            }
+            SyntaxTreeNode::ReturnValue => vec![Some(ret.to_string())],
+            SyntaxTreeNode::AddOp
+            | SyntaxTreeNode::SubOp
+            | SyntaxTreeNode::MulOp
+            | SyntaxTreeNode::DivOp
+            | SyntaxTreeNode::ModOp
+            | SyntaxTreeNode::ShlOp
+            | SyntaxTreeNode::ShrOp
+            | SyntaxTreeNode::BitAndOp
+            | SyntaxTreeNode::BitXorOp
+            | SyntaxTreeNode::BitOrOp => operands(ast, expected),
+            SyntaxTreeNode::CompEq
+            | SyntaxTreeNode::CompNeq
+            | SyntaxTreeNode::CompLess
+            | SyntaxTreeNode::CompGreater
+            | SyntaxTreeNode::CompLeq
+            | SyntaxTreeNode::CompGeq => operands(ast, None),
+            SyntaxTreeNode::NegOp | SyntaxTreeNode::BitNotOp => vec![expected],
+            // the elements of an array literal are expected to have the element type
+            SyntaxTreeNode::InputList => {
+                let elem = expected.as_deref().and_then(|t| {
+                    let (elem, _) = t.strip_prefix('[')?.rsplit_once("; ")?;
+                    Some(elem.to_string())
+                });
+                vec![elem, expected]
+            }
+            // the arguments are a list like the elements of an array, of which both take the
+            // type of the one that has a sized type
+            SyntaxTreeNode::FnCall if is_call_to(ast, "assert_eq") => {
+                let t = flatten_inputs(children[1].clone())
+                    .iter()
+                    .find_map(|arg| Self::sized_type(functions, var_set, arg));
+                vec![None, t.map(|t| format!("[{t}; 2]"))]
+            }
+            _ => vec![None; children.len()],
+        }
+    }
+
     fn check_types(
-        functions: Vec<(String, String, Vec<(String, String)>)>,
+        functions: Vec<FunctionSignature>,
         var_set: HashSet<(String, String)>,
         ast: AbstractSyntaxTree,
-    ) -> Result<(), usize> {
+    ) -> Result<(), SemanticError> {
         let children = ast.children.clone();
 
         match ast.node {
@@ -481,10 +1513,13 @@ impl Source {
                     SyntaxTreeNode::Identifier(id) => id,
                     _ => "".to_string(),
                 };
+                if !fixed_types_valid(&l_value) {
+                    return Err(38.into());
+                }
 
                 let r_value = Self::get_type(functions, var_set, children[2].clone())?;
                 if l_value != r_value {
-                    return Err(8);
+                    return Err(8.into());
                 }
             }
             SyntaxTreeNode::Assign => {
@@ -494,8 +1529,8 @@ impl Source {
                 let arr_type =
                     Self::get_type(functions.clone(), var_set.clone(), children[1].clone())?;
 
-                if arr_type != "int" && arr_type != "" {
-                    return Err(22);
+                if arr_type != "int" && !arr_type.is_empty() {
+                    return Err(22.into());
                 }
 
                 l_value = Self::get_indexed(l_value, children[1].clone())?;
@@ -504,7 +1539,24 @@ impl Source {
                     Self::get_type(functions.clone(), var_set.clone(), children[2].clone())?;
 
                 if l_value != r_value {
-                    return Err(9);
+                    return Err(9.into());
+                }
+            }
+            SyntaxTreeNode::CompoundAssign => {
+                let arr_type =
+                    Self::get_type(functions.clone(), var_set.clone(), children[1].clone())?;
+
+                if arr_type != "int" && !arr_type.is_empty() {
+                    return Err(22.into());
+                }
+
+                let (target, op) = Self::desugar_compound_assign(ast.clone());
+
+                let l_value = Self::get_type(functions.clone(), var_set.clone(), target)?;
+                let r_value = Self::get_type(functions.clone(), var_set.clone(), op)?;
+
+                if l_value != r_value {
+                    return Err(9.into());
                 }
             }
             SyntaxTreeNode::AndOp
@@ -520,8 +1572,53 @@ impl Source {
                 let r_value =
                     Self::get_type(functions.clone(), var_set.clone(), children[1].clone())?;
 
-                if l_value != r_value {
-                    return Err(10);
+                if units_differ(&l_value, &r_value) {
+                    return Err(37.into());
+                }
+                if Self::promote(&l_value, &r_value).is_none() {
+                    return Err(10.into());
+                }
+            }
+            // these builtins only take some types, which only `get_type` checks
+            SyntaxTreeNode::FnCall
+                if is_assertion_call(&ast)
+                    || [
+                        "print_fixed",
+                        "print_int",
+                        "print_float",
+                        "print_bool",
+                        "print_char",
+                    ]
+                    .iter()
+                    .any(|id| is_call_to(&ast, id)) =>
+            {
+                Self::get_type(functions, var_set, ast)?;
+            }
+            SyntaxTreeNode::StmtSeq => {
+                Self::check_types(functions.clone(), var_set.clone(), children[0].clone())
+                    .map_err(|error| error.at(children[0].span))?;
+                Self::check_types(functions, var_set, children[1].clone())?;
+            }
+            // assertions are statements of their own and leave nothing to pass on
+            _ if children.iter().any(is_assertion_call) => {
+                return Err(34.into());
+            }
+            SyntaxTreeNode::FnCall => {
+                for child in children.clone() {
+                    Self::check_types(functions.clone(), var_set.clone(), child)?;
+                }
+
+                Self::get_inputs(functions, var_set, children[1].clone())?;
+            }
+            // conditions and returned values are expressions no other arm looks at as a whole
+            SyntaxTreeNode::WhileLoop | SyntaxTreeNode::IfStmt | SyntaxTreeNode::ReturnValue => {
+                for child in children.clone() {
+                    Self::check_types(functions.clone(), var_set.clone(), child)?;
+                }
+
+                let t = Self::get_type(functions, var_set, children[0].clone())?;
+                if ast.node != SyntaxTreeNode::ReturnValue && t != "bool" {
+                    return Err(40.into());
                 }
             }
             _ => {
@@ -535,7 +1632,7 @@ impl Source {
     }
 
     fn get_type(
-        functions: Vec<(String, String, Vec<(String, String)>)>,
+        functions: Vec<FunctionSignature>,
         var_set: HashSet<(String, String)>,
         ast: AbstractSyntaxTree,
     ) -> Result<String, usize> {
@@ -550,9 +1647,31 @@ impl Source {
                 let r_value =
                     Self::get_type(functions.clone(), var_set.clone(), children[1].clone())?;
 
-                if l_value == r_value
-                    && (l_value == "int" || l_value == "float" || l_value == "char")
-                {
+                if let Some(t) = linear_algebra_op(&ast.node, &l_value, &r_value) {
+                    return t;
+                }
+                if let Some(t) = unit_op(&ast.node, &l_value, &r_value) {
+                    return t;
+                }
+
+                match Self::promote(&l_value, &r_value) {
+                    Some(t) if t == "int" || t == "float" || t == "char" || is_fixed(&t) => Ok(t),
+                    Some(t) if sized_kind(&t).is_some() => Ok(t),
+                    _ => Err(11),
+                }
+            }
+            SyntaxTreeNode::ModOp
+            | SyntaxTreeNode::ShlOp
+            | SyntaxTreeNode::ShrOp
+            | SyntaxTreeNode::BitAndOp
+            | SyntaxTreeNode::BitXorOp
+            | SyntaxTreeNode::BitOrOp => {
+                let l_value =
+                    Self::get_type(functions.clone(), var_set.clone(), children[0].clone())?;
+                let r_value =
+                    Self::get_type(functions.clone(), var_set.clone(), children[1].clone())?;
+
+                if l_value == r_value && (l_value == "int" || is_sized_int(&l_value)) {
                     Ok(l_value)
                 } else {
                     Err(11)
@@ -585,14 +1704,78 @@ impl Source {
                 let r_value =
                     Self::get_type(functions.clone(), var_set.clone(), children[1].clone())?;
 
-                if l_value == r_value
-                    && (l_value == "int" || l_value == "float" || l_value == "char")
+                // vectors and matrices can only be compared for equality
+                let equality = matches!(ast.node, SyntaxTreeNode::CompEq | SyntaxTreeNode::CompNeq);
+                if units_differ(&l_value, &r_value) {
+                    return Err(37);
+                }
+
+                match Self::promote(&l_value, &r_value) {
+                    Some(t) if t == "int" || t == "float" || t == "char" => Ok("bool".to_string()),
+                    Some(t) if is_unit_float(&t) || is_fixed(&t) => Ok("bool".to_string()),
+                    Some(t) if sized_kind(&t).is_some() => Ok("bool".to_string()),
+                    Some(t) if equality && is_linear_algebra_type(&t) => Ok("bool".to_string()),
+                    _ => Err(11),
+                }
+            }
+            SyntaxTreeNode::NegOp => {
+                let t = Self::get_type(functions.clone(), var_set.clone(), children[0].clone())?;
+
+                if t == "int"
+                    || t == "float"
+                    || is_unit_float(&t)
+                    || is_fixed(&t)
+                    || sized_kind(&t).is_some_and(|kind| !matches!(kind, Kind::Unsigned(_)))
+                    || vector_len(&t).or(matrix_len(&t)).is_some()
                 {
-                    Ok("bool".to_string())
+                    Ok(t)
+                } else {
+                    Err(11)
+                }
+            }
+            SyntaxTreeNode::NotOp => {
+                let t = Self::get_type(functions.clone(), var_set.clone(), children[0].clone())?;
+
+                if t == "bool" {
+                    Ok(t)
+                } else {
+                    Err(11)
+                }
+            }
+            SyntaxTreeNode::BitNotOp => {
+                let t = Self::get_type(functions.clone(), var_set.clone(), children[0].clone())?;
+
+                if t == "int" || is_sized_int(&t) {
+                    Ok(t)
                 } else {
                     Err(11)
                 }
             }
+            SyntaxTreeNode::Cast => {
+                let from = Self::get_type(functions.clone(), var_set.clone(), children[0].clone())?;
+
+                let to = match children[1].clone().node {
+                    SyntaxTreeNode::Identifier(id) => id,
+                    _ => "".to_string(),
+                };
+                if !fixed_types_valid(&to) {
+                    return Err(38);
+                }
+
+                // a unit can be attached to or stripped from a number, and converted into another
+                // unit of the same quantity
+                let ((from_name, from_unit), (to_name, to_unit)) =
+                    (units::split(&from), units::split(&to));
+                let convertible = from_unit.is_dimensionless()
+                    || to_unit.is_dimensionless()
+                    || from_unit.factor(&to_unit).is_some();
+
+                if convertible && Self::cast_opcode(from_name, to_name).is_some() {
+                    Ok(to)
+                } else {
+                    Err(24)
+                }
+            }
             SyntaxTreeNode::AndOp | SyntaxTreeNode::OrOp => {
                 let l_value =
                     Self::get_type(functions.clone(), var_set.clone(), children[0].clone())?;
@@ -606,14 +1789,31 @@ impl Source {
                 }
             }
             SyntaxTreeNode::Identifier(id) => {
-                if ast.children.len() > 0 {
+                // `v.x` is read as `v` with the field as its child and assigned to as `v.x`
+                let is_local = var_set.iter().any(|(var_id, _)| *var_id == id);
+                if let Some((base, field)) = id.split_once('.').filter(|_| !is_local) {
+                    return Self::get_field(&var_set, base, field);
+                }
+                if let Some(SyntaxTreeNode::Identifier(field)) = children.first().map(|c| &c.node) {
+                    if children[0]
+                        .children
+                        .iter()
+                        .any(|c| c.node != SyntaxTreeNode::Null)
+                    {
+                        return Err(23);
+                    }
+
+                    return Self::get_field(&var_set, &id, field);
+                }
+
+                if !ast.children.is_empty() {
                     Self::get_type(functions.clone(), var_set.clone(), children[0].clone())?;
                 }
 
                 for (var_id, var_type) in var_set {
                     if var_id == id {
                         let mut fin = var_type.clone();
-                        if ast.children.len() > 0 {
+                        if !ast.children.is_empty() {
                             fin = Self::get_indexed(fin.clone(), children[0].clone())?;
                         }
 
@@ -625,7 +1825,7 @@ impl Source {
             }
             SyntaxTreeNode::InputList => {
                 let inputs = Self::get_inputs(functions.clone(), var_set.clone(), ast.clone())?;
-                let first = inputs[0].clone();
+                let first = inputs.first().cloned().ok_or(35_usize)?;
 
                 for ty in inputs.clone() {
                     if ty != first {
@@ -636,7 +1836,13 @@ impl Source {
                 Ok(format!("[{first}; {}]", inputs.len()))
             }
             SyntaxTreeNode::Integer(_) => Ok(String::from("int")),
-            SyntaxTreeNode::Float(_) => Ok(String::from("float")),
+            SyntaxTreeNode::SizedNumber(text) => sized_literal_type(&text),
+            SyntaxTreeNode::Float(_) => match children.first().map(|c| &c.node) {
+                Some(SyntaxTreeNode::Identifier(unit)) => {
+                    Ok(units::with_unit("float", &Unit::symbol(unit)))
+                }
+                _ => Ok(String::from("float")),
+            },
             SyntaxTreeNode::True | SyntaxTreeNode::False => Ok(String::from("bool")),
             SyntaxTreeNode::Character(_) => Ok(String::from("char")),
             SyntaxTreeNode::FnCall => {
@@ -653,12 +1859,56 @@ impl Source {
                             }
                         }
 
-                        if id == "print_int"
-                            || id == "print_float"
-                            || id == "print_bool"
-                            || id == "print_char"
-                        {
-                            return Ok("int".to_string());
+                        // the other numbers are printed like the `int` or `float` they widen
+                        if let [t] = params.as_slice() {
+                            match (id.as_str(), sized_kind(t)) {
+                                ("print_int", Some(kind)) if kind.is_int() => {
+                                    return Ok("int".to_string())
+                                }
+                                ("print_float", Some(Kind::Float(_))) => {
+                                    return Ok("int".to_string())
+                                }
+                                ("print_int" | "print_float", Some(_)) => return Err(13),
+                                _ => {}
+                            }
+                        }
+
+                        // each of the other printers takes the one type it prints
+                        let params: Vec<&str> = params.iter().map(String::as_str).collect();
+                        match (id.as_str(), params.as_slice()) {
+                            ("print_int", ["int"])
+                            | ("print_float", ["float"])
+                            | ("print_bool", ["bool"])
+                            | ("print_char", ["char"]) => return Ok("int".to_string()),
+                            ("print_float", [t]) if is_unit_float(t) => {
+                                return Ok("int".to_string())
+                            }
+                            ("print_int" | "print_float" | "print_bool" | "print_char", _) => {
+                                return Err(13)
+                            }
+                            _ => {}
+                        }
+
+                        // `assert` takes a condition and `assert_eq` two values of the same
+                        // primitive, unit, vector or matrix type
+                        match (id.as_str(), params.as_slice()) {
+                            ("print_fixed", [t]) if is_fixed(t) => return Ok("int".to_string()),
+                            ("assert", ["bool"]) => return Ok(String::new()),
+                            ("assert_eq", [a, b])
+                                if a == b
+                                    && (matches!(*a, "int" | "float" | "bool" | "char")
+                                        || is_unit_float(a)
+                                        || is_fixed(a)
+                                        || sized_kind(a).is_some()
+                                        || is_linear_algebra_type(a)) =>
+                            {
+                                return Ok(String::new())
+                            }
+                            _ => {}
+                        }
+
+                        if let Some(t) = linear_algebra_call(&id, &params) {
+                            return Ok(t);
                         }
 
                         Err(13)
@@ -670,6 +1920,24 @@ impl Source {
         }
     }
 
+    /// The type of the field `field` of the variable `var_id`. Only the components of vectors
+    /// and quaternions are fields so far.
+    fn get_field(
+        var_set: &HashSet<(String, String)>,
+        var_id: &str,
+        field: &str,
+    ) -> Result<String, usize> {
+        let (_, var_type) = var_set
+            .iter()
+            .find(|(id, _)| id == var_id)
+            .ok_or(12_usize)?;
+
+        match component(var_type, field) {
+            Some(_) => Ok("float".to_string()),
+            None => Err(36),
+        }
+    }
+
     fn get_indexed(l_value: String, ast: AbstractSyntaxTree) -> Result<String, usize> {
         let children = ast.children.clone();
 
@@ -677,17 +1945,14 @@ impl Source {
             SyntaxTreeNode::Index => {
                 let indexed_l_value = Self::get_indexed(l_value.clone(), children[1].clone())?;
 
-                match children[0].clone().node {
-                    SyntaxTreeNode::Integer(i) => {
-                        if i < 0 {
-                            return Err(22);
-                        }
+                if let SyntaxTreeNode::Integer(i) = children[0].clone().node {
+                    if i < 0 {
+                        return Err(22);
                     }
-                    _ => {}
                 }
 
                 let last_semicolon = indexed_l_value.rfind(";");
-                if last_semicolon == None {
+                if last_semicolon.is_none() {
                     return Err(23);
                 }
 
@@ -700,8 +1965,65 @@ impl Source {
         }
     }
 
+    /// Returns the type both operands of a binary operator are evaluated as. Identical types
+    /// are left alone and an `int` mixed with a `float` is implicitly widened to `float`;
+    /// every other combination needs an explicit `as` cast.
+    fn promote(l_value: &str, r_value: &str) -> Option<String> {
+        match (l_value, r_value) {
+            (l, r) if l == r => Some(l.to_string()),
+            ("int", "float") | ("float", "int") => Some("float".to_string()),
+            _ => None,
+        }
+    }
+
+    /// Returns the conversion instruction for `from as to`, or `None` if the cast is not
+    /// allowed. A cast to the same type is a no-op and is encoded as an empty sequence.
+    fn cast_opcode(from: &str, to: &str) -> Option<Vec<u8>> {
+        match (from, to) {
+            (f, t) if f == t && (f == "int" || f == "float" || f == "char" || f == "bool") => {
+                Some(vec![])
+            }
+            ("int", "float") => Some(vec![0x40]),
+            ("float", "int") => Some(vec![0x41]),
+            ("char", "int") => Some(vec![0x42]),
+            ("int", "char") => Some(vec![0x43]),
+            ("bool", "int") => Some(vec![0x44]),
+            (f, t) if f == t && is_fixed(f) => Some(vec![]),
+            ("int", t) if is_fixed(t) => {
+                Some([&[0xCD][..], &Format::of_type(t)?.encode()].concat())
+            }
+            ("float", t) if is_fixed(t) => {
+                Some([&[0xCF][..], &Format::of_type(t)?.encode()].concat())
+            }
+            (f, "int") if is_fixed(f) => Some(vec![0xCE, Format::of_type(f)?.frac]),
+            (f, "float") if is_fixed(f) => Some(vec![0xD0, Format::of_type(f)?.frac]),
+            (f, t) if is_fixed(f) && is_fixed(t) => {
+                let from = Format::of_type(f)?;
+                Some([&[0xD1, from.frac][..], &Format::of_type(t)?.encode()].concat())
+            }
+            // the other types go through an `int` to and from a number of another width
+            (f, t) if sized_kind(f).is_some() || sized_kind(t).is_some() => {
+                let (mut bytes, from) = match Kind::of_type(f) {
+                    Some(kind) => (vec![], kind),
+                    None => (Self::cast_opcode(f, "int")?, Kind::INT),
+                };
+                let (suffix, to) = match Kind::of_type(t) {
+                    Some(kind) => (vec![], kind),
+                    None => (Self::cast_opcode("int", t)?, Kind::INT),
+                };
+
+                if from != to {
+                    bytes.extend_from_slice(&[0xF0, from.encode(), to.encode()]);
+                }
+                bytes.extend_from_slice(&suffix);
+                Some(bytes)
+            }
+            _ => None,
+        }
+    }
+
     fn get_inputs(
-        functions: Vec<(String, String, Vec<(String, String)>)>,
+        functions: Vec<FunctionSignature>,
         var_set: HashSet<(String, String)>,
         ast: AbstractSyntaxTree,
     ) -> Result<Vec<String>, usize> {
@@ -716,7 +2038,7 @@ impl Source {
                         ast.children[0].clone(),
                     )?;
 
-                    let first = t[0].clone();
+                    let first = t.first().cloned().ok_or(35_usize)?;
 
                     for ty in t.clone() {
                         if ty != first {
@@ -727,10 +2049,13 @@ impl Source {
                     vec![format!("[{first}; {}]", t.len())]
                 }
                 SyntaxTreeNode::Integer(_) => vec![String::from("int")],
-                SyntaxTreeNode::Float(_) => vec![String::from("float")],
                 SyntaxTreeNode::Character(_) => vec![String::from("char")],
                 SyntaxTreeNode::True | SyntaxTreeNode::False => vec![String::from("bool")],
-                SyntaxTreeNode::Identifier(id) => {
+                // assertions are statements and leave nothing to pass on
+                SyntaxTreeNode::FnCall if is_assertion_call(&ast.children[0]) => return Err(34),
+                SyntaxTreeNode::Identifier(id)
+                    if ast.children[0].children.is_empty() && !id.contains('.') =>
+                {
                     let mut fin = vec![];
                     for (var_id, var_type) in var_set.clone() {
                         if var_id == id {
@@ -744,7 +2069,30 @@ impl Source {
                 SyntaxTreeNode::AddOp
                 | SyntaxTreeNode::SubOp
                 | SyntaxTreeNode::MulOp
-                | SyntaxTreeNode::DivOp => {
+                | SyntaxTreeNode::DivOp
+                | SyntaxTreeNode::ModOp
+                | SyntaxTreeNode::ShlOp
+                | SyntaxTreeNode::ShrOp
+                | SyntaxTreeNode::BitAndOp
+                | SyntaxTreeNode::BitXorOp
+                | SyntaxTreeNode::BitOrOp
+                | SyntaxTreeNode::NegOp
+                | SyntaxTreeNode::NotOp
+                | SyntaxTreeNode::BitNotOp
+                | SyntaxTreeNode::AndOp
+                | SyntaxTreeNode::OrOp
+                | SyntaxTreeNode::CompEq
+                | SyntaxTreeNode::CompNeq
+                | SyntaxTreeNode::CompLess
+                | SyntaxTreeNode::CompGreater
+                | SyntaxTreeNode::CompLeq
+                | SyntaxTreeNode::CompGeq
+                | SyntaxTreeNode::FnCall
+                | SyntaxTreeNode::Index
+                | SyntaxTreeNode::Identifier(_)
+                | SyntaxTreeNode::Float(_)
+                | SyntaxTreeNode::SizedNumber(_)
+                | SyntaxTreeNode::Cast => {
                     let t = Self::get_type(
                         functions.clone(),
                         var_set.clone(),
@@ -764,27 +2112,50 @@ impl Source {
     }
 
     fn check_return(
-        functions: Vec<(String, String, Vec<(String, String)>)>,
+        functions: Vec<FunctionSignature>,
         var_set: HashSet<(String, String)>,
         ast: AbstractSyntaxTree,
         ret: String,
-    ) -> Result<(), usize> {
-        if ret == "" {
+    ) -> Result<(), SemanticError> {
+        if ret.is_empty() {
             Self::check_return_func_1(ast)?;
         } else if ret == "!" {
             Self::check_return_func_3(ast)?;
         } else {
-            Self::check_return_func_2(functions, var_set, ast, ret)?;
+            Self::check_returned_types(functions.clone(), var_set.clone(), ast.clone(), &ret)?;
+            Self::check_return_func_2(ast)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that every value returned in `ast` has the return type `ret_type`.
+    fn check_returned_types(
+        functions: Vec<FunctionSignature>,
+        var_set: HashSet<(String, String)>,
+        ast: AbstractSyntaxTree,
+        ret_type: &str,
+    ) -> Result<(), SemanticError> {
+        if ast.node == SyntaxTreeNode::ReturnValue {
+            return match Self::get_type(functions, var_set, ast.children[0].clone()) {
+                Ok(t) if t == ret_type => Ok(()),
+                Ok(_) => Err(SemanticError::from(15).at(ast.span)),
+                Err(code) => Err(SemanticError::from(code).at(ast.span)),
+            };
+        }
+
+        for child in ast.children {
+            Self::check_returned_types(functions.clone(), var_set.clone(), child, ret_type)?;
         }
 
         Ok(())
     }
 
-    fn check_return_func_1(ast: AbstractSyntaxTree) -> Result<(), usize> {
+    fn check_return_func_1(ast: AbstractSyntaxTree) -> Result<(), SemanticError> {
         let children = ast.children.clone();
         match ast.node {
             SyntaxTreeNode::ReturnValue => {
-                return Err(14);
+                return Err(SemanticError::from(14).at(ast.span));
             }
             _ => {
                 for child in children {
@@ -796,54 +2167,35 @@ impl Source {
         Ok(())
     }
 
-    fn check_return_func_3(ast: AbstractSyntaxTree) -> Result<(), usize> {
-        let children = ast.children.clone();
-        match ast.node {
-            SyntaxTreeNode::WhileLoop => Ok(()),
-            SyntaxTreeNode::ReturnValue => Err(18),
-            _ => {
-                for child in children {
-                    match Self::check_return_func_1(child) {
-                        Ok(_) => {
-                            return Ok(());
-                        }
-                        _ => {}
-                    }
-                }
+    /// Checks the body `ast` of a function returning `!`, which must not return and must have a
+    /// `while true` loop among its statements, the one loop known to never end.
+    fn check_return_func_3(ast: AbstractSyntaxTree) -> Result<(), SemanticError> {
+        if let Err(error) = Self::check_return_func_1(ast.clone()) {
+            return Err(SemanticError { code: 18, ..error });
+        }
 
-                Err(19)
+        let mut statements = &ast;
+        while statements.node == SyntaxTreeNode::StmtSeq {
+            let statement = &statements.children[0];
+            if statement.node == SyntaxTreeNode::WhileLoop
+                && statement.children[0].node == SyntaxTreeNode::True
+            {
+                return Ok(());
             }
+
+            statements = &statements.children[1];
         }
+
+        Err(19.into())
     }
 
-    fn check_return_func_2(
-        functions: Vec<(String, String, Vec<(String, String)>)>,
-        var_set: HashSet<(String, String)>,
-        ast: AbstractSyntaxTree,
-        ret_type: String,
-    ) -> Result<(), usize> {
-        let children = ast.children.clone();
+    fn check_return_func_2(ast: AbstractSyntaxTree) -> Result<(), usize> {
         match ast.node {
-            SyntaxTreeNode::ReturnValue => {
-                let t = Self::get_type(functions, var_set, children[0].clone())?;
-                if t == ret_type {
-                    Ok(())
-                } else {
-                    Err(15)
-                }
-            }
+            SyntaxTreeNode::ReturnValue => Ok(()),
             _ => {
-                for child in children {
-                    match Self::check_return_func_2(
-                        functions.clone(),
-                        var_set.clone(),
-                        child,
-                        ret_type.clone(),
-                    ) {
-                        Ok(()) => {
-                            return Ok(());
-                        }
-                        _ => {}
+                for child in ast.children {
+                    if let Ok(()) = Self::check_return_func_2(child) {
+                        return Ok(());
                     }
                 }
                 Err(20)
@@ -851,279 +2203,557 @@ impl Source {
         }
     }
 
-    pub fn compile(&self) -> Result<(), std::io::Error> {
-        if !std::path::Path::new("comp").exists() {
-            std::fs::create_dir("comp")?;
+    pub fn graph(&self) -> &HashMap<String, Vec<String>> {
+        &self.graph
+    }
+
+    /// The nodes in the order they should be started, dependencies first.
+    pub fn startup_order(&self) -> &[String] {
+        &self.order
+    }
+
+    /// The type of the variable or parameter `var_id` in the function `fn_id` of node `node_id`,
+    /// as the checker infers it.
+    pub fn variable_type(&self, node_id: &str, fn_id: &str, var_id: &str) -> Option<String> {
+        let ast = AbstractSyntaxTree {
+            node: SyntaxTreeNode::Identifier(var_id.to_string()),
+            children: vec![],
+            span: None,
+        };
+
+        self.expression_type(node_id, fn_id, &ast).ok()
+    }
+
+    /// The name the checker gives the variable whose name is written at `span` in the function
+    /// `fn_id` of node `node_id`. It differs from the written name when an earlier block of the
+    /// function declares the same name.
+    pub fn local_name(&self, node_id: &str, fn_id: &str, span: Span) -> Option<String> {
+        let Some(TLElement::Function(_, _, _, tree)) = self.symbol_table.get(node_id)?.get(fn_id)
+        else {
+            return None;
+        };
+
+        let mut stack = vec![tree];
+        while let Some(ast) = stack.pop() {
+            if let (SyntaxTreeNode::DeclareVar | SyntaxTreeNode::DeclareConst, Some(name)) =
+                (&ast.node, ast.children.first())
+            {
+                if let (SyntaxTreeNode::Identifier(id), Some(name_span)) = (&name.node, name.span) {
+                    if name_span == span {
+                        return Some(id.clone());
+                    }
+                }
+            }
+            stack.extend(&ast.children);
         }
 
-        self.generate_bytecode()?;
+        None
+    }
 
-        Ok(())
+    /// The type of the expression `ast` when it is written in the function `fn_id` of node
+    /// `node_id`, or the code of the error that makes it ill-typed.
+    pub fn expression_type(
+        &self,
+        node_id: &str,
+        fn_id: &str,
+        ast: &AbstractSyntaxTree,
+    ) -> Result<String, usize> {
+        let Some(TLElement::Function(_, _, var_set, _)) =
+            self.symbol_table.get(node_id).ok_or(25_usize)?.get(fn_id)
+        else {
+            return Err(7);
+        };
+
+        let mut ast = ast.clone();
+        Self::type_literals(&self.signatures(), var_set, "", &mut ast, None);
+
+        Self::get_type(self.signatures(), var_set.clone(), ast)
+    }
+
+    /// The bytecode of the expression `ast` when it is written in the `main` function of node
+    /// `node_id`. Calls are left pointing at address 0, since no functions are laid out.
+    pub fn expression_bytecode(&self, node_id: &str, ast: &AbstractSyntaxTree) -> Option<Vec<u8>> {
+        let Some(TLElement::Function(_, _, var_set, _)) =
+            self.symbol_table.get(node_id)?.get("main")
+        else {
+            return None;
+        };
+
+        let mut ast = ast.clone();
+        Self::type_literals(&self.signatures(), var_set, "", &mut ast, None);
+        Self::erase_tree(&self.signatures(), var_set, &mut ast);
+
+        let erased = self.erase_units();
+        let Some(TLElement::Function(_, _, var_set, _)) = erased.symbol_table[node_id].get("main")
+        else {
+            return None;
+        };
+
+        // locals are laid out in the order `generate_bytecode` declares them
+        let mut variable_addresses = HashMap::new();
+        let mut addr = 0;
+        for (var_id, var_type) in sorted(var_set) {
+            let size = variable_size(&var_type);
+            variable_addresses.insert(var_id, (var_type, addr));
+            addr += size;
+        }
+
+        let mut bytes = vec![];
+        Self::generate_expr_bytecode(
+            &mut bytes,
+            &erased.signatures(),
+            var_set,
+            &variable_addresses,
+            &mut vec![],
+            ast,
+        );
+
+        Some(bytes)
     }
 
-    fn generate_bytecode(&self) -> Result<(), std::io::Error> {
+    /// The signatures of every function of every node.
+    fn signatures(&self) -> Vec<FunctionSignature> {
         let mut functions = vec![];
-        for (_, node_tl) in self.symbol_table.clone() {
+        for node_tl in self.symbol_table.values() {
             for (tl_id, tl_elem) in node_tl {
-                match tl_elem {
-                    TLElement::Function(ret, params, _, _) => {
-                        functions.push((tl_id, ret, params));
-                    }
-                    _ => {}
+                if let TLElement::Function(ret, params, _, _) = tl_elem {
+                    functions.push((tl_id.clone(), ret.clone(), params.clone()));
                 }
             }
         }
 
-        for node_id in self.symbol_table.keys() {
-            let filename = format!("comp/{node_id}.k");
-            let mut file = if std::path::Path::new(&filename).exists() {
-                std::fs::remove_file(filename.clone())?;
+        functions
+    }
 
-                std::fs::File::create(filename.clone())?
-            } else {
-                std::fs::File::create(filename.clone())?
-            };
+    /// The test functions of each node, in the order they are declared.
+    pub fn tests(&self) -> &HashMap<String, Vec<String>> {
+        &self.tests
+    }
 
-            let mut bytes: Vec<u8> = vec![];
-            let mut function_locations: HashMap<String, usize> = HashMap::new();
-            let mut variable_addresses: HashMap<String, (String, u32)> = HashMap::new();
-            let mut calls = vec![];
-            let mut addr: u32 = 0x0;
+    /// Lists the name and type of every exported variable, sorted by name and keyed by node.
+    pub fn exports(&self) -> HashMap<String, Vec<(String, String)>> {
+        let mut nodes = HashMap::new();
 
-            match self.symbol_table[node_id]["main"].clone() {
-                TLElement::Function(ret_type, params, var_set, tree) => {
-                    function_locations.insert("main".to_string(), bytes.len());
+        for (node_id, node_tl) in &self.symbol_table {
+            let mut exports = vec![];
+            for (tl_id, tl_elem) in node_tl {
+                if let TLElement::Export(t) = tl_elem {
+                    exports.push((tl_id.clone(), t.clone()));
+                }
+            }
 
-                    for (var_id, var_type) in var_set.clone() {
-                        variable_addresses.insert(var_id, (var_type.clone(), addr));
-                        bytes.push(match var_type.as_str() {
-                            "int" => 0x20,
-                            "float" => 0x21,
-                            "bool" => 0x28,
-                            "char" => 0x2C,
-                            _ => {
-                                if var_type.get(0..1).unwrap() == "[" {
-                                    0x80
-                                } else {
-                                    0x0
-                                }
-                            }
-                        });
+            exports.sort();
+            nodes.insert(node_id.clone(), exports);
+        }
 
-                        let b = addr.to_be_bytes();
-                        bytes.extend_from_slice(&b);
+        nodes
+    }
 
-                        if var_type.get(0..1).unwrap() == "[" {
-                            let mut last_semicolon = var_type.rfind(";");
-                            let mut s = var_type.clone();
+    /// Lists the checked signature and typed locals of every function, keyed by node name.
+    pub fn ir(&self) -> HashMap<String, String> {
+        let mut nodes = HashMap::new();
+
+        for (node_id, node_tl) in &self.symbol_table {
+            let mut fn_ids: Vec<&String> = node_tl.keys().collect();
+            fn_ids.sort();
+
+            let mut text = String::new();
+            for fn_id in fn_ids {
+                if let TLElement::Function(ret_type, params, var_set, _) = &node_tl[fn_id] {
+                    let params: Vec<String> = params
+                        .iter()
+                        .map(|(param_id, param_type)| format!("{param_id}: {param_type}"))
+                        .collect();
+                    let ret_type = if ret_type.is_empty() { "()" } else { ret_type };
+
+                    text.push_str(&format!(
+                        "fn {fn_id}({}) -> {ret_type}\n",
+                        params.join(", ")
+                    ));
+
+                    let mut vars: Vec<&(String, String)> = var_set.iter().collect();
+                    vars.sort();
+                    for (var_id, var_type) in vars {
+                        // a local declared again in a later scope is listed by its source name
+                        let var_id = var_id.split('#').next().unwrap_or(var_id);
+                        text.push_str(&format!("    var {var_id}: {var_type}\n"));
+                    }
+                }
+            }
 
-                            let mut len = 1;
-                            while last_semicolon != None {
-                                let i = last_semicolon.unwrap();
-                                let str_len = s.get(i + 2..s.len() - 1).unwrap();
-                                len = len * str_len.parse::<i32>().expect("could not parse to int");
+            nodes.insert(node_id.clone(), text);
+        }
 
-                                s = s.get(1..i).unwrap().to_string();
-                                last_semicolon = s.rfind(";");
-                            }
+        nodes
+    }
 
-                            match s.as_str() {
-                                "int" | "float" => bytes.push(0x4),
-                                "bool" | "char" => bytes.push(0x1),
-                                _ => {}
-                            }
+    /// The program with the units erased from every type and literal, which is what code is
+    /// generated from so units cost nothing at runtime.
+    fn erase_units(&self) -> Source {
+        let functions = self.signatures();
+        let mut symbol_table = self.symbol_table.clone();
 
-                            bytes.extend_from_slice(&len.to_be_bytes());
+        for node_tl in symbol_table.values_mut() {
+            for tl_elem in node_tl.values_mut() {
+                match tl_elem {
+                    TLElement::Function(ret_type, params, var_set, tree) => {
+                        Self::erase_tree(&functions, var_set, tree);
 
-                            addr += match s.as_str() {
-                                "int" | "float" => 4 * len as u32,
-                                "bool" | "char" => 1 * len as u32,
-                                _ => 0,
-                            };
+                        *ret_type = units::erase(ret_type);
+                        for (_, param_type) in params.iter_mut() {
+                            *param_type = units::erase(param_type);
                         }
-
-                        addr += match var_type.as_str() {
-                            "int" | "float" => 4,
-                            "bool" | "char" => 1,
-                            _ => 0,
-                        };
+                        *var_set = var_set
+                            .iter()
+                            .map(|(var_id, var_type)| (var_id.clone(), units::erase(var_type)))
+                            .collect();
                     }
+                    TLElement::Export(t) => *t = units::erase(t),
+                    TLElement::Struct(_) => {}
+                }
+            }
+        }
 
-                    for (param_id, param_type) in params.clone() {
-                        let addr = variable_addresses[&param_id].1;
-                        bytes.push(match param_type.as_str() {
-                            "int" => 0x24,
-                            "float" => 0x25,
-                            "bool" => 0x2A,
-                            "char" => 0x2E,
-                            _ => {
-                                if param_type.get(0..1).unwrap() == "[" {
-                                    0x81
-                                } else {
-                                    0x0
-                                }
-                            }
-                        });
+        Source {
+            graph: self.graph.clone(),
+            order: self.order.clone(),
+            symbol_table,
+            tests: self.tests.clone(),
+        }
+    }
 
-                        let b = addr.to_be_bytes();
-                        bytes.extend_from_slice(&b);
+    /// Erases the units in `ast`, a tree of a function with the locals `var_set`. A conversion
+    /// between two units of the same quantity becomes a multiplication by the factor between
+    /// them.
+    fn erase_tree(
+        functions: &[FunctionSignature],
+        var_set: &HashSet<(String, String)>,
+        ast: &mut AbstractSyntaxTree,
+    ) {
+        match &mut ast.node {
+            SyntaxTreeNode::Float(_) => ast.children.clear(),
+            SyntaxTreeNode::Identifier(id) if units::has_unit(id) => *id = units::erase(id),
+            SyntaxTreeNode::Cast => {
+                let from =
+                    Self::get_type(functions.to_vec(), var_set.clone(), ast.children[0].clone())
+                        .unwrap_or_default();
+                let factor = match &ast.children[1].node {
+                    SyntaxTreeNode::Identifier(to) => {
+                        units::split(&from).1.factor(&units::split(to).1)
                     }
-                    Self::generate_function_bytecode(
-                        &mut bytes,
-                        &functions,
-                        &var_set,
-                        &variable_addresses,
-                        &mut calls,
-                        tree,
-                    );
+                    _ => None,
+                };
 
-                    if ret_type == "" {
-                        bytes.push(0x64);
-                    }
+                for child in &mut ast.children {
+                    Self::erase_tree(functions, var_set, child);
+                }
+
+                if let Some(factor) = factor.filter(|factor| *factor != 1.0) {
+                    *ast = AbstractSyntaxTree {
+                        node: SyntaxTreeNode::MulOp,
+                        children: vec![
+                            ast.clone(),
+                            AbstractSyntaxTree {
+                                node: SyntaxTreeNode::Float(factor as f32),
+                                children: vec![],
+                                span: None,
+                            },
+                        ],
+                        span: ast.span,
+                    };
                 }
-                _ => {}
+
+                return;
             }
+            _ => {}
+        }
+
+        for child in &mut ast.children {
+            Self::erase_tree(functions, var_set, child);
+        }
+    }
 
-            function_locations.insert("print_int".to_string(), bytes.len());
-            bytes.extend_from_slice(&[0x90, 0x64]);
+    /// Generates the bytecode of every node, keyed by node name.
+    pub fn generate_bytecode(&self) -> HashMap<String, Vec<u8>> {
+        let erased = self.erase_units();
+        let mut nodes = HashMap::new();
 
-            function_locations.insert("print_float".to_string(), bytes.len());
-            bytes.extend_from_slice(&[0x91, 0x64]);
+        for node_id in erased.symbol_table.keys() {
+            nodes.insert(
+                node_id.clone(),
+                erased.generate_node_bytecode(node_id, "main"),
+            );
+        }
+
+        nodes
+    }
 
-            function_locations.insert("print_bool".to_string(), bytes.len());
-            bytes.extend_from_slice(&[0x92, 0x64]);
+    /// Generates the bytecode of the test function `test_id` of node `node_id`, which runs the
+    /// test in place of `main`.
+    pub fn generate_test_bytecode(&self, node_id: &str, test_id: &str) -> Option<Vec<u8>> {
+        self.tests
+            .get(node_id)?
+            .contains(&test_id.to_string())
+            .then(|| self.erase_units().generate_node_bytecode(node_id, test_id))
+    }
 
-            function_locations.insert("print_char".to_string(), bytes.len());
-            bytes.extend_from_slice(&[0x93, 0x64]);
+    /// Generates the bytecode of a node that starts by running the function `entry`.
+    fn generate_node_bytecode(&self, node_id: &str, entry: &str) -> Vec<u8> {
+        let functions = self.signatures();
+
+        let mut bytes: Vec<u8> = vec![];
+        let mut function_locations: HashMap<String, usize> = HashMap::new();
+        let mut variable_addresses: HashMap<String, (String, u32)> = HashMap::new();
+        let mut calls = vec![];
+        let mut addr: u32 = 0x0;
+
+        if let Some(TLElement::Function(ret_type, params, var_set, tree)) =
+            self.symbol_table[node_id].get(entry).cloned()
+        {
+            function_locations.insert(entry.to_string(), bytes.len());
+
+            for (var_id, var_type) in sorted(&var_set) {
+                variable_addresses.insert(var_id, (var_type.clone(), addr));
+                bytes.push(match storage_type(&var_type) {
+                    "int" => 0x20,
+                    "float" => 0x21,
+                    "bool" => 0x28,
+                    "char" => 0x2C,
+                    "quat" => 0xB5,
+                    "transform3" => 0xB6,
+                    t if vector_len(t).is_some() => 0xA0,
+                    t if matrix_len(t).is_some() => 0xA1,
+                    t if sized_kind(t).is_some() => 0xD9,
+                    _ => {
+                        if var_type.get(0..1).unwrap() == "[" {
+                            0x80
+                        } else {
+                            0x0
+                        }
+                    }
+                });
 
-            function_locations.insert("println".to_string(), bytes.len());
-            bytes.extend_from_slice(&[0x15, 0xA, 0x93, 0x64]);
+                let b = addr.to_be_bytes();
+                bytes.extend_from_slice(&b);
 
-            for fn_id in self.symbol_table[node_id].keys() {
-                if fn_id == "main" {
-                    continue;
+                if let Some(n) = vector_len(&var_type).or(matrix_len(&var_type)) {
+                    bytes.push(n);
                 }
-                match self.symbol_table[node_id][fn_id].clone() {
-                    TLElement::Function(ret_type, params, var_set, tree) => {
-                        function_locations.insert(fn_id.clone(), bytes.len());
-
-                        for (var_id, var_type) in var_set.clone() {
-                            variable_addresses.insert(var_id, (var_type.clone(), addr));
-                            bytes.push(match var_type.as_str() {
-                                "int" => 0x20,
-                                "float" => 0x21,
-                                "bool" => 0x28,
-                                "char" => 0x2C,
-                                _ => {
-                                    if var_type.get(0..1).unwrap() == "[" {
-                                        0x80
-                                    } else {
-                                        0x0
-                                    }
-                                }
-                            });
+                bytes.extend(sized_kind(&var_type).map(Kind::encode));
 
-                            let b = addr.to_be_bytes();
-                            bytes.extend_from_slice(&b);
+                if var_type.get(0..1).unwrap() == "[" {
+                    let mut last_semicolon = var_type.rfind(";");
+                    let mut s = var_type.clone();
 
-                            if var_type.get(0..1).unwrap() == "[" {
-                                let last_semicolon = var_type.rfind(";").unwrap();
-                                let len = var_type
-                                    .get(last_semicolon + 2..var_type.len() - 1)
-                                    .unwrap();
-                                let len = len.parse::<i32>().expect("could not parse to int");
-
-                                let arr_type = var_type.get(1..last_semicolon).unwrap();
-                                match arr_type {
-                                    "int" | "float" => bytes.push(0x4),
-                                    "bool" | "char" => bytes.push(0x1),
-                                    _ => {}
-                                }
+                    let mut len = 1;
+                    while last_semicolon.is_some() {
+                        let i = last_semicolon.unwrap();
+                        let str_len = s.get(i + 2..s.len() - 1).unwrap();
+                        len *= str_len.parse::<i32>().expect("could not parse to int");
 
-                                bytes.extend_from_slice(&len.to_be_bytes());
+                        s = s.get(1..i).unwrap().to_string();
+                        last_semicolon = s.rfind(";");
+                    }
 
-                                addr += match arr_type {
-                                    "int" | "float" => 4 * len as u32,
-                                    "bool" | "char" => 1 * len as u32,
-                                    _ => 0,
-                                };
-                            }
+                    match storage_type(&s) {
+                        "int" | "float" => bytes.push(0x4),
+                        "bool" | "char" => bytes.push(0x1),
+                        t => bytes.extend(sized_kind(t).map(|kind| kind.size() as u8)),
+                    }
+
+                    bytes.extend_from_slice(&len.to_be_bytes());
+
+                    addr += match storage_type(&s) {
+                        "int" | "float" => 4 * len as u32,
+                        "bool" | "char" => len as u32,
+                        t => sized_kind(t).map_or(0, Kind::size) * len as u32,
+                    };
+                }
 
-                            addr += match var_type.as_str() {
-                                "int" | "float" => 4,
-                                "bool" | "char" => 1,
-                                _ => 0,
-                            };
+                addr += match storage_type(&var_type) {
+                    "int" | "float" => 4,
+                    "bool" | "char" => 1,
+                    t if is_linear_algebra_type(t) => 4,
+                    t => sized_kind(t).map_or(0, Kind::size),
+                };
+            }
+
+            for (param_id, param_type) in params.clone() {
+                let addr = variable_addresses[&param_id].1;
+                bytes.push(match storage_type(&param_type) {
+                    "int" => 0x24,
+                    "float" => 0x25,
+                    "bool" => 0x2A,
+                    "char" => 0x2E,
+                    t if is_linear_algebra_type(t) => 0xA3,
+                    t if sized_kind(t).is_some() => 0xDB,
+                    _ => {
+                        if param_type.get(0..1).unwrap() == "[" {
+                            0x81
+                        } else {
+                            0x0
                         }
+                    }
+                });
 
-                        for (param_id, param_type) in params.clone() {
-                            let addr = variable_addresses[&param_id].1;
-                            bytes.push(match param_type.as_str() {
-                                "int" => 0x24,
-                                "float" => 0x25,
-                                "bool" => 0x2A,
-                                "char" => 0x2E,
-                                _ => {
-                                    if param_type.get(0..1).unwrap() == "[" {
-                                        0x81
-                                    } else {
-                                        0x0
-                                    }
-                                }
-                            });
-                            let b = addr.to_be_bytes();
-                            bytes.extend_from_slice(&b);
+                let b = addr.to_be_bytes();
+                bytes.extend_from_slice(&b);
+                bytes.extend(sized_kind(&param_type).map(Kind::encode));
+            }
+            Self::generate_function_bytecode(
+                &mut bytes,
+                &functions,
+                &var_set,
+                &variable_addresses,
+                &mut calls,
+                tree,
+            );
+
+            if ret_type.is_empty() {
+                bytes.push(0x64);
+            }
+        }
+
+        function_locations.insert("print_int".to_string(), bytes.len());
+        bytes.extend_from_slice(&[0x90, 0x64]);
+
+        function_locations.insert("print_float".to_string(), bytes.len());
+        bytes.extend_from_slice(&[0x91, 0x64]);
+
+        function_locations.insert("print_bool".to_string(), bytes.len());
+        bytes.extend_from_slice(&[0x92, 0x64]);
+
+        function_locations.insert("print_char".to_string(), bytes.len());
+        bytes.extend_from_slice(&[0x93, 0x64]);
+
+        function_locations.insert("println".to_string(), bytes.len());
+        bytes.extend_from_slice(&[0x15, 0xA, 0x93, 0x64]);
+
+        // functions are laid out by name, so the same program always compiles to the same bytes
+        let mut fn_ids: Vec<&String> = self.symbol_table[node_id].keys().collect();
+        fn_ids.sort();
+
+        for fn_id in fn_ids {
+            if fn_id == entry {
+                continue;
+            }
+            if let TLElement::Function(ret_type, params, var_set, tree) =
+                self.symbol_table[node_id][fn_id].clone()
+            {
+                function_locations.insert(fn_id.clone(), bytes.len());
+
+                for (var_id, var_type) in sorted(&var_set) {
+                    variable_addresses.insert(var_id, (var_type.clone(), addr));
+                    bytes.push(match storage_type(&var_type) {
+                        "int" => 0x20,
+                        "float" => 0x21,
+                        "bool" => 0x28,
+                        "char" => 0x2C,
+                        "quat" => 0xB5,
+                        "transform3" => 0xB6,
+                        t if vector_len(t).is_some() => 0xA0,
+                        t if matrix_len(t).is_some() => 0xA1,
+                        t if sized_kind(t).is_some() => 0xD9,
+                        _ => {
+                            if var_type.get(0..1).unwrap() == "[" {
+                                0x80
+                            } else {
+                                0x0
+                            }
                         }
-                        Self::generate_function_bytecode(
-                            &mut bytes,
-                            &functions,
-                            &var_set,
-                            &variable_addresses,
-                            &mut calls,
-                            tree,
-                        );
+                    });
+
+                    let b = addr.to_be_bytes();
+                    bytes.extend_from_slice(&b);
 
-                        if ret_type == "" {
-                            bytes.push(0x64);
+                    if let Some(n) = vector_len(&var_type).or(matrix_len(&var_type)) {
+                        bytes.push(n);
+                    }
+                    bytes.extend(sized_kind(&var_type).map(Kind::encode));
+
+                    if var_type.get(0..1).unwrap() == "[" {
+                        let last_semicolon = var_type.rfind(";").unwrap();
+                        let len = var_type
+                            .get(last_semicolon + 2..var_type.len() - 1)
+                            .unwrap();
+                        let len = len.parse::<i32>().expect("could not parse to int");
+
+                        let arr_type = var_type.get(1..last_semicolon).unwrap();
+                        match storage_type(arr_type) {
+                            "int" | "float" => bytes.push(0x4),
+                            "bool" | "char" => bytes.push(0x1),
+                            t => bytes.extend(sized_kind(t).map(|kind| kind.size() as u8)),
                         }
+
+                        bytes.extend_from_slice(&len.to_be_bytes());
+
+                        addr += match storage_type(arr_type) {
+                            "int" | "float" => 4 * len as u32,
+                            "bool" | "char" => len as u32,
+                            t => sized_kind(t).map_or(0, Kind::size) * len as u32,
+                        };
                     }
-                    _ => {}
-                }
-            }
 
-            for (call_loc, function_name) in calls {
-                let function_location = function_locations[&function_name] as u32;
+                    addr += match storage_type(&var_type) {
+                        "int" | "float" => 4,
+                        "bool" | "char" => 1,
+                        t if is_linear_algebra_type(t) => 4,
+                        t => sized_kind(t).map_or(0, Kind::size),
+                    };
+                }
 
-                let b = function_location.to_be_bytes();
+                for (param_id, param_type) in params.clone() {
+                    let addr = variable_addresses[&param_id].1;
+                    bytes.push(match storage_type(&param_type) {
+                        "int" => 0x24,
+                        "float" => 0x25,
+                        "bool" => 0x2A,
+                        "char" => 0x2E,
+                        t if is_linear_algebra_type(t) => 0xA3,
+                        t if sized_kind(t).is_some() => 0xDB,
+                        _ => {
+                            if param_type.get(0..1).unwrap() == "[" {
+                                0x81
+                            } else {
+                                0x0
+                            }
+                        }
+                    });
+                    let b = addr.to_be_bytes();
+                    bytes.extend_from_slice(&b);
+                    bytes.extend(sized_kind(&param_type).map(Kind::encode));
+                }
+                Self::generate_function_bytecode(
+                    &mut bytes,
+                    &functions,
+                    &var_set,
+                    &variable_addresses,
+                    &mut calls,
+                    tree,
+                );
 
-                for (i, byte) in b.iter().enumerate() {
-                    bytes[call_loc + i] = *byte;
+                if ret_type.is_empty() {
+                    bytes.push(0x64);
                 }
             }
-
-            file.write(&bytes)?;
         }
 
-        let mut file = if std::path::Path::new("comp/graph.json").exists() {
-            std::fs::remove_file("comp/graph.json")?;
+        for (call_loc, function_name) in calls {
+            let function_location = function_locations[&function_name] as u32;
 
-            std::fs::File::create("comp/graph.json")?
-        } else {
-            std::fs::File::create("comp/graph.json")?
-        };
+            let b = function_location.to_be_bytes();
 
-        file.write(
-            serde_json::to_string(&self.graph)
-                .expect("could not convert to json")
-                .as_bytes(),
-        )?;
+            for (i, byte) in b.iter().enumerate() {
+                bytes[call_loc + i] = *byte;
+            }
+        }
 
-        Ok(())
+        bytes
     }
 
     fn generate_function_bytecode(
         bytes: &mut Vec<u8>,
-        functions: &Vec<(String, String, Vec<(String, String)>)>,
+        functions: &Vec<FunctionSignature>,
         var_set: &HashSet<(String, String)>,
         variable_addresses: &HashMap<String, (String, u32)>,
         calls: &mut Vec<(usize, String)>,
@@ -1149,27 +2779,28 @@ impl Source {
                 let (t, addr) = variable_addresses[&id].clone();
 
                 let mut slice = vec![];
-                bytes.extend_from_slice(match t.as_str() {
+                bytes.extend_from_slice(match storage_type(&t) {
                     "int" => &[0x24],
                     "float" => &[0x25],
                     "bool" => &[0x2A],
                     "char" => &[0x2E],
+                    t if sized_kind(t).is_some() => &[0xDB],
                     _ => {
                         if t.get(0..1).unwrap() == "[" {
                             let mut last_semicolon = t.rfind(";");
                             let mut s = t.clone();
 
                             let mut len = 1;
-                            while last_semicolon != None {
+                            while last_semicolon.is_some() {
                                 let i = last_semicolon.unwrap();
                                 let str_len = s.get(i + 2..s.len() - 1).unwrap();
-                                len = len * str_len.parse::<i32>().expect("could not parse to int");
+                                len *= str_len.parse::<i32>().expect("could not parse to int");
 
                                 s = s.get(1..i).unwrap().to_string();
                                 last_semicolon = s.rfind(";");
                             }
 
-                            match s.as_str() {
+                            match storage_type(&s) {
                                 "int" => {
                                     for _ in 0..len {
                                         slice.push(0x87);
@@ -1206,9 +2837,21 @@ impl Source {
 
                                     &slice
                                 }
+                                t if sized_kind(t).is_some() => {
+                                    for _ in 0..len {
+                                        slice.push(0xDD);
+                                        let b = addr.to_be_bytes();
+                                        slice.extend_from_slice(&b);
+                                        slice.extend(sized_kind(t).map(Kind::encode));
+                                    }
+
+                                    &slice
+                                }
 
                                 _ => &[0x0],
                             }
+                        } else if is_linear_algebra_type(&t) {
+                            &[0xA3]
                         } else {
                             &[0x0]
                         }
@@ -1218,23 +2861,47 @@ impl Source {
                 if t.get(0..1).unwrap() != "[" {
                     let b = addr.to_be_bytes();
                     bytes.extend_from_slice(&b);
+                    bytes.extend(sized_kind(&t).map(Kind::encode));
                 }
             }
             SyntaxTreeNode::Assign => {
-                Self::generate_expr_bytecode(
-                    bytes,
-                    functions,
-                    var_set,
-                    variable_addresses,
-                    calls,
-                    children[2].clone(),
-                );
-
                 let id = match children[0].clone().node {
                     SyntaxTreeNode::Identifier(id) => id,
                     _ => "".to_string(),
                 };
 
+                // a component is replaced in a copy of the vector, which is stored back
+                let is_local = variable_addresses.contains_key(&id);
+                if let Some((base, field)) = id.split_once('.').filter(|_| !is_local) {
+                    let (t, addr) = variable_addresses[base].clone();
+                    let b = addr.to_be_bytes();
+
+                    bytes.push(0xA2);
+                    bytes.extend_from_slice(&b);
+                    Self::generate_expr_bytecode(
+                        bytes,
+                        functions,
+                        var_set,
+                        variable_addresses,
+                        calls,
+                        children[2].clone(),
+                    );
+                    bytes.extend_from_slice(&[0xA7, component(&t, field).unwrap_or_default()]);
+                    bytes.push(0xA3);
+                    bytes.extend_from_slice(&b);
+
+                    return;
+                }
+
+                Self::generate_expr_bytecode(
+                    bytes,
+                    functions,
+                    var_set,
+                    variable_addresses,
+                    calls,
+                    children[2].clone(),
+                );
+
                 let (t, addr) = variable_addresses[&id].clone();
 
                 if children[1].clone().node == SyntaxTreeNode::Index {
@@ -1249,29 +2916,33 @@ impl Source {
                     );
                 }
 
-                bytes.push(match t.as_str() {
+                bytes.push(match storage_type(&t) {
                     "int" => 0x24,
                     "float" => 0x25,
                     "bool" => 0x2A,
                     "char" => 0x2E,
+                    t if sized_kind(t).is_some() => 0xDB,
                     _ => {
                         if children[1].clone().node == SyntaxTreeNode::Index {
                             let mut last_semicolon = t.rfind(";");
                             let mut s = t.clone();
-                            while last_semicolon != None {
+                            while last_semicolon.is_some() {
                                 let i = last_semicolon.unwrap();
 
                                 s = s.get(1..i).unwrap().to_string();
                                 last_semicolon = s.rfind(";");
                             }
 
-                            match s.as_str() {
+                            match storage_type(&s) {
                                 "int" => 0x87,
                                 "float" => 0x88,
                                 "bool" => 0x89,
                                 "char" => 0x8A,
+                                s if sized_kind(s).is_some() => 0xDD,
                                 _ => 0x0,
                             }
+                        } else if is_linear_algebra_type(&t) {
+                            0xA3
                         } else {
                             0x0
                         }
@@ -1280,6 +2951,91 @@ impl Source {
 
                 let b = addr.to_be_bytes();
                 bytes.extend_from_slice(&b);
+                bytes.extend(sized_kind(element_type(&t)).map(Kind::encode));
+            }
+            SyntaxTreeNode::CompoundAssign => {
+                let (target, op) = Self::desugar_compound_assign(ast.clone());
+
+                if children[1].clone().node != SyntaxTreeNode::Index {
+                    let mut assign = AbstractSyntaxTree::new();
+                    assign.node = SyntaxTreeNode::Assign;
+                    assign.children = vec![children[0].clone(), children[1].clone(), op];
+
+                    Self::generate_function_bytecode(
+                        bytes,
+                        functions,
+                        var_set,
+                        variable_addresses,
+                        calls,
+                        assign,
+                    );
+                    return;
+                }
+
+                let id = match children[0].clone().node {
+                    SyntaxTreeNode::Identifier(id) => id,
+                    _ => "".to_string(),
+                };
+
+                let (t, addr) = variable_addresses[&id].clone();
+                let b = addr.to_be_bytes();
+
+                // the element index is computed once and duplicated so it can serve both the
+                // load and the store
+                Self::generate_index_bytecode(
+                    bytes,
+                    functions,
+                    var_set,
+                    variable_addresses,
+                    calls,
+                    children[1].clone(),
+                    t.clone(),
+                );
+                bytes.push(0x16);
+
+                let elem_type = Self::get_type(functions.clone(), var_set.clone(), target)
+                    .expect("could not get type");
+
+                bytes.push(match storage_type(&elem_type) {
+                    "int" => 0x82,
+                    "float" => 0x83,
+                    "bool" => 0x84,
+                    "char" => 0x85,
+                    t if sized_kind(t).is_some() => 0xDC,
+                    _ => 0x0,
+                });
+                bytes.extend_from_slice(&b);
+                bytes.extend(sized_kind(&elem_type).map(Kind::encode));
+
+                Self::generate_expr_bytecode(
+                    bytes,
+                    functions,
+                    var_set,
+                    variable_addresses,
+                    calls,
+                    children[3].clone(),
+                );
+
+                let value_type =
+                    Self::get_type(functions.clone(), var_set.clone(), children[3].clone())
+                        .expect("could not get type");
+                if value_type == "int" && elem_type == "float" {
+                    bytes.push(0x40);
+                }
+
+                bytes.extend_from_slice(&Self::binary_opcode(&op.node, &elem_type));
+                bytes.push(0x17);
+
+                bytes.push(match storage_type(&elem_type) {
+                    "int" => 0x87,
+                    "float" => 0x88,
+                    "bool" => 0x89,
+                    "char" => 0x8A,
+                    t if sized_kind(t).is_some() => 0xDD,
+                    _ => 0x0,
+                });
+                bytes.extend_from_slice(&b);
+                bytes.extend(sized_kind(&elem_type).map(Kind::encode));
             }
             SyntaxTreeNode::FnCall => {
                 let id = match children[0].clone().node {
@@ -1287,6 +3043,53 @@ impl Source {
                     _ => "".to_string(),
                 };
 
+                if is_assertion(&id) {
+                    Self::generate_inputs_bytecode(
+                        bytes,
+                        functions,
+                        var_set,
+                        variable_addresses,
+                        calls,
+                        children[1].clone(),
+                    );
+
+                    let number = match children[2].node {
+                        SyntaxTreeNode::Integer(number) => number as u32,
+                        _ => 0,
+                    };
+                    bytes.push(if id == "assert" { 0x95 } else { 0x96 });
+                    bytes.extend_from_slice(&number.to_be_bytes());
+
+                    return;
+                }
+
+                let builtin = !functions.iter().any(|(fn_id, _, _)| *fn_id == id);
+                if is_linear_algebra(&id) && builtin {
+                    Self::generate_expr_bytecode(
+                        bytes,
+                        functions,
+                        var_set,
+                        variable_addresses,
+                        calls,
+                        ast,
+                    );
+                    bytes.push(0x12);
+
+                    return;
+                }
+                if builtin && Self::inlined_print(functions, var_set, &ast).is_some() {
+                    Self::generate_expr_bytecode(
+                        bytes,
+                        functions,
+                        var_set,
+                        variable_addresses,
+                        calls,
+                        ast,
+                    );
+
+                    return;
+                }
+
                 bytes.push(0x10);
                 bytes.extend_from_slice(&[0x0, 0x0, 0x0, 0x0]);
 
@@ -1431,7 +3234,7 @@ impl Source {
 
     fn generate_expr_bytecode(
         bytes: &mut Vec<u8>,
-        functions: &Vec<(String, String, Vec<(String, String)>)>,
+        functions: &Vec<FunctionSignature>,
         var_set: &HashSet<(String, String)>,
         variable_addresses: &HashMap<String, (String, u32)>,
         calls: &mut Vec<(usize, String)>,
@@ -1451,23 +3254,34 @@ impl Source {
             | SyntaxTreeNode::AddOp
             | SyntaxTreeNode::SubOp
             | SyntaxTreeNode::MulOp
-            | SyntaxTreeNode::DivOp => {
-                Self::generate_expr_bytecode(
-                    bytes,
-                    functions,
-                    var_set,
-                    variable_addresses,
-                    calls,
-                    children[0].clone(),
-                );
-                Self::generate_expr_bytecode(
-                    bytes,
-                    functions,
-                    var_set,
-                    variable_addresses,
-                    calls,
-                    children[1].clone(),
-                );
+            | SyntaxTreeNode::DivOp
+            | SyntaxTreeNode::ModOp
+            | SyntaxTreeNode::ShlOp
+            | SyntaxTreeNode::ShrOp
+            | SyntaxTreeNode::BitAndOp
+            | SyntaxTreeNode::BitXorOp
+            | SyntaxTreeNode::BitOrOp => {
+                let operand_type = Self::get_operand_type(functions, var_set, ast.clone());
+                // the scalar that scales a vector or matrix is a float
+                let scaled = Self::get_type(functions.clone(), var_set.clone(), ast.clone())
+                    .is_ok_and(|t| is_linear_algebra_type(&t));
+
+                for child in children.iter().take(2) {
+                    Self::generate_expr_bytecode(
+                        bytes,
+                        functions,
+                        var_set,
+                        variable_addresses,
+                        calls,
+                        child.clone(),
+                    );
+
+                    let t = Self::get_type(functions.clone(), var_set.clone(), child.clone())
+                        .expect("could not get type");
+                    if t == "int" && (operand_type == "float" || scaled) {
+                        bytes.push(0x40);
+                    }
+                }
             }
             _ => {}
         }
@@ -1491,6 +3305,42 @@ impl Source {
                     _ => "".to_string(),
                 };
 
+                // the vector and matrix builtins are single instructions, so they are inlined
+                if is_linear_algebra(&id) && !functions.iter().any(|(fn_id, _, _)| *fn_id == id) {
+                    for input in flatten_inputs(children[1].clone()) {
+                        Self::generate_expr_bytecode(
+                            bytes,
+                            functions,
+                            var_set,
+                            variable_addresses,
+                            calls,
+                            input.clone(),
+                        );
+
+                        let t = Self::get_type(functions.clone(), var_set.clone(), input)
+                            .expect("could not get type");
+                        if t == "int" {
+                            bytes.push(0x40);
+                        }
+                    }
+
+                    bytes.extend_from_slice(&linear_algebra_opcode(&id));
+                    return;
+                }
+
+                if let Some((input, opcode)) = Self::inlined_print(functions, var_set, &ast) {
+                    Self::generate_expr_bytecode(
+                        bytes,
+                        functions,
+                        var_set,
+                        variable_addresses,
+                        calls,
+                        input,
+                    );
+                    bytes.extend_from_slice(&opcode);
+                    return;
+                }
+
                 bytes.push(0x10);
                 bytes.extend_from_slice(&[0x0, 0x0, 0x0, 0x0]);
 
@@ -1523,105 +3373,112 @@ impl Source {
             SyntaxTreeNode::OrOp => {
                 bytes.push(0x59);
             }
-            SyntaxTreeNode::CompEq => {
-                let t = Self::get_type(functions.clone(), var_set.clone(), children[0].clone())
-                    .expect("could not get type");
-
-                bytes.push(match t.as_str() {
-                    "int" => 0x52,
-                    "float" => 0x5C,
-                    "bool" => 0x62,
-                    _ => 0x0,
-                });
-            }
-            SyntaxTreeNode::CompNeq => {
-                let t = Self::get_type(functions.clone(), var_set.clone(), children[0].clone())
-                    .expect("could not get type");
-
-                bytes.push(match t.as_str() {
-                    "int" => 0x53,
-                    "float" => 0x5D,
-                    "bool" => 0x63,
-                    _ => 0x0,
-                });
-            }
-            SyntaxTreeNode::CompLess => {
-                let t = Self::get_type(functions.clone(), var_set.clone(), children[0].clone())
-                    .expect("could not get type");
-
-                bytes.push(match t.as_str() {
-                    "int" => 0x54,
-                    "float" => 0x5E,
-                    _ => 0x0,
-                });
+            SyntaxTreeNode::CompEq
+            | SyntaxTreeNode::CompNeq
+            | SyntaxTreeNode::CompLess
+            | SyntaxTreeNode::CompGreater
+            | SyntaxTreeNode::CompLeq
+            | SyntaxTreeNode::CompGeq => {
+                let t = Self::get_operand_type(functions, var_set, ast.clone());
+                bytes.extend_from_slice(&Self::comparison_opcode(&ast.node, &t));
             }
-            SyntaxTreeNode::CompGreater => {
-                let t = Self::get_type(functions.clone(), var_set.clone(), children[0].clone())
+            SyntaxTreeNode::AddOp
+            | SyntaxTreeNode::SubOp
+            | SyntaxTreeNode::MulOp
+            | SyntaxTreeNode::DivOp
+            | SyntaxTreeNode::ModOp
+            | SyntaxTreeNode::ShlOp
+            | SyntaxTreeNode::ShrOp
+            | SyntaxTreeNode::BitAndOp
+            | SyntaxTreeNode::BitXorOp
+            | SyntaxTreeNode::BitOrOp => {
+                let t = Self::get_type(functions.clone(), var_set.clone(), ast.clone())
                     .expect("could not get type");
 
-                bytes.push(match t.as_str() {
-                    "int" => 0x56,
-                    "float" => 0x60,
-                    _ => 0x0,
-                });
+                if is_linear_algebra_type(&t) {
+                    let [l_value, r_value] = [&children[0], &children[1]].map(|child| {
+                        Self::get_type(functions.clone(), var_set.clone(), child.clone())
+                            .expect("could not get type")
+                    });
+                    bytes.extend_from_slice(&linear_algebra_binary_opcode(
+                        &ast.node, &l_value, &r_value,
+                    ));
+                } else {
+                    bytes.extend_from_slice(&Self::binary_opcode(&ast.node, &t));
+                }
             }
-            SyntaxTreeNode::CompLeq => {
-                let t = Self::get_type(functions.clone(), var_set.clone(), children[0].clone())
-                    .expect("could not get type");
+            SyntaxTreeNode::NegOp => {
+                Self::generate_expr_bytecode(
+                    bytes,
+                    functions,
+                    var_set,
+                    variable_addresses,
+                    calls,
+                    children[0].clone(),
+                );
 
-                bytes.push(match t.as_str() {
-                    "int" => 0x55,
-                    "float" => 0x5F,
-                    _ => 0x0,
-                });
-            }
-            SyntaxTreeNode::CompGeq => {
-                let t = Self::get_type(functions.clone(), var_set.clone(), children[0].clone())
-                    .expect("could not get type");
-                bytes.push(match t.as_str() {
-                    "int" => 0x57,
-                    "float" => 0x61,
-                    _ => 0x0,
-                });
-            }
-            SyntaxTreeNode::AddOp => {
                 let t = Self::get_type(functions.clone(), var_set.clone(), ast.clone())
                     .expect("could not get type");
-
-                bytes.push(match t.as_str() {
-                    "int" => 0x30,
-                    "float" => 0x31,
-                    "char" => 0x38,
-                    _ => 0x0,
-                });
+                bytes.extend_from_slice(&Self::unary_opcode(&ast.node, &t));
             }
-            SyntaxTreeNode::SubOp => {
-                let t = Self::get_type(functions.clone(), var_set.clone(), ast.clone())
-                    .expect("could not get type");
-                bytes.push(match t.as_str() {
-                    "int" => 0x32,
-                    "float" => 0x33,
-                    "char" => 0x39,
-                    _ => 0x0,
-                });
+            SyntaxTreeNode::NotOp => {
+                Self::generate_expr_bytecode(
+                    bytes,
+                    functions,
+                    var_set,
+                    variable_addresses,
+                    calls,
+                    children[0].clone(),
+                );
+                bytes.push(0x65);
             }
-            SyntaxTreeNode::MulOp => {
+            SyntaxTreeNode::BitNotOp => {
+                Self::generate_expr_bytecode(
+                    bytes,
+                    functions,
+                    var_set,
+                    variable_addresses,
+                    calls,
+                    children[0].clone(),
+                );
+
                 let t = Self::get_type(functions.clone(), var_set.clone(), ast.clone())
                     .expect("could not get type");
-                bytes.push(match t.as_str() {
-                    "int" => 0x34,
-                    "float" => 0x35,
-                    _ => 0x0,
-                });
+                bytes.extend_from_slice(&Self::unary_opcode(&ast.node, &t));
             }
-            SyntaxTreeNode::DivOp => {
-                let t = Self::get_type(functions.clone(), var_set.clone(), ast.clone())
+            SyntaxTreeNode::Cast => {
+                let to = match children[1].clone().node {
+                    SyntaxTreeNode::Identifier(id) => id,
+                    _ => "".to_string(),
+                };
+
+                // a literal is converted while compiling, so it needs no floating point at run time
+                if let Some(format) = Format::of_type(&to) {
+                    let raw = match children[0].node {
+                        SyntaxTreeNode::Integer(num) => Some(format.of_int(num)),
+                        SyntaxTreeNode::Float(num) => Some(format.of_float(num as f64)),
+                        _ => None,
+                    };
+                    if let Some(raw) = raw {
+                        bytes.push(0x10);
+                        bytes.extend_from_slice(&raw.to_be_bytes());
+                        return;
+                    }
+                }
+
+                Self::generate_expr_bytecode(
+                    bytes,
+                    functions,
+                    var_set,
+                    variable_addresses,
+                    calls,
+                    children[0].clone(),
+                );
+
+                let from = Self::get_type(functions.clone(), var_set.clone(), children[0].clone())
                     .expect("could not get type");
-                bytes.push(match t.as_str() {
-                    "int" => 0x36,
-                    "float" => 0x37,
-                    _ => 0x0,
-                });
+
+                bytes.extend_from_slice(&Self::cast_opcode(&from, &to).unwrap_or_default());
             }
             SyntaxTreeNode::Integer(num) => {
                 bytes.push(0x10);
@@ -1635,6 +3492,9 @@ impl Source {
                 let b = num.to_be_bytes();
                 bytes.extend_from_slice(&b);
             }
+            SyntaxTreeNode::SizedNumber(text) => {
+                bytes.extend_from_slice(&sized_literal_bytecode(&text));
+            }
             SyntaxTreeNode::True => {
                 bytes.extend_from_slice(&[0x14, 0x1]);
             }
@@ -1645,8 +3505,23 @@ impl Source {
                 bytes.extend_from_slice(&[0x15, c as u8]);
             }
             SyntaxTreeNode::Identifier(id) => {
+                let field = match children.first().map(|child| &child.node) {
+                    Some(SyntaxTreeNode::Identifier(field)) => Some((id.as_str(), field.as_str())),
+                    _ if variable_addresses.contains_key(&id) => None,
+                    _ => id.split_once('.'),
+                };
+                if let Some((base, field)) = field {
+                    let (t, addr) = variable_addresses[base].clone();
+
+                    bytes.push(0xA2);
+                    bytes.extend_from_slice(&addr.to_be_bytes());
+                    bytes.extend_from_slice(&[0xA6, component(&t, field).unwrap_or_default()]);
+
+                    return;
+                }
+
                 let (t, addr) = variable_addresses[&id].clone();
-                if children.len() > 0 {
+                if !children.is_empty() {
                     Self::generate_index_bytecode(
                         bytes,
                         functions,
@@ -1658,30 +3533,34 @@ impl Source {
                     );
                 }
 
-                bytes.push(match t.as_str() {
+                bytes.push(match storage_type(&t) {
                     "int" => 0x22,
                     "float" => 0x23,
                     "bool" => 0x29,
                     "char" => 0x2D,
+                    t if sized_kind(t).is_some() => 0xDA,
                     _ => {
                         if t.get(0..1).unwrap() == "[" {
                             let mut last_semicolon = t.rfind(";");
                             let mut s = t.clone();
 
-                            while last_semicolon != None {
+                            while last_semicolon.is_some() {
                                 let i = last_semicolon.unwrap();
 
                                 s = s.get(1..i).unwrap().to_string();
                                 last_semicolon = s.rfind(";");
                             }
 
-                            match s.as_str() {
+                            match storage_type(&s) {
                                 "int" => 0x82,
                                 "float" => 0x83,
                                 "bool" => 0x84,
                                 "char" => 0x85,
+                                s if sized_kind(s).is_some() => 0xDC,
                                 _ => 0x0,
                             }
+                        } else if is_linear_algebra_type(&t) {
+                            0xA2
                         } else {
                             0x0
                         }
@@ -1690,6 +3569,7 @@ impl Source {
 
                 let b = addr.to_be_bytes();
                 bytes.extend_from_slice(&b);
+                bytes.extend(sized_kind(element_type(&t)).map(Kind::encode));
             }
             _ => {
                 for child in children {
@@ -1706,9 +3586,161 @@ impl Source {
         }
     }
 
+    /// The argument of a call to a print builtin that is compiled to a single instruction, and
+    /// that instruction. Printing a fixed-point number needs its format and printing a sized
+    /// number its kind, which a function cannot take.
+    fn inlined_print(
+        functions: &[FunctionSignature],
+        var_set: &HashSet<(String, String)>,
+        call: &AbstractSyntaxTree,
+    ) -> Option<(AbstractSyntaxTree, Vec<u8>)> {
+        let id = match &call.children.first()?.node {
+            SyntaxTreeNode::Identifier(id) => id.as_str(),
+            _ => return None,
+        };
+        if functions.iter().any(|(fn_id, _, _)| fn_id == id) {
+            return None;
+        }
+
+        let input = call.children.get(1)?.children.first()?.clone();
+        let t = Self::get_type(functions.to_vec(), var_set.clone(), input.clone()).ok()?;
+        let opcode = match (id, Format::of_type(&t), sized_kind(&t)) {
+            ("print_fixed", Some(format), _) => vec![0xD2, format.frac],
+            ("print_int" | "print_float", _, Some(kind)) => vec![0xF1, kind.encode()],
+            _ => return None,
+        };
+
+        Some((input, opcode))
+    }
+
+    fn binary_opcode(op: &SyntaxTreeNode, t: &str) -> Vec<u8> {
+        if let Some(format) = Format::of_type(t) {
+            let opcode = match op {
+                SyntaxTreeNode::AddOp => 0xC8,
+                SyntaxTreeNode::SubOp => 0xC9,
+                SyntaxTreeNode::MulOp => 0xCA,
+                SyntaxTreeNode::DivOp => 0xCB,
+                _ => 0x0,
+            };
+            return [&[opcode][..], &format.encode()].concat();
+        }
+
+        if let Some(kind) = sized_kind(t) {
+            let opcode = match op {
+                SyntaxTreeNode::AddOp => 0xDE,
+                SyntaxTreeNode::SubOp => 0xDF,
+                SyntaxTreeNode::MulOp => 0xE0,
+                SyntaxTreeNode::DivOp => 0xE1,
+                SyntaxTreeNode::ModOp => 0xE2,
+                SyntaxTreeNode::BitAndOp => 0xE4,
+                SyntaxTreeNode::BitOrOp => 0xE5,
+                SyntaxTreeNode::BitXorOp => 0xE6,
+                SyntaxTreeNode::ShlOp => 0xE7,
+                SyntaxTreeNode::ShrOp => 0xE8,
+                _ => 0x0,
+            };
+            return vec![opcode, kind.encode()];
+        }
+
+        vec![match (op, t) {
+            (SyntaxTreeNode::AddOp, "int") => 0x30,
+            (SyntaxTreeNode::AddOp, "float") => 0x31,
+            (SyntaxTreeNode::AddOp, "char") => 0x38,
+            (SyntaxTreeNode::SubOp, "int") => 0x32,
+            (SyntaxTreeNode::SubOp, "float") => 0x33,
+            (SyntaxTreeNode::SubOp, "char") => 0x39,
+            (SyntaxTreeNode::MulOp, "int") => 0x34,
+            (SyntaxTreeNode::MulOp, "float") => 0x35,
+            (SyntaxTreeNode::DivOp, "int") => 0x36,
+            (SyntaxTreeNode::DivOp, "float") => 0x37,
+            (SyntaxTreeNode::ModOp, _) => 0x3D,
+            (SyntaxTreeNode::BitAndOp, _) => 0x71,
+            (SyntaxTreeNode::BitOrOp, _) => 0x72,
+            (SyntaxTreeNode::BitXorOp, _) => 0x73,
+            (SyntaxTreeNode::ShlOp, _) => 0x74,
+            (SyntaxTreeNode::ShrOp, _) => 0x75,
+            _ => 0x0,
+        }]
+    }
+
+    fn unary_opcode(op: &SyntaxTreeNode, t: &str) -> Vec<u8> {
+        if let Some(format) = Format::of_type(t) {
+            return [&[0xCC][..], &format.encode()].concat();
+        }
+        if let Some(kind) = sized_kind(t) {
+            let opcode = if *op == SyntaxTreeNode::NegOp {
+                0xE3
+            } else {
+                0xE9
+            };
+            return vec![opcode, kind.encode()];
+        }
+
+        vec![match (op, t) {
+            (SyntaxTreeNode::NegOp, "int") => 0x3A,
+            (SyntaxTreeNode::NegOp, "float") => 0x3B,
+            (SyntaxTreeNode::NegOp, t) if is_linear_algebra_type(t) => 0xAA,
+            (SyntaxTreeNode::BitNotOp, _) => 0x70,
+            _ => 0x0,
+        }]
+    }
+
+    fn comparison_opcode(op: &SyntaxTreeNode, t: &str) -> Vec<u8> {
+        let i = match op {
+            SyntaxTreeNode::CompEq => 0,
+            SyntaxTreeNode::CompNeq => 1,
+            SyntaxTreeNode::CompLess => 2,
+            SyntaxTreeNode::CompLeq => 3,
+            SyntaxTreeNode::CompGreater => 4,
+            _ => 5,
+        };
+        if let Some(kind) = sized_kind(t) {
+            return vec![0xEA + i, kind.encode()];
+        }
+
+        vec![match (storage_type(t), i) {
+            ("int", _) => 0x52 + i,
+            ("float", _) => 0x5C + i,
+            ("bool", 0 | 1) => 0x62 + i,
+            (t, 0 | 1) if is_linear_algebra_type(t) => 0xB3 + i,
+            _ => 0x0,
+        }]
+    }
+
+    /// Splits `target op= value` into the target as it would be read in an expression and the
+    /// binary operation `target op value` it stands for.
+    fn desugar_compound_assign(
+        ast: AbstractSyntaxTree,
+    ) -> (AbstractSyntaxTree, AbstractSyntaxTree) {
+        let children = ast.children;
+
+        let mut target = children[0].clone();
+        if children[1].node == SyntaxTreeNode::Index {
+            target.children = vec![children[1].clone()];
+        }
+
+        let mut op = children[2].clone();
+        op.children = vec![target.clone(), children[3].clone()];
+
+        (target, op)
+    }
+
+    fn get_operand_type(
+        functions: &[FunctionSignature],
+        var_set: &HashSet<(String, String)>,
+        ast: AbstractSyntaxTree,
+    ) -> String {
+        let l_value = Self::get_type(functions.to_vec(), var_set.clone(), ast.children[0].clone())
+            .expect("could not get type");
+        let r_value = Self::get_type(functions.to_vec(), var_set.clone(), ast.children[1].clone())
+            .expect("could not get type");
+
+        Self::promote(&l_value, &r_value).unwrap_or(l_value)
+    }
+
     fn generate_inputs_bytecode(
         bytes: &mut Vec<u8>,
-        functions: &Vec<(String, String, Vec<(String, String)>)>,
+        functions: &Vec<FunctionSignature>,
         var_set: &HashSet<(String, String)>,
         variable_addresses: &HashMap<String, (String, u32)>,
         calls: &mut Vec<(usize, String)>,
@@ -1751,7 +3783,7 @@ impl Source {
 
     fn generate_arr_bytecode(
         bytes: &mut Vec<u8>,
-        functions: &Vec<(String, String, Vec<(String, String)>)>,
+        functions: &Vec<FunctionSignature>,
         var_set: &HashSet<(String, String)>,
         variable_addresses: &HashMap<String, (String, u32)>,
         calls: &mut Vec<(usize, String)>,
@@ -1759,37 +3791,34 @@ impl Source {
         idx: u32,
     ) {
         let children = ast.children.clone();
-        match ast.node {
-            SyntaxTreeNode::InputList => {
-                Self::generate_arr_bytecode(
-                    bytes,
-                    functions,
-                    var_set,
-                    variable_addresses,
-                    calls,
-                    children[1].clone(),
-                    idx + 1,
-                );
-
-                Self::generate_expr_bytecode(
-                    bytes,
-                    functions,
-                    var_set,
-                    variable_addresses,
-                    calls,
-                    children[0].clone(),
-                );
-
-                bytes.push(0x10);
-                bytes.extend_from_slice(&idx.to_be_bytes());
-            }
-            _ => {}
+        if ast.node == SyntaxTreeNode::InputList {
+            Self::generate_arr_bytecode(
+                bytes,
+                functions,
+                var_set,
+                variable_addresses,
+                calls,
+                children[1].clone(),
+                idx + 1,
+            );
+
+            Self::generate_expr_bytecode(
+                bytes,
+                functions,
+                var_set,
+                variable_addresses,
+                calls,
+                children[0].clone(),
+            );
+
+            bytes.push(0x10);
+            bytes.extend_from_slice(&idx.to_be_bytes());
         }
     }
 
     fn generate_index_bytecode(
         bytes: &mut Vec<u8>,
-        functions: &Vec<(String, String, Vec<(String, String)>)>,
+        functions: &Vec<FunctionSignature>,
         var_set: &HashSet<(String, String)>,
         variable_addresses: &HashMap<String, (String, u32)>,
         calls: &mut Vec<(usize, String)>,
@@ -1797,56 +3826,53 @@ impl Source {
         t: String,
     ) {
         let children = ast.children.clone();
-        match ast.node {
-            SyntaxTreeNode::Index => {
-                Self::generate_expr_bytecode(
-                    bytes,
-                    functions,
-                    var_set,
-                    variable_addresses,
-                    calls,
-                    children[0].clone(),
-                );
-
-                let mut last_semicolon = t.rfind(";");
-                let mut s = t.clone();
-
-                let mut len = 1;
-                while last_semicolon != None {
-                    let i = last_semicolon.unwrap();
-                    let str_len = s.get(i + 2..s.len() - 1).unwrap();
-                    len = len * str_len.parse::<i32>().expect("could not parse to int");
-
-                    let new_t = s.get(1..i).unwrap().to_string();
-                    last_semicolon = new_t.rfind(";");
-
-                    if last_semicolon == None {
-                        len /= str_len.parse::<i32>().expect("could not parse to int");
-                        break;
-                    }
-
-                    s = new_t;
+        if ast.node == SyntaxTreeNode::Index {
+            Self::generate_expr_bytecode(
+                bytes,
+                functions,
+                var_set,
+                variable_addresses,
+                calls,
+                children[0].clone(),
+            );
+
+            let mut last_semicolon = t.rfind(";");
+            let mut s = t.clone();
+
+            let mut len = 1;
+            while last_semicolon.is_some() {
+                let i = last_semicolon.unwrap();
+                let str_len = s.get(i + 2..s.len() - 1).unwrap();
+                len *= str_len.parse::<i32>().expect("could not parse to int");
+
+                let new_t = s.get(1..i).unwrap().to_string();
+                last_semicolon = new_t.rfind(";");
+
+                if last_semicolon.is_none() {
+                    len /= str_len.parse::<i32>().expect("could not parse to int");
+                    break;
                 }
 
-                bytes.push(0x10);
-                bytes.extend_from_slice(&len.to_be_bytes());
-                bytes.push(0x34);
-
-                Self::generate_index_bytecode(
-                    bytes,
-                    functions,
-                    var_set,
-                    variable_addresses,
-                    calls,
-                    children[1].clone(),
-                    s.clone(),
-                );
+                s = new_t;
+            }
 
-                if children[1].clone().node != SyntaxTreeNode::Null {
-                    bytes.push(0x30);
-                }
+            bytes.push(0x10);
+            bytes.extend_from_slice(&len.to_be_bytes());
+            bytes.push(0x34);
+
+            Self::generate_index_bytecode(
+                bytes,
+                functions,
+                var_set,
+                variable_addresses,
+                calls,
+                children[1].clone(),
+                s.clone(),
+            );
+
+            if children[1].clone().node != SyntaxTreeNode::Null {
+                bytes.push(0x30);
             }
-            _ => {}
         }
     }
 
@@ -1858,9 +3884,7 @@ impl Source {
         let left = Self::build_arr_from_input_list(ast.children[0].clone());
         let right = Self::build_arr_from_input_list(ast.children[1].clone());
 
-        let tree = Self::build_arr_helper(left, right);
-
-        tree
+        Self::build_arr_helper(left, right)
     }
 
     fn build_arr_helper(left: AbstractSyntaxTree, right: AbstractSyntaxTree) -> AbstractSyntaxTree {
@@ -1888,3 +3912,617 @@ impl Source {
         }
     }
 }
+
+/// The variables of a function ordered by name, which is the order they are laid out in.
+fn sorted(var_set: &HashSet<(String, String)>) -> Vec<(String, String)> {
+    let mut vars: Vec<(String, String)> = var_set.iter().cloned().collect();
+    vars.sort();
+    vars
+}
+
+/// The number of bytes a variable of type `var_type` takes up in memory.
+fn variable_size(var_type: &str) -> u32 {
+    match storage_type(var_type) {
+        "int" | "float" => 4,
+        "bool" | "char" => 1,
+        t if is_linear_algebra_type(t) => 4,
+        _ => match var_type
+            .strip_prefix('[')
+            .and_then(|t| t.strip_suffix(']'))
+            .and_then(|t| t.rsplit_once("; "))
+        {
+            Some((elem_type, len)) => variable_size(elem_type) * len.parse::<u32>().unwrap_or(0),
+            None => sized_kind(var_type).map_or(0, Kind::size),
+        },
+    }
+}
+
+/// Whether `id` names one of the assertion builtins, which are compiled to instructions that
+/// stop the node when the assertion fails.
+fn is_assertion(id: &str) -> bool {
+    id == "assert" || id == "assert_eq"
+}
+
+/// Whether `ast` is a call to the function `id`.
+fn is_call_to(ast: &AbstractSyntaxTree, id: &str) -> bool {
+    ast.node == SyntaxTreeNode::FnCall
+        && matches!(&ast.children[0].node, SyntaxTreeNode::Identifier(call) if call == id)
+}
+
+/// Whether `ast` is a call to `assert` or `assert_eq`.
+fn is_assertion_call(ast: &AbstractSyntaxTree) -> bool {
+    ast.node == SyntaxTreeNode::FnCall
+        && matches!(&ast.children[0].node, SyntaxTreeNode::Identifier(id) if is_assertion(id))
+}
+
+/// The number of components of the vector type `t`.
+fn vector_len(t: &str) -> Option<u8> {
+    match t {
+        "vec2" => Some(2),
+        "vec3" => Some(3),
+        "vec4" => Some(4),
+        _ => None,
+    }
+}
+
+/// The number of rows, and columns, of the matrix type `t`.
+fn matrix_len(t: &str) -> Option<u8> {
+    match t {
+        "mat2" => Some(2),
+        "mat3" => Some(3),
+        "mat4" => Some(4),
+        _ => None,
+    }
+}
+
+/// Whether values of type `t` are vectors, matrices, quaternions or transforms, which the VM
+/// holds as single values.
+fn is_linear_algebra_type(t: &str) -> bool {
+    vector_len(t).is_some() || matrix_len(t).is_some() || t == "quat" || t == "transform3"
+}
+
+/// The index of the component `field` of a vector or quaternion of type `t`, if it has one.
+fn component(t: &str, field: &str) -> Option<u8> {
+    if t == "quat" {
+        return ["w", "x", "y", "z"]
+            .iter()
+            .position(|c| *c == field)
+            .map(|k| k as u8);
+    }
+
+    let k = ["x", "y", "z", "w"].iter().position(|c| *c == field)? as u8;
+    (k < vector_len(t)?).then_some(k)
+}
+
+/// Whether `id` names one of the vector, matrix and rotation builtins, which are the
+/// constructors named after the types and the functions of linear algebra.
+fn is_linear_algebra(id: &str) -> bool {
+    is_linear_algebra_type(id)
+        || matches!(
+            id,
+            "dot"
+                | "cross"
+                | "norm"
+                | "transpose"
+                | "inverse"
+                | "axis_angle"
+                | "euler"
+                | "slerp"
+                | "normalize"
+                | "rotation"
+                | "translation"
+        )
+}
+
+/// The type returned by the vector, matrix and rotation builtin `id` when it is called with
+/// arguments of types `params`, or `None` if it cannot be. Matrices take their elements row by
+/// row and quaternions take `w` first.
+fn linear_algebra_call(id: &str, params: &[&str]) -> Option<String> {
+    let scalar = |t: &str| t == "int" || t == "float";
+    let scalars = params.iter().all(|t| scalar(t));
+
+    match (id, params) {
+        (t, _) if vector_len(t).map(usize::from) == Some(params.len()) && scalars => {
+            Some(t.to_string())
+        }
+        (t, _) if matrix_len(t).map(|n| usize::from(n * n)) == Some(params.len()) && scalars => {
+            Some(t.to_string())
+        }
+        ("quat", [_, _, _, _]) | ("euler", [_, _, _]) if scalars => Some("quat".to_string()),
+        ("axis_angle", ["vec3", angle]) if scalar(angle) => Some("quat".to_string()),
+        ("slerp", ["quat", "quat", t]) if scalar(t) => Some("quat".to_string()),
+        ("transform3", ["quat", "vec3"]) => Some("transform3".to_string()),
+        ("rotation", ["transform3"]) => Some("quat".to_string()),
+        ("translation", ["transform3"]) => Some("vec3".to_string()),
+        ("dot", [a, b]) if a == b && vector_len(a).is_some() => Some("float".to_string()),
+        ("cross", ["vec3", "vec3"]) => Some("vec3".to_string()),
+        ("norm", [a]) if vector_len(a).is_some() => Some("float".to_string()),
+        ("normalize", [a]) if vector_len(a).is_some() || *a == "quat" => Some(a.to_string()),
+        ("transpose", [a]) if matrix_len(a).is_some() => Some(a.to_string()),
+        ("inverse", [a]) if matrix_len(a).is_some() || matches!(*a, "quat" | "transform3") => {
+            Some(a.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// The type of `l_value op r_value` when either operand is a vector, matrix, quaternion or
+/// transform, or `None` when neither is. Vectors and matrices add to and subtract from their
+/// own type, and multiply with a scalar on either side or, for matrices, with a vector or
+/// matrix of the same size. Quaternions and transforms compose with their own type and move a
+/// `vec3`.
+fn linear_algebra_op(
+    op: &SyntaxTreeNode,
+    l_value: &str,
+    r_value: &str,
+) -> Option<Result<String, usize>> {
+    if !is_linear_algebra_type(l_value) && !is_linear_algebra_type(r_value) {
+        return None;
+    }
+
+    let scalar = |t: &str| t == "int" || t == "float";
+    let size = |t: &str| vector_len(t).or(matrix_len(t));
+
+    Some(match op {
+        SyntaxTreeNode::AddOp | SyntaxTreeNode::SubOp
+            if l_value == r_value && size(l_value).is_some() =>
+        {
+            Ok(l_value.to_string())
+        }
+        SyntaxTreeNode::MulOp if scalar(l_value) && size(r_value).is_some() => {
+            Ok(r_value.to_string())
+        }
+        SyntaxTreeNode::MulOp if scalar(r_value) && size(l_value).is_some() => {
+            Ok(l_value.to_string())
+        }
+        SyntaxTreeNode::MulOp
+            if matrix_len(l_value).is_some() && size(l_value) == size(r_value) =>
+        {
+            Ok(r_value.to_string())
+        }
+        SyntaxTreeNode::MulOp
+            if matches!(l_value, "quat" | "transform3")
+                && (r_value == l_value || r_value == "vec3") =>
+        {
+            Ok(r_value.to_string())
+        }
+        _ => Err(11),
+    })
+}
+
+/// The instructions for `l_value op r_value` where the result is a vector, matrix, quaternion
+/// or transform.
+fn linear_algebra_binary_opcode(op: &SyntaxTreeNode, l_value: &str, r_value: &str) -> Vec<u8> {
+    match (op, l_value, r_value) {
+        (SyntaxTreeNode::AddOp, _, _) => vec![0xA8],
+        (SyntaxTreeNode::SubOp, _, _) => vec![0xA9],
+        (_, "quat", "quat") => vec![0xBB],
+        (_, "quat", _) => vec![0xBC],
+        (_, "transform3", "transform3") => vec![0xBD],
+        (_, "transform3", _) => vec![0xBE],
+        // `scalev` expects the scalar on top
+        (_, "int" | "float", _) => vec![0x17, 0xAB],
+        (_, _, "int" | "float") => vec![0xAB],
+        _ if vector_len(r_value).is_some() => vec![0xAC],
+        _ => vec![0xAD],
+    }
+}
+
+/// The instructions a call to the vector, matrix and rotation builtin `id` compiles to once
+/// its arguments are on the stack.
+fn linear_algebra_opcode(id: &str) -> Vec<u8> {
+    match id {
+        "dot" => vec![0xAE],
+        "cross" => vec![0xAF],
+        "norm" => vec![0xB0],
+        "transpose" => vec![0xB1],
+        "inverse" => vec![0xB2],
+        "quat" => vec![0xB7],
+        "axis_angle" => vec![0xB8],
+        "euler" => vec![0xB9],
+        "transform3" => vec![0xBA],
+        "slerp" => vec![0xBF],
+        "normalize" => vec![0xC0],
+        "rotation" => vec![0xC1],
+        "translation" => vec![0xC2],
+        t => match (vector_len(t), matrix_len(t)) {
+            (Some(n), _) => vec![0xA4, n],
+            (_, Some(n)) => vec![0xA5, n],
+            _ => vec![],
+        },
+    }
+}
+
+/// The arguments of a call, in the order they are written.
+fn flatten_inputs(mut ast: AbstractSyntaxTree) -> Vec<AbstractSyntaxTree> {
+    let mut inputs = vec![];
+    while ast.node == SyntaxTreeNode::InputList {
+        inputs.push(ast.children[0].clone());
+        ast = ast.children[1].clone();
+    }
+
+    inputs
+}
+
+/// Whether `t` is a fixed-point type, like `fixed<16, 16>`.
+fn is_fixed(t: &str) -> bool {
+    t.starts_with("fixed<")
+}
+
+/// Whether every fixed-point type in `t`, which can be an array of them, has a valid format.
+fn fixed_types_valid(t: &str) -> bool {
+    t.match_indices("fixed<").all(|(i, _)| {
+        let t = &t[i..];
+        t.find('>')
+            .is_some_and(|end| Format::of_type(&t[..=end]).is_some())
+    })
+}
+
+/// The type values of type `t` are stored as. Fixed-point numbers are stored as the integers
+/// that count their steps.
+fn storage_type(t: &str) -> &str {
+    if is_fixed(t) {
+        "int"
+    } else {
+        t
+    }
+}
+
+/// The kind of the numeric type `t` when it is one of the sized types, which are the ones
+/// other than `int` and `float`.
+fn sized_kind(t: &str) -> Option<Kind> {
+    Kind::of_type(t).filter(|kind| *kind != Kind::INT && *kind != Kind::FLOAT)
+}
+
+fn is_sized_int(t: &str) -> bool {
+    sized_kind(t).is_some_and(Kind::is_int)
+}
+
+/// The type of the suffixed literal `text`, or error 39 when it does not fit in it.
+fn sized_literal_type(text: &str) -> Result<String, usize> {
+    let (digits, kind) = sized::split_literal(text).ok_or(39_usize)?;
+    kind.literal(digits).map(|_| kind.type_name()).ok_or(39)
+}
+
+/// The type of the scalars in `t`, which is `t` itself unless it is an array.
+fn element_type(t: &str) -> &str {
+    let mut s = t;
+    while let Some(i) = s.rfind(';').filter(|_| s.starts_with('[')) {
+        s = &s[1..i];
+    }
+    s
+}
+
+/// Pushes the suffixed literal `text`, with the instructions of `int` and `float` when it is
+/// one of them.
+fn sized_literal_bytecode(text: &str) -> Vec<u8> {
+    let Some((digits, kind)) = sized::split_literal(text) else {
+        return vec![];
+    };
+
+    match kind.literal(digits) {
+        Some(Number::Int(value)) if kind == Kind::INT => {
+            [&[0x10][..], &(value as i32).to_be_bytes()].concat()
+        }
+        Some(Number::Float(value)) if kind == Kind::FLOAT => {
+            [&[0x11][..], &(value as f32).to_be_bytes()].concat()
+        }
+        Some(Number::Int(value)) => {
+            [&[0xD8, kind.encode()][..], &(value as u64).to_be_bytes()].concat()
+        }
+        Some(Number::Float(value)) => {
+            [&[0xD8, kind.encode()][..], &value.to_bits().to_be_bytes()].concat()
+        }
+        None => vec![],
+    }
+}
+
+/// Whether `t` is a `float` with a unit, like `float<m/s>`.
+fn is_unit_float(t: &str) -> bool {
+    units::has_unit(t) && units::split(t).0 == "float"
+}
+
+/// Whether `l_value` and `r_value` are numbers that cannot be added or compared because they
+/// are measured in different units, counting a number without a unit as different from one
+/// with a unit.
+fn units_differ(l_value: &str, r_value: &str) -> bool {
+    let number = |t: &str| matches!(units::split(t).0, "int" | "float");
+
+    (units::has_unit(l_value) || units::has_unit(r_value))
+        && number(l_value)
+        && number(r_value)
+        && l_value != r_value
+}
+
+/// The type of `l_value op r_value` when either operand has a unit, or `None` when neither
+/// does. Only numbers in the same unit add and subtract, and multiplying and dividing multiply
+/// and divide the units, where a plain `int` or `float` has no unit.
+fn unit_op(op: &SyntaxTreeNode, l_value: &str, r_value: &str) -> Option<Result<String, usize>> {
+    if !units::has_unit(l_value) && !units::has_unit(r_value) {
+        return None;
+    }
+
+    let ((l_name, l_unit), (r_name, r_unit)) = (units::split(l_value), units::split(r_value));
+    if !matches!(l_name, "int" | "float") || !matches!(r_name, "int" | "float") {
+        return Some(Err(11));
+    }
+
+    Some(match op {
+        SyntaxTreeNode::AddOp | SyntaxTreeNode::SubOp if l_value == r_value => {
+            Ok(l_value.to_string())
+        }
+        SyntaxTreeNode::AddOp | SyntaxTreeNode::SubOp => Err(37),
+        SyntaxTreeNode::MulOp => Ok(units::with_unit("float", &l_unit.mul(&r_unit))),
+        SyntaxTreeNode::DivOp => Ok(units::with_unit("float", &l_unit.div(&r_unit))),
+        _ => Err(11),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(source: &str) -> Result<Source, usize> {
+        Source::new(crate::parse(source).unwrap().1).map_err(|error| error.code)
+    }
+
+    /// The bytecode of the body of `main` in the node `Test`, without the declarations.
+    fn main_bytecode(body: &str) -> Vec<u8> {
+        node_bytecode("", body)
+    }
+
+    /// Like `main_bytecode`, with the other functions `functions` in the node.
+    fn node_bytecode(functions: &str, body: &str) -> Vec<u8> {
+        let program = format!("node Test {{ {functions} fn main() -> () {{ {body} }} }}");
+        let source = check(&program).expect("the program does not check");
+        let TLElement::Function(_, _, var_set, tree) = source.symbol_table["Test"]["main"].clone()
+        else {
+            panic!("main is not a function");
+        };
+        let signatures = source.symbol_table["Test"]
+            .iter()
+            .filter_map(|(id, elem)| match elem {
+                TLElement::Function(ret, params, _, _) => {
+                    Some((id.clone(), ret.clone(), params.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let variable_addresses = var_set
+            .iter()
+            .enumerate()
+            .map(|(i, (id, t))| (id.clone(), (t.clone(), 4 * i as u32)))
+            .collect();
+        let mut bytes = vec![];
+        Source::generate_function_bytecode(
+            &mut bytes,
+            &signatures,
+            &var_set,
+            &variable_addresses,
+            &mut vec![],
+            tree,
+        );
+
+        bytes
+    }
+
+    fn contains(bytes: &[u8], sequence: &[u8]) -> bool {
+        bytes
+            .windows(sequence.len())
+            .any(|window| window == sequence)
+    }
+
+    #[test]
+    fn casts_convert_with_one_instruction() {
+        let bytes = main_bytecode("var i: int = 3.7 as int;");
+        assert!(contains(
+            &bytes,
+            &[&[0x11][..], &3.7_f32.to_be_bytes(), &[0x41]].concat()
+        ));
+
+        let bytes = main_bytecode("var c: char = 'a' as int as char;");
+        assert!(contains(&bytes, &[0x15, b'a', 0x42, 0x43]));
+
+        let bytes = main_bytecode("var i: int = 1 as int;");
+        assert!(contains(&bytes, &[0x10, 0, 0, 0, 1, 0x24]));
+    }
+
+    #[test]
+    fn casts_between_other_types_are_rejected() {
+        let program = "node Test { fn main() -> () { var b: bool = 1 as bool; } }";
+        assert_eq!(check(program).err(), Some(24));
+
+        let program = "node Test { fn main() -> () { var f: float = true as float; } }";
+        assert_eq!(check(program).err(), Some(24));
+    }
+
+    #[test]
+    fn int_operands_widen_to_float() {
+        let bytes = main_bytecode("var f: float = 1 + 2.5;");
+        assert!(contains(&bytes, &[0x10, 0, 0, 0, 1, 0x40, 0x11]));
+
+        let bytes = main_bytecode("var b: bool = 2.5 < 3;");
+        assert!(contains(&bytes, &[0x10, 0, 0, 0, 3, 0x40, 0x5E]));
+    }
+
+    #[test]
+    fn int_values_widen_when_stored_in_floats() {
+        let bytes = main_bytecode("var f: float = 1; f = 2; const g: float = 3;");
+        for value in 1..=3 {
+            assert!(contains(&bytes, &[push(value), vec![0x40, 0x25]].concat()));
+        }
+
+        let bytes = main_bytecode("var a: [float; 2] = [0.5, 1.5]; a[1] = 4;");
+        assert!(contains(&bytes, &[push(4), vec![0x40]].concat()));
+
+        let program = "node Test { fn main() -> () { var i: int = 1.5; } }";
+        assert_eq!(check(program).err(), Some(8));
+    }
+
+    #[test]
+    fn unary_operators_have_one_instruction_each() {
+        let bytes = main_bytecode("var i: int = 2; var b: bool = !true; i = ~-i;");
+        assert!(contains(&bytes, &[0x14, 0x1, 0x65]));
+        assert!(contains(&bytes, &[0x3A, 0x70]));
+
+        let bytes = main_bytecode("var f: float = 1.5; f = -f;");
+        assert!(contains(&bytes, &[0x3B]));
+    }
+
+    #[test]
+    fn minus_is_folded_into_literals() {
+        let bytes = main_bytecode("var i: int = -5;");
+        assert!(contains(
+            &bytes,
+            &[&[0x10][..], &(-5_i32).to_be_bytes(), &[0x24]].concat()
+        ));
+        assert!(!bytes.contains(&0x3A));
+    }
+
+    #[test]
+    fn unary_operators_need_their_operand_type() {
+        for value in ["-true", "!1", "~1.5"] {
+            let program = format!("node Test {{ fn main() -> () {{ var x: int = {value}; }} }}");
+            assert_eq!(check(&program).err(), Some(11), "{value}");
+        }
+    }
+
+    /// The bytecode of `var x: int = value;`, starting with the instructions of `value`.
+    fn value_bytecode(value: &str) -> Vec<u8> {
+        main_bytecode(&format!("var x: int = {value};"))
+    }
+
+    fn push(value: i32) -> Vec<u8> {
+        [&[0x10][..], &value.to_be_bytes()].concat()
+    }
+
+    #[test]
+    fn bitwise_operators_bind_looser_than_shifts_and_arithmetic() {
+        let bytes = value_bytecode("1 << 2 + 3");
+        assert!(bytes.starts_with(&[push(1), push(2), push(3), vec![0x30, 0x74]].concat()));
+
+        let bytes = value_bytecode("1 | 2 ^ 3 & 4 << 5");
+        let pushes: Vec<u8> = (1..=5).flat_map(push).collect();
+        assert!(bytes.starts_with(&[pushes, vec![0x74, 0x71, 0x73, 0x72]].concat()));
+    }
+
+    #[test]
+    fn shifts_associate_to_the_left() {
+        let bytes = value_bytecode("64 >> 2 >> 1 % 3");
+        assert!(bytes.starts_with(&[push(64), push(2), vec![0x75], push(1), push(3)].concat()));
+        assert_eq!(&bytes[bytes.len() - 7..bytes.len() - 5], &[0x3D, 0x75]);
+    }
+
+    #[test]
+    fn integer_operators_reject_floats() {
+        for value in ["5.0 % 2", "1 << 2.0", "1.5 & 1"] {
+            let program = format!("node Test {{ fn main() -> () {{ var x: int = {value}; }} }}");
+            assert_eq!(check(&program).err(), Some(11), "{value}");
+        }
+    }
+
+    #[test]
+    fn compound_assignment_computes_the_index_once() {
+        let bytes = node_bytecode(
+            "fn i(n: int) -> int { return n; }",
+            "var a: [int; 2] = [1, 2]; a[i(1)] += 3;",
+        );
+
+        assert_eq!(bytes.iter().filter(|byte| **byte == 0x5A).count(), 1);
+        let dup = bytes.iter().position(|byte| *byte == 0x16).unwrap();
+        assert_eq!(bytes[dup + 1], 0x82);
+        assert!(contains(
+            &bytes[dup..],
+            &[&push(3)[..], &[0x30, 0x17, 0x87]].concat()
+        ));
+    }
+
+    #[test]
+    fn compound_assignment_is_typed_like_its_operator() {
+        let bytes = main_bytecode("var f: float = 1.5; f *= 2;");
+        assert!(contains(
+            &bytes,
+            &[&push(2)[..], &[0x40, 0x35, 0x25]].concat()
+        ));
+
+        let program = "node Test { fn main() -> () { var i: int = 1; i += 1.5; } }";
+        assert_eq!(check(program).err(), Some(9));
+
+        let program = "node Test { fn main() -> () { var b: bool = true; b %= 2; } }";
+        assert_eq!(check(program).err(), Some(11));
+    }
+
+    #[test]
+    fn conditions_and_printed_values_have_the_types_the_vm_expects() {
+        for (body, code) in [
+            ("if 1 { }", 40),
+            ("while 1.5 { }", 40),
+            ("print_float(1);", 13),
+            ("print_int(true);", 13),
+            ("print_char(1.5);", 13),
+        ] {
+            let program = format!("node Test {{ fn main() -> () {{ {body} }} }}");
+            assert_eq!(check(&program).err(), Some(code), "{body}");
+        }
+    }
+
+    #[test]
+    fn ir_lists_redeclared_locals_by_their_source_name() {
+        let source = check(
+            "node Test { fn main() -> () {
+                if true { var x: int = 1; }
+                if true { var x: float = 1.5; }
+            } }",
+        )
+        .unwrap();
+
+        assert_eq!(
+            source.ir()["Test"],
+            "fn main() -> ()\n    var x: int\n    var x: float\n"
+        );
+    }
+
+    #[test]
+    fn struct_fields_are_locals_of_their_own() {
+        let point = "struct P { x: int, y: float, }";
+        let bytes = node_bytecode(point, "var p: P = { y: 2.0, x: 1, }; p.x += 3;");
+        assert!(contains(&bytes, &[&push(3)[..], &[0x30, 0x24]].concat()));
+
+        for (body, code) in [
+            ("var p: P = { x: 1, y: 2.0, z: 3, };", 36),
+            ("var p: P = { x: 1, };", 8),
+            ("var p: P = { x: 1, x: 2, y: 2.0, };", 8),
+            ("var p: P = { x: 1, y: 2.0, }; var q: P = p;", 8),
+            ("var p: P = { x: 1, y: 2.0, }; p.z += 1;", 16),
+            ("var p: P = { x: 1, y: 2.0, }; print_int(p.z);", 36),
+            ("var p: P = { x: 1, y: 2.0, }; p.x += 1.5;", 9),
+        ] {
+            let program = format!("node Test {{ {point} fn main() -> () {{ {body} }} }}");
+            assert_eq!(check(&program).err(), Some(code), "{body}");
+        }
+    }
+
+    #[test]
+    fn node_graph_errors_are_placed_at_the_dependency() {
+        for (program, code, column) in [
+            ("node A : B { fn main() -> () {} }", 25, 10),
+            ("node A : A { fn main() -> () {} }", 30, 10),
+            (
+                "node A : B { fn main() -> () {} } node B : A { fn main() -> () {} }",
+                31,
+                44,
+            ),
+        ] {
+            let error = Source::new(crate::parse(program).unwrap().1)
+                .err()
+                .expect("the program does not check");
+            assert_eq!(error.code, code, "{program}");
+            assert_eq!(
+                error.span.map(|span| span.column),
+                Some(column),
+                "{program}"
+            );
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Running the `test fn`s of a program, which is what `karma test` does.
+//!
+//! Each test is compiled as the entry point of its node in place of `main` and run in the VM
+//! on its own. A test passes when it returns, and fails when an `assert` or `assert_eq` in it
+//! does not hold or the node fails in any other way.
+
+use std::{fmt, path::PathBuf};
+
+use crate::{diagnostic, lexer::Span, vm, Compilation};
+
+/// The outcome of one test.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestResult {
+    pub node: String,
+    pub name: String,
+    /// Everything the test printed.
+    pub output: String,
+    /// Why the test failed, or `None` when it passed.
+    pub failure: Option<TestFailure>,
+}
+
+impl TestResult {
+    /// The name of the test qualified by its node, as `Node::test`.
+    pub fn qualified_name(&self) -> String {
+        format!("{}::{}", self.node, self.name)
+    }
+}
+
+/// A failed test, placed at the assertion that failed when it was one and at the test function
+/// otherwise.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestFailure {
+    pub message: String,
+    pub file: Option<PathBuf>,
+    pub span: Option<Span>,
+}
+
+impl fmt::Display for TestFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        diagnostic::write_location(f, self.file.as_deref(), self.span)?;
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Runs every test of `compilation` whose qualified name contains `filter`, or every test when
+/// there is no filter. Tests are run node by node and by name.
+pub fn run_tests(compilation: &Compilation, filter: Option<&str>) -> Vec<TestResult> {
+    let mut results = vec![];
+
+    for (node, tests) in &compilation.tests {
+        for (name, bytes) in tests {
+            let mut result = TestResult {
+                node: node.clone(),
+                name: name.clone(),
+                output: String::new(),
+                failure: None,
+            };
+            if filter.is_some_and(|filter| !result.qualified_name().contains(filter)) {
+                continue;
+            }
+
+            let mut out = vec![];
+            if let Err(e) = vm::run(bytes, &mut out) {
+                result.failure = Some(failure(compilation, &result, bytes, e));
+            }
+            result.output = String::from_utf8_lossy(&out).into_owned();
+
+            results.push(result);
+        }
+    }
+
+    results
+}
+
+/// Traces a runtime error back to the assertion that raised it, when an assertion did, and to
+/// the function of the test `result` otherwise.
+fn failure(
+    compilation: &Compilation,
+    result: &TestResult,
+    bytes: &[u8],
+    e: vm::RuntimeError,
+) -> TestFailure {
+    let assertion = match bytes.get(e.pc..e.pc + 5) {
+        Some([0x95 | 0x96, number @ ..]) => {
+            let number = u32::from_be_bytes([number[0], number[1], number[2], number[3]]);
+            compilation.assertions.get(number as usize)
+        }
+        _ => None,
+    };
+
+    match assertion {
+        Some((file, span)) => TestFailure {
+            message: e.message,
+            file: file.clone(),
+            span: *span,
+        },
+        None => {
+            let (file, span) = compilation
+                .test_locations
+                .get(&result.node)
+                .and_then(|tests| tests.get(&result.name))
+                .cloned()
+                .unwrap_or_default();
+
+            TestFailure {
+                message: e.to_string(),
+                file,
+                span,
+            }
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! Units of measure, which the checker tracks on `float` types and erases before code generation.
+//!
+//! A unit is a product of symbols raised to integer powers, written like `m`, `rad/s` or
+//! `N*m/s^2`. Any identifier is a unit symbol; the ones in [`KNOWN`] can also be converted into
+//! each other with `as` when they measure the same quantity.
+
+use std::{collections::BTreeMap, f64::consts::PI, fmt};
+
+/// The symbols that measure the same quantity as another one, with the symbol they are
+/// measured against and how many of it one of them is.
+const KNOWN: [(&str, &str, f64); 16] = [
+    ("mm", "m", 1e-3),
+    ("cm", "m", 1e-2),
+    ("km", "m", 1e3),
+    ("in", "m", 0.0254),
+    ("ft", "m", 0.3048),
+    ("deg", "rad", PI / 180.0),
+    ("rev", "rad", 2.0 * PI),
+    ("ms", "s", 1e-3),
+    ("us", "s", 1e-6),
+    ("min", "s", 60.0),
+    ("h", "s", 3600.0),
+    ("g", "kg", 1e-3),
+    ("mN", "N", 1e-3),
+    ("kN", "N", 1e3),
+    ("mA", "A", 1e-3),
+    ("mV", "V", 1e-3),
+];
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Unit(BTreeMap<String, i32>);
+
+impl Unit {
+    /// The unit made of the single symbol `symbol`.
+    pub fn symbol(symbol: &str) -> Self {
+        Unit(BTreeMap::from([(symbol.to_string(), 1)]))
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn pow(&self, exponent: i32) -> Self {
+        let mut powers = BTreeMap::new();
+        if exponent != 0 {
+            for (symbol, power) in &self.0 {
+                powers.insert(symbol.clone(), power * exponent);
+            }
+        }
+
+        Unit(powers)
+    }
+
+    pub fn mul(&self, other: &Unit) -> Self {
+        let mut powers = self.0.clone();
+        for (symbol, power) in &other.0 {
+            let sum = powers.get(symbol).copied().unwrap_or(0) + power;
+            if sum == 0 {
+                powers.remove(symbol);
+            } else {
+                powers.insert(symbol.clone(), sum);
+            }
+        }
+
+        Unit(powers)
+    }
+
+    pub fn div(&self, other: &Unit) -> Self {
+        self.mul(&other.pow(-1))
+    }
+
+    /// The number that converts a quantity in this unit into `other`, or `None` when they do
+    /// not measure the same quantity.
+    pub fn factor(&self, other: &Unit) -> Option<f64> {
+        let (from, from_factor) = self.base();
+        let (to, to_factor) = other.base();
+
+        (from == to).then_some(from_factor / to_factor)
+    }
+
+    /// The unit with every known symbol replaced by the one it is measured against, and the
+    /// number that converts into it.
+    fn base(&self) -> (Unit, f64) {
+        let mut unit = Unit::default();
+        let mut factor = 1.0;
+
+        for (symbol, power) in &self.0 {
+            let (base, scale) = match KNOWN.iter().find(|(known, _, _)| known == symbol) {
+                Some((_, base, scale)) => (*base, *scale),
+                None => (symbol.as_str(), 1.0),
+            };
+
+            unit = unit.mul(&Unit::symbol(base).pow(*power));
+            factor *= scale.powi(*power);
+        }
+
+        (unit, factor)
+    }
+}
+
+impl fmt::Display for Unit {
+    /// Writes the symbols with positive powers joined by `*`, then each of the rest after a `/`,
+    /// in name order, so equal units are written the same way.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = |positive: bool| {
+            self.0
+                .iter()
+                .filter(move |(_, power)| (**power > 0) == positive)
+                .map(|(symbol, power)| match power.abs() {
+                    1 => symbol.clone(),
+                    power => format!("{symbol}^{power}"),
+                })
+        };
+
+        let numerator: Vec<String> = side(true).collect();
+        if numerator.is_empty() {
+            write!(f, "1")?;
+        } else {
+            write!(f, "{}", numerator.join("*"))?;
+        }
+
+        for symbol in side(false) {
+            write!(f, "/{symbol}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits a type into its name and unit, so `float<m/s>` is `float` in metres per second and
+/// every other type has no unit.
+pub fn split(t: &str) -> (&str, Unit) {
+    match t.strip_prefix("float<").and_then(|t| t.strip_suffix('>')) {
+        Some(unit) => ("float", parse(unit).unwrap_or_default()),
+        None => (t, Unit::default()),
+    }
+}
+
+/// The type `name` in `unit`, which is `name` itself when the unit is dimensionless.
+pub fn with_unit(name: &str, unit: &Unit) -> String {
+    if unit.is_dimensionless() {
+        name.to_string()
+    } else {
+        format!("{name}<{unit}>")
+    }
+}
+
+/// Whether values of type `t` carry a unit.
+pub fn has_unit(t: &str) -> bool {
+    t.contains("float<")
+}
+
+/// The type `t` with its units, and those of the elements of arrays, removed.
+pub fn erase(t: &str) -> String {
+    let mut erased = String::new();
+    let mut in_unit = false;
+
+    for c in t.chars() {
+        match c {
+            '<' if erased.ends_with("float") => in_unit = true,
+            '>' if in_unit => in_unit = false,
+            _ if !in_unit => erased.push(c),
+            _ => {}
+        }
+    }
+
+    erased
+}
+
+/// Parses a unit as it is written in a type, where `/` divides by the symbol after it only.
+fn parse(text: &str) -> Option<Unit> {
+    let mut unit = Unit::default();
+    let mut divide = false;
+
+    for (i, part) in text.split_inclusive(['*', '/']).enumerate() {
+        let (factor, next_divides) = match part.strip_suffix('/') {
+            Some(factor) => (factor, true),
+            None => (part.strip_suffix('*').unwrap_or(part), false),
+        };
+
+        let factor = match factor.split_once('^') {
+            Some((symbol, power)) => Unit::symbol(symbol).pow(power.parse().ok()?),
+            None if factor == "1" && i == 0 => Unit::default(),
+            None => Unit::symbol(factor),
+        };
+
+        unit = if divide {
+            unit.div(&factor)
+        } else {
+            unit.mul(&factor)
+        };
+        divide = next_divides;
+    }
+
+    Some(unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_are_written_in_one_canonical_form() {
+        let newton_metre = Unit::symbol("N").mul(&Unit::symbol("m"));
+        assert_eq!(newton_metre.to_string(), "N*m");
+        assert_eq!(Unit::symbol("m").mul(&Unit::symbol("N")), newton_metre);
+
+        let acceleration = Unit::symbol("m").div(&Unit::symbol("s").pow(2));
+        assert_eq!(acceleration.to_string(), "m/s^2");
+        assert_eq!(Unit::default().div(&Unit::symbol("s")).to_string(), "1/s");
+        assert!(Unit::symbol("m").div(&Unit::symbol("m")).is_dimensionless());
+    }
+
+    #[test]
+    fn types_split_into_name_and_unit() {
+        let (name, unit) = split("float<m/s^2>");
+        assert_eq!(name, "float");
+        assert_eq!(unit.to_string(), "m/s^2");
+
+        assert_eq!(split("float<N*m>").1.to_string(), "N*m");
+        assert_eq!(split("float<1/s>").1.to_string(), "1/s");
+        assert_eq!(split("float<kg/m/s^2>").1.to_string(), "kg/m/s^2");
+        assert_eq!(split("float<W/m*K>").1.to_string(), "K*W/m");
+        assert!(split("float").1.is_dimensionless());
+
+        assert_eq!(with_unit("float", &Unit::default()), "float");
+        assert_eq!(erase("[float<m/s>; 3]"), "[float; 3]");
+
+        // the parameters of other types are not units
+        assert!(!has_unit("fixed<16, 16>"));
+        assert_eq!(split("fixed<16, 16>").0, "fixed<16, 16>");
+        assert_eq!(erase("[fixed<8, 8, wrap>; 2]"), "[fixed<8, 8, wrap>; 2]");
+    }
+
+    #[test]
+    fn converts_between_units_of_the_same_quantity() {
+        let factor = |from: &str, to: &str| split(from).1.factor(&split(to).1);
+
+        assert_eq!(factor("float<m>", "float<mm>"), Some(1000.0));
+        assert_eq!(factor("float<deg>", "float<rad>"), Some(PI / 180.0));
+
+        let speed = factor("float<km/h>", "float<m/s>").unwrap();
+        assert!((speed - 1.0 / 3.6).abs() < 1e-12);
+
+        assert_eq!(factor("float<m>", "float<s>"), None);
+        assert_eq!(factor("float<N*m>", "float<N*mm>"), Some(1000.0));
+    }
+}
@@ -0,0 +1,1271 @@
+//! An interpreter and disassembler for the bytecode described in `specs/vm_specification.toml`.
+
+use std::{cmp::Ordering, collections::HashMap, fmt, io::Write};
+
+use crate::{
+    fixed::{self, Format},
+    geometry::{self, Transform},
+    sized::{Kind, Number},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Float(f32),
+    Bool(bool),
+    Char(u8),
+    /// An integer of any other width than `int`, which is in the range of its type.
+    Sized(i128),
+    /// An `f64`.
+    Double(f64),
+    /// A `vec2`, `vec3` or `vec4`: the number of components and the components.
+    Vector(u8, [f32; 4]),
+    /// A `mat2`, `mat3` or `mat4`: the number of rows and the elements, row by row.
+    Matrix(u8, [f32; 16]),
+    /// A `quat`, as `[w, x, y, z]`.
+    Quat([f32; 4]),
+    /// A `transform3`: a rotation followed by a translation.
+    Transform(Transform),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{value}"),
+            Value::Float(value) => write!(f, "{value}"),
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::Char(value) => write!(f, "{:?}", *value as char),
+            Value::Sized(value) => write!(f, "{value}"),
+            Value::Double(value) => write!(f, "{value}"),
+            Value::Vector(n, elems) => write_elems(f, &format!("vec{n}"), &elems[..*n as usize]),
+            Value::Matrix(n, elems) => {
+                write_elems(f, &format!("mat{n}"), &elems[..(n * n) as usize])
+            }
+            Value::Quat(q) => write_elems(f, "quat", q),
+            Value::Transform(t) => write!(
+                f,
+                "transform3({}, {})",
+                Value::Quat(t.rotation),
+                Value::Vector(3, vector(t.translation))
+            ),
+        }
+    }
+}
+
+fn write_elems(f: &mut fmt::Formatter<'_>, name: &str, elems: &[f32]) -> fmt::Result {
+    let elems: Vec<String> = elems.iter().map(f32::to_string).collect();
+    write!(f, "{name}({})", elems.join(", "))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuntimeError {
+    /// The offset of the instruction that failed.
+    pub pc: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "runtime error at {:#06x}: {}", self.pc, self.message)
+    }
+}
+
+/// Returns the mnemonic of an opcode and the number of operand bytes that follow it.
+fn instruction(opcode: u8) -> Option<(&'static str, usize)> {
+    Some(match opcode {
+        0x10 => ("pushi", 4),
+        0x11 => ("pushf", 4),
+        0x12 => ("pop", 0),
+        0x13 => ("pushsp", 4),
+        0x14 => ("pushb", 1),
+        0x15 => ("pushc", 1),
+        0x16 => ("dup", 0),
+        0x17 => ("swap", 0),
+        0x20 => ("decli", 4),
+        0x21 => ("declf", 4),
+        0x22 => ("loadi", 4),
+        0x23 => ("loadf", 4),
+        0x24 => ("stori", 4),
+        0x25 => ("storf", 4),
+        0x26 => ("dstri", 4),
+        0x27 => ("dstrf", 4),
+        0x28 => ("declb", 4),
+        0x29 => ("loadb", 4),
+        0x2A => ("storb", 4),
+        0x2B => ("dstrb", 4),
+        0x2C => ("declc", 4),
+        0x2D => ("loadc", 4),
+        0x2E => ("storc", 4),
+        0x2F => ("dstrc", 4),
+        0x30 => ("addi", 0),
+        0x31 => ("addf", 0),
+        0x32 => ("subi", 0),
+        0x33 => ("subf", 0),
+        0x34 => ("muli", 0),
+        0x35 => ("mulf", 0),
+        0x36 => ("divi", 0),
+        0x37 => ("divf", 0),
+        0x38 => ("addc", 0),
+        0x39 => ("subc", 0),
+        0x3A => ("negi", 0),
+        0x3B => ("negf", 0),
+        0x3D => ("modi", 0),
+        0x40 => ("itof", 0),
+        0x41 => ("ftoi", 0),
+        0x42 => ("ctoi", 0),
+        0x43 => ("itoc", 0),
+        0x44 => ("btoi", 0),
+        0x50 => ("ifTrue", 4),
+        0x51 => ("ifFalse", 4),
+        0x52 => ("eqi", 0),
+        0x53 => ("neqi", 0),
+        0x54 => ("lessi", 0),
+        0x55 => ("leqi", 0),
+        0x56 => ("grti", 0),
+        0x57 => ("geqi", 0),
+        0x58 => ("and", 0),
+        0x59 => ("or", 0),
+        0x5A => ("jump", 4),
+        0x5B => ("retval", 0),
+        0x5C => ("eqf", 0),
+        0x5D => ("neqf", 0),
+        0x5E => ("lessf", 0),
+        0x5F => ("leqf", 0),
+        0x60 => ("grtf", 0),
+        0x61 => ("geqf", 0),
+        0x62 => ("eqb", 0),
+        0x63 => ("neqb", 0),
+        0x64 => ("ret", 0),
+        0x65 => ("not", 0),
+        0x70 => ("noti", 0),
+        0x71 => ("andi", 0),
+        0x72 => ("ori", 0),
+        0x73 => ("xori", 0),
+        0x74 => ("shli", 0),
+        0x75 => ("shri", 0),
+        0x80 => ("decla", 9),
+        0x81 => ("loada", 4),
+        0x82 => ("loadai", 4),
+        0x83 => ("loadaf", 4),
+        0x84 => ("loadab", 4),
+        0x85 => ("loadac", 4),
+        0x86 => ("stora", 4),
+        0x87 => ("storai", 4),
+        0x88 => ("storaf", 4),
+        0x89 => ("storab", 4),
+        0x8A => ("storac", 4),
+        0x8B => ("dstra", 4),
+        0x90 => ("prnti", 0),
+        0x91 => ("prntf", 0),
+        0x92 => ("prntb", 0),
+        0x93 => ("prntc", 0),
+        0x94 => ("input", 0),
+        0x95 => ("asrt", 4),
+        0x96 => ("asrteq", 4),
+        0xA0 => ("declv", 5),
+        0xA1 => ("declm", 5),
+        0xA2 => ("loadv", 4),
+        0xA3 => ("storv", 4),
+        0xA4 => ("mkvec", 1),
+        0xA5 => ("mkmat", 1),
+        0xA6 => ("getc", 1),
+        0xA7 => ("setc", 1),
+        0xA8 => ("addv", 0),
+        0xA9 => ("subv", 0),
+        0xAA => ("negv", 0),
+        0xAB => ("scalev", 0),
+        0xAC => ("mulmv", 0),
+        0xAD => ("mulmm", 0),
+        0xAE => ("dot", 0),
+        0xAF => ("cross", 0),
+        0xB0 => ("norm", 0),
+        0xB1 => ("transp", 0),
+        0xB2 => ("inv", 0),
+        0xB3 => ("eqv", 0),
+        0xB4 => ("neqv", 0),
+        0xB5 => ("declq", 4),
+        0xB6 => ("declt", 4),
+        0xB7 => ("mkquat", 0),
+        0xB8 => ("axang", 0),
+        0xB9 => ("euler", 0),
+        0xBA => ("mktf", 0),
+        0xBB => ("mulq", 0),
+        0xBC => ("rotq", 0),
+        0xBD => ("multf", 0),
+        0xBE => ("applytf", 0),
+        0xBF => ("slerp", 0),
+        0xC0 => ("normal", 0),
+        0xC1 => ("tfrot", 0),
+        0xC2 => ("tfpos", 0),
+        0xC8 => ("addx", 2),
+        0xC9 => ("subx", 2),
+        0xCA => ("mulx", 2),
+        0xCB => ("divx", 2),
+        0xCC => ("negx", 2),
+        0xCD => ("itox", 2),
+        0xCE => ("xtoi", 1),
+        0xCF => ("ftox", 2),
+        0xD0 => ("xtof", 1),
+        0xD1 => ("xtox", 3),
+        0xD2 => ("prntx", 1),
+        0xD8 => ("pushn", 9),
+        0xD9 => ("decln", 5),
+        0xDA => ("loadn", 5),
+        0xDB => ("storn", 5),
+        0xDC => ("loadan", 5),
+        0xDD => ("storan", 5),
+        0xDE => ("addn", 1),
+        0xDF => ("subn", 1),
+        0xE0 => ("muln", 1),
+        0xE1 => ("divn", 1),
+        0xE2 => ("modn", 1),
+        0xE3 => ("negn", 1),
+        0xE4 => ("andn", 1),
+        0xE5 => ("orn", 1),
+        0xE6 => ("xorn", 1),
+        0xE7 => ("shln", 1),
+        0xE8 => ("shrn", 1),
+        0xE9 => ("notn", 1),
+        0xEA => ("eqn", 1),
+        0xEB => ("neqn", 1),
+        0xEC => ("lessn", 1),
+        0xED => ("leqn", 1),
+        0xEE => ("grtn", 1),
+        0xEF => ("geqn", 1),
+        0xF0 => ("cvt", 2),
+        0xF1 => ("prntn", 1),
+        _ => return None,
+    })
+}
+
+fn read_u32(code: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([code[at], code[at + 1], code[at + 2], code[at + 3]])
+}
+
+fn read_u64(code: &[u8], at: usize) -> u64 {
+    u64::from_be_bytes(code[at..at + 8].try_into().unwrap())
+}
+
+/// The number `pushn` pushes for the 8 operand bytes `bits`: the low bits of an integer in
+/// two's complement, or the bits of an `f64`.
+fn immediate(kind: Kind, bits: u64) -> Number {
+    match kind {
+        Kind::Float(_) => kind.convert(Number::Float(f64::from_bits(bits))),
+        kind => Number::Int(kind.wrap(bits as i128)),
+    }
+}
+
+/// Lists the instructions of `code`, one per line, prefixed by their offset.
+pub fn disassemble(code: &[u8]) -> Result<String, RuntimeError> {
+    let mut text = String::new();
+    let mut pc = 0;
+
+    while pc < code.len() {
+        let opcode = code[pc];
+        let (mnemonic, operand_len) = instruction(opcode).ok_or_else(|| RuntimeError {
+            pc,
+            message: format!("unknown opcode {opcode:#04x}"),
+        })?;
+
+        if pc + 1 + operand_len > code.len() {
+            return Err(RuntimeError {
+                pc,
+                message: format!("`{mnemonic}` is missing its operand"),
+            });
+        }
+
+        let at = pc + 1;
+        let operand = match opcode {
+            0x10 => format!(" {}", read_u32(code, at) as i32),
+            0x11 => format!(" {:?}", f32::from_bits(read_u32(code, at))),
+            0x14 => format!(" {}", code[at] != 0),
+            0x15 => format!(" {:?}", code[at] as char),
+            0x80 => format!(
+                " {:#06x} {} {}",
+                read_u32(code, at),
+                code[at + 4],
+                read_u32(code, at + 5)
+            ),
+            0xA0 | 0xA1 => format!(" {:#06x} {}", read_u32(code, at), code[at + 4]),
+            0xC8..=0xCD | 0xCF => format!(" {}", format_operand([code[at], code[at + 1]])),
+            0xD1 => format!(
+                " {} {}",
+                code[at],
+                format_operand([code[at + 1], code[at + 2]])
+            ),
+            0xD8 => match Kind::decode(code[at]) {
+                Some(kind) => match immediate(kind, read_u64(code, at + 1)) {
+                    Number::Int(value) => format!(" {kind} {value}"),
+                    Number::Float(value) => format!(" {kind} {value:?}"),
+                },
+                None => format!(" {:#04x} {:#018x}", code[at], read_u64(code, at + 1)),
+            },
+            0xD9..=0xDD => format!(
+                " {:#06x} {}",
+                read_u32(code, at),
+                kind_operand(code[at + 4])
+            ),
+            0xDE..=0xEF | 0xF1 => format!(" {}", kind_operand(code[at])),
+            0xF0 => format!(" {} {}", kind_operand(code[at]), kind_operand(code[at + 1])),
+            _ if operand_len == 4 => format!(" {:#06x}", read_u32(code, at)),
+            _ if operand_len == 1 => format!(" {}", code[at]),
+            _ => String::new(),
+        };
+
+        text.push_str(&format!("{pc:#06x}  {mnemonic}{operand}\n"));
+        pc += 1 + operand_len;
+    }
+
+    Ok(text)
+}
+
+/// A fixed-point format as the type it belongs to, like `<16, 16, wrap>`.
+fn format_operand(bytes: [u8; 2]) -> String {
+    match Format::decode(bytes) {
+        Some(format) => {
+            let wrap = if format.wrap { ", wrap" } else { "" };
+            format!("<{}, {}{wrap}>", format.bits - format.frac, format.frac)
+        }
+        None => format!("{:#06x}", u16::from_be_bytes(bytes)),
+    }
+}
+
+/// A numeric type operand as the type, like `u8`.
+fn kind_operand(byte: u8) -> String {
+    match Kind::decode(byte) {
+        Some(kind) => kind.to_string(),
+        None => format!("{byte:#04x}"),
+    }
+}
+
+/// Runs a node's bytecode from its first instruction until `main` returns, writing anything
+/// it prints to `out`.
+pub fn run(code: &[u8], out: &mut impl Write) -> Result<(), RuntimeError> {
+    run_for(code, out, usize::MAX).map(|_| ())
+}
+
+/// Runs a node's bytecode like [`run`], but stops after `steps` instructions, returning
+/// whether `main` returned by then.
+pub fn run_for(code: &[u8], out: &mut impl Write, steps: usize) -> Result<bool, RuntimeError> {
+    let mut vm = Vm {
+        code,
+        pc: 0,
+        // returning from `main` jumps past the last instruction, which halts
+        stack: vec![Value::Int(code.len() as i32)],
+        memory: HashMap::new(),
+        arrays: HashMap::new(),
+    };
+
+    for _ in 0..steps {
+        if vm.pc >= code.len() {
+            break;
+        }
+
+        let pc = vm.pc;
+        vm.step(out)
+            .map_err(|message| RuntimeError { pc, message })?;
+    }
+
+    out.flush().map_err(|e| RuntimeError {
+        pc: vm.pc,
+        message: e.to_string(),
+    })?;
+
+    Ok(vm.pc >= code.len())
+}
+
+struct Vm<'a> {
+    code: &'a [u8],
+    pc: usize,
+    stack: Vec<Value>,
+    memory: HashMap<u32, Value>,
+    /// The element size and length of every declared array, keyed by address.
+    arrays: HashMap<u32, (u32, u32)>,
+}
+
+impl Vm<'_> {
+    fn step(&mut self, out: &mut impl Write) -> Result<(), String> {
+        let opcode = self.code[self.pc];
+        let (mnemonic, operand_len) =
+            instruction(opcode).ok_or_else(|| format!("unknown opcode {opcode:#04x}"))?;
+
+        if self.pc + 1 + operand_len > self.code.len() {
+            return Err(format!("`{mnemonic}` is missing its operand"));
+        }
+
+        let at = self.pc + 1;
+        self.pc += 1 + operand_len;
+
+        match opcode {
+            0x10 => self.stack.push(Value::Int(read_u32(self.code, at) as i32)),
+            0x11 => self
+                .stack
+                .push(Value::Float(f32::from_bits(read_u32(self.code, at)))),
+            0x12 => {
+                self.pop()?;
+            }
+            0x14 => self.stack.push(Value::Bool(self.code[at] != 0)),
+            0x15 => self.stack.push(Value::Char(self.code[at])),
+            0x16 => {
+                let value = self.pop()?;
+                self.stack.extend([value, value]);
+            }
+            0x17 => {
+                let top = self.pop()?;
+                let below = self.pop()?;
+                self.stack.extend([top, below]);
+            }
+
+            0x20 | 0x21 | 0x28 | 0x2C => {
+                let zero = match opcode {
+                    0x20 => Value::Int(0),
+                    0x21 => Value::Float(0.0),
+                    0x28 => Value::Bool(false),
+                    _ => Value::Char(0),
+                };
+                self.memory.insert(read_u32(self.code, at), zero);
+            }
+            0x22 | 0x23 | 0x29 | 0x2D => {
+                let addr = read_u32(self.code, at);
+                let value = self.memory.get(&addr).copied();
+                self.stack.push(value.unwrap_or(Self::zero(opcode)));
+            }
+            0x24 | 0x25 | 0x2A | 0x2E => {
+                let value = self.pop()?;
+                self.memory.insert(read_u32(self.code, at), value);
+            }
+
+            0x30 => self.int_op(i32::wrapping_add)?,
+            0x32 => self.int_op(i32::wrapping_sub)?,
+            0x34 => self.int_op(i32::wrapping_mul)?,
+            0x36 | 0x3D => {
+                let rhs = self.pop_int()?;
+                let lhs = self.pop_int()?;
+                if rhs == 0 {
+                    return Err("division by zero".to_string());
+                }
+
+                self.stack.push(Value::Int(if opcode == 0x36 {
+                    lhs.wrapping_div(rhs)
+                } else {
+                    lhs.wrapping_rem(rhs)
+                }));
+            }
+            0x31 => self.float_op(|a, b| a + b)?,
+            0x33 => self.float_op(|a, b| a - b)?,
+            0x35 => self.float_op(|a, b| a * b)?,
+            0x37 => self.float_op(|a, b| a / b)?,
+            0x38 | 0x39 => {
+                let rhs = self.pop_char()?;
+                let lhs = self.pop_char()?;
+                self.stack.push(Value::Char(if opcode == 0x38 {
+                    lhs.wrapping_add(rhs)
+                } else {
+                    lhs.wrapping_sub(rhs)
+                }));
+            }
+            0x3A => {
+                let value = self.pop_int()?;
+                self.stack.push(Value::Int(value.wrapping_neg()));
+            }
+            0x3B => {
+                let value = self.pop_float()?;
+                self.stack.push(Value::Float(-value));
+            }
+
+            0x40 => {
+                let value = self.pop_int()?;
+                self.stack.push(Value::Float(value as f32));
+            }
+            0x41 => {
+                let value = self.pop_float()?;
+                self.stack.push(Value::Int(value as i32));
+            }
+            0x42 => {
+                let value = self.pop_char()?;
+                self.stack.push(Value::Int(value as i32));
+            }
+            0x43 => {
+                let value = self.pop_int()?;
+                self.stack.push(Value::Char(value as u8));
+            }
+            0x44 => {
+                let value = self.pop_bool()?;
+                self.stack.push(Value::Int(value as i32));
+            }
+
+            0x50 | 0x51 => {
+                let condition = self.pop_bool()?;
+                if condition == (opcode == 0x50) {
+                    self.pc = read_u32(self.code, at) as usize;
+                }
+            }
+            0x52 => self.int_cmp(|a, b| a == b)?,
+            0x53 => self.int_cmp(|a, b| a != b)?,
+            0x54 => self.int_cmp(|a, b| a < b)?,
+            0x55 => self.int_cmp(|a, b| a <= b)?,
+            0x56 => self.int_cmp(|a, b| a > b)?,
+            0x57 => self.int_cmp(|a, b| a >= b)?,
+            0x5C => self.float_cmp(|a, b| a == b)?,
+            0x5D => self.float_cmp(|a, b| a != b)?,
+            0x5E => self.float_cmp(|a, b| a < b)?,
+            0x5F => self.float_cmp(|a, b| a <= b)?,
+            0x60 => self.float_cmp(|a, b| a > b)?,
+            0x61 => self.float_cmp(|a, b| a >= b)?,
+            0x58 | 0x59 | 0x62 | 0x63 => {
+                let rhs = self.pop_bool()?;
+                let lhs = self.pop_bool()?;
+                self.stack.push(Value::Bool(match opcode {
+                    0x58 => lhs && rhs,
+                    0x59 => lhs || rhs,
+                    0x62 => lhs == rhs,
+                    _ => lhs != rhs,
+                }));
+            }
+            0x65 => {
+                let value = self.pop_bool()?;
+                self.stack.push(Value::Bool(!value));
+            }
+            0x5A => self.pc = read_u32(self.code, at) as usize,
+            0x5B => {
+                let value = self.pop()?;
+                self.pc = self.pop_int()? as usize;
+                self.stack.push(value);
+            }
+            0x64 => self.pc = self.pop_int()? as usize,
+
+            0x70 => {
+                let value = self.pop_int()?;
+                self.stack.push(Value::Int(!value));
+            }
+            0x71 => self.int_op(|a, b| a & b)?,
+            0x72 => self.int_op(|a, b| a | b)?,
+            0x73 => self.int_op(|a, b| a ^ b)?,
+            0x74 => self.int_op(|a, b| a.wrapping_shl(b as u32 & 31))?,
+            0x75 => self.int_op(|a, b| a.wrapping_shr(b as u32 & 31))?,
+
+            0x80 => {
+                let addr = read_u32(self.code, at);
+                let elem_size = self.code[at + 4] as u32;
+                let len = read_u32(self.code, at + 5);
+                self.arrays.insert(addr, (elem_size, len));
+            }
+            0x82..=0x85 => {
+                let slot = self.element(read_u32(self.code, at))?;
+                let value = self.memory.get(&slot).copied();
+                self.stack.push(value.unwrap_or(Self::zero(opcode)));
+            }
+            0x87..=0x8A => {
+                let slot = self.element(read_u32(self.code, at))?;
+                let value = self.pop()?;
+                self.memory.insert(slot, value);
+            }
+
+            0x90 => {
+                let value = self.pop_int()?;
+                write!(out, "{value}").map_err(|e| e.to_string())?;
+            }
+            0x91 => {
+                let value = self.pop_float()?;
+                write!(out, "{value}").map_err(|e| e.to_string())?;
+            }
+            0x92 => {
+                let value = self.pop_bool()?;
+                write!(out, "{value}").map_err(|e| e.to_string())?;
+            }
+            0x93 => {
+                let value = self.pop_char()?;
+                out.write_all(&[value]).map_err(|e| e.to_string())?;
+            }
+
+            0x95 => {
+                if !self.pop_bool()? {
+                    return Err("assertion failed".to_string());
+                }
+            }
+            0x96 => {
+                // arguments are pushed last to first
+                let lhs = self.pop()?;
+                let rhs = self.pop()?;
+                if lhs != rhs {
+                    return Err(format!("assertion failed: `{lhs}` != `{rhs}`"));
+                }
+            }
+
+            0xA0 | 0xA1 => {
+                let n = self.code[at + 4];
+                let zero = match opcode {
+                    0xA0 => Value::Vector(n, [0.0; 4]),
+                    _ => Value::Matrix(n, [0.0; 16]),
+                };
+                self.memory.insert(read_u32(self.code, at), zero);
+            }
+            0xA2 => {
+                let addr = read_u32(self.code, at);
+                let value = self
+                    .memory
+                    .get(&addr)
+                    .copied()
+                    .ok_or_else(|| format!("nothing is stored at {addr:#06x}"))?;
+                self.stack.push(value);
+            }
+            0xA3 => {
+                let value = self.pop()?;
+                self.memory.insert(read_u32(self.code, at), value);
+            }
+            0xA4 | 0xA5 => {
+                // the first element was pushed first
+                let n = self.code[at];
+                let count = match opcode {
+                    0xA4 => n,
+                    _ => n * n,
+                };
+                let mut elems = [0.0; 16];
+                for i in (0..count as usize).rev() {
+                    elems[i] = self.pop_float()?;
+                }
+
+                self.stack.push(match opcode {
+                    0xA4 => Value::Vector(n, elems[..4].try_into().unwrap()),
+                    _ => Value::Matrix(n, elems),
+                });
+            }
+            0xA6 => {
+                let (n, elems) = components(self.pop()?)?;
+                let i = self.component(n, at)?;
+                self.stack.push(Value::Float(elems[i]));
+            }
+            0xA7 => {
+                let component = self.pop_float()?;
+                let value = self.pop()?;
+                let (n, mut elems) = components(value)?;
+                elems[self.component(n, at)?] = component;
+
+                self.stack.push(match value {
+                    Value::Quat(_) => Value::Quat(elems),
+                    _ => Value::Vector(n, elems),
+                });
+            }
+            0xA8 | 0xA9 => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
+                let op = |a: f32, b: f32| if opcode == 0xA8 { a + b } else { a - b };
+
+                self.stack.push(match (lhs, rhs) {
+                    (Value::Vector(n, a), Value::Vector(m, b)) if n == m => {
+                        Value::Vector(n, std::array::from_fn(|i| op(a[i], b[i])))
+                    }
+                    (Value::Matrix(n, a), Value::Matrix(m, b)) if n == m => {
+                        Value::Matrix(n, std::array::from_fn(|i| op(a[i], b[i])))
+                    }
+                    (lhs, rhs) => return Err(format!("cannot combine {lhs:?} and {rhs:?}")),
+                });
+            }
+            0xAA => {
+                let value = self.pop()?;
+                self.stack.push(scale(value, -1.0)?);
+            }
+            0xAB => {
+                let factor = self.pop_float()?;
+                let value = self.pop()?;
+                self.stack.push(scale(value, factor)?);
+            }
+            0xAC => {
+                let (m, v) = self.pop_vector()?;
+                let (n, a) = self.pop_matrix()?;
+                if n != m {
+                    return Err(format!("cannot multiply a mat{n} by a vec{m}"));
+                }
+
+                let n = n as usize;
+                let mut elems = [0.0; 4];
+                for (i, elem) in elems.iter_mut().enumerate().take(n) {
+                    *elem = (0..n).map(|j| a[i * n + j] * v[j]).sum();
+                }
+                self.stack.push(Value::Vector(n as u8, elems));
+            }
+            0xAD => {
+                let (m, b) = self.pop_matrix()?;
+                let (n, a) = self.pop_matrix()?;
+                if n != m {
+                    return Err(format!("cannot multiply a mat{n} by a mat{m}"));
+                }
+
+                let n = n as usize;
+                let mut elems = [0.0; 16];
+                for i in 0..n {
+                    for j in 0..n {
+                        elems[i * n + j] = (0..n).map(|k| a[i * n + k] * b[k * n + j]).sum();
+                    }
+                }
+                self.stack.push(Value::Matrix(n as u8, elems));
+            }
+            0xAE => {
+                let (m, b) = self.pop_vector()?;
+                let (n, a) = self.pop_vector()?;
+                if n != m {
+                    return Err(format!(
+                        "cannot take the dot product of a vec{n} and a vec{m}"
+                    ));
+                }
+
+                let dot = (0..n as usize).map(|i| a[i] * b[i]).sum();
+                self.stack.push(Value::Float(dot));
+            }
+            0xAF => {
+                let (m, b) = self.pop_vector()?;
+                let (n, a) = self.pop_vector()?;
+                if n != 3 || m != 3 {
+                    return Err(format!(
+                        "cannot take the cross product of a vec{n} and a vec{m}"
+                    ));
+                }
+
+                self.stack.push(Value::Vector(
+                    3,
+                    [
+                        a[1] * b[2] - a[2] * b[1],
+                        a[2] * b[0] - a[0] * b[2],
+                        a[0] * b[1] - a[1] * b[0],
+                        0.0,
+                    ],
+                ));
+            }
+            0xB0 => {
+                let (n, v) = self.pop_vector()?;
+                let norm = v[..n as usize].iter().map(|x| x * x).sum::<f32>().sqrt();
+                self.stack.push(Value::Float(norm));
+            }
+            0xB1 => {
+                let (n, a) = self.pop_matrix()?;
+                let n = n as usize;
+                let mut elems = [0.0; 16];
+                for i in 0..n {
+                    for j in 0..n {
+                        elems[j * n + i] = a[i * n + j];
+                    }
+                }
+                self.stack.push(Value::Matrix(n as u8, elems));
+            }
+            0xB2 => {
+                let inverse = match self.pop()? {
+                    Value::Matrix(n, a) => {
+                        Value::Matrix(n, invert(n as usize, a).ok_or("the matrix is singular")?)
+                    }
+                    Value::Quat(q) => Value::Quat(geometry::inverse(q).ok_or(ZERO_QUAT)?),
+                    Value::Transform(t) => Value::Transform(t.inverse().ok_or(ZERO_QUAT)?),
+                    value => return Err(format!("cannot invert {value:?}")),
+                };
+                self.stack.push(inverse);
+            }
+            0xB3 | 0xB4 => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
+                self.stack
+                    .push(Value::Bool((lhs == rhs) == (opcode == 0xB3)));
+            }
+            0xB5 => {
+                let addr = read_u32(self.code, at);
+                self.memory.insert(addr, Value::Quat(geometry::IDENTITY));
+            }
+            0xB6 => {
+                let addr = read_u32(self.code, at);
+                self.memory
+                    .insert(addr, Value::Transform(Transform::IDENTITY));
+            }
+            0xB7 => {
+                let mut q = [0.0; 4];
+                for c in q.iter_mut().rev() {
+                    *c = self.pop_float()?;
+                }
+                self.stack.push(Value::Quat(q));
+            }
+            0xB8 => {
+                let angle = self.pop_float()?;
+                let axis = self.pop_vec3()?;
+                let q = geometry::axis_angle(axis, angle).ok_or("the axis of rotation is zero")?;
+                self.stack.push(Value::Quat(q));
+            }
+            0xB9 => {
+                let yaw = self.pop_float()?;
+                let pitch = self.pop_float()?;
+                let roll = self.pop_float()?;
+                self.stack
+                    .push(Value::Quat(geometry::euler(roll, pitch, yaw)));
+            }
+            0xBA => {
+                let translation = self.pop_vec3()?;
+                let rotation = self.pop_quat()?;
+                self.stack.push(Value::Transform(Transform {
+                    rotation,
+                    translation,
+                }));
+            }
+            0xBB => {
+                let b = self.pop_quat()?;
+                let a = self.pop_quat()?;
+                self.stack.push(Value::Quat(geometry::mul(a, b)));
+            }
+            0xBC => {
+                let v = self.pop_vec3()?;
+                let q = self.pop_quat()?;
+                let v = geometry::rotate(q, v).ok_or(ZERO_QUAT)?;
+                self.stack.push(Value::Vector(3, vector(v)));
+            }
+            0xBD => {
+                let b = self.pop_transform()?;
+                let a = self.pop_transform()?;
+                let t = a.compose(b).ok_or(ZERO_QUAT)?;
+                self.stack.push(Value::Transform(t));
+            }
+            0xBE => {
+                let v = self.pop_vec3()?;
+                let t = self.pop_transform()?;
+                let v = t.apply(v).ok_or(ZERO_QUAT)?;
+                self.stack.push(Value::Vector(3, vector(v)));
+            }
+            0xBF => {
+                let t = self.pop_float()?;
+                let b = self.pop_quat()?;
+                let a = self.pop_quat()?;
+                let q = geometry::slerp(a, b, t).ok_or(ZERO_QUAT)?;
+                self.stack.push(Value::Quat(q));
+            }
+            0xC0 => {
+                let normal = match self.pop()? {
+                    Value::Quat(q) => Value::Quat(geometry::normalize(q).ok_or(ZERO_QUAT)?),
+                    Value::Vector(n, v) => {
+                        let len = v.iter().map(|c| c * c).sum::<f32>().sqrt();
+                        if len == 0.0 {
+                            return Err("the vector is zero".to_string());
+                        }
+                        Value::Vector(n, v.map(|c| c / len))
+                    }
+                    value => return Err(format!("cannot normalise {value:?}")),
+                };
+                self.stack.push(normal);
+            }
+            0xC1 => {
+                let t = self.pop_transform()?;
+                self.stack.push(Value::Quat(t.rotation));
+            }
+            0xC2 => {
+                let t = self.pop_transform()?;
+                self.stack.push(Value::Vector(3, vector(t.translation)));
+            }
+
+            0xC8..=0xCB => {
+                let format = self.format(at)?;
+                let rhs = self.pop_int()?;
+                let lhs = self.pop_int()?;
+                self.stack.push(Value::Int(match opcode {
+                    0xC8 => format.add(lhs, rhs),
+                    0xC9 => format.sub(lhs, rhs),
+                    0xCA => format.mul(lhs, rhs),
+                    _ => format.div(lhs, rhs).ok_or("division by zero")?,
+                }));
+            }
+            0xCC => {
+                let format = self.format(at)?;
+                let value = self.pop_int()?;
+                self.stack.push(Value::Int(format.neg(value)));
+            }
+            0xCD => {
+                let format = self.format(at)?;
+                let value = self.pop_int()?;
+                self.stack.push(Value::Int(format.of_int(value)));
+            }
+            0xCE => {
+                let value = self.pop_int()?;
+                let frac = self.fraction(at)?;
+                self.stack.push(Value::Int(fixed::to_int(value, frac)));
+            }
+            0xCF => {
+                let format = self.format(at)?;
+                let value = self.pop_float()?;
+                self.stack.push(Value::Int(format.of_float(value as f64)));
+            }
+            0xD0 => {
+                let value = self.pop_int()?;
+                let frac = self.fraction(at)?;
+                self.stack.push(Value::Float(fixed::to_float(value, frac)));
+            }
+            0xD1 => {
+                let frac = self.fraction(at)?;
+                let format = self.format(at + 1)?;
+                let value = self.pop_int()?;
+                self.stack.push(Value::Int(format.convert(value, frac)));
+            }
+            0xD2 => {
+                let value = self.pop_int()?;
+                let frac = self.fraction(at)?;
+                write!(out, "{}", fixed::to_string(value, frac)).map_err(|e| e.to_string())?;
+            }
+
+            0xD8 => {
+                let kind = self.kind(at)?;
+                let value = immediate(kind, read_u64(self.code, at + 1));
+                self.stack.push(number(kind, value));
+            }
+            0xD9 => {
+                let kind = self.kind(at + 4)?;
+                let zero = kind.convert(Number::Int(0));
+                self.memory
+                    .insert(read_u32(self.code, at), number(kind, zero));
+            }
+            0xDA | 0xDC => {
+                let kind = self.kind(at + 4)?;
+                let addr = read_u32(self.code, at);
+                let slot = if opcode == 0xDC {
+                    self.element(addr)?
+                } else {
+                    addr
+                };
+
+                let zero = number(kind, kind.convert(Number::Int(0)));
+                let value = self.memory.get(&slot).copied().unwrap_or(zero);
+                self.stack.push(value);
+            }
+            0xDB | 0xDD => {
+                let kind = self.kind(at + 4)?;
+                let addr = read_u32(self.code, at);
+                // like `storai`, the index is above the value
+                let slot = if opcode == 0xDD {
+                    self.element(addr)?
+                } else {
+                    addr
+                };
+                let value = self.pop_number(kind)?;
+
+                self.memory.insert(slot, number(kind, value));
+            }
+            0xDE..=0xE2 | 0xE4..=0xE8 => {
+                let kind = self.kind(at)?;
+                let rhs = self.pop_number(kind)?;
+                let lhs = self.pop_number(kind)?;
+                let result = match (lhs, rhs) {
+                    (Number::Int(a), Number::Int(b)) => {
+                        Number::Int(int_result(opcode, kind, a, b)?)
+                    }
+                    (Number::Float(a), Number::Float(b)) => Number::Float(match opcode {
+                        0xDE => a + b,
+                        0xDF => a - b,
+                        0xE0 => a * b,
+                        0xE1 => a / b,
+                        0xE2 => a % b,
+                        _ => return Err(format!("`{mnemonic}` needs an integer type")),
+                    }),
+                    _ => unreachable!("both operands are of one kind"),
+                };
+
+                self.stack.push(number(kind, kind.convert(result)));
+            }
+            0xE3 | 0xE9 => {
+                let kind = self.kind(at)?;
+                let result = match self.pop_number(kind)? {
+                    Number::Int(value) if opcode == 0xE3 => Number::Int(-value),
+                    Number::Int(value) => Number::Int(!value),
+                    Number::Float(value) if opcode == 0xE3 => Number::Float(-value),
+                    Number::Float(_) => return Err(format!("`{mnemonic}` needs an integer type")),
+                };
+
+                self.stack.push(number(kind, kind.convert(result)));
+            }
+            0xEA..=0xEF => {
+                let kind = self.kind(at)?;
+                let rhs = self.pop_number(kind)?;
+                let lhs = self.pop_number(kind)?;
+                let ordering = match (lhs, rhs) {
+                    (Number::Int(a), Number::Int(b)) => a.partial_cmp(&b),
+                    (Number::Float(a), Number::Float(b)) => a.partial_cmp(&b),
+                    _ => unreachable!("both operands are of one kind"),
+                };
+
+                // every comparison but `!=` is false when either side is NaN
+                self.stack.push(Value::Bool(match (opcode, ordering) {
+                    (0xEB, ordering) => ordering != Some(Ordering::Equal),
+                    (_, None) => false,
+                    (0xEA, Some(ordering)) => ordering.is_eq(),
+                    (0xEC, Some(ordering)) => ordering.is_lt(),
+                    (0xED, Some(ordering)) => ordering.is_le(),
+                    (0xEE, Some(ordering)) => ordering.is_gt(),
+                    (_, Some(ordering)) => ordering.is_ge(),
+                }));
+            }
+            0xF0 => {
+                let from = self.kind(at)?;
+                let to = self.kind(at + 1)?;
+                let value = self.pop_number(from)?;
+                self.stack.push(number(to, to.convert(value)));
+            }
+            0xF1 => {
+                let kind = self.kind(at)?;
+                let value = self.pop_number(kind)?;
+                write!(out, "{}", number(kind, value)).map_err(|e| e.to_string())?;
+            }
+
+            _ => return Err(format!("`{mnemonic}` is not supported")),
+        }
+
+        Ok(())
+    }
+
+    /// The value an unwritten slot reads as for a load opcode.
+    fn zero(load: u8) -> Value {
+        match load {
+            0x23 | 0x83 => Value::Float(0.0),
+            0x29 | 0x84 => Value::Bool(false),
+            0x2D | 0x85 => Value::Char(0),
+            _ => Value::Int(0),
+        }
+    }
+
+    /// Pops an index and returns the address of that element of the array at `addr`.
+    fn element(&mut self, addr: u32) -> Result<u32, String> {
+        let index = self.pop_int()?;
+        let (elem_size, len) = *self
+            .arrays
+            .get(&addr)
+            .ok_or_else(|| format!("no array is declared at {addr:#06x}"))?;
+
+        if index < 0 || index as u32 >= len {
+            return Err(format!(
+                "index {index} is out of bounds for an array of length {len}"
+            ));
+        }
+
+        Ok(addr + index as u32 * elem_size)
+    }
+
+    fn pop(&mut self) -> Result<Value, String> {
+        self.stack
+            .pop()
+            .ok_or_else(|| "the stack is empty".to_string())
+    }
+
+    fn pop_int(&mut self) -> Result<i32, String> {
+        match self.pop()? {
+            Value::Int(value) => Ok(value),
+            value => Err(format!("expected an int, found {value:?}")),
+        }
+    }
+
+    fn pop_float(&mut self) -> Result<f32, String> {
+        match self.pop()? {
+            Value::Float(value) => Ok(value),
+            value => Err(format!("expected a float, found {value:?}")),
+        }
+    }
+
+    fn pop_bool(&mut self) -> Result<bool, String> {
+        match self.pop()? {
+            Value::Bool(value) => Ok(value),
+            value => Err(format!("expected a bool, found {value:?}")),
+        }
+    }
+
+    fn pop_char(&mut self) -> Result<u8, String> {
+        match self.pop()? {
+            Value::Char(value) => Ok(value),
+            value => Err(format!("expected a char, found {value:?}")),
+        }
+    }
+
+    fn pop_vector(&mut self) -> Result<(u8, [f32; 4]), String> {
+        match self.pop()? {
+            Value::Vector(n, elems) => Ok((n, elems)),
+            value => Err(format!("expected a vector, found {value:?}")),
+        }
+    }
+
+    fn pop_matrix(&mut self) -> Result<(u8, [f32; 16]), String> {
+        match self.pop()? {
+            Value::Matrix(n, elems) => Ok((n, elems)),
+            value => Err(format!("expected a matrix, found {value:?}")),
+        }
+    }
+
+    fn pop_vec3(&mut self) -> Result<[f32; 3], String> {
+        match self.pop_vector()? {
+            (3, [x, y, z, _]) => Ok([x, y, z]),
+            (n, _) => Err(format!("expected a vec3, found a vec{n}")),
+        }
+    }
+
+    fn pop_quat(&mut self) -> Result<[f32; 4], String> {
+        match self.pop()? {
+            Value::Quat(q) => Ok(q),
+            value => Err(format!("expected a quaternion, found {value:?}")),
+        }
+    }
+
+    fn pop_transform(&mut self) -> Result<Transform, String> {
+        match self.pop()? {
+            Value::Transform(t) => Ok(t),
+            value => Err(format!("expected a transform, found {value:?}")),
+        }
+    }
+
+    /// The component operand at `at`, which must be one a vector of `n` components has.
+    fn component(&self, n: u8, at: usize) -> Result<usize, String> {
+        let i = self.code[at];
+        if i >= n {
+            return Err(format!("a vec{n} has no component {i}"));
+        }
+
+        Ok(i as usize)
+    }
+
+    /// The fixed-point format operand at `at`.
+    fn format(&self, at: usize) -> Result<Format, String> {
+        let bytes = [self.code[at], self.code[at + 1]];
+        Format::decode(bytes).ok_or_else(|| format!("{bytes:02x?} is not a fixed-point format"))
+    }
+
+    /// The fraction operand at `at`, the number of fractional bits of a fixed-point value.
+    fn fraction(&self, at: usize) -> Result<u8, String> {
+        match self.code[at] {
+            frac if frac < 32 => Ok(frac),
+            frac => Err(format!("a fixed-point value has no {frac} fractional bits")),
+        }
+    }
+
+    /// The numeric type operand at `at`.
+    fn kind(&self, at: usize) -> Result<Kind, String> {
+        let byte = self.code[at];
+        Kind::decode(byte).ok_or_else(|| format!("{byte:#04x} is not a numeric type"))
+    }
+
+    /// Pops a value of the numeric type `kind`.
+    fn pop_number(&mut self, kind: Kind) -> Result<Number, String> {
+        match (kind, self.pop()?) {
+            (Kind::INT, Value::Int(value)) => Ok(Number::Int(value as i128)),
+            (Kind::FLOAT, Value::Float(value)) => Ok(Number::Float(value as f64)),
+            (Kind::Float(64), Value::Double(value)) => Ok(Number::Float(value)),
+            (kind, Value::Sized(value)) if kind.is_int() && kind != Kind::INT => {
+                Ok(Number::Int(value))
+            }
+            (kind, value) => Err(format!("expected a {kind}, found {value:?}")),
+        }
+    }
+
+    fn int_op(&mut self, op: impl Fn(i32, i32) -> i32) -> Result<(), String> {
+        let rhs = self.pop_int()?;
+        let lhs = self.pop_int()?;
+        self.stack.push(Value::Int(op(lhs, rhs)));
+        Ok(())
+    }
+
+    fn float_op(&mut self, op: impl Fn(f32, f32) -> f32) -> Result<(), String> {
+        let rhs = self.pop_float()?;
+        let lhs = self.pop_float()?;
+        self.stack.push(Value::Float(op(lhs, rhs)));
+        Ok(())
+    }
+
+    fn int_cmp(&mut self, cmp: impl Fn(i32, i32) -> bool) -> Result<(), String> {
+        let rhs = self.pop_int()?;
+        let lhs = self.pop_int()?;
+        self.stack.push(Value::Bool(cmp(lhs, rhs)));
+        Ok(())
+    }
+
+    fn float_cmp(&mut self, cmp: impl Fn(f32, f32) -> bool) -> Result<(), String> {
+        let rhs = self.pop_float()?;
+        let lhs = self.pop_float()?;
+        self.stack.push(Value::Bool(cmp(lhs, rhs)));
+        Ok(())
+    }
+}
+
+/// `value`, which is in the range of `kind`, as a value of that type.
+fn number(kind: Kind, value: Number) -> Value {
+    match (kind, value) {
+        (Kind::INT, Number::Int(value)) => Value::Int(value as i32),
+        (_, Number::Int(value)) => Value::Sized(value),
+        (Kind::FLOAT, Number::Float(value)) => Value::Float(value as f32),
+        (_, Number::Float(value)) => Value::Double(value),
+    }
+}
+
+/// The result of the integer instruction `opcode` on `a` and `b`, before it is wrapped into
+/// `kind`. Shifts only use as many low bits of `b` as it takes to count the bits of `kind`.
+fn int_result(opcode: u8, kind: Kind, a: i128, b: i128) -> Result<i128, String> {
+    let shift = (b & (kind.bits() as i128 - 1)) as u32;
+
+    Ok(match opcode {
+        0xDE => a + b,
+        0xDF => a - b,
+        0xE0 => a.wrapping_mul(b),
+        0xE1 | 0xE2 if b == 0 => return Err("division by zero".to_string()),
+        0xE1 => a / b,
+        0xE2 => a % b,
+        0xE4 => a & b,
+        0xE5 => a | b,
+        0xE6 => a ^ b,
+        0xE7 => a.wrapping_shl(shift),
+        _ => a >> shift,
+    })
+}
+
+const ZERO_QUAT: &str = "the quaternion is zero";
+
+/// The components of a vector or quaternion, which are read and written alike.
+fn components(value: Value) -> Result<(u8, [f32; 4]), String> {
+    match value {
+        Value::Vector(n, elems) => Ok((n, elems)),
+        Value::Quat(q) => Ok((4, q)),
+        value => Err(format!("expected a vector or quaternion, found {value:?}")),
+    }
+}
+
+fn vector([x, y, z]: [f32; 3]) -> [f32; 4] {
+    [x, y, z, 0.0]
+}
+
+/// Multiplies every element of a vector or matrix by `factor`.
+fn scale(value: Value, factor: f32) -> Result<Value, String> {
+    match value {
+        Value::Vector(n, elems) => Ok(Value::Vector(n, elems.map(|x| x * factor))),
+        Value::Matrix(n, elems) => Ok(Value::Matrix(n, elems.map(|x| x * factor))),
+        value => Err(format!("expected a vector or matrix, found {value:?}")),
+    }
+}
+
+/// Inverts the `n` by `n` matrix `a` by Gauss-Jordan elimination with partial pivoting, or
+/// returns `None` when it is singular.
+fn invert(n: usize, mut a: [f32; 16]) -> Option<[f32; 16]> {
+    let mut inv = [0.0; 16];
+    for i in 0..n {
+        inv[i * n + i] = 1.0;
+    }
+
+    for col in 0..n {
+        let pivot =
+            (col..n).max_by(|&i, &j| a[i * n + col].abs().total_cmp(&a[j * n + col].abs()))?;
+        if a[pivot * n + col] == 0.0 {
+            return None;
+        }
+
+        for k in 0..n {
+            a.swap(col * n + k, pivot * n + k);
+            inv.swap(col * n + k, pivot * n + k);
+        }
+
+        let p = a[col * n + col];
+        for k in 0..n {
+            a[col * n + k] /= p;
+            inv[col * n + k] /= p;
+        }
+
+        for row in (0..n).filter(|&row| row != col) {
+            let factor = a[row * n + col];
+            for k in 0..n {
+                a[row * n + k] -= factor * a[col * n + k];
+                inv[row * n + k] -= factor * inv[col * n + k];
+            }
+        }
+    }
+
+    Some(inv)
+}
@@ -0,0 +1,257 @@
+use std::fs;
+
+use karma::{compile_file, compile_project, compile_str, diagram, Stage};
+
+#[test]
+fn compiles_to_bytecode_in_memory() {
+    let compilation = compile_str(
+        "node Counter {
+            fn main() -> () {
+                var x: int = 1;
+                x += 2;
+            }
+        }",
+    );
+
+    assert!(compilation.is_ok(), "{:?}", compilation.diagnostics);
+    assert!(!compilation.bytecode["Counter"].is_empty());
+    assert_eq!(compilation.graph["Counter"], Vec::<String>::new());
+}
+
+#[test]
+fn reports_syntax_errors_with_a_span() {
+    let compilation =
+        compile_str("node Broken {\n    fn main() -> () {\n        var x: int = ;\n    }\n}");
+
+    let diagnostic = &compilation.diagnostics[0];
+    assert_eq!(diagnostic.stage, Stage::Syntax);
+
+    let span = diagnostic.span.expect("syntax errors have a span");
+    assert_eq!((span.line, span.column), (3, 22));
+}
+
+#[test]
+fn reports_semantic_errors_with_their_code() {
+    let compilation = compile_str(
+        "node Broken {
+            fn main() -> () {
+                y = 1;
+            }
+        }",
+    );
+
+    let diagnostic = &compilation.diagnostics[0];
+    assert_eq!(diagnostic.stage, Stage::Semantic);
+    assert_eq!(diagnostic.code, Some(16));
+    assert!(compilation.bytecode.is_empty());
+
+    // the error is reported at the statement it is in
+    let span = diagnostic
+        .span
+        .expect("semantic errors in a statement have a span");
+    assert_eq!((span.line, span.column), (3, 17));
+}
+
+#[test]
+fn only_public_module_items_can_be_imported() {
+    let compilation = compile_str(
+        "module geometry {
+            fn helper() -> int {
+                return 1;
+            }
+        }
+
+        node Planner {
+            use geometry::helper;
+
+            fn main() -> () { }
+        }",
+    );
+
+    assert_eq!(compilation.diagnostics[0].code, Some(27));
+}
+
+#[test]
+fn compiles_every_file_in_a_project() {
+    let dir = std::env::temp_dir().join(format!("karma-project-{}", std::process::id()));
+    fs::create_dir_all(dir.join("nodes")).unwrap();
+
+    fs::write(dir.join("a.krm"), "node A { fn main() -> () { } }").unwrap();
+    fs::write(
+        dir.join("nodes/b.krm"),
+        "node B : A { fn main() -> () { } }",
+    )
+    .unwrap();
+
+    let compilation = compile_project(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(compilation.is_ok(), "{:?}", compilation.diagnostics);
+    assert_eq!(
+        compilation.bytecode.keys().collect::<Vec<_>>(),
+        vec!["A", "B"]
+    );
+    assert_eq!(compilation.graph["B"], vec!["A".to_string()]);
+}
+
+#[test]
+fn compiles_the_sources_listed_in_the_manifest() {
+    let dir = std::env::temp_dir().join(format!("karma-manifest-{}", std::process::id()));
+    fs::create_dir_all(dir.join("nodes")).unwrap();
+
+    fs::write(
+        dir.join("karma.toml"),
+        "[project]\nsources = [\"nodes\", \"drive.krm\"]\nentry = [\"Planner\"]\n",
+    )
+    .unwrap();
+    fs::write(dir.join("drive.krm"), "node Drive { fn main() -> () { } }").unwrap();
+    fs::write(
+        dir.join("ignored.krm"),
+        "node Ignored { fn main() -> () { } }",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("nodes/planner.krm"),
+        "node Planner : Localization, Drive { fn main() -> () { } }",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("nodes/localization.krm"),
+        "node Localization { fn main() -> () { } }",
+    )
+    .unwrap();
+
+    let compilation = compile_project(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(compilation.is_ok(), "{:?}", compilation.diagnostics);
+    assert_eq!(
+        compilation.bytecode.keys().collect::<Vec<_>>(),
+        vec!["Drive", "Localization", "Planner"]
+    );
+    assert_eq!(compilation.graph["Planner"], vec!["Localization", "Drive"]);
+}
+
+#[test]
+fn reports_duplicate_and_unknown_nodes_across_files() {
+    let dir = std::env::temp_dir().join(format!("karma-nodes-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    fs::write(dir.join("karma.toml"), "[project]\nentry = [\"Missing\"]\n").unwrap();
+    fs::write(dir.join("a.krm"), "node A { fn main() -> () { } }").unwrap();
+    fs::write(
+        dir.join("b.krm"),
+        "node A { fn main() -> () { } }\nnode B : A, C { fn main() -> () { } }",
+    )
+    .unwrap();
+
+    let compilation = compile_project(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let diagnostics: Vec<_> = compilation
+        .diagnostics
+        .iter()
+        .map(|d| {
+            let file = d.file.as_ref().unwrap().file_name().unwrap().to_owned();
+            (d.code, file, d.span.map(|span| (span.line, span.column)))
+        })
+        .collect();
+
+    assert_eq!(
+        diagnostics,
+        vec![
+            (Some(1), "b.krm".into(), Some((1, 6))),
+            (Some(25), "b.krm".into(), Some((2, 13))),
+            (Some(25), "karma.toml".into(), None),
+        ]
+    );
+}
+
+#[test]
+fn orders_nodes_after_their_dependencies() {
+    let compilation = compile_str(
+        "node Planner : Localization, Drive { fn main() -> () { } }
+        node Drive : Motor { fn main() -> () { } }
+        node Localization : Motor, Imu { fn main() -> () { } }
+        node Imu { fn main() -> () { } }
+        node Motor { fn main() -> () { } }",
+    );
+
+    assert!(compilation.is_ok(), "{:?}", compilation.diagnostics);
+    assert_eq!(
+        compilation.order,
+        vec!["Imu", "Motor", "Drive", "Localization", "Planner"]
+    );
+}
+
+#[test]
+fn reports_self_dependencies_and_cycles_where_they_are_written() {
+    let compilation = compile_str(
+        "node A : C { fn main() -> () { } }
+node B : A { fn main() -> () { } }
+node C : B { fn main() -> () { } }
+node D : D { fn main() -> () { } }",
+    );
+
+    let diagnostics: Vec<_> = compilation
+        .diagnostics
+        .iter()
+        .map(|d| (d.code, d.span.map(|span| (span.line, span.column))))
+        .collect();
+
+    assert_eq!(
+        diagnostics,
+        vec![(Some(30), Some((4, 10))), (Some(31), Some((2, 10)))]
+    );
+    assert!(compilation.diagnostics[1]
+        .message
+        .contains("`A` -> `C` -> `B` -> `A`"));
+}
+
+#[test]
+fn renders_the_node_graph_with_exports() {
+    let compilation = compile_str(
+        "node Imu {
+            export var heading: float = 0.0;
+            export var rate: float<rad/s> = 0.0 rad/s;
+            fn main() -> () { }
+        }
+        node Planner : Imu { fn main() -> () { } }",
+    );
+
+    assert_eq!(
+        diagram::to_mermaid(&compilation),
+        "flowchart LR
+    node_Imu[\"Imu<br/>heading: float<br/>rate: float#lt;rad/s#gt;\"]
+    node_Planner[\"Planner\"]
+    node_Planner -->|\"depends on\"| node_Imu
+"
+    );
+
+    let dot = diagram::to_dot(&compilation);
+    assert!(dot.contains("\"Imu\" [label=\"{Imu|heading: float\\lrate: float\\<rad/s\\>\\l}\"];"));
+    assert!(dot.contains("\"Planner\" -> \"Imu\" [label=\"depends on\"];"));
+}
+
+#[test]
+fn every_sample_compiles() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/code");
+    let mut samples = vec![];
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|extension| extension == "krm") {
+            samples.push(path);
+        }
+    }
+    assert!(samples.iter().any(|path| path.ends_with("hello_world.krm")));
+
+    for path in samples {
+        let compilation = compile_file(&path).unwrap();
+        assert!(
+            compilation.is_ok(),
+            "{}: {:?}",
+            path.display(),
+            compilation.diagnostics
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use std::{fs, process::Command};
+
+fn karma(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_karma"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn exit_codes_reflect_the_failure() {
+    let dir = std::env::temp_dir().join(format!("karma-cli-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let ok = dir.join("ok.krm");
+    fs::write(&ok, "node A { fn main() -> () { print_int(1 / 0); } }").unwrap();
+    let broken = dir.join("broken.krm");
+    fs::write(&broken, "node A { fn main() -> () { var x: int = ; } }").unwrap();
+
+    let ok = ok.to_str().unwrap();
+    let broken = broken.to_str().unwrap();
+
+    assert_eq!(karma(&["check", ok]).status.code(), Some(0));
+    assert_eq!(karma(&["check", broken]).status.code(), Some(1));
+    assert_eq!(karma(&["run", ok, "--node", "B"]).status.code(), Some(2));
+    assert_eq!(karma(&["run", ok]).status.code(), Some(3));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn prints_json_diagnostics() {
+    let dir = std::env::temp_dir().join(format!("karma-cli-json-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let broken = dir.join("broken.krm");
+    fs::write(
+        &broken,
+        "node A {\n    fn main() -> () {\n        y = 1;\n    }\n}",
+    )
+    .unwrap();
+
+    let output = karma(&["check", "--message-format=json", broken.to_str().unwrap()]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let line = String::from_utf8(output.stderr).unwrap();
+    let json: serde_json::Value = serde_json::from_str(line.trim()).unwrap();
+    assert_eq!(json["stage"], "semantic");
+    assert_eq!(json["code"], 16);
+    assert_eq!(json["file"], broken.to_str().unwrap());
+    assert_eq!(
+        (json["line"].as_u64(), json["column"].as_u64()),
+        (Some(3), Some(9))
+    );
+}
+
+#[test]
+fn builds_only_the_requested_outputs() {
+    let dir = std::env::temp_dir().join(format!("karma-cli-build-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let source = dir.join("nodes.krm");
+    fs::write(
+        &source,
+        "node A { fn main() -> () { } } node B : A { fn main() -> () { } }",
+    )
+    .unwrap();
+    let out_dir = dir.join("out");
+
+    let output = karma(&[
+        "build",
+        source.to_str().unwrap(),
+        "--out-dir",
+        out_dir.to_str().unwrap(),
+        "--node",
+        "B",
+        "--emit=bytecode,ir",
+    ]);
+
+    let mut written: Vec<_> = fs::read_dir(&out_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    written.sort();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(written, vec!["B.ir", "B.k"]);
+}
+
+#[test]
+fn emits_the_syntax_and_parse_trees() {
+    let dir = std::env::temp_dir().join(format!("karma-cli-trees-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let source = dir.join("a.krm");
+    fs::write(&source, "node A { fn main() -> () { print_int(1); } }").unwrap();
+    let out_dir = dir.join("out");
+
+    let output = karma(&[
+        "build",
+        source.to_str().unwrap(),
+        "--out-dir",
+        out_dir.to_str().unwrap(),
+        "--emit=ast-json,parse-tree",
+    ]);
+
+    let ast = fs::read_to_string(out_dir.join("ast.json")).unwrap();
+    let parse_tree = fs::read_to_string(out_dir.join("parse_tree.txt")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success());
+    let ast: serde_json::Value = serde_json::from_str(&ast).unwrap();
+    assert_eq!(ast["node"], "NodeSeq");
+    assert_eq!(ast["children"][0]["node"], "DeclareNode");
+    assert!(parse_tree.starts_with("Program\n  NodeNT\n    Terminal(Node)\n"));
+}
+
+#[test]
+fn fmt_check_fails_until_the_file_is_formatted() {
+    let dir = std::env::temp_dir().join(format!("karma-cli-fmt-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let source = dir.join("a.krm");
+    fs::write(&source, "node A{fn main()->(){print_int(1);}}").unwrap();
+    let source = source.to_str().unwrap();
+
+    let unformatted = karma(&["fmt", "--check", source]).status.code();
+    let fmt = karma(&["fmt", source]).status.code();
+    let formatted = karma(&["fmt", "--check", source]).status.code();
+    let text = fs::read_to_string(source).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(unformatted, Some(1));
+    assert_eq!(fmt, Some(0));
+    assert_eq!(formatted, Some(0));
+    assert_eq!(
+        text,
+        "node A {\n    fn main() -> () {\n        print_int(1);\n    }\n}\n"
+    );
+}
+
+#[test]
+fn test_reports_each_test_and_fails_when_one_does() {
+    let dir = std::env::temp_dir().join(format!("karma-cli-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let source = dir.join("a.krm");
+    fs::write(
+        &source,
+        "node A {\n    fn main() -> () {}\n\n    test fn passes() -> () {\n        assert(true);\n    }\n\n    test fn fails() -> () {\n        assert(1 > 2);\n    }\n}\n",
+    )
+    .unwrap();
+    let source = source.to_str().unwrap();
+
+    let all = karma(&["test", source]);
+    let filtered = karma(&["test", source, "passes"]);
+    let json = karma(&["test", "--message-format=json", source]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let stdout = String::from_utf8(all.stdout).unwrap();
+    assert_eq!(all.status.code(), Some(3));
+    assert!(stdout.contains("test A::fails ... FAILED\ntest A::passes ... ok\n"));
+    assert!(stdout.contains("a.krm:9:9: assertion failed\n"));
+    assert!(stdout.contains("test result: FAILED. 1 passed; 1 failed; 0 filtered out"));
+    // each failure is printed once, with the results
+    assert_eq!(String::from_utf8(all.stderr).unwrap(), "");
+
+    let stderr = String::from_utf8(json.stderr).unwrap();
+    let failure: serde_json::Value = serde_json::from_str(stderr.trim()).unwrap();
+    assert_eq!(failure["stage"], "test");
+    assert_eq!(failure["test"], "A::fails");
+    assert_eq!(failure["message"], "assertion failed");
+    assert_eq!(failure["file"], source);
+    assert_eq!(
+        (failure["line"].as_u64(), failure["column"].as_u64()),
+        (Some(9), Some(9))
+    );
+
+    assert_eq!(filtered.status.code(), Some(0));
+    assert!(String::from_utf8(filtered.stdout)
+        .unwrap()
+        .contains("1 passed; 0 failed; 1 filtered out"));
+}
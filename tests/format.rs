@@ -0,0 +1,143 @@
+use karma::{format::format_source, Stage};
+
+#[test]
+fn lays_out_nodes_structs_and_statements() {
+    let source = "node Demo:Imu{
+  struct Point{x:float,y:float}
+    fn main()->(){
+     var p:Point={x:-1.5,y:2.0,};
+     var arr:[int;3]=[1,2,3,];
+     while(p.x<0.0){p.x+=1.0;}
+     if !true {print_int(arr[0]);} else {print_float(p.x as float * 2.0);}
+  }
+}
+node Imu { fn main() -> () { } }";
+
+    assert_eq!(
+        format_source(source).unwrap(),
+        "node Demo : Imu {
+    struct Point {
+        x: float,
+        y: float,
+    }
+
+    fn main() -> () {
+        var p: Point = {
+            x: -1.5,
+            y: 2.0,
+        };
+        var arr: [int; 3] = [1, 2, 3];
+        while (p.x < 0.0) {
+            p.x += 1.0;
+        }
+        if !true {
+            print_int(arr[0]);
+        } else {
+            print_float(p.x as float * 2.0);
+        }
+    }
+}
+
+node Imu {
+    fn main() -> () {}
+}
+"
+    );
+}
+
+#[test]
+fn keeps_comments_and_is_idempotent() {
+    let source = "// a node
+node Demo {
+
+
+    /* counts */ export var count: int = 0x1F;
+    fn main() -> () { // entry
+        count = count + // carried
+            1;
+
+        // done
+    }
+}
+// end";
+
+    let formatted = format_source(source).unwrap();
+    assert_eq!(
+        formatted,
+        "// a node
+node Demo {
+    /* counts */ export var count: int = 0x1F;
+    fn main() -> () { // entry
+        count = count + // carried
+            1;
+
+        // done
+    }
+}
+
+// end
+"
+    );
+    assert_eq!(format_source(&formatted).unwrap(), formatted);
+}
+
+#[test]
+fn refuses_source_that_does_not_parse() {
+    let error = format_source("node A { fn main() -> () { var x: int = ; } }").unwrap_err();
+
+    assert_eq!(error.stage, Stage::Syntax);
+}
+
+#[test]
+fn writes_units_without_spaces() {
+    let source = "node A { fn main() -> () {
+    var g: float < m / s ^ 2 > = 9.8 m/(1.0 s*1.0 s);
+    var t: float<N*m> = 2.0 N*0.5 m as float<N * m>;
+} }";
+
+    assert_eq!(
+        format_source(source).unwrap(),
+        "node A {
+    fn main() -> () {
+        var g: float<m/s^2> = 9.8 m / (1.0 s * 1.0 s);
+        var t: float<N*m> = 2.0 N * 0.5 m as float<N*m>;
+    }
+}
+"
+    );
+}
+
+#[test]
+fn keeps_fixed_point_types_on_one_line() {
+    let source = "node A { fn main() -> () {
+    var x: fixed < 16 , 16 > = 1.5 as fixed<16,16>;
+    var y: [fixed<4, 4, wrap>; 2] = [x as fixed < 4,4,wrap >, 0 as fixed<4, 4, wrap>];
+} }";
+
+    assert_eq!(
+        format_source(source).unwrap(),
+        "node A {
+    fn main() -> () {
+        var x: fixed<16, 16> = 1.5 as fixed<16, 16>;
+        var y: [fixed<4, 4, wrap>; 2] = [x as fixed<4, 4, wrap>, 0 as fixed<4, 4, wrap>];
+    }
+}
+"
+    );
+}
+
+#[test]
+fn keeps_adjacent_minus_signs_apart() {
+    let source = "node A { fn main() -> () { print_int(- -3); print_int(1 - -(-3)); } }";
+
+    assert_eq!(
+        format_source(source).unwrap(),
+        "node A {
+    fn main() -> () {
+        print_int(- -3);
+        print_int(1 - -(-3));
+    }
+}
+"
+    );
+}
@@ -0,0 +1,163 @@
+//! Golden tests. Every `.krm` program under `tests/golden` is compiled and what the compiler
+//! makes of it is compared with the files next to it: `<name>.diagnostics` for a program that
+//! does not compile, and `<name>.disasm` and `<name>.stdout` for one that does, where the
+//! output is what its nodes print when run in startup order.
+//!
+//! Run with `KARMA_BLESS=1` to write the expected files from what the compiler does now.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use karma::{compile_str, vm, Compilation};
+
+const GOLDEN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+/// Semantic errors no program can get: every name is looked up in scope before its type is,
+/// and one that is not declared is reported as 6, so 12 only guards the type lookup.
+const UNREACHABLE_SEMANTIC_ERRORS: &[&str] = &["12"];
+
+fn programs(dir: &Path, found: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            programs(&path, found);
+        } else if path.extension().is_some_and(|ext| ext == "krm") {
+            found.push(path);
+        }
+    }
+}
+
+fn all_programs() -> Vec<PathBuf> {
+    let mut found = vec![];
+    programs(Path::new(GOLDEN_DIR), &mut found);
+    found.sort();
+    found
+}
+
+/// What the compiler makes of a program, as the files it is compared with.
+fn outcome(compilation: &Compilation) -> Vec<(&'static str, String)> {
+    if !compilation.is_ok() {
+        let diagnostics: String = compilation
+            .diagnostics
+            .iter()
+            .map(|diagnostic| format!("{diagnostic}\n"))
+            .collect();
+
+        return vec![("diagnostics", diagnostics)];
+    }
+
+    let mut disasm = String::new();
+    for (node, bytes) in &compilation.bytecode {
+        disasm += &format!("{node}:\n{}", vm::disassemble(bytes).unwrap());
+    }
+
+    let mut stdout = vec![];
+    for node in &compilation.order {
+        if let Err(e) = vm::run(&compilation.bytecode[node], &mut stdout) {
+            stdout.extend(format!("\n{e}\n").bytes());
+            break;
+        }
+    }
+
+    vec![
+        ("disasm", disasm),
+        ("stdout", String::from_utf8(stdout).unwrap()),
+    ]
+}
+
+#[test]
+fn programs_match_their_golden_files() {
+    let bless = env::var_os("KARMA_BLESS").is_some();
+    let mut mismatches = vec![];
+
+    for program in all_programs() {
+        let source = fs::read_to_string(&program).unwrap();
+
+        for (extension, actual) in outcome(&compile_str(&source)) {
+            let expected_file = program.with_extension(extension);
+
+            if bless {
+                fs::write(&expected_file, &actual).unwrap();
+                continue;
+            }
+
+            match fs::read_to_string(&expected_file) {
+                Ok(expected) if expected == actual => {}
+                Ok(expected) => mismatches.push(format!(
+                    "{}:\n--- expected\n{expected}--- actual\n{actual}",
+                    expected_file.display()
+                )),
+                Err(_) => mismatches.push(format!("{} is missing", expected_file.display())),
+            }
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "{}\n\nrun with KARMA_BLESS=1 to update the golden files",
+        mismatches.join("\n")
+    );
+}
+
+/// The text of every `.diagnostics` golden file.
+fn all_diagnostics() -> String {
+    all_programs()
+        .iter()
+        .filter_map(|program| fs::read_to_string(program.with_extension("diagnostics")).ok())
+        .collect()
+}
+
+#[test]
+fn every_semantic_error_has_a_golden_program() {
+    let checker = include_str!("../src/source.rs");
+    let diagnostics = all_diagnostics();
+
+    let mut missing = vec![];
+    for (i, _) in checker.match_indices("Err(") {
+        let digits: String = checker[i + 4..]
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .collect();
+        if digits.is_empty()
+            || !checker[i + 4 + digits.len()..].starts_with(')')
+            || UNREACHABLE_SEMANTIC_ERRORS.contains(&digits.as_str())
+        {
+            continue;
+        }
+
+        if !diagnostics.contains(&format!("semantic error {digits}:")) && !missing.contains(&digits)
+        {
+            missing.push(digits);
+        }
+    }
+
+    assert!(missing.is_empty(), "no golden program reports {missing:?}");
+}
+
+#[test]
+fn every_syntax_error_has_a_golden_program() {
+    let parser = include_str!("../src/parser.rs");
+    let diagnostics = all_diagnostics();
+
+    let mut missing = vec![];
+    for (i, _) in parser.match_indices("\"syntax error: ") {
+        let message = &parser[i + "\"syntax error: ".len()..];
+        let message = &message[..message.find('"').unwrap()];
+        // formatted messages are matched up to their first argument, the others in full
+        let argument = message.match_indices('{').find(|(j, _)| {
+            message[j + 1..].starts_with(|c: char| c.is_alphanumeric() || c == ':' || c == '}')
+        });
+        let reported = |line: &str| match argument {
+            Some((j, _)) => line.contains(&format!("syntax error: {}", &message[..j])),
+            None => line.ends_with(&format!("syntax error: {message}")),
+        };
+
+        if !diagnostics.lines().any(reported) && !missing.contains(&message) {
+            missing.push(message);
+        }
+    }
+
+    assert!(missing.is_empty(), "no golden program reports {missing:?}");
+}
@@ -0,0 +1,315 @@
+use std::{fs, io::Cursor};
+
+use serde_json::{json, Value};
+
+const SOURCE: &str = "node Imu {
+    export var heading: float = 0.0;
+
+    fn main() -> () {}
+}
+
+node Planner : Imu {
+    struct Point {
+        x: float,
+    }
+
+    fn scale(x: int) -> int {
+        return x * 2;
+    }
+
+    fn main() -> () {
+        var total: int = scale(3);
+        while total < 10 {
+            var step: int = 1;
+            total += step;
+        }
+        print_int(total);
+    }
+}
+";
+
+/// Sends `messages` to the server and returns everything it wrote back.
+fn exchange(messages: &[Value]) -> Vec<Value> {
+    let mut input = vec![];
+    for message in messages {
+        let body = message.to_string();
+        input.extend(format!("Content-Length: {}\r\n\r\n{body}", body.len()).bytes());
+    }
+
+    let mut output = vec![];
+    karma::lsp::serve(Cursor::new(input), &mut output).unwrap();
+
+    let mut output = String::from_utf8(output).unwrap();
+    let mut replies = vec![];
+    while let Some(header_end) = output.find("\r\n\r\n") {
+        let length: usize = output["Content-Length: ".len()..header_end]
+            .parse()
+            .unwrap();
+        let body = output[header_end + 4..header_end + 4 + length].to_string();
+        replies.push(serde_json::from_str(&body).unwrap());
+        output = output[header_end + 4 + length..].to_string();
+    }
+
+    replies
+}
+
+fn request(id: u64, method: &str, uri: &str, line: u64, character: u64) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": method,
+        "params": {
+            "textDocument": { "uri": uri },
+            "position": { "line": line, "character": character },
+        },
+    })
+}
+
+fn reply(replies: &[Value], id: u64) -> &Value {
+    &replies.iter().find(|reply| reply["id"] == id).unwrap()["result"]
+}
+
+#[test]
+fn answers_requests_about_an_open_document() {
+    let uri = "file:///tmp/karma-lsp/planner.krm";
+    let replies = exchange(&[
+        json!({ "jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {} }),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "languageId": "karma", "version": 1, "text": SOURCE } },
+        }),
+        // `scale` in `scale(3)`
+        request(1, "textDocument/definition", uri, 16, 25),
+        // `Imu` in the header of `Planner`
+        request(2, "textDocument/definition", uri, 6, 16),
+        // `total` in `print_int(total)`
+        request(3, "textDocument/hover", uri, 21, 19),
+        request(4, "textDocument/hover", uri, 6, 16),
+        // inside the loop, after `step` is declared
+        request(5, "textDocument/completion", uri, 19, 12),
+        json!({
+            "jsonrpc": "2.0",
+            "id": 6,
+            "method": "textDocument/documentSymbol",
+            "params": { "textDocument": { "uri": uri } },
+        }),
+        json!({ "jsonrpc": "2.0", "id": 7, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ]);
+
+    assert_eq!(reply(&replies, 0)["capabilities"]["hoverProvider"], true);
+
+    let diagnostics = replies
+        .iter()
+        .find(|reply| reply["method"] == "textDocument/publishDiagnostics")
+        .unwrap();
+    assert_eq!(diagnostics["params"]["diagnostics"], json!([]));
+
+    assert_eq!(
+        reply(&replies, 1)["range"]["start"],
+        json!({ "line": 11, "character": 7 })
+    );
+    assert_eq!(
+        reply(&replies, 2)["range"]["start"],
+        json!({ "line": 0, "character": 5 })
+    );
+    assert_eq!(
+        reply(&replies, 3)["contents"]["value"],
+        "```karma\nvar total: int\n```"
+    );
+    assert_eq!(
+        reply(&replies, 4)["contents"]["value"],
+        "```karma\nnode Imu\nexport var heading: float\n```"
+    );
+
+    let labels: Vec<&str> = reply(&replies, 5)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|completion| completion["label"].as_str().unwrap())
+        .collect();
+    for label in [
+        "total",
+        "step",
+        "scale",
+        "main",
+        "Point",
+        "Imu",
+        "print_int",
+        "while",
+        "u8",
+    ] {
+        assert!(labels.contains(&label), "{label} is not in {labels:?}");
+    }
+    assert!(!labels.contains(&"x"));
+
+    let symbols = reply(&replies, 6).as_array().unwrap();
+    let names: Vec<&Value> = symbols.iter().map(|symbol| &symbol["name"]).collect();
+    assert_eq!(names, ["Imu", "Planner"]);
+    let children: Vec<&Value> = symbols[1]["children"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| &symbol["name"])
+        .collect();
+    assert_eq!(children, ["Point", "scale", "main"]);
+}
+
+#[test]
+fn publishes_diagnostics_for_every_file_of_a_project() {
+    let dir = std::env::temp_dir().join(format!("karma-lsp-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("karma.toml"), "[project]\n").unwrap();
+    fs::write(dir.join("a.krm"), "node A { fn main() -> () { } }").unwrap();
+    let b = dir.join("b.krm");
+    fs::write(&b, "node B : A, C { fn main() -> () { } }").unwrap();
+
+    let uri = format!("file://{}", fs::canonicalize(&b).unwrap().display());
+    let replies = exchange(&[json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didSave",
+        "params": { "textDocument": { "uri": uri } },
+    })]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(replies.len(), 2);
+    let b = replies
+        .iter()
+        .find(|reply| reply["params"]["uri"] == uri)
+        .unwrap();
+    let diagnostics = b["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["code"], 25);
+    assert_eq!(
+        diagnostics[0]["range"]["start"],
+        json!({ "line": 0, "character": 12 })
+    );
+}
+
+#[test]
+fn publishes_every_diagnostic_at_a_place_in_the_document() {
+    let dir = std::env::temp_dir().join(format!("karma-lsp-places-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("karma.toml"), "[project]\nentry = [\"Missing\"]\n").unwrap();
+    let a = dir.join("a.krm");
+    fs::write(&a, "\nnode A { fn main() -> () { } }").unwrap();
+
+    let uri = format!("file://{}", fs::canonicalize(&a).unwrap().display());
+    let save = json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didSave",
+        "params": { "textDocument": { "uri": uri } },
+    });
+    let replies = exchange(std::slice::from_ref(&save));
+
+    // the missing entry node belongs to the manifest, and is shown at the first node
+    let diagnostics = replies[0]["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics[0]["code"], 25);
+    assert_eq!(
+        diagnostics[0]["range"]["start"],
+        json!({ "line": 1, "character": 5 })
+    );
+
+    fs::write(dir.join("karma.toml"), "[project]\n").unwrap();
+    fs::write(
+        &a,
+        "node A {\n    fn main() -> () {\n        y = 1;\n    }\n}",
+    )
+    .unwrap();
+    let replies = exchange(&[save]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    // a checker error is shown at the statement it is in
+    let diagnostics = replies[0]["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics[0]["code"], 16);
+    assert_eq!(
+        diagnostics[0]["range"]["start"],
+        json!({ "line": 2, "character": 8 })
+    );
+}
+
+#[test]
+fn hovers_a_variable_declared_again_with_its_own_type() {
+    let uri = "file:///tmp/karma-lsp/shadow.krm";
+    let text = "node A {
+    fn main() -> () {
+        if true {
+            var x: float = 1.0;
+            print_float(x);
+        }
+        var x: i32 = 2;
+        print_int(x);
+    }
+}
+";
+    let replies = exchange(&[
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "languageId": "karma", "version": 1, "text": text } },
+        }),
+        // `x` in `print_float(x)` and in `print_int(x)`
+        request(1, "textDocument/hover", uri, 4, 24),
+        request(2, "textDocument/hover", uri, 7, 18),
+    ]);
+
+    assert_eq!(
+        reply(&replies, 1)["contents"]["value"],
+        "```karma\nvar x: float\n```"
+    );
+    // the type as the checker infers it, rather than as written
+    assert_eq!(
+        reply(&replies, 2)["contents"]["value"],
+        "```karma\nvar x: int\n```"
+    );
+}
+
+#[test]
+fn keeps_the_outline_of_a_document_that_stops_parsing() {
+    let uri = "file:///tmp/karma-lsp/broken.krm";
+    let replies = exchange(&[
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "languageId": "karma", "version": 1, "text": SOURCE } },
+        }),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": {
+                "textDocument": { "uri": uri, "version": 2 },
+                "contentChanges": [{ "text": SOURCE.replace("print_int(total);", "print_int(total") }],
+            },
+        }),
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "textDocument/documentSymbol",
+            "params": { "textDocument": { "uri": uri } },
+        }),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didSave",
+            "params": { "textDocument": { "uri": uri } },
+        }),
+    ]);
+
+    let names: Vec<&Value> = reply(&replies, 1)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| &symbol["name"])
+        .collect();
+    assert_eq!(names, ["Imu", "Planner"]);
+
+    // a syntax error has no code, so none is sent
+    let diagnostics = replies
+        .iter()
+        .rev()
+        .find(|reply| reply["method"] == "textDocument/publishDiagnostics")
+        .unwrap();
+    let diagnostic = &diagnostics["params"]["diagnostics"][0];
+    assert_eq!(diagnostic["source"], "karma");
+    assert!(diagnostic.get("code").is_none());
+}
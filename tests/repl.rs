@@ -0,0 +1,92 @@
+use std::io::Cursor;
+
+use karma::repl::{self, Session};
+
+#[test]
+fn keeps_variables_between_entries() {
+    let mut session = Session::new();
+
+    assert_eq!(session.eval("var x: int = 3;"), Ok(String::new()));
+    assert_eq!(session.eval("x * 2 + 1"), Ok("7: int\n".to_string()));
+    assert_eq!(session.eval("print_int(x);"), Ok("3".to_string()));
+    assert_eq!(session.eval("x += 1;"), Ok(String::new()));
+    assert_eq!(session.eval("[x, 2]"), Ok("[4, 2]: [int; 2]\n".to_string()));
+    assert_eq!(session.eval("x > 2"), Ok("true: bool\n".to_string()));
+}
+
+#[test]
+fn failed_statements_are_not_kept() {
+    let mut session = Session::new();
+
+    assert!(session.eval("var x: int = 1.5;").is_err());
+    assert!(session.eval("var x: int = 2;").is_ok());
+    assert_eq!(session.eval(":type x / 2.0"), Ok("float\n".to_string()));
+    assert!(session.eval("x +").is_err());
+}
+
+#[test]
+fn shows_the_tree_and_instructions_of_an_expression() {
+    let input = "var x: int = 3;\n:ast x * 2\n:bytecode x * 2\nwhile x < 5 {\n    x += 1;\n}\nx\n";
+    let mut output = vec![];
+    repl::run(Cursor::new(input), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.contains("MulOp\n  Identifier(\"x\")\n  Integer(2)\n"));
+    assert!(output.contains("loadi 0x0000\n0x0005  pushi 2\n0x000a  muli\n"));
+    assert!(output.contains(". > 5: int\n"));
+}
+
+#[test]
+fn prints_vectors_matrices_and_rotations() {
+    let mut session = Session::new();
+
+    assert!(session.eval("var m: mat2 = mat2(1, 2, 3, 4);").is_ok());
+    assert_eq!(
+        session.eval("m * vec2(1, 1)"),
+        Ok("vec2(3, 7): vec2\n".to_string())
+    );
+    assert_eq!(
+        session.eval("transpose(m)"),
+        Ok("mat2(1, 3, 2, 4): mat2\n".to_string())
+    );
+
+    assert_eq!(
+        session.eval("quat(0, 0, 1, 0) * quat(0, 1, 0, 0)"),
+        Ok("quat(0, 0, 0, -1): quat\n".to_string())
+    );
+}
+
+#[test]
+fn shows_units_with_the_type() {
+    let mut session = Session::new();
+
+    assert!(session.eval("var d: float<km> = 1.5 km;").is_ok());
+    assert_eq!(
+        session.eval("d as float<m> / 2.0 s"),
+        Ok("750: float<m/s>\n".to_string())
+    );
+    assert_eq!(
+        session.eval(":type d + 1.0 m"),
+        Err("semantic error 37: the units of the operands do not match".to_string())
+    );
+}
+
+#[test]
+fn prints_fixed_point_numbers_exactly() {
+    let mut session = Session::new();
+
+    assert!(session
+        .eval("var x: fixed<8, 8> = 0.1 as fixed<8, 8>;")
+        .is_ok());
+    assert_eq!(
+        session.eval("x * (3 as fixed<8, 8>)"),
+        Ok("0.3046875: fixed<8, 8>\n".to_string())
+    );
+    assert_eq!(
+        session.eval("x + 1"),
+        Err(
+            "semantic error 11: the operator cannot be applied to operands of these types"
+                .to_string()
+        )
+    );
+}
@@ -0,0 +1,137 @@
+use karma::{compile_str, testing::run_tests};
+
+const SOURCE: &str = "node Math {
+    fn double(x: int) -> int {
+        return x * 2;
+    }
+
+    fn main() -> () {}
+
+    test fn doubles() -> () {
+        assert(double(2) == 4);
+        assert_eq(double(3), 6);
+    }
+
+    test fn rounds() -> () {
+        print_int(double(1));
+        assert_eq(double(3), 7);
+    }
+}
+";
+
+#[test]
+fn runs_each_test_and_reports_the_failed_assertion() {
+    let compilation = compile_str(SOURCE);
+    assert!(compilation.is_ok(), "{:?}", compilation.diagnostics);
+
+    let results = run_tests(&compilation, None);
+    let names: Vec<String> = results.iter().map(|r| r.qualified_name()).collect();
+    assert_eq!(names, ["Math::doubles", "Math::rounds"]);
+
+    assert_eq!(results[0].failure, None);
+
+    let failure = results[1].failure.as_ref().expect("the second test fails");
+    assert_eq!(failure.message, "assertion failed: `6` != `7`");
+    let span = failure.span.expect("failed assertions have a span");
+    assert_eq!((span.line, span.column), (15, 9));
+    assert_eq!(failure.to_string(), "15:9: assertion failed: `6` != `7`");
+    assert_eq!(results[1].output, "2");
+}
+
+#[test]
+fn a_function_named_like_an_assertion_does_not_move_the_failures() {
+    let compilation = compile_str(
+        "node Math {
+            fn assert(x: int) -> () {
+                print_int(x);
+            }
+
+            fn main() -> () {}
+
+            test fn fails() -> () {
+                assert(true);
+                assert_eq(3, 4);
+            }
+        }",
+    );
+    assert!(compilation.is_ok(), "{:?}", compilation.diagnostics);
+
+    let results = run_tests(&compilation, None);
+    let failure = results[0].failure.as_ref().expect("the test fails");
+    let span = failure.span.expect("failed assertions have a span");
+    assert_eq!((span.line, span.column), (10, 17));
+}
+
+#[test]
+fn a_failure_that_is_not_an_assertion_is_placed_at_its_test() {
+    let compilation = compile_str(
+        "node Math {
+    fn main() -> () {}
+
+    test fn divides() -> () {
+        var zero: int = 0;
+        print_int(1 / zero);
+    }
+}",
+    );
+    assert!(compilation.is_ok(), "{:?}", compilation.diagnostics);
+
+    let results = run_tests(&compilation, None);
+    let failure = results[0].failure.as_ref().expect("the test fails");
+    assert!(failure.message.ends_with("division by zero"));
+    let span = failure.span.expect("the test function has a span");
+    assert_eq!((span.line, span.column), (4, 13));
+}
+
+#[test]
+fn filters_tests_by_name() {
+    let compilation = compile_str(SOURCE);
+
+    let results = run_tests(&compilation, Some("Math::doub"));
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].name, "doubles");
+}
+
+#[test]
+fn tests_take_nothing_and_return_nothing() {
+    let compilation = compile_str(
+        "node Math {
+            fn main() -> () {}
+
+            test fn returns() -> int {
+                return 1;
+            }
+        }",
+    );
+    assert_eq!(compilation.diagnostics[0].code, Some(32));
+
+    let compilation = compile_str(
+        "node Math {
+            fn main() -> () {}
+
+            test fn compares() -> () {
+                assert_eq(1, 1.0);
+            }
+        }",
+    );
+    assert_eq!(compilation.diagnostics[0].code, Some(13));
+}
+
+#[test]
+fn assertions_are_not_values() {
+    for statement in [
+        "if assert(true) { println(); }",
+        "while assert_eq(1, 1) { println(); }",
+        "print_float([assert(true)]);",
+        "assert(assert(true));",
+    ] {
+        let compilation = compile_str(&format!(
+            "node Math {{
+                fn main() -> () {{
+                    {statement}
+                }}
+            }}"
+        ));
+        assert_eq!(compilation.diagnostics[0].code, Some(34), "{statement}");
+    }
+}
@@ -0,0 +1,153 @@
+use karma::{compile_str, vm};
+
+fn run(source: &str) -> Result<String, vm::RuntimeError> {
+    let compilation = compile_str(source);
+    assert!(compilation.is_ok(), "{:?}", compilation.diagnostics);
+
+    let mut out = vec![];
+    let bytes = compilation.bytecode.values().next().unwrap();
+    vm::run(bytes, &mut out)?;
+
+    Ok(String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn runs_loops_calls_and_arrays() {
+    let out = run("node Demo {
+            fn square(x: int) -> int {
+                return x * x;
+            }
+
+            fn main() -> () {
+                var arr: [int; 3] = [1, 2, 3];
+                var i: int = 0;
+                var total: int = 0;
+                while i < 3 {
+                    arr[i] += square(i);
+                    total = total + arr[i];
+                    i += 1;
+                }
+                print_int(total);
+                println();
+                if total % 4 == 3 { print_float(total * 0.5); } else { print_char('n'); }
+            }
+        }")
+    .unwrap();
+
+    assert_eq!(out, "11\n5.5");
+}
+
+#[test]
+fn casts_truncate_toward_zero() {
+    let out = run("node Demo {
+            fn main() -> () {
+                print_int(3.7 as int);
+                print_int(-3.7 as int);
+                print_char(98 as char);
+                print_int('a' as int << 1 | 1);
+            }
+        }")
+    .unwrap();
+
+    assert_eq!(out, "3-3b195");
+}
+
+#[test]
+fn reports_out_of_bounds_indices() {
+    let error = run("node Demo {
+            fn main() -> () {
+                var arr: [int; 2] = [1, 2];
+                var i: int = 2;
+                print_int(arr[i]);
+            }
+        }")
+    .unwrap_err();
+
+    assert_eq!(
+        error.message,
+        "index 2 is out of bounds for an array of length 2"
+    );
+}
+
+#[test]
+fn reports_sized_division_by_zero() {
+    let error = run("node Demo {
+            fn main() -> () {
+                var x: u8 = 200;
+                var y: u8 = x + 56;
+                print_int(x % y);
+            }
+        }")
+    .unwrap_err();
+
+    assert_eq!(error.message, "division by zero");
+}
+
+#[test]
+fn stops_after_the_given_number_of_steps() {
+    let compilation = compile_str(
+        "node Demo {
+            fn main() -> () {
+                while true { print_char('a'); }
+            }
+        }",
+    );
+    let bytes = compilation.bytecode.values().next().unwrap();
+
+    let mut out = vec![];
+    assert_eq!(vm::run_for(bytes, &mut out, 1000), Ok(false));
+    assert!(out.starts_with(b"aaa"));
+
+    let compilation = compile_str("node Demo { fn main() -> () { print_int(1); } }");
+    let bytes = compilation.bytecode.values().next().unwrap();
+    assert_eq!(vm::run_for(bytes, &mut vec![], 1000), Ok(true));
+}
+
+#[test]
+fn disassembles_instructions_with_their_offsets() {
+    let text = vm::disassemble(&[0x10, 0, 0, 0, 7, 0x15, b'a', 0x93, 0x64]).unwrap();
+
+    assert_eq!(
+        text,
+        "0x0000  pushi 7\n0x0005  pushc 'a'\n0x0007  prntc\n0x0008  ret\n"
+    );
+}
+
+#[test]
+fn links_imported_module_functions_into_the_node() {
+    let out = run("module util {
+            pub fn square(x: int) -> int {
+                return x * x;
+            }
+        }
+
+        module geometry {
+            use util::square;
+
+            fn sum(a: int, b: int) -> int {
+                return a + b;
+            }
+
+            pub fn norm2(x: int, y: int) -> int {
+                var xx: int = square(x);
+                var yy: int = square(y);
+                return sum(xx, yy);
+            }
+        }
+
+        node Planner {
+            use geometry::norm2;
+
+            fn sum(a: int) -> int {
+                return a;
+            }
+
+            fn main() -> () {
+                print_int(norm2(3, 4));
+                print_int(sum(1));
+            }
+        }")
+    .unwrap();
+
+    assert_eq!(out, "251");
+}